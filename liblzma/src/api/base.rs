@@ -27,6 +27,9 @@ pub enum LzmaRet {
     ProgError = 11,
     SeekNeeded = 12,
     RetInternal1 = 13,
+    Canceled = 14,
+    /// 加密过滤器找不到能解密数据的密钥，或编码时没有给出密钥
+    KeyError = 15,
 }
 
 #[derive(Debug)]
@@ -17,7 +17,7 @@ pub const LZMA_FILTER_ARMTHUMB: LzmaVli = LZMA_VLI_C!(0x08);
 pub const LZMA_FILTER_SPARC: LzmaVli = LZMA_VLI_C!(0x09);
 pub const LZMA_FILTER_ARM64: LzmaVli = LZMA_VLI_C!(0x0A);
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct LzmaOptionsBcj {
     pub start_offset: u32,
 }
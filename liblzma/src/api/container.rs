@@ -9,6 +9,8 @@ use super::{LzmaCheck, LzmaFilter, LzmaReservedEnum};
 pub const LZMA_PRESET_DEFAULT: u32 = 6;
 pub const LZMA_PRESET_LEVEL_MASK: u32 = 0x1F;
 pub const LZMA_PRESET_EXTREME: u32 = 1 << 31;
+/// 快速预设标志，只能用于 0-3 级，使用 HT4 匹配查找器换取更高的压缩速度
+pub const LZMA_PRESET_FAST: u32 = 1 << 30;
 
 pub struct LzmaMt<'a> {
     pub flags: u32,
@@ -42,3 +44,20 @@ pub const LZMA_TELL_ANY_CHECK: u32 = 0x04;
 pub const LZMA_IGNORE_CHECK: u32 = 0x10;
 pub const LZMA_CONCATENATED: u32 = 0x08;
 pub const LZMA_FAIL_FAST: u32 = 0x20;
+
+// .lzma（LZMA_Alone）解码器标志
+/// 按 LZMA Utils 的规则检查头部：字典大小须为 2^n 或 2^n+2^(n-1)，已知的未压缩大小须小于 256 GiB
+pub const LZMA_ALONE_PICKY: u32 = 0x01;
+/// 即使设置了 LZMA_ALONE_PICKY，也接受任意字典大小
+pub const LZMA_ALONE_ANY_DICT_SIZE: u32 = 0x02;
+/// 头部给出了未压缩大小时，仍要求数据以结束标记（EOPM）结尾
+pub const LZMA_ALONE_REQUIRE_EOPM: u32 = 0x04;
+/// 头部给出了未压缩大小时，不允许出现结束标记
+pub const LZMA_ALONE_FORBID_EOPM: u32 = 0x08;
+/// 忽略 LZMA 数据之后的尾随数据，直到 LZMA_FINISH 时才返回 LZMA_STREAM_END
+pub const LZMA_ALONE_ALLOW_TRAILING: u32 = 0x10;
+pub const LZMA_ALONE_SUPPORTED_FLAGS: u32 = LZMA_ALONE_PICKY
+    | LZMA_ALONE_ANY_DICT_SIZE
+    | LZMA_ALONE_REQUIRE_EOPM
+    | LZMA_ALONE_FORBID_EOPM
+    | LZMA_ALONE_ALLOW_TRAILING;
@@ -0,0 +1,81 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use crate::LZMA_VLI_C;
+
+use super::{LzmaAction, LzmaRet, LzmaVli};
+
+/// 自定义过滤器 ID 的下限
+///
+/// .xz 格式规范 5.4.1 节规定自定义过滤器 ID 由静态前缀 0x3F、40 位开发者 ID
+/// 和 16 位过滤器编号组成。
+pub const LZMA_FILTER_CUSTOM_MIN: LzmaVli = LZMA_VLI_C!(0x3F00_0000_0000_0000);
+
+/// 自定义过滤器 ID 的上限
+pub const LZMA_FILTER_CUSTOM_MAX: LzmaVli = LZMA_VLI_C!(0x3FFF_FFFF_FFFF_FFFF);
+
+/// 自定义过滤器的选项
+///
+/// 保存过滤器属性（Filter Properties）的原始字节，由 Filter 的实现解释。
+/// 属性编码和解码时原样复制。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LzmaOptionsCustom {
+    pub props: Vec<u8>,
+}
+
+/// 应用程序定义的过滤器，用 lzma_filter_register() 注册
+///
+/// 自定义过滤器只能用作过滤器链中的非最后过滤器，注册之后可以用于 raw 编解码器、
+/// .xz 块和过滤器链字符串。
+pub trait Filter: Send + Sync {
+    /// 过滤器 ID，必须在 LZMA_FILTER_CUSTOM_MIN 到 LZMA_FILTER_CUSTOM_MAX 之间
+    fn id(&self) -> LzmaVli;
+
+    /// 过滤器链字符串中使用的名称
+    fn name(&self) -> &str;
+
+    /// 编码后的数据大小是否可能与输入不同
+    fn changes_size(&self) -> bool {
+        false
+    }
+
+    /// 创建编码器；属性无效时返回 LzmaRet::OptionsError
+    fn encoder(&self, props: &[u8]) -> Result<Box<dyn FilterCoder>, LzmaRet>;
+
+    /// 创建解码器；属性无效时返回 LzmaRet::OptionsError
+    fn decoder(&self, props: &[u8]) -> Result<Box<dyn FilterCoder>, LzmaRet>;
+
+    /// 把过滤器链字符串中的选项（名称后 ':' 或 '=' 之后的部分）转换为属性
+    fn parse_options(&self, options: &str) -> Result<Vec<u8>, String> {
+        if options.is_empty() {
+            Ok(Vec::new())
+        } else {
+            Err("此过滤器不支持选项".to_string())
+        }
+    }
+
+    /// 把属性转换为过滤器链字符串中的选项，空字符串表示没有选项
+    fn options_to_str(&self, _props: &[u8]) -> String {
+        String::new()
+    }
+}
+
+/// 自定义过滤器的编码器或解码器
+pub trait FilterCoder: Send {
+    /// 从 input[*in_pos..] 读取数据，转换后写入 output[*out_pos..]，并更新两个位置
+    ///
+    /// 输出空间足够时必须读取全部输入，需要更多数据才能转换的部分由实现自己缓存。
+    /// action 不是 LzmaAction::Run 时 input 包含到本次 flush 或 finish 为止的全部数据，
+    /// 缓存的数据都输出后返回 LzmaRet::StreamEnd；flush 之后还可能有更多数据。
+    fn code(
+        &mut self,
+        input: &[u8],
+        in_pos: &mut usize,
+        output: &mut [u8],
+        out_pos: &mut usize,
+        action: LzmaAction,
+    ) -> LzmaRet;
+}
@@ -0,0 +1,67 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use std::path::PathBuf;
+
+use crate::LZMA_VLI_C;
+
+use super::LzmaVli;
+
+/// 长距离去重过滤器
+///
+/// 按 .xz 格式规范 5.4.1 节的自定义过滤器 ID 规则分配：静态前缀 0x3F、
+/// 随机生成的 40 位开发者 ID 0x6922C107CD、过滤器编号 0x0001。
+/// 其他 .xz 实现不认识这个过滤器，无法解压使用它的文件。
+///
+/// 引用可以指向之前输出的任意位置，所以解码器把全部输出保存在一个窗口文件中，
+/// 文件最终会增长到未压缩数据的大小。窗口文件创建在
+/// `LzmaOptionsDedup::window_dir` 中，没有名字（O_TMPFILE，文件系统不支持时
+/// 创建后立即删除目录项），解码器结束或进程退出时由系统回收。窗口文件不计入
+/// 解码器的内存用量，不受 memlimit 限制，其大小由
+/// `LzmaOptionsDedup::window_limit` 限制。
+pub const LZMA_FILTER_DEDUP: LzmaVli = LZMA_VLI_C!(0x3F69_22C1_07CD_0001);
+
+/// 平均分块大小的下限（以 2 为底的对数）
+pub const LZMA_DEDUP_CHUNK_LOG_MIN: u32 = 12;
+
+/// 平均分块大小的上限（以 2 为底的对数）
+pub const LZMA_DEDUP_CHUNK_LOG_MAX: u32 = 22;
+
+/// 平均分块大小的缺省值（64 KiB）
+pub const LZMA_DEDUP_CHUNK_LOG_DEFAULT: u32 = 16;
+
+/// 窗口文件大小上限的缺省值：不限制
+pub const LZMA_DEDUP_WINDOW_LIMIT_DEFAULT: u64 = u64::MAX;
+
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LzmaOptionsDedup {
+    /// 平均分块大小的以 2 为底的对数
+    ///
+    /// 分块越小，能找到的重复越多，但编码器的索引越大。最小分块为平均值的
+    /// 1/4，最大分块为平均值的 4 倍。
+    pub chunk_log: u32,
+
+    /// 解码器创建窗口文件的目录，None 表示使用临时目录（TMPDIR）
+    ///
+    /// 只用于解码器，不写入过滤器属性。从 .xz 文件中读出的过滤器使用缺省值。
+    pub window_dir: Option<PathBuf>,
+
+    /// 窗口文件的大小上限，即解码器最多输出的字节数
+    ///
+    /// 超过时解码器返回 LzmaRet::MemlimitError。只用于解码器，不写入过滤器属性。
+    pub window_limit: u64,
+}
+
+impl Default for LzmaOptionsDedup {
+    fn default() -> Self {
+        LzmaOptionsDedup {
+            chunk_log: LZMA_DEDUP_CHUNK_LOG_DEFAULT,
+            window_dir: None,
+            window_limit: LZMA_DEDUP_WINDOW_LIMIT_DEFAULT,
+        }
+    }
+}
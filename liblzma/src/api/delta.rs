@@ -16,7 +16,7 @@ pub enum LzmaDeltaType {
     Byte,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct LzmaOptionsDelta {
     pub type_: LzmaDeltaType,
     pub dist: u32,
@@ -0,0 +1,81 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use crate::LZMA_VLI_C;
+
+use super::{LzmaOptionsLzma, LzmaVli};
+
+/// 认证加密过滤器
+///
+/// 过滤器编号 0x0002，ID 的分配方法与 LZMA_FILTER_DEDUP 相同。加密后的数据
+/// 无法压缩，所以这个过滤器先用 LZMA2 压缩数据，再用 ChaCha20-Poly1305 加密，
+/// 可以用作过滤器链中的最后过滤器（代替 LZMA2），也可以用作非最后过滤器。
+/// 其他 .xz 实现不认识这个过滤器，无法解压使用它的文件。
+pub const LZMA_FILTER_ENCRYPT: LzmaVli = LZMA_VLI_C!(0x3F69_22C1_07CD_0002);
+
+/// 密钥的长度
+pub const LZMA_ENCRYPT_KEY_SIZE: usize = 32;
+
+/// Argon2id 的盐的长度
+pub const LZMA_ENCRYPT_SALT_SIZE: usize = 16;
+
+/// Argon2id 迭代次数的缺省值
+pub const LZMA_ENCRYPT_TIME_COST_DEFAULT: u32 = 3;
+
+/// Argon2id 迭代次数的上限，限制解码时由文件决定的计算量
+pub const LZMA_ENCRYPT_TIME_COST_MAX: u32 = 16;
+
+/// Argon2id 所用内存（KiB）以 2 为底的对数的缺省值（64 MiB）
+pub const LZMA_ENCRYPT_MEMORY_COST_LOG_DEFAULT: u32 = 16;
+
+/// Argon2id 所用内存（KiB）以 2 为底的对数的下限（8 KiB）
+pub const LZMA_ENCRYPT_MEMORY_COST_LOG_MIN: u32 = 3;
+
+/// Argon2id 所用内存（KiB）以 2 为底的对数的上限（4 GiB）
+pub const LZMA_ENCRYPT_MEMORY_COST_LOG_MAX: u32 = 22;
+
+/// 在需要口令而密钥环中没有匹配的口令时调用，返回 None 表示没有更多口令
+pub type LzmaEncryptSecretFn = fn() -> Option<Vec<u8>>;
+
+/// 加密过滤器的选项
+///
+/// 密钥由口令或密钥文件的内容经 Argon2id 派生。Filter Properties 中记录
+/// Argon2id 的参数和盐以及密钥的标识，不包含密钥本身：从 Filter Properties
+/// 解码得到的选项中 key 为 None，解码器用这些参数派生密钥环中的每个口令，
+/// 找到标识相同的密钥。
+#[derive(Debug, Clone, PartialEq)]
+pub struct LzmaOptionsEncrypt {
+    /// 加密前压缩数据所用的 LZMA2 选项，解码时只用到字典大小
+    pub lzma: LzmaOptionsLzma,
+
+    /// Argon2id 的盐，由 lzma_encrypt_key_set() 随机生成
+    pub salt: [u8; LZMA_ENCRYPT_SALT_SIZE],
+
+    /// Argon2id 的迭代次数
+    pub time_cost: u32,
+
+    /// Argon2id 所用内存（KiB）以 2 为底的对数
+    pub memory_cost_log: u32,
+
+    /// 派生出的密钥，编码时必须设置
+    pub key: Option<[u8; LZMA_ENCRYPT_KEY_SIZE]>,
+
+    /// 密钥的标识，用于在解码时区分缺少密钥和密钥错误
+    pub key_id: u64,
+}
+
+impl Default for LzmaOptionsEncrypt {
+    fn default() -> Self {
+        LzmaOptionsEncrypt {
+            lzma: LzmaOptionsLzma::default(),
+            salt: [0; LZMA_ENCRYPT_SALT_SIZE],
+            time_cost: LZMA_ENCRYPT_TIME_COST_DEFAULT,
+            memory_cost_log: LZMA_ENCRYPT_MEMORY_COST_LOG_DEFAULT,
+            key: None,
+            key_id: 0,
+        }
+    }
+}
@@ -8,16 +8,21 @@ use std::any::Any;
 
 use crate::api::LzmaVli;
 
-use super::{LzmaOptionsBcj, LzmaOptionsDelta, LzmaOptionsLzma};
+use super::{
+    LzmaOptionsBcj, LzmaOptionsCustom, LzmaOptionsDedup, LzmaOptionsDelta, LzmaOptionsEncrypt,
+    LzmaOptionsLzma,
+};
 
 pub const LZMA_FILTERS_MAX: usize = 4;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LzmaOptionsType {
     LzmaOptionsLzma(LzmaOptionsLzma),
     Delta(LzmaOptionsDelta),
     Bcj(LzmaOptionsBcj),
-    Lod(LzmaOptionsDelta),
+    Dedup(LzmaOptionsDedup),
+    Encrypt(LzmaOptionsEncrypt),
+    Custom(LzmaOptionsCustom),
 
     None,
 }
@@ -43,9 +48,30 @@ impl LzmaOptionsType {
             _ => None,
         }
     }
+
+    pub fn as_dedup(&self) -> Option<&LzmaOptionsDedup> {
+        match self {
+            LzmaOptionsType::Dedup(ref opts) => Some(opts),
+            _ => None,
+        }
+    }
+
+    pub fn as_encrypt(&self) -> Option<&LzmaOptionsEncrypt> {
+        match self {
+            LzmaOptionsType::Encrypt(ref opts) => Some(opts),
+            _ => None,
+        }
+    }
+
+    pub fn as_custom(&self) -> Option<&LzmaOptionsCustom> {
+        match self {
+            LzmaOptionsType::Custom(ref opts) => Some(opts),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LzmaFilter {
     pub id: LzmaVli,
     pub options: Option<LzmaOptionsType>,
@@ -22,6 +22,7 @@ pub enum LzmaMatchFinder {
     LzmaMfBt2 = 0x12,
     LzmaMfBt3 = 0x13,
     LzmaMfBt4 = 0x14,
+    LzmaMfHt4 = 0x24,
 }
 
 pub const LZMA_MF_HC3: u64 = 0x03;
@@ -29,6 +30,7 @@ pub const LZMA_MF_HC4: u64 = 0x04;
 pub const LZMA_MF_BT2: u64 = 0x12;
 pub const LZMA_MF_BT3: u64 = 0x13;
 pub const LZMA_MF_BT4: u64 = 0x14;
+pub const LZMA_MF_HT4: u64 = 0x24;
 
 #[derive(PartialEq, Debug, Default, Clone)]
 pub enum LzmaMode {
@@ -48,7 +50,7 @@ pub const LZMA_PB_MIN: u32 = 0;
 pub const LZMA_PB_MAX: u32 = 4;
 pub const LZMA_PB_DEFAULT: u32 = 2;
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct LzmaOptionsLzma {
     pub dict_size: u32,
     pub preset_dict: Option<Vec<u8>>,
@@ -9,11 +9,15 @@ mod bcj;
 mod block;
 mod check;
 mod container;
+mod custom;
+mod dedup;
 mod delta;
+mod encrypt;
 mod filter;
 // mod index_bak;
 mod index;
 mod lama12;
+mod progress;
 mod stream_flags;
 mod version;
 mod vli;
@@ -23,11 +27,15 @@ pub use bcj::*;
 pub use block::*;
 pub use check::*;
 pub use container::*;
+pub use custom::*;
+pub use dedup::*;
 pub use delta::*;
+pub use encrypt::*;
 pub use filter::*;
 // pub use index_bak::*;
 pub use index::*;
 pub use lama12::*;
+pub use progress::*;
 pub use stream_flags::*;
 pub use version::*;
 pub use vli::*;
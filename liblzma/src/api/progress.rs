@@ -0,0 +1,54 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 取消编码或解码的令牌
+///
+/// 克隆出的令牌共享同一个状态，可以交给其他线程。调用 cancel() 之后，
+/// lzma_code() 返回 `LzmaRet::Canceled`，编码器或解码器被释放，多线程
+/// 编码器的工作线程在当前的一小段数据之后退出。
+#[derive(Debug, Clone, Default)]
+pub struct LzmaCancelToken(Arc<AtomicBool>);
+
+impl LzmaCancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消，可以在任何线程中调用，也可以在信号处理函数中调用
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 传给进度回调函数的进度信息
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LzmaProgress {
+    /// 已经处理的输入字节数，含义与 lzma_get_progress() 相同
+    pub progress_in: u64,
+    /// 已经产生的输出字节数
+    pub progress_out: u64,
+    /// 正在处理的 Block 的序号，从 1 开始；没有 Block 或还没有开始时为 0
+    pub block: u64,
+}
+
+/// 进度回调函数，每次 lzma_code() 处理了数据之后调用
+///
+/// 要求 Send，这样设置了回调函数的 LzmaStream 仍然可以交给其他线程。
+pub struct LzmaProgressCallback(pub Box<dyn FnMut(&LzmaProgress) + Send>);
+
+impl fmt::Debug for LzmaProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LzmaProgressCallback")
+    }
+}
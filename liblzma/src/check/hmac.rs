@@ -0,0 +1,155 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! HMAC-SHA256（RFC 2104），基于 sha256.rs
+//!
+//! 完整性校验值任何人都可以重新计算，HMAC 需要密钥，可以用来证明数据没有被篡改。
+
+use super::{lzma_check_finish, lzma_check_init, lzma_check_update, LzmaCheckState};
+use crate::api::LzmaCheck;
+
+/// HMAC-SHA256 的长度
+pub const LZMA_HMAC_SHA256_SIZE: usize = 32;
+
+/// SHA-256 的分组大小
+const SHA256_BLOCK_SIZE: usize = 64;
+
+/// HMAC-SHA256 的计算状态
+#[derive(Debug, Clone)]
+pub struct LzmaHmacSha256State {
+    /// 内层散列：SHA-256(K ^ ipad || 数据)
+    inner: LzmaCheckState,
+    /// 与 opad 异或后的密钥
+    outer_key: [u8; SHA256_BLOCK_SIZE],
+}
+
+/// 计算 data 的 SHA-256
+fn sha256(data: &[u8]) -> [u8; LZMA_HMAC_SHA256_SIZE] {
+    let mut check = LzmaCheckState::default();
+    lzma_check_init(&mut check, LzmaCheck::Sha256);
+    lzma_check_update(&mut check, LzmaCheck::Sha256, data, data.len());
+    lzma_check_finish(&mut check, LzmaCheck::Sha256);
+    check.buffer.u8[..LZMA_HMAC_SHA256_SIZE].try_into().unwrap()
+}
+
+/// 用密钥 key 开始计算 HMAC-SHA256，key 可以是任意长度
+pub fn lzma_hmac_sha256_init(key: &[u8]) -> LzmaHmacSha256State {
+    // 比分组长的密钥先散列
+    let mut block_key = [0u8; SHA256_BLOCK_SIZE];
+    if key.len() > SHA256_BLOCK_SIZE {
+        block_key[..LZMA_HMAC_SHA256_SIZE].copy_from_slice(&sha256(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let inner_key = block_key.map(|b| b ^ 0x36);
+    let outer_key = block_key.map(|b| b ^ 0x5C);
+
+    let mut inner = LzmaCheckState::default();
+    lzma_check_init(&mut inner, LzmaCheck::Sha256);
+    lzma_check_update(&mut inner, LzmaCheck::Sha256, &inner_key, SHA256_BLOCK_SIZE);
+
+    LzmaHmacSha256State { inner, outer_key }
+}
+
+/// 加入更多数据
+pub fn lzma_hmac_sha256_update(hmac: &mut LzmaHmacSha256State, buf: &[u8]) {
+    lzma_check_update(&mut hmac.inner, LzmaCheck::Sha256, buf, buf.len());
+}
+
+/// 结束计算并返回 HMAC
+pub fn lzma_hmac_sha256_finish(mut hmac: LzmaHmacSha256State) -> [u8; LZMA_HMAC_SHA256_SIZE] {
+    lzma_check_finish(&mut hmac.inner, LzmaCheck::Sha256);
+
+    let mut outer = [0u8; SHA256_BLOCK_SIZE + LZMA_HMAC_SHA256_SIZE];
+    outer[..SHA256_BLOCK_SIZE].copy_from_slice(&hmac.outer_key);
+    outer[SHA256_BLOCK_SIZE..].copy_from_slice(&hmac.inner.buffer.u8[..LZMA_HMAC_SHA256_SIZE]);
+    sha256(&outer)
+}
+
+/// 比较两个 HMAC，所用时间与内容无关，不会泄露第一个不同字节的位置
+pub fn lzma_hmac_sha256_equal(
+    a: &[u8; LZMA_HMAC_SHA256_SIZE],
+    b: &[u8; LZMA_HMAC_SHA256_SIZE],
+) -> bool {
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        lzma_hmac_sha256_equal, lzma_hmac_sha256_finish, lzma_hmac_sha256_init,
+        lzma_hmac_sha256_update,
+    };
+    use crate::test_util::hex;
+
+    /// RFC 4231 第 4 节的测试用例
+    #[test]
+    fn rfc_4231_vectors() {
+        let key4: Vec<u8> = (1..=25).collect();
+        let cases: [(&[u8], &[u8], &str); 7] = [
+            (
+                &[0x0B; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &[0xAA; 20],
+                &[0xDD; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                &key4,
+                &[0xCD; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            // 用例 5 只给出前 128 位
+            (
+                &[0x0C; 20],
+                b"Test With Truncation",
+                "a3b6167473100ee06e0c796c2955552b",
+            ),
+            (
+                &[0xAA; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                &[0xAA; 131],
+                b"This is a test using a larger than block-size key and a larger \
+                  than block-size data. The key needs to be hashed before being \
+                  used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+
+        for (key, data, expected) in cases {
+            let expected = hex(expected);
+
+            let mut hmac = lzma_hmac_sha256_init(key);
+            lzma_hmac_sha256_update(&mut hmac, data);
+            let mac = lzma_hmac_sha256_finish(hmac);
+            assert_eq!(&mac[..expected.len()], &expected[..]);
+
+            // 分成多次加入数据得到相同的结果
+            let mut hmac = lzma_hmac_sha256_init(key);
+            for chunk in data.chunks(7) {
+                lzma_hmac_sha256_update(&mut hmac, chunk);
+            }
+            let split = lzma_hmac_sha256_finish(hmac);
+            assert!(lzma_hmac_sha256_equal(&mac, &split));
+
+            let mut other = mac;
+            other[31] ^= 1;
+            assert!(!lzma_hmac_sha256_equal(&mac, &other));
+        }
+    }
+}
@@ -7,9 +7,11 @@
 mod check;
 mod crc32_small;
 mod crc64_small;
+mod hmac;
 mod sha256;
 
 pub use check::*;
 pub use crc32_small::*;
 pub use crc64_small::*;
+pub use hmac::*;
 pub use sha256::*;
@@ -89,8 +89,8 @@ pub fn transform(state: &mut [u32; 8], data: &[u32; 16]) {
             t1.wrapping_add(t2),
             T[0],
             T[1],
-            T[2].wrapping_add(t1),
-            T[3],
+            T[2],
+            T[3].wrapping_add(t1),
             T[4],
             T[5],
             T[6],
@@ -105,7 +105,11 @@ pub fn transform(state: &mut [u32; 8], data: &[u32; 16]) {
 
 /// process 函数：调用 transform 对 check 中的 SHA256 状态进行处理
 pub fn process(check: &mut LzmaCheckState) {
-    // 假定 check.buffer.u32 为 [u32; 16]
+    // Buffer 不是 C 中的联合体，需要先把按字节填充的数据转换到 u32 视图中
+    for i in 0..16 {
+        check.buffer.u32[i] =
+            u32::from_ne_bytes(check.buffer.u8[i * 4..(i + 1) * 4].try_into().unwrap());
+    }
     transform(&mut check.state.sha256.state, &check.buffer.u32);
 }
 
@@ -162,10 +166,59 @@ pub fn lzma_sha256_finish(check: &mut LzmaCheckState) {
     // 将消息长度（以比特为单位）存入缓冲区的最后 8 字节
     // 使用 to_be() 将长度转换为大端格式
     check.state.sha256.size *= 8;
-    check.buffer.u64[(64 - 8) / 8] = check.state.sha256.size.to_be();
+    check.buffer.u8[64 - 8..].copy_from_slice(&check.state.sha256.size.to_be_bytes());
     process(check);
     // 将最终状态转换为大端字节序写入缓冲区（作为最终校验值）
     for i in 0..8 {
         check.buffer.u32[i] = check.state.sha256.state[i].to_be();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::LzmaCheck;
+    use crate::check::{lzma_check_finish, lzma_check_init, lzma_check_update, LzmaCheckState};
+
+    /// 分 step 字节一段加入数据，返回十六进制的散列值
+    fn sha256_hex(data: &[u8], step: usize) -> String {
+        let mut check = LzmaCheckState::default();
+        lzma_check_init(&mut check, LzmaCheck::Sha256);
+        for chunk in data.chunks(step.max(1)) {
+            lzma_check_update(&mut check, LzmaCheck::Sha256, chunk, chunk.len());
+        }
+        lzma_check_finish(&mut check, LzmaCheck::Sha256);
+        check.buffer.u8[..32]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// FIPS 180-2 附录 B 的测试向量，跨越分组边界的填充，以及分段加入数据
+    #[test]
+    fn fips_180_2_vectors() {
+        let vectors: [(&[u8], &str); 4] = [
+            (
+                b"",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                b"abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+            (
+                &[b'a'; 1_000_000],
+                "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0",
+            ),
+        ];
+
+        for (data, expected) in vectors {
+            for step in [data.len(), 1, 63, 64, 65] {
+                assert_eq!(sha256_hex(data, step), expected);
+            }
+        }
+    }
+}
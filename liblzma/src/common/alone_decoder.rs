@@ -7,6 +7,8 @@
 #[macro_use]
 use crate::api::{
     LzmaAction, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream, LzmaVli,
+    LZMA_ALONE_ALLOW_TRAILING, LZMA_ALONE_ANY_DICT_SIZE, LZMA_ALONE_FORBID_EOPM,
+    LZMA_ALONE_PICKY, LZMA_ALONE_REQUIRE_EOPM, LZMA_ALONE_SUPPORTED_FLAGS,
     LZMA_FILTER_LZMA1EXT, LZMA_LZMA1EXT_ALLOW_EOPM, LZMA_VLI_UNKNOWN,
 };
 use crate::common::CoderType;
@@ -30,6 +32,7 @@ pub enum Sequence {
     UncompressedSize,
     CoderInit,
     Code,
+    Trailing,
 }
 
 #[repr(C)]
@@ -38,8 +41,11 @@ pub struct LzmaAloneDecoder {
     pub next: Box<LzmaNextCoder>,
     pub sequence: Sequence,
     pub picky: bool,
+    pub flags: u32,
     pub pos: usize,
     pub uncompressed_size: LzmaVli,
+    /// 已输出的未压缩字节数，仅在 LZMA_ALONE_REQUIRE_EOPM 时用于核对头部中的大小
+    pub uncompressed_done: LzmaVli,
     pub memlimit: u64,
     pub memusage: u64,
     pub options: LzmaOptionsLzma,
@@ -62,7 +68,11 @@ fn alone_decode(
     //     _ => return LzmaRet::ProgError, // 如果不是 AloneDecoder 类型，则返回错误
     // };
     if let CoderType::AloneDecoder(ref mut coder) = coder_ptr {
-        while *out_pos < out_size && (coder.sequence == Sequence::Code || *in_pos < in_size) {
+        while *out_pos < out_size
+            && (coder.sequence == Sequence::Code
+                || coder.sequence == Sequence::Trailing
+                || *in_pos < in_size)
+        {
             match coder.sequence {
                 Sequence::Properties => {
                     if lzma_lzma_lclppb_decode(&mut coder.options, in_[*in_pos]) != false {
@@ -74,7 +84,10 @@ fn alone_decode(
                 Sequence::DictionarySize => {
                     coder.options.dict_size |= (in_[*in_pos] as u32) << (coder.pos * 8);
                     if coder.pos == 3 {
-                        if coder.picky && coder.options.dict_size != u32::MAX as u32 {
+                        if coder.picky
+                            && coder.flags & LZMA_ALONE_ANY_DICT_SIZE == 0
+                            && coder.options.dict_size != u32::MAX as u32
+                        {
                             let mut d = coder.options.dict_size - 1;
                             d |= d >> 2;
                             d |= d >> 3;
@@ -103,8 +116,18 @@ fn alone_decode(
                         {
                             return LzmaRet::FormatError;
                         }
-                        coder.options.ext_flags = LZMA_LZMA1EXT_ALLOW_EOPM;
-                        lzma_set_ext_size(&mut coder.options, coder.uncompressed_size);
+                        // 大小已知时默认允许可选的结束标记；要求结束标记时把大小当作未知交给
+                        // LZMA 解码器，之后再自行核对输出的字节数。
+                        coder.options.ext_flags = if coder.flags & LZMA_ALONE_FORBID_EOPM != 0 {
+                            0
+                        } else {
+                            LZMA_LZMA1EXT_ALLOW_EOPM
+                        };
+                        if coder.flags & LZMA_ALONE_REQUIRE_EOPM != 0 {
+                            lzma_set_ext_size(&mut coder.options, LZMA_VLI_UNKNOWN);
+                        } else {
+                            lzma_set_ext_size(&mut coder.options, coder.uncompressed_size);
+                        }
                         coder.memusage = lzma_lzma_decoder_memusage(
                             &LzmaOptionsType::LzmaOptionsLzma(coder.options.clone()),
                         ) + LZMA_MEMUSAGE_BASE;
@@ -138,22 +161,48 @@ fn alone_decode(
                     coder.sequence = Sequence::Code;
                 }
                 Sequence::Code => {
-                    if let Some(code) = coder.next.code {
-                        return code(
-                            &mut coder.next.coder.as_mut().unwrap(),
-                            in_,
-                            in_pos,
-                            in_size,
-                            out,
-                            out_pos,
-                            out_size,
-                            action,
-                        );
-                    } else {
-                        return LzmaRet::ProgError;
+                    let code = match coder.next.code {
+                        Some(code) => code,
+                        None => return LzmaRet::ProgError,
+                    };
+                    let out_start = *out_pos;
+                    let ret = code(
+                        &mut coder.next.coder.as_mut().unwrap(),
+                        in_,
+                        in_pos,
+                        in_size,
+                        out,
+                        out_pos,
+                        out_size,
+                        action,
+                    );
+
+                    if coder.flags & LZMA_ALONE_REQUIRE_EOPM != 0
+                        && coder.uncompressed_size != LZMA_VLI_UNKNOWN
+                    {
+                        coder.uncompressed_done += (*out_pos - out_start) as LzmaVli;
+                        if coder.uncompressed_done > coder.uncompressed_size
+                            || (ret == LzmaRet::StreamEnd
+                                && coder.uncompressed_done != coder.uncompressed_size)
+                        {
+                            return LzmaRet::DataError;
+                        }
                     }
+
+                    if ret != LzmaRet::StreamEnd || coder.flags & LZMA_ALONE_ALLOW_TRAILING == 0 {
+                        return ret;
+                    }
+
+                    coder.sequence = Sequence::Trailing;
+                }
+                Sequence::Trailing => {
+                    // 丢弃 LZMA 数据之后的所有输入，直到调用者表明输入已经结束
+                    *in_pos = in_size;
+                    if action == LzmaAction::Finish {
+                        return LzmaRet::StreamEnd;
+                    }
+                    return LzmaRet::Ok;
                 }
-                _ => return LzmaRet::ProgError,
             }
         }
         LzmaRet::Ok
@@ -198,7 +247,14 @@ fn alone_decoder_memconfig(
     LzmaRet::Ok
 }
 
-pub fn lzma_alone_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, picky: bool) -> LzmaRet {
+pub fn lzma_alone_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, flags: u32) -> LzmaRet {
+    if flags & !LZMA_ALONE_SUPPORTED_FLAGS != 0
+        || (flags & LZMA_ALONE_REQUIRE_EOPM != 0 && flags & LZMA_ALONE_FORBID_EOPM != 0)
+    {
+        return LzmaRet::OptionsError;
+    }
+    let picky = flags & LZMA_ALONE_PICKY != 0;
+
     if next.init != Some(NextCoderInitFunction::AloneDecoder(lzma_alone_decoder_init)) {
         lzma_next_end(next);
     }
@@ -210,8 +266,10 @@ pub fn lzma_alone_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, picky: b
             next: Box::new(LzmaNextCoder::default()), // Use default without mutable reference
             sequence: Sequence::Properties,
             picky,
+            flags,
             pos: 0,
             uncompressed_size: 0,
+            uncompressed_done: 0,
             memlimit: my_max(1, memlimit),
             memusage: LZMA_MEMUSAGE_BASE,
             options: LzmaOptionsLzma::default(),
@@ -237,11 +295,13 @@ pub fn lzma_alone_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, picky: b
 
     coder.sequence = Sequence::Properties;
     coder.picky = picky;
+    coder.flags = flags;
     coder.pos = 0;
     coder.options.dict_size = 0;
     coder.options.preset_dict = None;
     coder.options.preset_dict_size = 0;
     coder.uncompressed_size = 0;
+    coder.uncompressed_done = 0;
     coder.memlimit = my_max(1, memlimit);
     coder.memusage = LZMA_MEMUSAGE_BASE;
     coder.next = Box::new(LzmaNextCoder::default());
@@ -249,6 +309,12 @@ pub fn lzma_alone_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, picky: b
 }
 
 pub fn lzma_alone_decoder(strm: &mut LzmaStream, memlimit: u64) -> LzmaRet {
+    lzma_alone_decoder_flags(strm, memlimit, 0)
+}
+
+/// 与 `lzma_alone_decoder` 相同，但可以用 `LZMA_ALONE_*` 标志控制头部检查的严格程度、
+/// 结束标记的处理方式以及是否允许尾随数据。
+pub fn lzma_alone_decoder_flags(strm: &mut LzmaStream, memlimit: u64, flags: u32) -> LzmaRet {
     let ret_: LzmaRet = lzma_strm_init(Some(strm));
     if ret_ != LzmaRet::Ok {
         return ret_;
@@ -257,7 +323,7 @@ pub fn lzma_alone_decoder(strm: &mut LzmaStream, memlimit: u64) -> LzmaRet {
     let mut internal = strm.internal.borrow_mut();
     if let Some(ref mut internal) = *internal {
         if let Some(ref mut next) = internal.next {
-            let ret_0: LzmaRet = lzma_alone_decoder_init(next, memlimit, false);
+            let ret_0: LzmaRet = lzma_alone_decoder_init(next, memlimit, flags);
             if ret_0 != LzmaRet::Ok {
                 let _ = internal;
                 // lzma_end(Some(strm));
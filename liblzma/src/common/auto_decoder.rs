@@ -8,7 +8,8 @@ use common::my_max;
 
 use crate::{
     api::{
-        LzmaAction, LzmaCheck, LzmaRet, LzmaStream, LZMA_CONCATENATED, LZMA_TELL_ANY_CHECK,
+        LzmaAction, LzmaCheck, LzmaRet, LzmaStream, LZMA_ALONE_PICKY, LZMA_CONCATENATED,
+        LZMA_TELL_ANY_CHECK,
         LZMA_TELL_NO_CHECK,
     },
     common::{
@@ -71,7 +72,7 @@ fn auto_decode(
                     return ret;
                 }
             } else {
-                let ret = lzma_alone_decoder_init(&mut coder.next, coder.memlimit, true);
+                let ret = lzma_alone_decoder_init(&mut coder.next, coder.memlimit, LZMA_ALONE_PICKY);
                 if ret != LzmaRet::Ok {
                     return ret;
                 }
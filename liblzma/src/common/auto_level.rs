@@ -0,0 +1,342 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 自动选择压缩预设和过滤器链
+//!
+//! 在输入数据的样本上用候选的预设和过滤器链实际压缩一遍，测量速度和压缩率，
+//! 选出满足目标的一个。可执行文件会尝试对应的 BCJ 过滤器，
+//! 看起来像 PCM 或定长记录的数据会尝试 Delta 过滤器。
+
+use std::time::Instant;
+
+use crate::{
+    api::{
+        LzmaAction, LzmaDeltaType, LzmaFilter, LzmaOptionsBcj, LzmaOptionsDelta, LzmaOptionsLzma,
+        LzmaOptionsType, LzmaRet, LZMA_DICT_SIZE_MIN, LZMA_FILTER_DELTA, LZMA_FILTER_LZMA2,
+        LZMA_PRESET_FAST, LZMA_VLI_UNKNOWN,
+    },
+    delta::lzma_delta_suggest_dist,
+    lzma::lzma_lzma_preset,
+    simple::{lzma_bcj_start_offset, lzma_detect_bcj},
+};
+
+use super::{lzma_next_end, lzma_raw_encoder_init, LzmaNextCoder};
+
+/// 建议的样本总大小
+pub const LZMA_AUTO_SAMPLE_SIZE: usize = 1 << 20;
+
+/// 建议的样本段数，样本从输入中均匀取出这么多段拼接而成，第一段总是从头开始
+pub const LZMA_AUTO_SAMPLE_PIECES: usize = 4;
+
+/// 自动选择的目标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LzmaAutoTarget {
+    /// 单线程压缩速度不低于每秒这么多字节（按未压缩数据计）
+    Speed(u64),
+    /// 压缩率（压缩后大小 / 原始大小）不高于该值
+    Ratio(f64),
+}
+
+/// 自动选择的结果
+#[derive(Debug, Clone)]
+pub struct LzmaAutoChoice {
+    /// 选中的预设（级别和 LZMA_PRESET_* 标志）
+    pub preset: u32,
+    /// 过滤器链，最后一个是 LZMA2，以 LZMA_VLI_UNKNOWN 结尾
+    pub filters: Vec<LzmaFilter>,
+    /// 在样本上测得的压缩速度（字节/秒）
+    pub speed: u64,
+    /// 在样本上测得的压缩率
+    pub ratio: f64,
+    /// 是否满足目标；无法满足时为 false，此时给出最接近目标的选择
+    pub target_met: bool,
+}
+
+/// 候选预设，大致按从快到慢排列
+const CANDIDATE_PRESETS: [u32; 14] = [
+    LZMA_PRESET_FAST,
+    1 | LZMA_PRESET_FAST,
+    2 | LZMA_PRESET_FAST,
+    3 | LZMA_PRESET_FAST,
+    0,
+    1,
+    2,
+    3,
+    4,
+    5,
+    6,
+    7,
+    8,
+    9,
+];
+
+/// 选择前置过滤器时使用的预设
+const PROBE_PRESET: u32 = 1;
+
+/// 样本上的一次测量结果
+#[derive(Debug, Clone, Copy)]
+struct Measurement {
+    size: usize,
+    nanos: u128,
+}
+
+fn filter_chain(prefix: &Option<LzmaFilter>, lzma: LzmaOptionsLzma) -> Vec<LzmaFilter> {
+    let mut filters: Vec<LzmaFilter> = prefix.iter().cloned().collect();
+    filters.push(LzmaFilter {
+        id: LZMA_FILTER_LZMA2,
+        options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma)),
+    });
+    filters.push(LzmaFilter {
+        id: LZMA_VLI_UNKNOWN,
+        options: None,
+    });
+    filters
+}
+
+/// 用 filters 压缩 data 并计时
+fn measure(filters: &[LzmaFilter], data: &[u8]) -> Result<Measurement, LzmaRet> {
+    let start = Instant::now();
+
+    let mut next = LzmaNextCoder::default();
+    let ret = lzma_raw_encoder_init(&mut next, filters);
+    if ret != LzmaRet::Ok {
+        lzma_next_end(&mut next);
+        return Err(ret);
+    }
+
+    let mut out = vec![0u8; data.len() + data.len() / 1024 + 64];
+    let mut out_pos = 0;
+    let mut in_pos = 0;
+    let ret = loop {
+        let code = match next.code {
+            Some(code) => code,
+            None => break LzmaRet::ProgError,
+        };
+        let out_size = out.len();
+        let ret = code(
+            next.coder.as_mut().unwrap(),
+            data,
+            &mut in_pos,
+            data.len(),
+            &mut out,
+            &mut out_pos,
+            out_size,
+            LzmaAction::Finish,
+        );
+        if ret != LzmaRet::Ok {
+            break ret;
+        }
+        if out_pos == out.len() {
+            out.resize(out.len() * 2, 0);
+        }
+    };
+    lzma_next_end(&mut next);
+
+    if ret != LzmaRet::StreamEnd {
+        return Err(ret);
+    }
+
+    Ok(Measurement {
+        size: out_pos,
+        nanos: start.elapsed().as_nanos().max(1),
+    })
+}
+
+/// 预设对应的 LZMA2 选项，字典大小限制为不超过样本大小，
+/// 否则大字典的初始化开销会淹没样本上的计时
+fn sample_options(preset: u32, sample_len: usize) -> Result<LzmaOptionsLzma, LzmaRet> {
+    let mut opt = LzmaOptionsLzma::default();
+    if lzma_lzma_preset(&mut opt, preset) {
+        return Err(LzmaRet::OptionsError);
+    }
+    let limit = (sample_len.next_power_of_two() as u64).clamp(
+        LZMA_DICT_SIZE_MIN as u64,
+        u32::MAX as u64,
+    ) as u32;
+    opt.dict_size = opt.dict_size.min(limit);
+    Ok(opt)
+}
+
+/// 两组选项在样本上的表现是否相同（只比较编码器会用到的字段）
+fn same_options(a: &LzmaOptionsLzma, b: &LzmaOptionsLzma) -> bool {
+    a.dict_size == b.dict_size
+        && a.lc == b.lc
+        && a.lp == b.lp
+        && a.pb == b.pb
+        && a.mode == b.mode
+        && a.nice_len == b.nice_len
+        && a.mf == b.mf
+        && a.depth == b.depth
+}
+
+/// 在样本上测量候选的预设和过滤器链，选出满足 target 的一个
+///
+/// 速度目标选满足速度要求的候选中压缩率最好的，都达不到时选最快的；
+/// 压缩率目标选满足压缩率要求的候选中最快的，都达不到时选压缩率最好的。
+/// 返回的过滤器链使用预设原本的字典大小。
+pub fn lzma_auto_level(sample: &[u8], target: LzmaAutoTarget) -> Result<LzmaAutoChoice, LzmaRet> {
+    if sample.is_empty() {
+        return Err(LzmaRet::BufError);
+    }
+
+    // 先用一个快速预设比较前置过滤器
+    let mut prefixes = vec![None];
+    if let Some(id) = lzma_detect_bcj(sample) {
+        prefixes.push(Some(LzmaFilter {
+            id,
+            options: Some(LzmaOptionsType::Bcj(LzmaOptionsBcj {
+                start_offset: lzma_bcj_start_offset(sample, id),
+            })),
+        }));
+    }
+    if let Some(dist) = lzma_delta_suggest_dist(sample) {
+        prefixes.push(Some(LzmaFilter {
+            id: LZMA_FILTER_DELTA,
+            options: Some(LzmaOptionsType::Delta(LzmaOptionsDelta {
+                type_: LzmaDeltaType::Byte,
+                dist,
+                ..Default::default()
+            })),
+        }));
+    }
+
+    let mut prefix = None;
+    if prefixes.len() > 1 {
+        let probe = sample_options(PROBE_PRESET, sample.len())?;
+        let mut best_size = usize::MAX;
+        for candidate in prefixes {
+            let m = measure(&filter_chain(&candidate, probe.clone()), sample)?;
+            if m.size < best_size {
+                best_size = m.size;
+                prefix = candidate;
+            }
+        }
+    }
+
+    // 再在选定的前置过滤器下测量各个预设
+    let mut results: Vec<(u32, LzmaOptionsLzma, Measurement)> = Vec::new();
+    for preset in CANDIDATE_PRESETS {
+        let opt = sample_options(preset, sample.len())?;
+        let m = match results.iter().find(|(_, o, _)| same_options(o, &opt)) {
+            Some(&(_, _, m)) => m,
+            None => measure(&filter_chain(&prefix, opt.clone()), sample)?,
+        };
+        results.push((preset, opt, m));
+    }
+
+    let speed = |m: &Measurement| (sample.len() as u128 * 1_000_000_000 / m.nanos) as u64;
+    let ratio = |m: &Measurement| m.size as f64 / sample.len() as f64;
+
+    // 相同时保留靠前（更快、字典更小）的候选
+    let mut met = None;
+    let mut fallback = 0;
+    for (i, (_, _, m)) in results.iter().enumerate() {
+        let best = |j: Option<usize>| j.map(|j| &results[j].2);
+        match target {
+            LzmaAutoTarget::Speed(min_speed) => {
+                if speed(m) >= min_speed && best(met).map_or(true, |b| m.size < b.size) {
+                    met = Some(i);
+                }
+                if speed(m) > speed(&results[fallback].2) {
+                    fallback = i;
+                }
+            }
+            LzmaAutoTarget::Ratio(max_ratio) => {
+                if ratio(m) <= max_ratio && best(met).map_or(true, |b| m.nanos < b.nanos) {
+                    met = Some(i);
+                }
+                if m.size < results[fallback].2.size {
+                    fallback = i;
+                }
+            }
+        }
+    }
+
+    let target_met = met.is_some();
+    let (preset, _, m) = &results[met.unwrap_or(fallback)];
+    let mut opt = LzmaOptionsLzma::default();
+    lzma_lzma_preset(&mut opt, *preset);
+
+    Ok(LzmaAutoChoice {
+        preset: *preset,
+        filters: filter_chain(&prefix, opt),
+        speed: speed(m),
+        ratio: ratio(m),
+        target_met,
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::{lzma_auto_level, LzmaAutoTarget};
+    use crate::api::{
+        LzmaOptionsType, LzmaRet, LZMA_FILTER_DELTA, LZMA_FILTER_LZMA2, LZMA_VLI_UNKNOWN,
+    };
+    use crate::lzma::lzma_lzma_preset;
+    use crate::test_util::{pcm16, words};
+
+    /// 达不到目标时，压缩率目标退而选压缩率最好的候选
+    #[test]
+    fn ratio_target() {
+        let sample = words(20_000);
+
+        let best = lzma_auto_level(&sample, LzmaAutoTarget::Ratio(0.0)).unwrap();
+        assert!(!best.target_met);
+
+        for target in [1.0, best.ratio * 1.2, best.ratio] {
+            let choice = lzma_auto_level(&sample, LzmaAutoTarget::Ratio(target)).unwrap();
+            assert!(choice.target_met, "{}", target);
+            assert!(choice.ratio <= target, "{} > {}", choice.ratio, target);
+            assert!(choice.ratio >= best.ratio);
+        }
+
+        // 选出的过滤器链使用预设原本的字典大小
+        let mut opt = Default::default();
+        lzma_lzma_preset(&mut opt, best.preset);
+        assert_eq!(best.filters.len(), 2);
+        assert_eq!(best.filters[0].id, LZMA_FILTER_LZMA2);
+        assert_eq!(
+            best.filters[0].options,
+            Some(LzmaOptionsType::LzmaOptionsLzma(opt))
+        );
+        assert_eq!(best.filters[1].id, LZMA_VLI_UNKNOWN);
+    }
+
+    /// 达不到目标时，速度目标退而选最快的候选
+    #[test]
+    fn speed_target() {
+        let sample = words(20_000);
+
+        let easy = lzma_auto_level(&sample, LzmaAutoTarget::Speed(1)).unwrap();
+        assert!(easy.target_met);
+        assert!(easy.speed >= 1);
+
+        let fastest = lzma_auto_level(&sample, LzmaAutoTarget::Speed(u64::MAX)).unwrap();
+        assert!(!fastest.target_met);
+        // 速度都能达到时选压缩率最好的，它不会比最快的候选更差
+        assert!(easy.ratio <= fastest.ratio);
+    }
+
+    /// PCM 数据在 LZMA2 前面加上距离为 4 的 Delta 过滤器
+    #[test]
+    fn delta_prefix() {
+        let choice = lzma_auto_level(&pcm16(2, 16 << 10), LzmaAutoTarget::Ratio(1.0)).unwrap();
+        assert_eq!(choice.filters.len(), 3);
+        assert_eq!(choice.filters[0].id, LZMA_FILTER_DELTA);
+        match &choice.filters[0].options {
+            Some(LzmaOptionsType::Delta(delta)) => assert_eq!(delta.dist, 4),
+            options => panic!("{:?}", options),
+        }
+        assert_eq!(choice.filters[1].id, LZMA_FILTER_LZMA2);
+    }
+
+    #[test]
+    fn empty_sample() {
+        assert_eq!(
+            lzma_auto_level(&[], LzmaAutoTarget::Ratio(1.0)).unwrap_err(),
+            LzmaRet::BufError
+        );
+    }
+}
@@ -8,19 +8,23 @@ use common::my_min;
 
 use crate::{
     api::{
-        LzmaAction, LzmaAllocator, LzmaBlock, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType,
-        LzmaRet, LZMA_CHECK_ID_MAX, LZMA_CHECK_SIZE_MAX, LZMA_DICT_SIZE_MIN, LZMA_FILTER_LZMA2,
-        LZMA_VLI_BYTES_MAX, LZMA_VLI_UNKNOWN,
+        LzmaAction, LzmaAllocator, LzmaBlock, LzmaCancelToken, LzmaFilter, LzmaOptionsLzma,
+        LzmaOptionsType, LzmaRet, LZMA_CHECK_ID_MAX, LZMA_CHECK_SIZE_MAX, LZMA_DICT_SIZE_MIN,
+        LZMA_FILTER_ENCRYPT, LZMA_FILTER_LZMA2, LZMA_VLI_BYTES_MAX, LZMA_VLI_UNKNOWN,
     },
     check::{
         lzma_check_finish, lzma_check_init, lzma_check_is_supported, lzma_check_size,
         lzma_check_update, LzmaCheckState,
     },
     common::{lzma_block_header_encode, lzma_block_header_size},
+    encrypt::encrypt_overhead,
     lzma::{LZMA2_CHUNK_MAX, LZMA2_HEADER_UNCOMPRESSED},
 };
 
-use super::{lzma_next_end, lzma_raw_encoder_init, LzmaNextCoder, COMPRESSED_SIZE_MAX};
+use super::{
+    lzma_next_end, lzma_raw_encoder_init, LzmaNextCoder, COMPRESSED_SIZE_MAX,
+    LZMA_CANCEL_CHECK_SIZE,
+};
 
 /// Maximum size of block headers, aligned to 4 bytes
 
@@ -72,6 +76,49 @@ pub fn lzma_block_buffer_bound(uncompressed_size: usize) -> usize {
     ret as usize
 }
 
+fn filters_encrypted(filters: &[LzmaFilter]) -> bool {
+    filters
+        .iter()
+        .take_while(|f| f.id != LZMA_VLI_UNKNOWN)
+        .any(|f| f.id == LZMA_FILTER_ENCRYPT)
+}
+
+// 计算含加密过滤器的过滤器链的压缩数据边界
+//
+// 加密的 Block 不能退回到未压缩的 LZMA2 块，所以边界要能容纳不可压缩数据
+// 经过整个过滤器链后的大小。
+fn encrypt_bound(filters: &[LzmaFilter], uncompressed_size: u64) -> u64 {
+    // 加密过滤器之前的去重过滤器最多使数据变长约 2%
+    let lzma2_size = lzma2_bound(uncompressed_size + uncompressed_size / 32);
+    if lzma2_size == 0 {
+        return 0;
+    }
+
+    let bound = lzma2_size + encrypt_overhead(lzma2_size);
+
+    // 加密过滤器后面的 LZMA2 无法再压缩加密后的数据
+    let last = filters.iter().take_while(|f| f.id != LZMA_VLI_UNKNOWN).last();
+    if last.map(|f| f.id) == Some(LZMA_FILTER_ENCRYPT) {
+        bound
+    } else {
+        lzma2_bound(bound)
+    }
+}
+
+// 计算用 filters 编码时 LZMA 块缓冲区的边界
+pub fn lzma_block_buffer_bound_filters(filters: &[LzmaFilter], uncompressed_size: u64) -> u64 {
+    if !filters_encrypted(filters) {
+        return lzma_block_buffer_bound64(uncompressed_size);
+    }
+
+    let size = encrypt_bound(filters, uncompressed_size);
+    if size == 0 {
+        return 0;
+    }
+
+    HEADERS_BOUND as u64 + ((size + 3) & !3)
+}
+
 fn block_encode_uncompressed(
     block: &mut LzmaBlock,
     input: &[u8],
@@ -178,6 +225,7 @@ fn block_encode_normal(
     out_pos: &mut usize,
     out_size: usize,
     compressed_size_bound: u64,
+    cancel: Option<&LzmaCancelToken>,
 ) -> LzmaRet {
     // 获取块头部大小
     let ret: LzmaRet = lzma_block_header_size(block);
@@ -206,16 +254,35 @@ fn block_encode_normal(
     if ret == LzmaRet::Ok {
         let mut in_pos: usize = 0;
         if let Some(cpde) = raw_encoder.code {
-            ret = cpde(
-                &mut raw_encoder.coder.as_mut().unwrap(),
-                input,
-                &mut in_pos,
-                insize,
-                output,
-                out_pos,
-                out_size,
-                LzmaAction::Finish,
-            );
+            // 有取消令牌时分段送入输入，每段之前检查一次令牌
+            loop {
+                let (in_end, action) = match cancel {
+                    Some(cancel) if cancel.is_canceled() => {
+                        ret = LzmaRet::Canceled;
+                        break;
+                    }
+                    Some(_) if insize - in_pos > LZMA_CANCEL_CHECK_SIZE => {
+                        (in_pos + LZMA_CANCEL_CHECK_SIZE, LzmaAction::Run)
+                    }
+                    _ => (insize, LzmaAction::Finish),
+                };
+
+                ret = cpde(
+                    &mut raw_encoder.coder.as_mut().unwrap(),
+                    input,
+                    &mut in_pos,
+                    in_end,
+                    output,
+                    out_pos,
+                    out_size,
+                    action,
+                );
+
+                // 输入没有用完说明输出缓冲区已满，下面按 BufError 处理
+                if action == LzmaAction::Finish || ret != LzmaRet::Ok || in_pos < in_end {
+                    break;
+                }
+            }
         }
     }
 
@@ -265,6 +332,7 @@ fn block_buffer_encode(
     out_pos: &mut usize,
     out_size: usize,
     try_to_compress: bool,
+    cancel: Option<&LzmaCancelToken>,
 ) -> LzmaRet {
     // 验证参数
     if Some(block.clone()).is_none()
@@ -314,7 +382,12 @@ fn block_buffer_encode(
 
     // 初始化 uncompressed_size 用于内部 bound 计算
     block.uncompressed_size = in_size as u64;
-    block.compressed_size = lzma2_bound(in_size as u64);
+    let encrypted = filters_encrypted(&block.filters);
+    block.compressed_size = if encrypted {
+        encrypt_bound(&block.filters, in_size as u64)
+    } else {
+        lzma2_bound(in_size as u64)
+    };
     // Use LZMA_VLI_UNKNOWN for header encoding so both size fields are omitted
     // from the block header (the Index provides block boundaries)
     let compressed_size_bound = block.compressed_size;
@@ -336,11 +409,13 @@ fn block_buffer_encode(
             out_pos,
             out_size,
             compressed_size_bound,
+            cancel,
         );
     }
 
     if ret != LzmaRet::Ok && ret != LzmaRet::StreamEnd {
-        if ret != LzmaRet::BufError {
+        // 加密的 Block 不能以明文保存
+        if ret != LzmaRet::BufError || encrypted {
             return ret;
         }
 
@@ -394,7 +469,33 @@ pub fn lzma_block_buffer_encode(
 ) -> LzmaRet {
     // 调用内部实现
     block_buffer_encode(
-        block, allocator, input, input_size, output, out_pos, out_size, true,
+        block, allocator, input, input_size, output, out_pos, out_size, true, None,
+    )
+}
+
+/// 与 lzma_block_buffer_encode() 相同，但在压缩过程中检查取消令牌
+///
+/// 令牌被取消时返回 `LzmaRet::Canceled`，供多线程编码器的工作线程使用。
+pub fn lzma_block_buffer_encode_cancel(
+    block: &mut LzmaBlock,
+    allocator: &LzmaAllocator,
+    input: &mut Vec<u8>,
+    input_size: usize,
+    output: &mut Vec<u8>,
+    out_pos: &mut usize,
+    out_size: usize,
+    cancel: &LzmaCancelToken,
+) -> LzmaRet {
+    block_buffer_encode(
+        block,
+        allocator,
+        input,
+        input_size,
+        output,
+        out_pos,
+        out_size,
+        true,
+        Some(cancel),
     )
 }
 
@@ -417,5 +518,6 @@ pub fn lzma_block_uncomp_encode(
         out_pos,
         out_size,
         true,
+        None,
     )
 }
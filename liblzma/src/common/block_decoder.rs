@@ -330,13 +330,10 @@ pub fn lzma_block_decoder_init(next: &mut LzmaNextCoder, block: &mut LzmaBlock)
         false
     };
 
-    // 初始化滤波链
-    lzma_raw_decoder_init(&mut coder.next, &block.filters);
-
+    // 初始化滤波链，加密过滤器找不到密钥等错误要返回给调用者
     // 在解码完成后，将结果写回原始的 block 参数
     // 这里我们需要在 block_decode 函数中实现这个功能
-    // 暂时先返回成功
-    LzmaRet::Ok
+    lzma_raw_decoder_init(&mut coder.next, &block.filters)
 }
 
 pub fn lzma_block_decoder<'a>(strm: &mut LzmaStream<'a>, block: &'a mut LzmaBlock) -> LzmaRet {
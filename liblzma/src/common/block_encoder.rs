@@ -199,12 +199,7 @@ fn block_encode(
                 if let Some(block) = coder.block.as_ref() {
                     let check_size = lzma_check_size(block.check.clone());
 
-                    // 复制校验数据到输出缓冲区
-                    // 在这个地方，先将coder.check.buffer.u64中的数据转移保存到u8中
-                    for i in 0..8 {
-                        let bytes_ne = &mut coder.check.buffer.u64[i].to_ne_bytes();
-                        &mut coder.check.buffer.u8[i * 8..(i + 1) * 8].copy_from_slice(bytes_ne);
-                    }
+                    // 复制校验数据到输出缓冲区，lzma_check_finish() 已经把它写入 buffer.u8
                     lzma_bufcpy(
                         &mut coder.check.buffer.u8[..check_size as usize],
                         &mut coder.pos,
@@ -388,3 +383,35 @@ fn lzma_block_encoder<'a>(stream: &mut LzmaStream<'a>, block: &'a LzmaBlock) ->
 
     LzmaRet::Ok
 }
+#[cfg(test)]
+mod tests {
+    use crate::api::{LzmaCheck, LzmaRet, LzmaStream, LZMA_FILTER_LZMA2};
+    use crate::common::stream_decoder::lzma_stream_decoder;
+    use crate::common::stream_encoder::lzma_stream_encoder;
+    use crate::test_util::{code_all, lzma_filters, pseudo_random};
+
+    /// 每种完整性校验的 Block 都能通过解码器的检查
+    #[test]
+    fn check_round_trip() {
+        let data = pseudo_random(100_000, 3);
+        let filters = lzma_filters(LZMA_FILTER_LZMA2, 1);
+        for check in [
+            LzmaCheck::None,
+            LzmaCheck::Crc32,
+            LzmaCheck::Crc64,
+            LzmaCheck::Sha256,
+        ] {
+            let mut strm = LzmaStream::default();
+            assert_eq!(lzma_stream_encoder(&mut strm, &filters, check), LzmaRet::Ok);
+            let encoded = code_all(&mut strm, &data).unwrap();
+
+            let mut strm = LzmaStream::default();
+            assert_eq!(lzma_stream_decoder(&mut strm, u64::MAX, 0), LzmaRet::Ok);
+            assert!(
+                code_all(&mut strm, &encoded) == Ok(data.clone()),
+                "{:?}",
+                check
+            );
+        }
+    }
+}
@@ -7,7 +7,7 @@
 use common::read32le;
 
 use crate::{
-    api::{LzmaBlock, LzmaRet, LZMA_CHECK_ID_MAX, LZMA_VLI_UNKNOWN},
+    api::{LzmaBlock, LzmaRet, LZMA_CHECK_ID_MAX, LZMA_FILTERS_MAX, LZMA_VLI_UNKNOWN},
     check::lzma_crc32,
     lzma_block_header_size_decode,
 };
@@ -16,22 +16,27 @@ use super::{
     lzma_block_unpadded_size, lzma_filter_flags_decode, lzma_filters_free, lzma_vli_decode,
 };
 
-pub fn lzma_block_header_decode(block: &mut LzmaBlock, input: &mut [u8]) -> LzmaRet {
+/// 解码块头
+///
+/// 调用者需要先设置 block.version、block.header_size（用
+/// lzma_block_header_size_decode! 从 input[0] 得到）和 block.check，
+/// block.filters 至少要有 LZMA_FILTERS_MAX + 1 个元素。input 至少包含整个块头。
+pub fn lzma_block_header_decode(block: &mut LzmaBlock, input: &[u8]) -> LzmaRet {
     // 注意：我们认为以下情况头部数据损坏：
     // - CRC32 不匹配
     // - 可变长度整数无效或超过63位
     // - 头部太小，无法包含声明的信息
 
     // 检查 block 的 filters 是否已初始化
-    if block.filters.is_empty() {
+    if block.filters.len() <= LZMA_FILTERS_MAX {
         return LzmaRet::ProgError;
     }
 
     // 初始化过滤器选项数组
     // 这样即使函数出错，调用者也可以安全地释放选项
-    for i in 0..5 {
-        block.filters[i as usize].id = LZMA_VLI_UNKNOWN;
-        block.filters[i].options = None;
+    for filter in &mut block.filters[..=LZMA_FILTERS_MAX] {
+        filter.id = LZMA_VLI_UNKNOWN;
+        filter.options = None;
     }
 
     // 支持版本0和1。如果指定了更新的版本，需要降级
@@ -45,7 +50,9 @@ pub fn lzma_block_header_decode(block: &mut LzmaBlock, input: &mut [u8]) -> Lzma
 
     // 验证块头大小和校验类型
     // 调用者必须已经设置这些，所以如果此测试失败就是编程错误
-    if lzma_block_header_size_decode!(input[0]) != block.header_size
+    if input.is_empty()
+        || lzma_block_header_size_decode!(input[0]) != block.header_size
+        || input.len() < block.header_size as usize
         || (block.check.clone() as u32) > LZMA_CHECK_ID_MAX
     {
         return LzmaRet::ProgError;
@@ -73,7 +80,7 @@ pub fn lzma_block_header_decode(block: &mut LzmaBlock, input: &mut [u8]) -> Lzma
     if (input[1] & 0x40) != 0 {
         let ret = lzma_vli_decode(
             &mut block.compressed_size,
-            Some(&mut 0),
+            None,
             input,
             &mut in_pos,
             in_size as usize,
@@ -95,7 +102,7 @@ pub fn lzma_block_header_decode(block: &mut LzmaBlock, input: &mut [u8]) -> Lzma
     if (input[1] & 0x80) != 0 {
         let ret = lzma_vli_decode(
             &mut block.uncompressed_size,
-            Some(&mut 0),
+            None,
             input,
             &mut in_pos,
             in_size as usize,
@@ -135,3 +142,46 @@ pub fn lzma_block_header_decode(block: &mut LzmaBlock, input: &mut [u8]) -> Lzma
 
     LzmaRet::Ok
 }
+
+#[cfg(test)]
+mod tests {
+    use super::lzma_block_header_decode;
+    use crate::api::{
+        LzmaBlock, LzmaCheck, LzmaFilter, LzmaRet, LZMA_FILTERS_MAX, LZMA_FILTER_LZMA2,
+    };
+    use crate::common::{lzma_block_header_encode, lzma_block_header_size};
+    use crate::lzma_block_header_size_decode;
+    use crate::test_util::lzma_filters;
+
+    /// 带有压缩大小和未压缩大小的块头编码后解码得到相同的值
+    #[test]
+    fn header_round_trip() {
+        for (compressed, uncompressed) in [(1, 0), (12_345, 67_890), (1 << 40, (1 << 62) + 5)] {
+            let mut block = LzmaBlock {
+                version: 1,
+                check: LzmaCheck::Crc64,
+                compressed_size: compressed,
+                uncompressed_size: uncompressed,
+                filters: lzma_filters(LZMA_FILTER_LZMA2, 1),
+                ..Default::default()
+            };
+            assert_eq!(lzma_block_header_size(&mut block), LzmaRet::Ok);
+            let mut header = vec![0u8; block.header_size as usize];
+            assert_eq!(lzma_block_header_encode(&block, &mut header), LzmaRet::Ok);
+
+            let mut decoded = LzmaBlock {
+                version: 1,
+                header_size: lzma_block_header_size_decode!(header[0]),
+                check: LzmaCheck::Crc64,
+                filters: (0..=LZMA_FILTERS_MAX)
+                    .map(|_| LzmaFilter::default())
+                    .collect(),
+                ..Default::default()
+            };
+            assert_eq!(lzma_block_header_decode(&mut decoded, &header), LzmaRet::Ok);
+            assert_eq!(decoded.compressed_size, compressed);
+            assert_eq!(decoded.uncompressed_size, uncompressed);
+            assert_eq!(decoded.filters[0].id, LZMA_FILTER_LZMA2);
+        }
+    }
+}
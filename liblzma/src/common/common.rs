@@ -13,12 +13,16 @@ use std::{
 use crate::common::LzmaIndex;
 use crate::{
     api::{
-        lzma_version_string_c, LzmaAction, LzmaBlock, LzmaCheck, LzmaFilter, LzmaOptionsLzma,
-        LzmaOptionsType, LzmaReservedEnum, LzmaRet, LzmaStream, LzmaVli, LZMA_CONCATENATED,
-        LZMA_FAIL_FAST, LZMA_IGNORE_CHECK, LZMA_TELL_ANY_CHECK, LZMA_TELL_NO_CHECK,
-        LZMA_TELL_UNSUPPORTED_CHECK, LZMA_VERSION, LZMA_VERSION_COMMIT, LZMA_VERSION_MAJOR,
-        LZMA_VERSION_MINOR, LZMA_VERSION_PATCH, LZMA_VERSION_STABILITY_STRING, LZMA_VLI_UNKNOWN,
+        lzma_version_string_c, LzmaAction, LzmaBlock, LzmaCancelToken, LzmaCheck, LzmaFilter,
+        LzmaOptionsLzma, LzmaOptionsType, LzmaProgress, LzmaProgressCallback, LzmaReservedEnum,
+        LzmaRet, LzmaStream, LzmaVli, LZMA_CONCATENATED, LZMA_FAIL_FAST, LZMA_IGNORE_CHECK,
+        LZMA_TELL_ANY_CHECK, LZMA_TELL_NO_CHECK, LZMA_TELL_UNSUPPORTED_CHECK, LZMA_VERSION,
+        LZMA_VERSION_COMMIT, LZMA_VERSION_MAJOR, LZMA_VERSION_MINOR, LZMA_VERSION_PATCH,
+        LZMA_VERSION_STABILITY_STRING, LZMA_VLI_UNKNOWN,
     },
+    custom::LzmaCustomCoder,
+    dedup::{LzmaDedupDecoder, LzmaDedupEncoder},
+    encrypt::{LzmaEncryptDecoder, LzmaEncryptEncoder},
     delta::LzmaDeltaCoder,
     lz::{LzmaDecoder, LzmaEncoder},
     lzma::LzmaLzma2Decoder,
@@ -29,11 +33,13 @@ use common::{memzero, my_max};
 use super::{
     LzmaAloneDecoder, LzmaAloneEncoder, LzmaAutoCoder, LzmaBlockDecoder, LzmaBlockEncoder,
     LzmaFileInfoCoder, LzmaIndexDecoder, LzmaIndexEncoder, LzmaLzipCoder, LzmaMicrolzmaDecoder,
-    LzmaMicrolzmaEncoder, LzmaStreamDecoder, LzmaStreamEncoder,
+    LzmaMicrolzmaEncoder, LzmaRawDictCoder, LzmaStreamDecoder, LzmaStreamEncoder,
 };
 
 pub const LZMA_ACTION_MAX: usize = LzmaAction::FullBarrier as usize;
 pub const LZMA_BUFFER_SIZE: usize = 4096;
+/// 有取消令牌或进度回调函数时，每处理这么多输入或输出检查一次令牌并调用回调函数
+pub const LZMA_CANCEL_CHECK_SIZE: usize = 1 << 18;
 pub const LZMA_THREADS_MAX: u32 = 16384;
 
 // 用于存储与 lzma_strm_init、lzma_code 和 lzma_end 函数相关的内部数据。
@@ -47,6 +53,10 @@ pub struct LzmaInternal {
     pub avail_in: usize,
     pub supported_actions: [bool; LZMA_ACTION_MAX + 1],
     pub allow_buf_error: bool,
+    // lzma_set_cancel_token() 设置的取消令牌，lzma_code() 中定期检查
+    pub cancel: Option<LzmaCancelToken>,
+    // lzma_set_progress_callback() 设置的进度回调函数
+    pub progress: Option<LzmaProgressCallback>,
 }
 
 impl LzmaInternal {
@@ -57,6 +67,8 @@ impl LzmaInternal {
             avail_in: 0,
             supported_actions: [false; LZMA_ACTION_MAX + 1],
             allow_buf_error: false,
+            cancel: None,
+            progress: None,
         }
     }
 }
@@ -108,15 +120,22 @@ pub enum CoderType {
     StreamDecoder(LzmaStreamDecoder),
     StreamEncoder(LzmaStreamEncoder),
     DeltaCoder(LzmaDeltaCoder),
+    DedupEncoder(LzmaDedupEncoder),
+    DedupDecoder(LzmaDedupDecoder),
+    EncryptEncoder(LzmaEncryptEncoder),
+    EncryptDecoder(LzmaEncryptDecoder),
+    CustomCoder(LzmaCustomCoder),
     LzDecoder(LzmaDecoder),
     LzEncoder(LzmaEncoder),
     SimpleCoder(LzmaSimpleCoder),
     MtStreamEncoder(super::stream_encoder_mt::MtStreamEncoder),
+    MtChunkedEncoder(super::stream_encoder_mt_chunked::MtChunkedEncoder),
+    RawDictCoder(LzmaRawDictCoder),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum NextCoderInitFunction {
-    AloneDecoder(fn(&mut LzmaNextCoder, u64, bool) -> LzmaRet),
+    AloneDecoder(fn(&mut LzmaNextCoder, u64, u32) -> LzmaRet),
     AutoDecoder(fn(&mut LzmaNextCoder, u64, u32) -> LzmaRet),
     RawDecoder(fn(&mut LzmaNextCoder, &[LzmaFilter]) -> LzmaRet),
     IndexDecoder(fn(&mut LzmaNextCoder, Option<Arc<Mutex<Arc<Mutex<LzmaIndex>>>>>, u64) -> LzmaRet),
@@ -139,6 +158,7 @@ pub enum NextCoderInitFunction {
     MicroLzamEncoder(fn(&mut LzmaNextCoder, &LzmaOptionsLzma) -> LzmaRet),
     StreamDecoder(fn(&mut LzmaNextCoder, u64, u32) -> LzmaRet),
     StreamEncoder(fn(&mut LzmaNextCoder, Option<&[LzmaFilter]>, LzmaCheck) -> LzmaRet),
+    RawDict(fn(&mut LzmaNextCoder, &[LzmaFilter], &[u8], bool) -> LzmaRet),
 }
 
 #[repr(C)]
@@ -161,6 +181,8 @@ pub struct LzmaNextCoder {
     // 指向获取进度信息的函数指针。
     pub get_progress:
         Option<fn(coder: &mut CoderType, progress_in: &mut u64, progress_out: &mut u64)>,
+    // 指向返回当前 Block 序号的函数指针，用于进度回调。
+    pub get_block: Option<fn(coder: &mut CoderType) -> u64>,
     // 指向返回完整性校验类型的函数指针。
     pub get_check: Option<fn(coder: &mut CoderType) -> LzmaCheck>,
     // 指向设置或获取内存配置的函数指针。
@@ -212,6 +234,7 @@ pub fn lzma_next_coder_init() -> LzmaNextCoder {
         code: None,
         end: None,
         get_progress: None,
+        get_block: None,
         get_check: None,
         memconfig: None,
         update: None,
@@ -227,6 +250,7 @@ impl Default for LzmaNextCoder {
             code: None,
             end: None,
             get_progress: None,
+            get_block: None,
             get_check: None,
             memconfig: None,
             update: None,
@@ -437,6 +461,9 @@ pub fn lzma_strm_init(strm: Option<&mut LzmaStream>) -> LzmaRet {
             if strm.internal.borrow_mut().as_mut().is_none() {
                 // strm.internal.borrow_mut().as_mut().unwrap().next = Some(Box::new(LzmaNextCoder::default()));
                 *strm.internal.borrow_mut() = Some(LzmaInternal::new());
+            }
+            // 初始化之前可能已经用 lzma_set_cancel_token() 等创建了 internal
+            if strm.internal.borrow().as_ref().unwrap().next.is_none() {
                 strm.internal.borrow_mut().as_mut().unwrap().next =
                     Some(Box::new(LzmaNextCoder::default()));
             }
@@ -544,8 +571,12 @@ pub fn lzma_code(strm: &mut LzmaStream, action: LzmaAction) -> LzmaRet {
         }
     }
 
-    let mut in_pos = 0;
-    let mut out_pos = 0;
+    if internal.cancel.as_ref().is_some_and(|c| c.is_canceled()) {
+        return lzma_code_cancel(internal);
+    }
+
+    let mut in_pos: usize = 0;
+    let mut out_pos: usize = 0;
     let mut ret = LzmaRet::Ok;
 
     // 获取 next_out 的可变引用
@@ -554,19 +585,64 @@ pub fn lzma_code(strm: &mut LzmaStream, action: LzmaAction) -> LzmaRet {
     let next_out_pos = strm.next_out_pos;
 
     // 执行编码/解码操作
-    if let Some(next) = internal.next.as_mut() {
-        if let Some(code) = next.code {
-            ret = code(
-                &mut next.coder.as_mut().unwrap(),
-                strm.next_in,
-                &mut in_pos,
-                strm.avail_in.get(),
-                &mut next_out[next_out_pos as usize..],
-                &mut out_pos,
-                strm.avail_out.get(),
-                action,
+    //
+    // 设置了取消令牌或进度回调函数时，每次最多处理 LZMA_CANCEL_CHECK_SIZE 字节的
+    // 输入和输出，每段之后调用回调函数并检查令牌，一次调用给出很大的缓冲区时
+    // 也能及时取消。输入还没有全部交给编码器或解码器时，这一段使用 LZMA_RUN。
+    let avail_in = strm.avail_in.get();
+    let avail_out = strm.avail_out.get();
+    let step = if internal.cancel.is_some() || internal.progress.is_some() {
+        LZMA_CANCEL_CHECK_SIZE
+    } else {
+        usize::MAX
+    };
+    loop {
+        let in_end = avail_in.min(in_pos.saturating_add(step));
+        let out_end = avail_out.min(out_pos.saturating_add(step));
+        let step_action = if in_end < avail_in {
+            LzmaAction::Run
+        } else {
+            action.clone()
+        };
+        let (in_start, out_start) = (in_pos, out_pos);
+
+        if let Some(next) = internal.next.as_mut() {
+            if let Some(code) = next.code {
+                ret = code(
+                    &mut next.coder.as_mut().unwrap(),
+                    strm.next_in,
+                    &mut in_pos,
+                    in_end,
+                    &mut next_out[next_out_pos as usize..],
+                    &mut out_pos,
+                    out_end,
+                    step_action,
+                );
+            }
+        }
+
+        if in_pos > in_start || out_pos > out_start || ret == LzmaRet::StreamEnd {
+            lzma_code_progress(
+                internal,
+                strm.total_in.get() + in_pos as u64,
+                strm.total_out.get() + out_pos as u64,
             );
         }
+
+        if ret != LzmaRet::Ok {
+            break;
+        }
+        if internal.cancel.as_ref().is_some_and(|c| c.is_canceled()) {
+            ret = LzmaRet::Canceled;
+            break;
+        }
+
+        // 这一段的输入用完或输出缓冲区满了，而整个缓冲区还有剩余时继续
+        let more_in = in_pos == in_end && in_end < avail_in;
+        let more_out = out_pos == out_end && out_end < avail_out;
+        if !(more_in || more_out) || (in_pos == in_start && out_pos == out_start) {
+            break;
+        }
     }
 
     // 更新输入状态
@@ -641,6 +717,7 @@ pub fn lzma_code(strm: &mut LzmaStream, action: LzmaAction) -> LzmaRet {
             internal.allow_buf_error = false;
             ret
         }
+        LzmaRet::Canceled => lzma_code_cancel(internal),
         _ => {
             assert!(ret != LzmaRet::BufError);
             internal.sequence = Sequence::Error;
@@ -649,6 +726,38 @@ pub fn lzma_code(strm: &mut LzmaStream, action: LzmaAction) -> LzmaRet {
     }
 }
 
+// 调用进度回调函数，total_in 和 total_out 是到目前为止的输入和输出字节数
+fn lzma_code_progress(internal: &mut LzmaInternal, total_in: u64, total_out: u64) {
+    if let (Some(callback), Some(next)) = (internal.progress.as_mut(), internal.next.as_mut()) {
+        let mut progress = LzmaProgress {
+            progress_in: total_in,
+            progress_out: total_out,
+            block: 0,
+        };
+        if let Some(coder) = next.coder.as_mut() {
+            if let Some(get_progress) = next.get_progress {
+                get_progress(coder, &mut progress.progress_in, &mut progress.progress_out);
+            }
+            if let Some(get_block) = next.get_block {
+                progress.block = get_block(coder);
+            }
+        }
+        (callback.0)(&progress);
+    }
+}
+
+// 取消后立即释放编码器或解码器，多线程编码器在这里等待工作线程退出
+fn lzma_code_cancel(internal: &mut LzmaInternal) -> LzmaRet {
+    internal.sequence = Sequence::Error;
+    if let Some(next) = internal.next.as_mut() {
+        if let (Some(end), Some(coder)) = (next.end, next.coder.as_mut()) {
+            end(coder);
+        }
+        **next = lzma_next_coder_init();
+    }
+    LzmaRet::Canceled
+}
+
 pub fn lzma_end(strm: Option<&mut LzmaStream>) {
     if let Some(stream) = strm {
         if let Some(mut internal) = stream.internal.borrow_mut().take() {
@@ -690,6 +799,33 @@ pub fn lzma_get_progress(strm: &mut LzmaStream, progress_in: &mut u64, progress_
     }
 }
 
+/// 设置取消令牌，None 表示不再检查
+///
+/// 令牌在调用 lzma_code() 时以及其中每处理 LZMA_CANCEL_CHECK_SIZE 字节的输入或
+/// 输出之后检查。多线程编码器在初始化时取得令牌并交给工作线程，所以应当在
+/// 初始化编码器之前设置；之后设置的令牌不影响工作线程。
+pub fn lzma_set_cancel_token(strm: &mut LzmaStream, token: Option<LzmaCancelToken>) {
+    strm.internal
+        .borrow_mut()
+        .get_or_insert_with(LzmaInternal::new)
+        .cancel = token;
+}
+
+/// 设置进度回调函数，None 表示取消回调
+///
+/// 回调函数在 lzma_code() 处理了输入或产生了输出之后调用，给出的缓冲区很大时
+/// 每处理 LZMA_CANCEL_CHECK_SIZE 字节调用一次。可以在初始化编码器或解码器
+/// 之前或之后设置。
+pub fn lzma_set_progress_callback(
+    strm: &mut LzmaStream,
+    callback: Option<Box<dyn FnMut(&LzmaProgress) + Send>>,
+) {
+    strm.internal
+        .borrow_mut()
+        .get_or_insert_with(LzmaInternal::new)
+        .progress = callback.map(LzmaProgressCallback);
+}
+
 pub fn lzma_get_check(strm: &mut LzmaStream) -> LzmaCheck {
     if let Some(get_check) = strm
         .internal
@@ -807,3 +943,116 @@ pub const LZMA_SUPPORTED_FLAGS: u32 = LZMA_TELL_NO_CHECK
     | LZMA_IGNORE_CHECK
     | LZMA_CONCATENATED
     | LZMA_FAIL_FAST;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        lzma_code, lzma_set_cancel_token, lzma_set_progress_callback, LZMA_CANCEL_CHECK_SIZE,
+    };
+    use crate::api::{
+        LzmaAction, LzmaCancelToken, LzmaCheck, LzmaProgress, LzmaRet, LzmaStream,
+        LZMA_FILTER_LZMA2,
+    };
+    use crate::common::stream_decoder::lzma_stream_decoder;
+    use crate::common::stream_encoder::lzma_stream_encoder;
+    use crate::test_util::{code_all, lzma_filters, pseudo_random};
+
+    /// 一次调用 lzma_code() 处理全部输入，输出缓冲区足够大
+    fn code_once<'a>(strm: &mut LzmaStream<'a>, input: &'a [u8], out_size: usize) -> LzmaRet {
+        strm.next_in = input;
+        strm.avail_in.set(input.len());
+        strm.next_out.borrow_mut().resize(out_size, 0);
+        strm.next_out_pos = 0;
+        strm.avail_out.set(out_size);
+        lzma_code(strm, LzmaAction::Finish)
+    }
+
+    /// 记录每次进度回调的参数
+    fn record_progress(strm: &mut LzmaStream) -> Arc<Mutex<Vec<LzmaProgress>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        lzma_set_progress_callback(strm, Some(Box::new(move |p| sink.lock().unwrap().push(*p))));
+        events
+    }
+
+    fn encoder(strm: &mut LzmaStream) -> LzmaRet {
+        lzma_stream_encoder(strm, &lzma_filters(LZMA_FILTER_LZMA2, 1), LzmaCheck::Crc32)
+    }
+
+    /// 有一定压缩率的数据
+    fn test_data(len: usize) -> Vec<u8> {
+        pseudo_random(len, 3).iter().map(|b| b & 0x0F).collect()
+    }
+
+    /// 在一次 lzma_code() 调用的中途取消编码和解码
+    #[test]
+    fn cancel_mid_call() {
+        let data = test_data(16 * LZMA_CANCEL_CHECK_SIZE);
+        let mut strm = LzmaStream::default();
+        assert_eq!(encoder(&mut strm), LzmaRet::Ok);
+        let encoded = code_all(&mut strm, &data).unwrap();
+
+        for encode in [true, false] {
+            let mut strm = LzmaStream::default();
+            let (input, ret) = if encode {
+                (&data, encoder(&mut strm))
+            } else {
+                (&encoded, lzma_stream_decoder(&mut strm, u64::MAX, 0))
+            };
+            assert_eq!(ret, LzmaRet::Ok);
+
+            // 第一次进度回调时取消
+            let token = LzmaCancelToken::new();
+            lzma_set_cancel_token(&mut strm, Some(token.clone()));
+            lzma_set_progress_callback(&mut strm, Some(Box::new(move |_| token.cancel())));
+
+            assert_eq!(
+                code_once(&mut strm, input, 2 * data.len()),
+                LzmaRet::Canceled
+            );
+            assert!(strm.total_in.get() <= LZMA_CANCEL_CHECK_SIZE as u64);
+            assert!(strm.total_out.get() <= LZMA_CANCEL_CHECK_SIZE as u64);
+            assert_eq!(lzma_code(&mut strm, LzmaAction::Finish), LzmaRet::ProgError);
+        }
+    }
+
+    /// 进度回调函数在一次处理很多数据的调用中也多次被调用，最后的进度是全部数据
+    #[test]
+    fn progress_callback_fires() {
+        let data = test_data(4 * LZMA_CANCEL_CHECK_SIZE + 1000);
+
+        let mut strm = LzmaStream::default();
+        assert_eq!(encoder(&mut strm), LzmaRet::Ok);
+        let events = record_progress(&mut strm);
+        assert_eq!(
+            code_once(&mut strm, &data, 2 * data.len()),
+            LzmaRet::StreamEnd
+        );
+        let encoded = strm.next_out.borrow()[..strm.next_out_pos as usize].to_vec();
+
+        let events = events.lock().unwrap();
+        assert!(events.len() >= 5, "{} events", events.len());
+        assert!(events
+            .windows(2)
+            .all(|w| w[0].progress_in <= w[1].progress_in));
+        let last = events.last().unwrap();
+        assert_eq!(last.progress_in, data.len() as u64);
+        assert_eq!(last.progress_out, encoded.len() as u64);
+        assert_eq!(last.block, 1);
+
+        // 解码时用小缓冲区多次调用
+        let mut strm = LzmaStream::default();
+        assert_eq!(lzma_stream_decoder(&mut strm, u64::MAX, 0), LzmaRet::Ok);
+        let events = record_progress(&mut strm);
+        assert_eq!(code_all(&mut strm, &encoded), Ok(data.clone()));
+
+        let events = events.lock().unwrap();
+        assert!(events.len() > 1);
+        let last = events.last().unwrap();
+        assert_eq!(last.progress_in, encoded.len() as u64);
+        assert_eq!(last.progress_out, data.len() as u64);
+        assert_eq!(last.block, 1);
+    }
+}
@@ -6,13 +6,14 @@
 
 use crate::{
     api::{
-        LzmaFilter, LzmaOptionsBcj, LzmaOptionsDelta, LzmaOptionsLzma, LzmaOptionsType, LzmaRet,
+        LzmaFilter, LzmaOptionsBcj, LzmaOptionsCustom, LzmaOptionsDedup, LzmaOptionsDelta, LzmaOptionsEncrypt, LzmaOptionsLzma, LzmaOptionsType, LzmaRet,
         LzmaVli, LZMA_FILTERS_MAX, LZMA_FILTER_ARM, LZMA_FILTER_ARM64, LZMA_FILTER_ARMTHUMB,
-        LZMA_FILTER_DELTA, LZMA_FILTER_IA64, LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT,
+        LZMA_FILTER_DEDUP, LZMA_FILTER_DELTA, LZMA_FILTER_ENCRYPT, LZMA_FILTER_IA64, LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT,
         LZMA_FILTER_LZMA2, LZMA_FILTER_POWERPC, LZMA_FILTER_SPARC, LZMA_FILTER_X86,
         LZMA_VLI_UNKNOWN,
     },
     common::LzmaFilterDecoder,
+    custom::lzma_custom_filter_find,
 };
 
 use super::{
@@ -173,7 +174,7 @@ struct FilterFeatures {
 use std::sync::LazyLock;
 
 /// 过滤器特性的静态数组，使用 LazyLock 动态初始化
-static FEATURES: LazyLock<[FilterFeatures; 12]> = LazyLock::new(|| {
+static FEATURES: LazyLock<[FilterFeatures; 14]> = LazyLock::new(|| {
     [
         FilterFeatures {
             id: LZMA_FILTER_LZMA1,
@@ -263,6 +264,22 @@ static FEATURES: LazyLock<[FilterFeatures; 12]> = LazyLock::new(|| {
             last_ok: false,
             changes_size: false,
         },
+        FilterFeatures {
+            id: LZMA_FILTER_DEDUP,
+            options: LzmaOptionsType::Dedup(LzmaOptionsDedup::default()),
+            options_size: std::mem::size_of::<LzmaOptionsDedup>(),
+            non_last_ok: true,
+            last_ok: false,
+            changes_size: true,
+        },
+        FilterFeatures {
+            id: LZMA_FILTER_ENCRYPT,
+            options: LzmaOptionsType::Encrypt(LzmaOptionsEncrypt::default()),
+            options_size: std::mem::size_of::<LzmaOptionsEncrypt>(),
+            non_last_ok: true,
+            last_ok: true,
+            changes_size: true,
+        },
         FilterFeatures {
             id: LZMA_VLI_UNKNOWN,
             options: LzmaOptionsType::Bcj(LzmaOptionsBcj::default()),
@@ -276,6 +293,29 @@ static FEATURES: LazyLock<[FilterFeatures; 12]> = LazyLock::new(|| {
 
 // 其他代码保持不变
 
+/// 查找过滤器特性，不在静态数组中时查找已注册的自定义过滤器
+///
+/// 自定义过滤器只能用作非最后过滤器。
+fn features_find(id: LzmaVli) -> Option<FilterFeatures> {
+    if id == LZMA_VLI_UNKNOWN {
+        return None;
+    }
+
+    if let Some(features) = FEATURES.iter().find(|f| f.id == id) {
+        return Some(features.clone());
+    }
+
+    let filter = lzma_custom_filter_find(id)?;
+    Some(FilterFeatures {
+        id,
+        options: LzmaOptionsType::Custom(LzmaOptionsCustom::default()),
+        options_size: std::mem::size_of::<LzmaOptionsCustom>(),
+        non_last_ok: true,
+        last_ok: false,
+        changes_size: filter.changes_size(),
+    })
+}
+
 pub fn lzma_filters_copy(src: &[LzmaFilter], real_dest: &mut [LzmaFilter]) -> LzmaRet {
     // println!("src {:#?}", src);
     if src.is_empty() || real_dest.is_empty() {
@@ -304,16 +344,13 @@ pub fn lzma_filters_copy(src: &[LzmaFilter], real_dest: &mut [LzmaFilter]) -> Lz
 
         if let Some(options) = &src[i].options {
             // 检查过滤器是否受支持
-            let mut j = 0;
-            while j < FEATURES.len() && src[i].id != FEATURES[j].id {
-                if FEATURES[j].id == LZMA_VLI_UNKNOWN {
-                    return LzmaRet::OptionsError;
-                }
-                j += 1;
-            }
+            let features = match features_find(src[i].id) {
+                Some(features) => features,
+                None => return LzmaRet::OptionsError,
+            };
 
             // 分配并复制选项
-            dest[i].options = Some(FEATURES[j].options.clone());
+            dest[i].options = Some(features.options);
             if dest[i].options.is_none() {
                 while i > 0 {
                     // lzma_free(&mut dest[i].options, Some(allocator));
@@ -324,7 +361,7 @@ pub fn lzma_filters_copy(src: &[LzmaFilter], real_dest: &mut [LzmaFilter]) -> Lz
 
             // 复制选项
             // 报错位置
-            // unsafe{memcpy(dest[i].options.as_mut().unwrap() as *mut c_void, src[i].options as *mut c_void, FEATURES[j].options_size)};
+            // unsafe{memcpy(dest[i].options.as_mut().unwrap() as *mut c_void, src[i].options as *mut c_void, features.options_size)};
 
             if let (Some(dest_option), Some(src_option)) =
                 (dest[i].options.as_mut(), src[i].options.as_ref())
@@ -392,22 +429,19 @@ pub fn lzma_validate_chain(filters: &[LzmaFilter], count: &mut usize) -> LzmaRet
 
     let mut i = 0;
     while i < filters.len() && filters[i].id != LZMA_VLI_UNKNOWN {
-        let mut j = 0;
-        while j < FEATURES.len() && filters[i].id != FEATURES[j].id {
-            if FEATURES[j].id == LZMA_VLI_UNKNOWN {
-                return LzmaRet::OptionsError;
-            }
-            j += 1;
-        }
+        let features = match features_find(filters[i].id) {
+            Some(features) => features,
+            None => return LzmaRet::OptionsError,
+        };
 
         // 如果前一个过滤器不能作为非最后过滤器，则链无效
         if !non_last_ok {
             return LzmaRet::OptionsError;
         }
 
-        non_last_ok = FEATURES[j].non_last_ok;
-        last_ok = FEATURES[j].last_ok;
-        changes_size_count += FEATURES[j].changes_size as usize;
+        non_last_ok = features.non_last_ok;
+        last_ok = features.last_ok;
+        changes_size_count += features.changes_size as usize;
 
         i += 1;
     }
@@ -507,7 +541,8 @@ pub fn lzma_raw_coder_memusage(coder_find: LzmaFilterFind, filters: &[LzmaFilter
     while i < filters.len() && filters[i].id != LZMA_VLI_UNKNOWN {
         if let Some(fc) = coder_find(filters[i].id) {
             if let Some(memusage_fn) = fc.memusage {
-                let usage = memusage_fn(filters[i].options.as_ref().unwrap());
+                let usage =
+                    memusage_fn(filters[i].options.as_ref().unwrap_or(&LzmaOptionsType::None));
                 if usage == u64::MAX {
                     return u64::MAX;
                 }
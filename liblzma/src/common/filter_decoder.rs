@@ -7,11 +7,17 @@
 use crate::{
     api::{
         LzmaAction, LzmaFilter, LzmaOptionsType, LzmaRet, LzmaStream, LzmaVli, LZMA_FILTER_ARM,
-        LZMA_FILTER_ARM64, LZMA_FILTER_ARMTHUMB, LZMA_FILTER_DELTA, LZMA_FILTER_IA64,
+        LZMA_FILTER_ARM64, LZMA_FILTER_ARMTHUMB, LZMA_FILTER_DEDUP, LZMA_FILTER_DELTA, LZMA_FILTER_ENCRYPT, LZMA_FILTER_IA64,
         LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT, LZMA_FILTER_LZMA2, LZMA_FILTER_POWERPC,
         LZMA_FILTER_SPARC, LZMA_FILTER_X86,
     },
     common::LzmaFilterCoder,
+    custom::{
+        lzma_custom_decoder_init, lzma_custom_filter_find, lzma_custom_memusage,
+        lzma_custom_props_decode,
+    },
+    dedup::{lzma_dedup_decoder_init, lzma_dedup_decoder_memusage, lzma_dedup_props_decode},
+    encrypt::{lzma_encrypt_decoder_init, lzma_encrypt_decoder_memusage, lzma_encrypt_props_decode},
     delta::{lzma_delta_coder_memusage, lzma_delta_decoder_init, lzma_delta_props_decode},
     lzma::{
         lzma_lzma2_decoder_init, lzma_lzma2_decoder_memusage, lzma_lzma2_props_decode,
@@ -147,20 +153,27 @@ static DECODERS: &[LzmaFilterDecoder] = &[
         memusage: Some(lzma_delta_coder_memusage),
         props_decode: Some(lzma_delta_props_decode),
     },
+    LzmaFilterDecoder {
+        id: LZMA_FILTER_DEDUP,
+        init: Some(lzma_dedup_decoder_init),
+        memusage: Some(lzma_dedup_decoder_memusage),
+        props_decode: Some(lzma_dedup_props_decode),
+    },
+    LzmaFilterDecoder {
+        id: LZMA_FILTER_ENCRYPT,
+        init: Some(lzma_encrypt_decoder_init),
+        memusage: Some(lzma_encrypt_decoder_memusage),
+        props_decode: Some(lzma_encrypt_props_decode),
+    },
 ];
 
 fn decoder_find_base(id: LzmaVli) -> Option<LzmaFilterCoder> {
-    // ENCODERS.iter().find(|encoder| encoder.id == id)
-    for decoder in DECODERS.iter() {
-        if decoder.id == id {
-            let mut filterDecoder = LzmaFilterCoder::default();
-            filterDecoder.id = decoder.id;
-            filterDecoder.init = decoder.init;
-            filterDecoder.memusage = decoder.memusage;
-            return Some(filterDecoder.clone());
-        }
-    }
-    None
+    let decoder = decoder_find(id)?;
+    let mut filterDecoder = LzmaFilterCoder::default();
+    filterDecoder.id = decoder.id;
+    filterDecoder.init = decoder.init;
+    filterDecoder.memusage = decoder.memusage;
+    Some(filterDecoder)
 }
 
 /// 在解码器数组中查找指定 ID 的解码器
@@ -176,7 +189,15 @@ fn decoder_find(id: LzmaVli) -> Option<LzmaFilterDecoder> {
             return Some(decoder.clone());
         }
     }
-    None
+
+    // 不在静态表中时查找已注册的自定义过滤器
+    lzma_custom_filter_find(id)?;
+    Some(LzmaFilterDecoder {
+        id,
+        init: Some(lzma_custom_decoder_init),
+        memusage: Some(lzma_custom_memusage),
+        props_decode: Some(lzma_custom_props_decode),
+    })
 }
 
 /// 检查指定 ID 的解码器是否支持
@@ -246,11 +267,18 @@ pub fn lzma_raw_decoder_memusage(filters: &[LzmaFilter]) -> u64 {
     )
 }
 
-/// 解码属性
+/// 解码过滤器属性
+///
+/// filter.id 必须已经设置，解码得到的选项保存在 filter.options 中，
+/// 出错时 filter.options 为 None。只使用 props 的前 props_size 字节。
 pub fn lzma_properties_decode(filter: &mut LzmaFilter, props: &[u8], props_size: usize) -> LzmaRet {
     // 确保 options 为 NULL
     filter.options = None;
 
+    if props.len() < props_size {
+        return LzmaRet::ProgError;
+    }
+
     // 查找解码器
     let fd = match decoder_find(filter.id) {
         Some(fd) => fd,
@@ -275,3 +303,208 @@ pub fn lzma_properties_decode(filter: &mut LzmaFilter, props: &[u8], props_size:
         LzmaRet::OptionsError
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{
+        LzmaFilter, LzmaOptionsBcj, LzmaOptionsDedup, LzmaOptionsDelta, LzmaOptionsLzma,
+        LzmaOptionsType, LzmaRet, LZMA_DEDUP_CHUNK_LOG_MAX, LZMA_DEDUP_CHUNK_LOG_MIN,
+        LZMA_DELTA_DIST_MIN, LZMA_DICT_SIZE_MIN, LZMA_FILTER_ARM, LZMA_FILTER_ARM64,
+        LZMA_FILTER_ARMTHUMB, LZMA_FILTER_DEDUP, LZMA_FILTER_DELTA, LZMA_FILTER_IA64,
+        LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT, LZMA_FILTER_LZMA2, LZMA_FILTER_POWERPC,
+        LZMA_FILTER_SPARC, LZMA_FILTER_X86, LZMA_LCLP_MAX, LZMA_PB_MAX,
+    };
+    use crate::common::{lzma_properties_decode, lzma_properties_encode, lzma_properties_size};
+    use crate::test_util::pseudo_random;
+
+    /// 编码过滤器的属性后再解码，返回属性数据和解码得到的过滤器
+    fn props_round_trip(filter: &LzmaFilter) -> (Vec<u8>, LzmaFilter) {
+        let mut size = 0;
+        assert_eq!(lzma_properties_size(&mut size, filter), LzmaRet::Ok);
+        let mut props = vec![0u8; size as usize];
+        assert_eq!(lzma_properties_encode(filter, &mut props), LzmaRet::Ok);
+
+        let mut decoded = LzmaFilter {
+            id: filter.id,
+            options: None,
+        };
+        assert_eq!(
+            lzma_properties_decode(&mut decoded, &props, props.len()),
+            LzmaRet::Ok
+        );
+        (props, decoded)
+    }
+
+    /// 各过滤器的属性编码后解码得到与原来相同的选项
+    #[test]
+    fn properties_round_trip() {
+        let bcj_filters = [
+            (LZMA_FILTER_X86, 1),
+            (LZMA_FILTER_POWERPC, 4),
+            (LZMA_FILTER_IA64, 16),
+            (LZMA_FILTER_ARM, 4),
+            (LZMA_FILTER_ARMTHUMB, 2),
+            (LZMA_FILTER_ARM64, 4),
+            (LZMA_FILTER_SPARC, 4),
+        ];
+
+        for (i, r) in pseudo_random(4096, 23).chunks(4).enumerate() {
+            let word = u32::from_le_bytes([r[0], r[1], r[2], r[3]]);
+            let mut filters = Vec::new();
+
+            // LZMA1 的属性保存 lc、lp、pb 和完整的字典大小
+            let lc = r[0] as u32 % (LZMA_LCLP_MAX + 1);
+            let mut lzma = LzmaOptionsLzma::default();
+            lzma.lc = lc;
+            lzma.lp = r[1] as u32 % (LZMA_LCLP_MAX - lc + 1);
+            lzma.pb = r[2] as u32 % (LZMA_PB_MAX + 1);
+            lzma.dict_size = word;
+            for id in [LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT] {
+                filters.push(LzmaFilter {
+                    id,
+                    options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma.clone())),
+                });
+            }
+
+            // LZMA2 只保存字典大小，且只能表示 2^n 和 2^n + 2^(n-1)
+            let mut lzma2 = LzmaOptionsLzma::default();
+            let n = 12 + r[3] as u32 % 19;
+            lzma2.dict_size = if word & 1 == 0 { 2 << n } else { 3 << n } >> 1;
+            filters.push(LzmaFilter {
+                id: LZMA_FILTER_LZMA2,
+                options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma2)),
+            });
+
+            filters.push(LzmaFilter {
+                id: LZMA_FILTER_DELTA,
+                options: Some(LzmaOptionsType::Delta(LzmaOptionsDelta {
+                    dist: LZMA_DELTA_DIST_MIN + r[0] as u32,
+                    ..Default::default()
+                })),
+            });
+
+            // 每种 BCJ 过滤器的起始偏移量都要对齐；也测试起始偏移量为 0 的情况
+            let (id, alignment) = bcj_filters[i % bcj_filters.len()];
+            let start_offset = if i % 3 == 0 {
+                0
+            } else {
+                word & !(alignment - 1)
+            };
+            filters.push(LzmaFilter {
+                id,
+                options: Some(LzmaOptionsType::Bcj(LzmaOptionsBcj { start_offset })),
+            });
+
+            filters.push(LzmaFilter {
+                id: LZMA_FILTER_DEDUP,
+                options: Some(LzmaOptionsType::Dedup(LzmaOptionsDedup {
+                    chunk_log: LZMA_DEDUP_CHUNK_LOG_MIN
+                        + r[1] as u32 % (LZMA_DEDUP_CHUNK_LOG_MAX - LZMA_DEDUP_CHUNK_LOG_MIN + 1),
+                    ..Default::default()
+                })),
+            });
+
+            for filter in &filters {
+                let (props, decoded) = props_round_trip(filter);
+                assert_eq!(&decoded, filter);
+                assert_eq!(props_round_trip(&decoded).0, props);
+            }
+        }
+    }
+
+    /// LZMA2 的字典大小向上取整，再次编码得到相同的属性
+    #[test]
+    fn lzma2_dict_size_rounding() {
+        let mut sizes: Vec<u32> = vec![0, 1, LZMA_DICT_SIZE_MIN, (1 << 30) + 1, u32::MAX];
+        for r in pseudo_random(4096, 24).chunks(4) {
+            let word = u32::from_le_bytes([r[0], r[1], r[2], r[3]]);
+            sizes.push(word >> (r[0] % 24));
+        }
+
+        for dict_size in sizes {
+            let mut lzma = LzmaOptionsLzma::default();
+            lzma.dict_size = dict_size;
+            let filter = LzmaFilter {
+                id: LZMA_FILTER_LZMA2,
+                options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma)),
+            };
+            let (props, decoded) = props_round_trip(&filter);
+            let decoded_size = decoded
+                .options
+                .unwrap()
+                .as_lzma_options_lzma()
+                .unwrap()
+                .dict_size;
+
+            // 取整后不小于原来的大小，且最多大一半（4 GiB - 1 除外）
+            let min = dict_size.max(LZMA_DICT_SIZE_MIN) as u64;
+            assert!(decoded_size as u64 >= min);
+            assert!(decoded_size == u32::MAX || decoded_size as u64 <= min * 3 / 2);
+            let mut lzma = LzmaOptionsLzma::default();
+            lzma.dict_size = decoded_size;
+            let (again, _) = props_round_trip(&LzmaFilter {
+                id: LZMA_FILTER_LZMA2,
+                options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma)),
+            });
+            assert_eq!(again, props);
+        }
+    }
+
+    /// 所有合法的单字节属性解码后再编码得到相同的字节，非法的属性被拒绝
+    #[test]
+    fn decode_encode_all_bytes() {
+        for id in [LZMA_FILTER_LZMA2, LZMA_FILTER_DELTA, LZMA_FILTER_DEDUP] {
+            for byte in 0..=255u8 {
+                let mut filter = LzmaFilter { id, options: None };
+                let ret = lzma_properties_decode(&mut filter, &[byte], 1);
+                let valid = match id {
+                    LZMA_FILTER_LZMA2 => byte <= 40,
+                    LZMA_FILTER_DELTA => true,
+                    _ => (LZMA_DEDUP_CHUNK_LOG_MIN..=LZMA_DEDUP_CHUNK_LOG_MAX)
+                        .contains(&(byte as u32)),
+                };
+                if !valid {
+                    assert_eq!(ret, LzmaRet::OptionsError);
+                    continue;
+                }
+                assert_eq!(ret, LzmaRet::Ok);
+                let mut props = [0u8; 1];
+                assert_eq!(lzma_properties_encode(&filter, &mut props), LzmaRet::Ok);
+                assert_eq!(props[0], byte);
+            }
+        }
+
+        // LZMA1 的 lc/lp/pb 字节，lc + lp 不能超过 4
+        for byte in 0..=255u8 {
+            let props = [byte, 0x00, 0x00, 0x80, 0x00];
+            let mut filter = LzmaFilter {
+                id: LZMA_FILTER_LZMA1,
+                options: None,
+            };
+            let ret = lzma_properties_decode(&mut filter, &props, props.len());
+            let lc = byte as u32 % 9;
+            let lp = byte as u32 / 9 % 5;
+            if byte as u32 >= 9 * 5 * 5 || lc + lp > LZMA_LCLP_MAX {
+                assert_eq!(ret, LzmaRet::OptionsError);
+                continue;
+            }
+            assert_eq!(ret, LzmaRet::Ok);
+            let mut again = [0u8; 5];
+            assert_eq!(lzma_properties_encode(&filter, &mut again), LzmaRet::Ok);
+            assert_eq!(again, props);
+        }
+
+        // 长度不对的属性
+        for (id, len) in [
+            (LZMA_FILTER_X86, 3),
+            (LZMA_FILTER_DELTA, 2),
+            (LZMA_FILTER_LZMA1, 4),
+        ] {
+            let mut filter = LzmaFilter { id, options: None };
+            assert_eq!(
+                lzma_properties_decode(&mut filter, &[0u8; 5], len),
+                LzmaRet::OptionsError
+            );
+        }
+    }
+}
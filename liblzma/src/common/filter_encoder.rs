@@ -8,12 +8,21 @@
 #![deny(clippy::useless_attribute)]
 use crate::{
     api::{
-        LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream, LzmaVli,
+        LzmaAction, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream, LzmaVli,
         LZMA_FILTERS_MAX, LZMA_FILTER_ARM, LZMA_FILTER_ARM64, LZMA_FILTER_ARMTHUMB,
-        LZMA_FILTER_DELTA, LZMA_FILTER_IA64, LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT,
+        LZMA_FILTER_DEDUP, LZMA_FILTER_DELTA, LZMA_FILTER_ENCRYPT, LZMA_FILTER_IA64, LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA1EXT,
         LZMA_FILTER_LZMA2, LZMA_FILTER_POWERPC, LZMA_FILTER_SPARC, LZMA_FILTER_X86,
     },
     common::LzmaFilterCoder,
+    custom::{
+        lzma_custom_encoder_init, lzma_custom_filter_find, lzma_custom_memusage,
+        lzma_custom_props_encode, lzma_custom_props_size,
+    },
+    dedup::{lzma_dedup_encoder_init, lzma_dedup_encoder_memusage, lzma_dedup_props_encode},
+    encrypt::{
+        encrypt_options, lzma_encrypt_encoder_init, lzma_encrypt_encoder_memusage,
+        lzma_encrypt_props_encode, ENCRYPT_PROPS_SIZE,
+    },
     delta::{lzma_delta_coder_memusage, lzma_delta_encoder_init, lzma_delta_props_encode},
     lzma::{
         lzma_lzma2_encoder_init, lzma_lzma2_encoder_memusage, lzma_lzma2_props_encode,
@@ -105,6 +114,14 @@ fn lzma2_block_size(options: &LzmaOptionsType) -> u64 {
     }
 }
 
+/// 加密过滤器内部的 LZMA2 编码器决定 Block 大小
+fn encrypt_block_size(options: &LzmaOptionsType) -> u64 {
+    match encrypt_options(options) {
+        Some(opt) => lzma2_block_size(&LzmaOptionsType::LzmaOptionsLzma(opt.lzma.clone())),
+        None => u64::MAX,
+    }
+}
+
 static ENCODERS: &[LzmaFilterEncoder] = &[
     LzmaFilterEncoder {
         id: LZMA_FILTER_LZMA1,
@@ -205,22 +222,35 @@ static ENCODERS: &[LzmaFilterEncoder] = &[
         props_size_fixed: 1,
         props_encode: Some(lzma_delta_props_encode),
     },
+    LzmaFilterEncoder {
+        id: LZMA_FILTER_DEDUP,
+        init: Some(lzma_dedup_encoder_init),
+        memusage: Some(lzma_dedup_encoder_memusage),
+        block_size: None,
+        props_size_get: None,
+        props_size_fixed: 1,
+        props_encode: Some(lzma_dedup_props_encode),
+    },
+    LzmaFilterEncoder {
+        id: LZMA_FILTER_ENCRYPT,
+        init: Some(lzma_encrypt_encoder_init),
+        memusage: Some(lzma_encrypt_encoder_memusage),
+        block_size: Some(encrypt_block_size),
+        props_size_get: None,
+        props_size_fixed: ENCRYPT_PROPS_SIZE,
+        props_encode: Some(lzma_encrypt_props_encode),
+    },
 ];
 
 /// 在编码器数组中查找指定 ID 的编码器
 // fn encoder_find(id: LzmaVli) -> Option<&'static LzmaFilterEncoder> {
 fn encoder_find_fn(id: LzmaVli) -> Option<LzmaFilterCoder> {
-    // ENCODERS.iter().find(|encoder| encoder.id == id)
-    for encoder in ENCODERS.iter() {
-        if encoder.id == id {
-            let mut filterCoder = LzmaFilterCoder::default();
-            filterCoder.id = encoder.id;
-            filterCoder.init = encoder.init;
-            filterCoder.memusage = encoder.memusage;
-            return Some(filterCoder.clone());
-        }
-    }
-    None
+    let encoder = encoder_find(id)?;
+    let mut filterCoder = LzmaFilterCoder::default();
+    filterCoder.id = encoder.id;
+    filterCoder.init = encoder.init;
+    filterCoder.memusage = encoder.memusage;
+    Some(filterCoder)
 }
 
 fn encoder_find(id: LzmaVli) -> Option<LzmaFilterEncoder> {
@@ -230,7 +260,18 @@ fn encoder_find(id: LzmaVli) -> Option<LzmaFilterEncoder> {
             return Some(encoder.clone());
         }
     }
-    None
+
+    // 不在静态表中时查找已注册的自定义过滤器
+    lzma_custom_filter_find(id)?;
+    Some(LzmaFilterEncoder {
+        id,
+        init: Some(lzma_custom_encoder_init),
+        memusage: Some(lzma_custom_memusage),
+        block_size: None,
+        props_size_get: Some(lzma_custom_props_size),
+        props_size_fixed: 0,
+        props_encode: Some(lzma_custom_props_encode),
+    })
 }
 
 pub fn lzma_filter_encoder_is_supported(id: LzmaVli) -> bool {
@@ -361,9 +402,9 @@ pub fn lzma_raw_encoder(strm: &mut LzmaStream, options: &[LzmaFilter]) -> LzmaRe
     match strm.internal.try_borrow_mut() {
         Ok(mut internal_ref) => {
             if let Some(ref mut internal) = internal_ref.as_mut() {
-                internal.supported_actions[0] = true; // RUN
-                internal.supported_actions[1] = true; // SYNC_FLUSH
-                internal.supported_actions[2] = true; // FINISH
+                internal.supported_actions[LzmaAction::Run as usize] = true;
+                internal.supported_actions[LzmaAction::SyncFlush as usize] = true;
+                internal.supported_actions[LzmaAction::Finish as usize] = true;
             }
         }
         Err(_) => return LzmaRet::ProgError,
@@ -403,6 +444,10 @@ pub fn lzma_mt_block_size(filters: &[LzmaFilter]) -> u64 {
     max
 }
 
+/// 计算过滤器属性（Filter Properties）编码后的大小
+///
+/// 过滤器 ID 不受支持时返回 LzmaRet::OptionsError，ID 不合法时返回 LzmaRet::ProgError。
+/// BCJ 过滤器的 options 可以为 None，等同于起始偏移量为 0。
 pub fn lzma_properties_size(size: &mut u32, filter: &LzmaFilter) -> LzmaRet {
     let fe = match encoder_find(filter.id) {
         Some(fe) => fe,
@@ -416,21 +461,34 @@ pub fn lzma_properties_size(size: &mut u32, filter: &LzmaFilter) -> LzmaRet {
     };
 
     if let Some(props_size_get) = fe.props_size_get {
-        props_size_get(size, filter.options.as_ref().unwrap())
+        props_size_get(size, filter.options.as_ref().unwrap_or(&LzmaOptionsType::None))
     } else {
         *size = fe.props_size_fixed;
         LzmaRet::Ok
     }
 }
 
+/// 编码过滤器属性
+///
+/// props 至少要有 lzma_properties_size() 给出的大小，编码结果写在 props 的开头。
+/// 用 lzma_properties_decode() 解码得到的选项与这里接受的选项相同。
 pub fn lzma_properties_encode(filter: &LzmaFilter, props: &mut [u8]) -> LzmaRet {
     let fe = match encoder_find(filter.id) {
         Some(fe) => fe,
         None => return LzmaRet::ProgError,
     };
 
+    let mut size = 0;
+    let ret = lzma_properties_size(&mut size, filter);
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+    if props.len() < size as usize {
+        return LzmaRet::ProgError;
+    }
+
     if let Some(props_encode) = fe.props_encode {
-        props_encode(filter.options.as_ref().unwrap(), props)
+        props_encode(filter.options.as_ref().unwrap_or(&LzmaOptionsType::None), props)
     } else {
         LzmaRet::Ok
     }
@@ -8,17 +8,24 @@ use crate::api::{LzmaFilter, LzmaRet, LzmaVli};
 
 use super::{lzma_properties_decode, lzma_vli_decode, LZMA_FILTER_RESERVED_START};
 
-/// 解码过滤器标志
+/// 解码过滤器标志（Filter Flags），即块头中的一个过滤器
+///
+/// 从 in_data[*in_pos..in_size] 读取过滤器 ID 和属性，解码后的 ID 和选项保存在
+/// filter 中，*in_pos 前进到过滤器标志之后。过滤器 ID 不受支持时返回
+/// LzmaRet::OptionsError，数据损坏时返回 LzmaRet::DataError。
 pub fn lzma_filter_flags_decode(
     filter: &mut LzmaFilter,
-
-    in_data: &mut [u8],
+    in_data: &[u8],
     in_pos: &mut usize,
     in_size: usize,
 ) -> LzmaRet {
     // 将指针设为 None，以便调用者可以安全地释放它。
     filter.options = None;
 
+    if in_size > in_data.len() || *in_pos > in_size {
+        return LzmaRet::ProgError;
+    }
+
     // 解码过滤器 ID
     let id_ret = lzma_vli_decode(&mut filter.id, None, in_data, in_pos, in_size);
     if id_ret != LzmaRet::Ok {
@@ -43,7 +50,7 @@ pub fn lzma_filter_flags_decode(
     }
 
     // 解码属性并更新位置
-    let props_data = &mut in_data[*in_pos..*in_pos + props_size as usize];
+    let props_data = &in_data[*in_pos..*in_pos + props_size as usize];
     let ret = lzma_properties_decode(filter, props_data, props_size as usize);
     *in_pos += props_size as usize;
 
@@ -11,7 +11,7 @@ use super::{
     LZMA_FILTER_RESERVED_START,
 };
 
-/// 计算过滤器标志的大小
+/// 计算过滤器标志（Filter Flags）编码后的大小，包括过滤器 ID、属性大小和属性
 pub fn lzma_filter_flags_size(size: &mut u32, filter: &LzmaFilter) -> LzmaRet {
     // 如果过滤器 ID 大于或等于保留 ID，返回程序错误
     if filter.id >= LZMA_FILTER_RESERVED_START {
@@ -30,7 +30,9 @@ pub fn lzma_filter_flags_size(size: &mut u32, filter: &LzmaFilter) -> LzmaRet {
     LzmaRet::Ok
 }
 
-/// 编码过滤器标志
+/// 编码过滤器标志，写入 out[*out_pos..out_size]，*out_pos 前进到写入的数据之后
+///
+/// 输出空间不足时返回 LzmaRet::ProgError。
 pub fn lzma_filter_flags_encode(
     filter: &LzmaFilter,
     out: &mut [u8],
@@ -77,18 +77,18 @@ fn index_tree_init(tree: &mut IndexTree) {
 }
 
 /// 索引树节点结束的辅助函数
-fn index_tree_node_end(node: &mut IndexTreeNode, free_func: Option<fn(&mut IndexTreeNode)>) {
+fn index_tree_node_end(node: &mut IndexNode, free_func: Option<fn(&mut IndexNode)>) {
     // 如果节点有左子树，则递归处理
-    if let Some(mut left) = node.get_left() {
+    if let Some(mut left) = node.get_tree_node().get_left() {
         let mut left_refs = left.lock().unwrap();
-        index_tree_node_end(left_refs.get_tree_node_mut(), free_func);
+        index_tree_node_end(&mut left_refs, free_func);
     }
 
     // 如果节点有右子树，则递归处理
-    if let Some(mut right) = node.get_right() {
+    if let Some(mut right) = node.get_tree_node().get_right() {
         let mut right_refs = right.lock().unwrap();
 
-        index_tree_node_end(right_refs.get_tree_node_mut(), free_func);
+        index_tree_node_end(&mut right_refs, free_func);
     }
 
     match free_func {
@@ -100,10 +100,10 @@ fn index_tree_node_end(node: &mut IndexTreeNode, free_func: Option<fn(&mut Index
 /// 释放为树分配的内存。每个节点都使用给定的 free_func 进行释放，
 /// free_func 可以是 lzma_free 或 index_stream_end。
 /// 后者用于在释放 index_stream 本身之前释放每个 index_stream 中的 Record 组。
-fn index_tree_end(tree: &mut IndexTree, free_func: Option<fn(&mut IndexTreeNode)>) {
+fn index_tree_end(tree: &mut IndexTree, free_func: Option<fn(&mut IndexNode)>) {
     if let Some(ref root) = tree.root {
         let mut root_refs = root.lock().unwrap();
-        index_tree_node_end(root_refs.get_tree_node_mut(), free_func);
+        index_tree_node_end(&mut root_refs, free_func);
     }
 }
 
@@ -313,10 +313,11 @@ fn index_stream_init(
 }
 
 /// 释放分配给 Stream 及其记录组的内存。
-fn index_stream_end(node: Arc<Mutex<IndexStream>>) {
+fn index_stream_end(node: &mut IndexNode) {
     // 结束并释放 groups 相关资源
-    let mut s = node.lock().unwrap();
-    index_tree_end(&mut s.groups, None);
+    if let IndexNode::Stream(s) = node {
+        index_tree_end(&mut s.groups, None);
+    }
 }
 
 // 初始化一个空的 lzma_index。
@@ -341,20 +342,9 @@ pub fn lzma_index_init() -> Option<Arc<Mutex<LzmaIndex>>> {
     Some(i)
 }
 
-/// 适配器：将 &mut IndexTreeNode 转为 IndexStream 并调用 index_stream_end
-fn index_stream_end_adapter(node: &mut IndexTreeNode) {
-    // 安全前提：node 必须实际属于 IndexStream
-    use std::mem;
-    let stream_ptr = node as *mut IndexTreeNode as *mut IndexStream;
-    unsafe {
-        // 直接 clone 并包裹 Arc/Mutex 调用 index_stream_end
-        index_stream_end(Arc::new(Mutex::new((*stream_ptr).clone())));
-    }
-}
-
 /// 释放 lzma_index 相关的资源。
 pub fn lzma_index_end(i: &mut LzmaIndex) {
-    index_tree_end(&mut i.streams, Some(index_stream_end_adapter));
+    index_tree_end(&mut i.streams, Some(index_stream_end));
 }
 
 /// 设置 lzma_index 的预分配大小。
@@ -1241,6 +1231,11 @@ pub fn lzma_index_iter_next(iter: &mut LzmaIndexIter, mode: LzmaIndexIterMode) -
                         break;
                     }
                 }
+
+                // 所有 Stream 都没有 Block
+                if stream.is_none() {
+                    return true;
+                }
             }
             // 从 Stream 中的第一个 Record 开始
             if let Some(ref s) = stream {
@@ -1366,14 +1361,23 @@ pub fn lzma_index_iter_locate(iter: &mut LzmaIndexIter, target: LzmaVli) -> bool
         return true;
     }
     // 定位包含目标偏移量的 Stream
+    // 取出副本后立即释放锁，iter_set_info() 还会锁定同一节点
     let stream_arc = index_tree_locate(&i.streams, target).expect("stream not found");
-    let stream_borrow = stream_arc.lock().unwrap();
-    let stream = stream_borrow.as_stream().expect("not a stream");
-    let mut target = target - stream.node.uncompressed_base;
+    let stream = stream_arc
+        .lock()
+        .unwrap()
+        .as_stream()
+        .expect("not a stream")
+        .clone();
+    let target = target - stream.node.uncompressed_base;
     // 定位包含目标偏移量的 Group
     let group_arc = index_tree_locate(&stream.groups, target).expect("group not found");
-    let group_borrow = group_arc.lock().unwrap();
-    let group = group_borrow.as_group().expect("not a group");
+    let group = group_arc
+        .lock()
+        .unwrap()
+        .as_group()
+        .expect("not a group")
+        .clone();
     // 二分查找定位 Record
     let mut left = 0;
     let mut right = group.last;
@@ -1387,10 +1391,10 @@ pub fn lzma_index_iter_locate(iter: &mut LzmaIndexIter, target: LzmaVli) -> bool
     }
     // 设置 iter.internal
     iter.internal
-        .set(ITER_STREAM, Internal::Stream(Box::new(stream.clone())))
+        .set(ITER_STREAM, Internal::Stream(Box::new(stream)))
         .unwrap();
     iter.internal
-        .set(ITER_GROUP, Internal::Group(Box::new(group.clone())))
+        .set(ITER_GROUP, Internal::Group(Box::new(group)))
         .unwrap();
     iter.internal
         .set(ITER_RECORD, Internal::Size(left))
@@ -1398,3 +1402,59 @@ pub fn lzma_index_iter_locate(iter: &mut LzmaIndexIter, target: LzmaVli) -> bool
     iter_set_info(iter);
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{lzma_index_append, lzma_index_init, lzma_index_iter_init, lzma_index_iter_locate};
+    use crate::api::{LzmaIndexIter, LzmaRet};
+
+    /// 定位未压缩偏移所在的 Block；以前会因为重复锁定同一节点而死锁，
+    /// 所以在另一个线程中运行并限制时间
+    #[test]
+    fn iter_locate() {
+        let index = lzma_index_init().unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                lzma_index_append(&mut index.lock().unwrap(), 100, 1000),
+                LzmaRet::Ok
+            );
+        }
+        let index = index.lock().unwrap().clone();
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut iter = LzmaIndexIter::default();
+            lzma_index_iter_init(&mut iter, Box::new(index));
+            let found: Vec<_> = [0, 999, 1000, 2999, 3000]
+                .into_iter()
+                .map(|target| {
+                    (!lzma_index_iter_locate(&mut iter, target)).then(|| {
+                        (
+                            iter.block.number_in_file,
+                            iter.block.uncompressed_file_offset,
+                        )
+                    })
+                })
+                .collect();
+            tx.send(found).unwrap();
+        });
+
+        let found = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("lzma_index_iter_locate() did not return");
+        assert_eq!(
+            found,
+            [
+                Some((1, 0)),
+                Some((1, 0)),
+                Some((2, 1000)),
+                Some((3, 2000)),
+                None
+            ]
+        );
+    }
+}
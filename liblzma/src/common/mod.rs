@@ -14,6 +14,8 @@ pub use common::*;
 pub mod alone_decoder;
 pub mod alone_encoder;
 pub mod auto_decoder;
+pub mod auto_level;
+pub use auto_level::*;
 pub mod block_buffer_encoder;
 pub mod block_decoder;
 pub use block_buffer_encoder::*;
@@ -37,10 +39,13 @@ pub mod lzip_decoder;
 pub mod memcmplen;
 pub mod microlzma_decoder;
 pub mod microlzma_encoder;
+pub mod preset_dict;
 pub mod stream_decoder;
 pub mod stream_encoder;
 pub mod stream_encoder_mt;
 pub use stream_encoder_mt::*;
+pub mod stream_encoder_mt_chunked;
+pub use stream_encoder_mt_chunked::*;
 pub mod stream_flags_commom;
 pub mod stream_flags_decoder;
 pub mod stream_flags_encoder;
@@ -78,6 +83,7 @@ pub use index_decoder::*;
 pub use index_encoder::*;
 pub use index_hash::*;
 pub use lzip_decoder::*;
+pub use preset_dict::*;
 pub use memcmplen::*;
 pub use microlzma_decoder::*;
 pub use microlzma_encoder::*;
@@ -0,0 +1,345 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 预设字典（共享字典）
+//!
+//! 大量很小的记录单独压缩时，LZMA 在每条记录开头都没有可引用的历史数据。
+//! 预设字典把一段典型内容预先放入 LZ 字典中，编码器和解码器使用同一份字典时，
+//! 记录开头就可以直接引用其中的内容。
+//!
+//! 带字典的 raw 数据以 4 字节的字典 ID（字典内容的 CRC32，小端序）开头，
+//! 之后是 raw 过滤器链的输出。解码时先比较字典 ID，字典不一致时返回
+//! `LzmaRet::OptionsError`，不会输出错误的数据。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    api::{
+        LzmaAction, LzmaFilter, LzmaOptionsType, LzmaRet, LzmaStream, LZMA_FILTER_LZMA1,
+        LZMA_FILTER_LZMA1EXT, LZMA_FILTER_LZMA2, LZMA_VLI_UNKNOWN,
+    },
+    check::lzma_crc32,
+    common::{lzma_bufcpy, lzma_raw_decoder_init, lzma_raw_encoder_init, NextCoderInitFunction},
+};
+
+use super::{lzma_next_end, lzma_strm_init, CoderType, LzmaNextCoder};
+
+/// 字典 ID 的字节数
+pub const LZMA_DICT_ID_SIZE: usize = 4;
+
+/// 训练时统计的子串长度
+const TRAIN_KMER: usize = 8;
+
+/// 训练时从样本中挑选的片段长度
+const TRAIN_SEGMENT: usize = 128;
+
+#[derive(Debug, Default)]
+pub struct LzmaRawDictCoder {
+    /// raw 编码器或解码器
+    pub next: Box<LzmaNextCoder>,
+    pub encoder: bool,
+    /// 编码时为要写出的字典 ID，解码时为已读入的字典 ID
+    pub header: [u8; LZMA_DICT_ID_SIZE],
+    pub header_pos: usize,
+    /// 当前使用的字典的 ID
+    pub dict_id: u32,
+}
+
+/// 计算字典 ID
+pub fn lzma_dict_id(dict: &[u8]) -> u32 {
+    lzma_crc32(dict, dict.len(), 0)
+}
+
+/// 复制过滤器链，并把字典设置到其中的 LZMA1/LZMA2 过滤器上
+fn raw_dict_filters(filters: &[LzmaFilter], dict: &[u8]) -> Option<Vec<LzmaFilter>> {
+    let mut filters: Vec<LzmaFilter> = filters
+        .iter()
+        .take_while(|f| f.id != LZMA_VLI_UNKNOWN)
+        .cloned()
+        .collect();
+
+    let lzma = filters.iter_mut().find(|f| {
+        f.id == LZMA_FILTER_LZMA1 || f.id == LZMA_FILTER_LZMA1EXT || f.id == LZMA_FILTER_LZMA2
+    })?;
+    match lzma.options {
+        Some(LzmaOptionsType::LzmaOptionsLzma(ref mut opt)) => {
+            opt.preset_dict = Some(dict.to_vec());
+            opt.preset_dict_size = dict.len() as u32;
+        }
+        _ => return None,
+    }
+
+    filters.push(LzmaFilter {
+        id: LZMA_VLI_UNKNOWN,
+        options: None,
+    });
+    Some(filters)
+}
+
+fn raw_dict_code(
+    coder_ptr: &mut CoderType,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::RawDictCoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    if coder.header_pos < LZMA_DICT_ID_SIZE {
+        if coder.encoder {
+            lzma_bufcpy(
+                &coder.header,
+                &mut coder.header_pos,
+                LZMA_DICT_ID_SIZE,
+                out,
+                out_pos,
+                out_size,
+            );
+        } else {
+            lzma_bufcpy(
+                in_,
+                in_pos,
+                in_size,
+                &mut coder.header,
+                &mut coder.header_pos,
+                LZMA_DICT_ID_SIZE,
+            );
+
+            if coder.header_pos == LZMA_DICT_ID_SIZE
+                && u32::from_le_bytes(coder.header) != coder.dict_id
+            {
+                return LzmaRet::OptionsError;
+            }
+        }
+
+        if coder.header_pos < LZMA_DICT_ID_SIZE {
+            return LzmaRet::Ok;
+        }
+    }
+
+    match coder.next.code {
+        Some(code) => code(
+            coder.next.coder.as_mut().unwrap(),
+            in_,
+            in_pos,
+            in_size,
+            out,
+            out_pos,
+            out_size,
+            action,
+        ),
+        None => LzmaRet::ProgError,
+    }
+}
+
+fn raw_dict_end(coder_ptr: &mut CoderType) {
+    if let CoderType::RawDictCoder(ref mut coder) = coder_ptr {
+        lzma_next_end(&mut coder.next);
+    }
+}
+
+pub fn lzma_raw_dict_coder_init(
+    next: &mut LzmaNextCoder,
+    filters: &[LzmaFilter],
+    dict: &[u8],
+    encoder: bool,
+) -> LzmaRet {
+    if next.init != Some(NextCoderInitFunction::RawDict(lzma_raw_dict_coder_init)) {
+        lzma_next_end(next);
+    }
+    next.init = Some(NextCoderInitFunction::RawDict(lzma_raw_dict_coder_init));
+
+    if dict.is_empty() {
+        return LzmaRet::OptionsError;
+    }
+    let filters = match raw_dict_filters(filters, dict) {
+        Some(f) => f,
+        None => return LzmaRet::OptionsError,
+    };
+
+    if next.coder.is_none() {
+        next.coder = Some(CoderType::RawDictCoder(LzmaRawDictCoder::default()));
+        next.code = Some(raw_dict_code);
+        next.end = Some(raw_dict_end);
+    }
+
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::RawDictCoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    coder.encoder = encoder;
+    coder.dict_id = lzma_dict_id(dict);
+    coder.header = coder.dict_id.to_le_bytes();
+    coder.header_pos = 0;
+
+    if encoder {
+        lzma_raw_encoder_init(&mut coder.next, &filters)
+    } else {
+        lzma_raw_decoder_init(&mut coder.next, &filters)
+    }
+}
+
+fn raw_dict_strm_init(
+    strm: &mut LzmaStream,
+    filters: &[LzmaFilter],
+    dict: &[u8],
+    encoder: bool,
+) -> LzmaRet {
+    let ret = lzma_strm_init(Some(strm));
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+
+    let mut internal = strm.internal.borrow_mut();
+    let internal = match internal.as_mut() {
+        Some(internal) => internal,
+        None => return LzmaRet::ProgError,
+    };
+    let ret = match internal.next {
+        Some(ref mut next) => lzma_raw_dict_coder_init(next, filters, dict, encoder),
+        None => LzmaRet::ProgError,
+    };
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+
+    internal.supported_actions[LzmaAction::Run as usize] = true;
+    internal.supported_actions[LzmaAction::Finish as usize] = true;
+    if encoder {
+        internal.supported_actions[LzmaAction::SyncFlush as usize] = true;
+    }
+
+    LzmaRet::Ok
+}
+
+/// 使用预设字典初始化 raw 编码器
+///
+/// 输出以字典 ID 开头，之后与 `lzma_raw_encoder` 的输出格式相同，只是 LZ 字典
+/// 在开始时已经装入了 `dict`。`filters` 中必须有一个 LZMA1 或 LZMA2 过滤器。
+pub fn lzma_raw_dict_encoder(
+    strm: &mut LzmaStream,
+    filters: &[LzmaFilter],
+    dict: &[u8],
+) -> LzmaRet {
+    raw_dict_strm_init(strm, filters, dict, true)
+}
+
+/// 使用预设字典初始化 raw 解码器
+///
+/// `filters` 和 `dict` 必须与编码时相同。输入开头的字典 ID 与 `dict` 不符时
+/// 返回 `LzmaRet::OptionsError`。
+pub fn lzma_raw_dict_decoder(
+    strm: &mut LzmaStream,
+    filters: &[LzmaFilter],
+    dict: &[u8],
+) -> LzmaRet {
+    raw_dict_strm_init(strm, filters, dict, false)
+}
+
+/// 从样本中训练预设字典
+///
+/// 统计样本中重复出现的 8 字节子串，把样本按顺序分成与字典中片段数相同的区间，
+/// 在每个区间中挑选尚未覆盖的常见子串最多的片段。得分越高的片段放得越靠后，
+/// 这样它们与要压缩的数据距离最近，编码匹配距离所需的位数也最少。
+///
+/// 返回的字典不超过 `dict_size` 字节；样本总量不超过 `dict_size` 时直接返回
+/// 所有样本的拼接。
+pub fn lzma_dict_train(samples: &[&[u8]], dict_size: usize) -> Vec<u8> {
+    let total: usize = samples.iter().map(|s| s.len()).sum();
+    if total <= dict_size {
+        return samples.concat();
+    }
+
+    // 每个子串的出现次数，只出现一次的子串对其他记录没有帮助
+    let mut freq: HashMap<u64, u32> = HashMap::new();
+    for sample in samples {
+        for w in sample.windows(TRAIN_KMER) {
+            let kmer = u64::from_le_bytes(w.try_into().unwrap());
+            *freq.entry(kmer).or_insert(0) += 1;
+        }
+    }
+
+    // 拼接后的样本中每个位置起始的子串，跨越样本边界的位置没有子串
+    let data = samples.concat();
+    let mut kmers: Vec<Option<u64>> = Vec::with_capacity(data.len());
+    for sample in samples {
+        for i in 0..sample.len() {
+            kmers.push(
+                sample
+                    .get(i..i + TRAIN_KMER)
+                    .map(|w| u64::from_le_bytes(w.try_into().unwrap())),
+            );
+        }
+    }
+
+    let segment = TRAIN_SEGMENT.min(dict_size.max(1));
+    let epochs = (dict_size / segment).max(1);
+    let epoch_len = (data.len() / epochs).max(segment);
+
+    let mut covered: HashSet<u64> = HashSet::new();
+    let mut chosen: Vec<(u64, usize)> = Vec::new();
+    let score_of = |pos: usize, covered: &HashSet<u64>| -> u64 {
+        match kmers[pos] {
+            Some(k) if !covered.contains(&k) => match freq.get(&k) {
+                Some(&f) if f > 1 => f as u64,
+                _ => 0,
+            },
+            _ => 0,
+        }
+    };
+
+    let mut epoch_start = 0;
+    while epoch_start + segment <= data.len() && chosen.len() < epochs {
+        let epoch_end = (epoch_start + epoch_len).min(data.len());
+
+        // 在本区间内滑动窗口，找出得分最高的片段
+        let mut score: u64 = (epoch_start..epoch_start + segment)
+            .map(|i| score_of(i, &covered))
+            .sum();
+        let mut best = (score, epoch_start);
+        for start in epoch_start + 1..=epoch_end - segment {
+            score -= score_of(start - 1, &covered);
+            score += score_of(start + segment - 1, &covered);
+            if score > best.0 {
+                best = (score, start);
+            }
+        }
+
+        if best.0 > 0 {
+            for k in kmers[best.1..best.1 + segment].iter().flatten() {
+                covered.insert(*k);
+            }
+            chosen.push(best);
+        }
+        epoch_start = epoch_end;
+    }
+
+    chosen.sort_by_key(|&(score, _)| score);
+    let mut dict: Vec<u8> = Vec::with_capacity(dict_size);
+    for (_, start) in chosen {
+        dict.extend_from_slice(&data[start..start + segment]);
+    }
+
+    // 片段不足以填满字典时，用最后的样本补足
+    if dict.len() < dict_size {
+        let fill = (dict_size - dict.len()).min(data.len());
+        let mut filled = data[data.len() - fill..].to_vec();
+        filled.extend_from_slice(&dict);
+        dict = filled;
+    }
+
+    if dict.len() > dict_size {
+        dict.drain(..dict.len() - dict_size);
+    }
+    dict
+}
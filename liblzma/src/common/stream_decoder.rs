@@ -82,6 +82,9 @@ pub struct LzmaStreamDecoder {
     /// 用于保存流头部、块头部和流尾部的缓冲区
     /// 块头部具有最大的最大尺寸
     buffer: [u8; LZMA_BLOCK_HEADER_SIZE_MAX as usize],
+
+    /// 已经开始解码的 Block 数，连接的多个流累计计数，用于进度回调
+    block_count: u64,
 }
 
 impl Default for LzmaStreamDecoder {
@@ -102,6 +105,7 @@ impl Default for LzmaStreamDecoder {
             first_stream: false,
             pos: 0,
             buffer: [0; LZMA_BLOCK_HEADER_SIZE_MAX as usize], // 初始化为全零数组
+            block_count: 0,
         }
     }
 }
@@ -245,7 +249,7 @@ fn stream_decode(
                 let mut filters: [LzmaFilter; LZMA_FILTERS_MAX + 1] = Default::default();
                 coder.block_options.filters = filters.to_vec();
 
-                let ret = lzma_block_header_decode(&mut coder.block_options, &mut coder.buffer);
+                let ret = lzma_block_header_decode(&mut coder.block_options, &coder.buffer);
                 if ret != LzmaRet::Ok {
                     return ret;
                 }
@@ -281,6 +285,7 @@ fn stream_decode(
                     return ret;
                 }
 
+                coder.block_count += 1;
                 coder.sequence = Sequence::SeqBlockRun;
                 continue;
             }
@@ -480,6 +485,13 @@ fn stream_decoder_memconfig(
     LzmaRet::Ok
 }
 
+fn stream_decoder_get_block(coder_ptr: &mut CoderType) -> u64 {
+    match coder_ptr {
+        CoderType::StreamDecoder(coder) => coder.block_count,
+        _ => 0,
+    }
+}
+
 pub fn lzma_stream_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, flags: u32) -> LzmaRet {
     // lzma_next_coder_init!(lzma_stream_decoder_init, next, allocator);
     if next.init
@@ -503,6 +515,7 @@ pub fn lzma_stream_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, flags:
         next.code = Some(stream_decode);
         next.end = Some(stream_decoder_end);
         next.get_check = Some(stream_decoder_get_check);
+        next.get_block = Some(stream_decoder_get_block);
         next.memconfig = Some(stream_decoder_memconfig);
     }
 
@@ -519,6 +532,7 @@ pub fn lzma_stream_decoder_init(next: &mut LzmaNextCoder, memlimit: u64, flags:
     coder.ignore_check = (flags & LZMA_IGNORE_CHECK) != 0;
     coder.concatenated = (flags & LZMA_CONCATENATED) != 0;
     coder.first_stream = true;
+    coder.block_count = 0;
 
     stream_decoder_reset(coder)
 }
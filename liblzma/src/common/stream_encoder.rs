@@ -57,6 +57,9 @@ pub struct LzmaStreamEncoder {
     /// 用于保存流头部、块头部和流尾部的缓冲区。
     /// 块头部具有最大的最大尺寸。
     buffer: [u8; LZMA_BLOCK_HEADER_SIZE_MAX as usize],
+
+    /// 已经开始编码的 Block 数，用于进度回调
+    block_count: u64,
 }
 
 impl Clone for LzmaStreamEncoder {
@@ -72,6 +75,7 @@ impl Clone for LzmaStreamEncoder {
             buffer_pos: self.buffer_pos,
             buffer_size: self.buffer_size,
             buffer: self.buffer,
+            block_count: self.block_count,
         }
     }
 }
@@ -89,6 +93,7 @@ impl Default for LzmaStreamEncoder {
             buffer_pos: 0,
             buffer_size: 0,
             buffer: [0; LZMA_BLOCK_HEADER_SIZE_MAX as usize], // 初始化为全零数组
+            block_count: 0,
         }
     }
 }
@@ -224,6 +229,7 @@ fn stream_encode(
                 }
 
                 coder.buffer_size = coder.block_options.header_size as usize;
+                coder.block_count += 1;
                 coder.sequence = StreamSequence::BlockHeader;
             }
 
@@ -404,6 +410,13 @@ fn stream_encoder_update(
     LzmaRet::Ok
 }
 
+fn stream_encoder_get_block(coder_ptr: &mut CoderType) -> u64 {
+    match coder_ptr {
+        CoderType::StreamEncoder(coder) => coder.block_count,
+        _ => 0,
+    }
+}
+
 fn stream_encoder_init(
     next: &mut LzmaNextCoder,
     filters: Option<&[LzmaFilter]>,
@@ -437,6 +450,7 @@ fn stream_encoder_init(
         next.code = Some(stream_encode);
         next.end = Some(stream_encoder_end);
         next.update = Some(stream_encoder_update);
+        next.get_block = Some(stream_encoder_get_block);
     }
     coder = match next.coder.as_mut().unwrap() {
         CoderType::StreamEncoder(c) => c,
@@ -484,6 +498,7 @@ fn stream_encoder_init(
         buffer_pos: coder.buffer_pos,
         buffer_size: coder.buffer_size,
         buffer: coder.buffer,
+        block_count: 0,
     };
 
     next.coder = Some(CoderType::StreamEncoder(new_coder));
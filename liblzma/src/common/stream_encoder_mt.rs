@@ -9,15 +9,16 @@ use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 
 use crate::api::{
-    LzmaAction, LzmaAllocator, LzmaBlock, LzmaCheck, LzmaFilter, LzmaRet, LzmaStream,
-    LzmaStreamFlags, LZMA_STREAM_HEADER_SIZE, LZMA_VLI_UNKNOWN,
+    LzmaAction, LzmaAllocator, LzmaBlock, LzmaCancelToken, LzmaCheck, LzmaFilter, LzmaRet,
+    LzmaStream, LzmaStreamFlags, LZMA_STREAM_HEADER_SIZE, LZMA_VLI_UNKNOWN,
 };
 use crate::check::{lzma_check_is_supported, lzma_check_size, lzma_crc32};
 
 use super::{
-    lzma_block_buffer_bound, lzma_block_buffer_encode, lzma_block_unpadded_size, lzma_cputhreads,
-    lzma_end, lzma_mt_block_size, lzma_stream_footer_encode, lzma_stream_header_encode,
-    lzma_strm_init, lzma_vli_encode, CoderType, LzmaNextCoder, LZMA_THREADS_MAX,
+    lzma_block_buffer_bound_filters, lzma_block_buffer_encode, lzma_block_buffer_encode_cancel,
+    lzma_block_unpadded_size, lzma_cputhreads, lzma_end, lzma_mt_block_size,
+    lzma_stream_footer_encode, lzma_stream_header_encode, lzma_strm_init, lzma_vli_encode,
+    CoderType, LzmaNextCoder, LZMA_THREADS_MAX,
 };
 
 const INDEX_INDICATOR: u8 = 0x00;
@@ -29,7 +30,7 @@ struct BlockRecord {
     uncompressed_size: u64,
 }
 
-fn encode_index(
+pub(crate) fn encode_index(
     records: &[(u64, u64)],
     output: &mut [u8],
     out_pos: &mut usize,
@@ -125,12 +126,16 @@ struct CompressedBlock {
     is_last: bool,
 }
 
+/// 工作线程的名称，便于在调试器和 /proc 中辨认
+const MT_WORKER_NAME: &str = "lzma-mt-worker";
+
 fn worker_thread(
     rx: Arc<Mutex<Receiver<InputBlock>>>,
     filters: Vec<LzmaFilter>,
     check: LzmaCheck,
     outbuf_size: usize,
     tx: Sender<CompressedBlock>,
+    cancel: Option<LzmaCancelToken>,
 ) {
     let allocator = LzmaAllocator::default();
 
@@ -142,6 +147,10 @@ fn worker_thread(
                 Err(_) => return,
             }
         };
+        // 取消后不再编码剩下的 Block，直接退出
+        if cancel.as_ref().is_some_and(|c| c.is_canceled()) {
+            return;
+        }
         let mut block = LzmaBlock {
             version: 0,
             check: check.clone(),
@@ -154,16 +163,31 @@ fn worker_thread(
         let mut out_pos = 0;
         let input_len = input.data.len();
 
-        let ret = lzma_block_buffer_encode(
-            &mut block,
-            &allocator,
-            &mut input.data,
-            input_len,
-            &mut output,
-            &mut out_pos,
-            outbuf_size,
-        );
+        let ret = match &cancel {
+            Some(cancel) => lzma_block_buffer_encode_cancel(
+                &mut block,
+                &allocator,
+                &mut input.data,
+                input_len,
+                &mut output,
+                &mut out_pos,
+                outbuf_size,
+                cancel,
+            ),
+            None => lzma_block_buffer_encode(
+                &mut block,
+                &allocator,
+                &mut input.data,
+                input_len,
+                &mut output,
+                &mut out_pos,
+                outbuf_size,
+            ),
+        };
 
+        if ret == LzmaRet::Canceled {
+            return;
+        }
         if ret != LzmaRet::Ok {
             tx.send(CompressedBlock {
                 seq: input.seq,
@@ -221,7 +245,7 @@ pub fn lzma_stream_encoder_mt(
         return ret;
     }
 
-    let outbuf_size = lzma_block_buffer_bound(block_size as usize);
+    let outbuf_size = lzma_block_buffer_bound_filters(filters, block_size) as usize;
     if outbuf_size == 0 {
         lzma_end(Some(strm));
         return LzmaRet::MemError;
@@ -239,11 +263,14 @@ pub fn lzma_stream_encoder_mt(
         threads,
         block_size as usize,
         outbuf_size,
+        internal.cancel.clone(),
     );
 
     internal.next = Some(Box::new(LzmaNextCoder {
         coder: Some(CoderType::MtStreamEncoder(mt_coder)),
         code: Some(mt_encode_code),
+        end: Some(mt_encode_end),
+        get_block: Some(mt_encode_get_block),
         ..Default::default()
     }));
 
@@ -290,6 +317,9 @@ pub struct MtStreamEncoder {
     total_out: u64,
 
     finished: bool,
+
+    /// 初始化时从 lzma_stream 取得的取消令牌，工作线程各持有一份
+    cancel: Option<LzmaCancelToken>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
@@ -308,6 +338,7 @@ impl MtStreamEncoder {
         threads: u32,
         block_size: usize,
         outbuf_size: usize,
+        cancel: Option<LzmaCancelToken>,
     ) -> Self {
         let stream_flags = LzmaStreamFlags {
             version: 0,
@@ -341,6 +372,7 @@ impl MtStreamEncoder {
             total_in: 0,
             total_out: 0,
             finished: false,
+            cancel,
         }
     }
 
@@ -359,11 +391,13 @@ impl MtStreamEncoder {
             let filters = self.filters.clone();
             let check = self.check.clone();
             let outbuf_size = self.outbuf_size;
+            let cancel = self.cancel.clone();
 
             let handle = std::thread::Builder::new()
+                .name(MT_WORKER_NAME.to_string())
                 .stack_size(8 * 1024 * 1024)
                 .spawn(move || {
-                    worker_thread(rx, filters, check, outbuf_size, tx);
+                    worker_thread(rx, filters, check, outbuf_size, tx, cancel);
                 })
                 .expect("Failed to spawn worker thread");
             self.workers.push(handle);
@@ -437,6 +471,19 @@ impl MtStreamEncoder {
         None
     }
 
+    fn is_canceled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.is_canceled())
+    }
+
+    /// 关闭通道并等待所有工作线程退出
+    fn stop_workers(&mut self) {
+        self.input_tx.take();
+        self.output_rx.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+
     fn finalize(&mut self) {
         // Drop input sender so workers know no more work is coming
         self.input_tx.take();
@@ -483,6 +530,10 @@ fn mt_encode_code(
         _ => return LzmaRet::ProgError,
     };
 
+    if mt.is_canceled() {
+        return LzmaRet::Canceled;
+    }
+
     // Phase 1: Write stream header
     if mt.sequence == MtSequence::StreamHeader {
         let copy_len = (mt.header.len() - mt.header_pos).min(out_size - *out_pos);
@@ -520,6 +571,11 @@ fn mt_encode_code(
             mt.try_dispatch_block(true);
             mt.finalize();
             mt.finished = true;
+
+            // finalize() 等待工作线程时可能被取消
+            if mt.is_canceled() {
+                return LzmaRet::Canceled;
+            }
         } else if action == LzmaAction::FullFlush || action == LzmaAction::SyncFlush {
             mt.try_dispatch_block(true);
         }
@@ -618,3 +674,95 @@ fn mt_encode_code(
 
     LzmaRet::Ok
 }
+
+fn mt_encode_end(coder: &mut CoderType) {
+    if let CoderType::MtStreamEncoder(ref mut mt) = coder {
+        mt.stop_workers();
+    }
+}
+
+fn mt_encode_get_block(coder: &mut CoderType) -> u64 {
+    match coder {
+        CoderType::MtStreamEncoder(ref mt) => mt.block_seq as u64,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::{lzma_stream_encoder_mt, MT_WORKER_NAME};
+    use crate::api::{
+        LzmaCancelToken, LzmaCheck, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet,
+        LzmaStream, LZMA_FILTER_LZMA2, LZMA_VLI_UNKNOWN,
+    };
+    use crate::common::{lzma_set_cancel_token, lzma_set_progress_callback};
+    use crate::lzma::lzma_lzma_preset;
+    use crate::test_util::{code_all, pseudo_random};
+
+    /// 本进程中仍在运行的多线程编码器工作线程数
+    fn mt_workers() -> usize {
+        fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|task| fs::read_to_string(task.ok()?.path().join("comm")).ok())
+            .filter(|comm| comm.trim_end() == MT_WORKER_NAME)
+            .count()
+    }
+
+    /// 设置了进度回调函数的 LzmaStream 可以交给其他线程
+    #[test]
+    fn stream_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<LzmaStream>();
+    }
+
+    /// 在另一个线程中取消 3 个工作线程的编码
+    #[test]
+    fn cancel_from_other_thread() {
+        let mut lzma = LzmaOptionsLzma::default();
+        assert!(!lzma_lzma_preset(&mut lzma, 1));
+        lzma.dict_size = 64 << 10;
+        let filters = [
+            LzmaFilter {
+                id: LZMA_FILTER_LZMA2,
+                options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma)),
+            },
+            LzmaFilter {
+                id: LZMA_VLI_UNKNOWN,
+                options: None,
+            },
+        ];
+        let data = pseudo_random(8 << 20, 5);
+        let token = LzmaCancelToken::new();
+        let (tx, rx) = mpsc::channel();
+
+        let mut strm = LzmaStream::default();
+        lzma_set_cancel_token(&mut strm, Some(token.clone()));
+        lzma_set_progress_callback(
+            &mut strm,
+            Some(Box::new(move |progress| {
+                let _ = tx.send(progress.progress_in);
+            })),
+        );
+        assert_eq!(
+            lzma_stream_encoder_mt(&mut strm, &filters, LzmaCheck::Crc64, 3),
+            LzmaRet::Ok
+        );
+
+        let ret = thread::scope(|scope| {
+            let data = &data;
+            let encoder = scope.spawn(move || code_all(&mut strm, data));
+
+            // 收到第一次进度之后取消，这时工作线程还在编码
+            rx.recv().unwrap();
+            token.cancel();
+            encoder.join().unwrap()
+        });
+
+        assert_eq!(ret, Err(LzmaRet::Canceled));
+        assert_eq!(mt_workers(), 0);
+    }
+}
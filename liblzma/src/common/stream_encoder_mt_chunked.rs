@@ -0,0 +1,619 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 单个 Block 内并行编码 LZMA2 块的多线程编码器
+//!
+//! `lzma_stream_encoder_mt` 把输入分成相互独立的 Block，每个 Block 都从空字典
+//! 开始，Block 越小压缩率损失越大。这里整个 Stream 只有一个 Block：输入被分成
+//! 若干段，各线程把一段编码为 LZMA2 块，并以这一段之前的 dict_size 字节作为
+//! 预设字典。每段的第一个块只重置状态而不重置字典，解码器在解码时字典中已经有
+//! 前面的数据，所以各段的输出按顺序拼接起来就是一个普通的 LZMA2 数据流，
+//! 压缩率接近单线程编码。
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::api::{
+    LzmaAction, LzmaBlock, LzmaCancelToken, LzmaCheck, LzmaFilter, LzmaOptionsLzma,
+    LzmaOptionsType, LzmaRet, LzmaStream, LzmaStreamFlags, LZMA_BLOCK_HEADER_SIZE_MAX,
+    LZMA_FILTER_LZMA2, LZMA_STREAM_HEADER_SIZE, LZMA_VLI_UNKNOWN,
+};
+use crate::check::{
+    lzma_check_finish, lzma_check_init, lzma_check_is_supported, lzma_check_size,
+    lzma_check_update, LzmaCheckState,
+};
+use crate::lzma::lzma_lzma2_encode_segment;
+
+use super::{
+    encode_index, lzma_block_header_encode, lzma_block_header_size, lzma_bufcpy,
+    lzma_mt_block_size, lzma_stream_footer_encode, lzma_stream_header_encode, lzma_strm_init,
+    CoderType, LzmaNextCoder, LZMA_THREADS_MAX,
+};
+
+/// 交给工作线程的一段输入
+struct Segment {
+    seq: u64,
+    /// 这一段之前的数据，作为预设字典
+    window: Vec<u8>,
+    data: Vec<u8>,
+    is_last: bool,
+}
+
+/// 一段输入编码后的 LZMA2 块
+struct EncodedSegment {
+    seq: u64,
+    result: Result<Vec<u8>, LzmaRet>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sequence {
+    Block,
+    Trailer,
+    End,
+}
+
+#[derive(Debug)]
+pub struct MtChunkedEncoder {
+    sequence: Sequence,
+    options: LzmaOptionsLzma,
+    check: LzmaCheck,
+    check_state: LzmaCheckState,
+    threads: u32,
+    segment_size: usize,
+
+    segment_tx: Option<Sender<Segment>>,
+    encoded_rx: Option<Receiver<EncodedSegment>>,
+    workers: Vec<JoinHandle<()>>,
+
+    /// 正在累积的一段输入
+    segment_buf: Vec<u8>,
+    /// segment_buf 之前最多 dict_size 字节的输入
+    history: Vec<u8>,
+    /// 最后一段已交给工作线程
+    last_sent: bool,
+
+    next_seq: u64,
+    /// 下一个要输出的段
+    out_seq: u64,
+    /// 已编码、还不能输出的段
+    encoded: BTreeMap<u64, Vec<u8>>,
+
+    /// Stream Header、Block Header 或已编码的段中尚未输出的部分
+    out_buf: Vec<u8>,
+    out_pos: usize,
+
+    block_header_size: u64,
+    compressed_size: u64,
+    uncompressed_size: u64,
+
+    /// 初始化时从 lzma_stream 取得的取消令牌，工作线程各持有一份
+    cancel: Option<LzmaCancelToken>,
+}
+
+fn worker_thread(
+    rx: Arc<Mutex<Receiver<Segment>>>,
+    options: LzmaOptionsLzma,
+    tx: Sender<EncodedSegment>,
+    cancel: Option<LzmaCancelToken>,
+) {
+    loop {
+        let segment = {
+            let guard = rx.lock().unwrap();
+            match guard.recv() {
+                Ok(segment) => segment,
+                Err(_) => return,
+            }
+        };
+
+        let result = lzma_lzma2_encode_segment(
+            &options,
+            &segment.window,
+            &segment.data,
+            segment.is_last,
+            cancel.as_ref(),
+        );
+        if tx
+            .send(EncodedSegment {
+                seq: segment.seq,
+                result,
+            })
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+impl MtChunkedEncoder {
+    fn start_workers(&mut self) {
+        let (segment_tx, worker_rx) = mpsc::channel::<Segment>();
+        let (encoded_tx, encoded_rx) = mpsc::channel::<EncodedSegment>();
+        let worker_rx = Arc::new(Mutex::new(worker_rx));
+
+        for _ in 0..self.threads {
+            let rx = Arc::clone(&worker_rx);
+            let tx = encoded_tx.clone();
+            let options = self.options.clone();
+            let cancel = self.cancel.clone();
+
+            let handle = std::thread::Builder::new()
+                .stack_size(8 * 1024 * 1024)
+                .spawn(move || worker_thread(rx, options, tx, cancel))
+                .expect("Failed to spawn worker thread");
+            self.workers.push(handle);
+        }
+
+        self.segment_tx = Some(segment_tx);
+        self.encoded_rx = Some(encoded_rx);
+    }
+
+    /// 已交给工作线程、还没有输出的段数
+    fn in_flight(&self) -> u64 {
+        self.next_seq - self.out_seq
+    }
+
+    fn send_segment(&mut self, is_last: bool) {
+        let data = std::mem::replace(&mut self.segment_buf, Vec::with_capacity(self.segment_size));
+
+        let segment = Segment {
+            seq: self.next_seq,
+            window: self.history.clone(),
+            data,
+            is_last,
+        };
+
+        self.history.extend_from_slice(&segment.data);
+        let dict_size = self.options.dict_size as usize;
+        if self.history.len() > dict_size {
+            self.history.drain(..self.history.len() - dict_size);
+        }
+
+        self.next_seq += 1;
+        self.last_sent = is_last;
+        if let Some(tx) = &self.segment_tx {
+            let _ = tx.send(segment);
+        }
+    }
+
+    /// 收取工作线程的结果，wait 为 true 时至少等到一个结果
+    fn receive(&mut self, wait: bool) -> LzmaRet {
+        let rx = match &self.encoded_rx {
+            Some(rx) => rx,
+            None => return LzmaRet::ProgError,
+        };
+
+        let mut wait = wait;
+        loop {
+            let encoded = if wait {
+                match rx.recv() {
+                    Ok(encoded) => encoded,
+                    Err(_) => return LzmaRet::ProgError,
+                }
+            } else {
+                match rx.try_recv() {
+                    Ok(encoded) => encoded,
+                    Err(_) => return LzmaRet::Ok,
+                }
+            };
+            wait = false;
+
+            match encoded.result {
+                Ok(data) => {
+                    self.encoded.insert(encoded.seq, data);
+                }
+                Err(ret) => return ret,
+            }
+        }
+    }
+
+    fn stop_workers(&mut self) {
+        self.segment_tx.take();
+        self.encoded_rx.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+
+    /// 生成 Block Padding、Check、Index 和 Stream Footer
+    fn encode_trailer(&mut self) -> LzmaRet {
+        lzma_check_finish(&mut self.check_state, self.check);
+        let check_size = lzma_check_size(self.check) as usize;
+
+        let padding = ((4 - (self.compressed_size & 3)) & 3) as usize;
+        let mut trailer = vec![0u8; padding];
+        trailer.extend_from_slice(&self.check_state.buffer.u8[..check_size]);
+
+        let unpadded_size = self.block_header_size + self.compressed_size + check_size as u64;
+        let records = [(unpadded_size, self.uncompressed_size)];
+
+        // Index 最多 1 + 3 * 9 + 3 + 4 字节，encode_index 从缓冲区开头计算填充
+        let mut index = [0u8; 64];
+        let mut index_size = 0;
+        let index_len = index.len();
+        let ret = encode_index(&records, &mut index, &mut index_size, index_len);
+        if ret != LzmaRet::Ok {
+            return ret;
+        }
+        trailer.extend_from_slice(&index[..index_size]);
+
+        let mut stream_flags = LzmaStreamFlags {
+            version: 0,
+            check: self.check,
+            backward_size: index_size as u64,
+            ..Default::default()
+        };
+        let mut footer = [0u8; LZMA_STREAM_HEADER_SIZE];
+        let ret = lzma_stream_footer_encode(&mut stream_flags, &mut footer);
+        if ret != LzmaRet::Ok {
+            return ret;
+        }
+        trailer.extend_from_slice(&footer);
+
+        self.out_buf = trailer;
+        self.out_pos = 0;
+        LzmaRet::Ok
+    }
+}
+
+fn mt_chunked_encode(
+    coder: &mut CoderType,
+    input: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    output: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let mt = match coder {
+        CoderType::MtChunkedEncoder(ref mut m) => m,
+        _ => return LzmaRet::ProgError,
+    };
+
+    if mt.cancel.as_ref().is_some_and(|c| c.is_canceled()) {
+        return LzmaRet::Canceled;
+    }
+
+    loop {
+        // 先输出已有的数据
+        if mt.out_pos < mt.out_buf.len() {
+            let out_len = mt.out_buf.len();
+            lzma_bufcpy(
+                &mt.out_buf,
+                &mut mt.out_pos,
+                out_len,
+                output,
+                out_pos,
+                out_size,
+            );
+            if mt.out_pos < out_len {
+                return LzmaRet::Ok;
+            }
+        }
+
+        match mt.sequence {
+            Sequence::Block => {
+                // 按顺序输出已编码的段
+                if let Some(data) = mt.encoded.remove(&mt.out_seq) {
+                    mt.out_seq += 1;
+                    mt.compressed_size += data.len() as u64;
+                    mt.out_buf = data;
+                    mt.out_pos = 0;
+                    continue;
+                }
+
+                if mt.last_sent {
+                    if mt.in_flight() == 0 {
+                        mt.stop_workers();
+                        let ret = mt.encode_trailer();
+                        if ret != LzmaRet::Ok {
+                            return ret;
+                        }
+                        mt.sequence = Sequence::Trailer;
+                        continue;
+                    }
+
+                    // 没有更多输入，只能等待工作线程
+                    let ret = mt.receive(true);
+                    if ret != LzmaRet::Ok {
+                        return ret;
+                    }
+                    continue;
+                }
+
+                // 读入输入
+                let used = (in_size - *in_pos).min(mt.segment_size - mt.segment_buf.len());
+                let data = &input[*in_pos..*in_pos + used];
+                lzma_check_update(&mut mt.check_state, mt.check, data, used);
+                mt.segment_buf.extend_from_slice(data);
+                mt.uncompressed_size += used as u64;
+                *in_pos += used;
+
+                let finish = action == LzmaAction::Finish && *in_pos == in_size;
+                if mt.segment_buf.len() == mt.segment_size || finish {
+                    // 限制同时在内存中的段数
+                    if mt.in_flight() >= 2 * mt.threads as u64 {
+                        let ret = mt.receive(true);
+                        if ret != LzmaRet::Ok {
+                            return ret;
+                        }
+                        continue;
+                    }
+
+                    mt.send_segment(finish);
+                }
+
+                let ret = mt.receive(false);
+                if ret != LzmaRet::Ok {
+                    return ret;
+                }
+
+                if *in_pos == in_size && !mt.last_sent && !mt.encoded.contains_key(&mt.out_seq) {
+                    return LzmaRet::Ok;
+                }
+            }
+
+            Sequence::Trailer => {
+                mt.sequence = Sequence::End;
+                return LzmaRet::StreamEnd;
+            }
+
+            Sequence::End => return LzmaRet::StreamEnd,
+        }
+    }
+}
+
+fn mt_chunked_end(coder: &mut CoderType) {
+    if let CoderType::MtChunkedEncoder(ref mut mt) = coder {
+        mt.stop_workers();
+    }
+}
+
+/// 整个 Stream 只有一个 Block
+fn mt_chunked_get_block(_coder: &mut CoderType) -> u64 {
+    1
+}
+
+/// 初始化在单个 Block 内并行编码的 .xz 编码器
+///
+/// 过滤器链只能是一个 LZMA2 过滤器。输出是只有一个 Block 的 .xz 文件，
+/// Block Header 中不记录压缩前后的大小。只支持 `LzmaAction::Run` 和
+/// `LzmaAction::Finish`。
+pub fn lzma_stream_encoder_mt_chunked(
+    strm: &mut LzmaStream,
+    filters: &[LzmaFilter],
+    check: LzmaCheck,
+    threads: u32,
+) -> LzmaRet {
+    if !lzma_check_is_supported(check) {
+        return LzmaRet::UnsupportedCheck;
+    }
+
+    if threads < 1 || threads > LZMA_THREADS_MAX {
+        return LzmaRet::OptionsError;
+    }
+
+    let filters: Vec<LzmaFilter> = filters
+        .iter()
+        .take_while(|f| f.id != LZMA_VLI_UNKNOWN)
+        .cloned()
+        .collect();
+    let options = match filters.as_slice() {
+        [LzmaFilter {
+            id: LZMA_FILTER_LZMA2,
+            options: Some(LzmaOptionsType::LzmaOptionsLzma(opt)),
+        }] => opt.clone(),
+        _ => return LzmaRet::OptionsError,
+    };
+
+    let segment_size = lzma_mt_block_size(&filters) as usize;
+    if segment_size == 0 {
+        return LzmaRet::OptionsError;
+    }
+
+    // Block Header 不记录大小，所以可以先写出
+    let mut block = LzmaBlock {
+        version: 0,
+        check,
+        compressed_size: LZMA_VLI_UNKNOWN,
+        uncompressed_size: LZMA_VLI_UNKNOWN,
+        filters: filters.clone(),
+        ..Default::default()
+    };
+    block.filters.push(LzmaFilter {
+        id: LZMA_VLI_UNKNOWN,
+        options: None,
+    });
+    let ret = lzma_block_header_size(&mut block);
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+    let mut block_header = [0u8; LZMA_BLOCK_HEADER_SIZE_MAX as usize];
+    let ret = lzma_block_header_encode(&block, &mut block_header);
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+
+    let stream_flags = LzmaStreamFlags {
+        version: 0,
+        check,
+        ..Default::default()
+    };
+    let mut out_buf = vec![0u8; LZMA_STREAM_HEADER_SIZE];
+    let ret = lzma_stream_header_encode(&stream_flags, &mut out_buf);
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+    out_buf.extend_from_slice(&block_header[..block.header_size as usize]);
+
+    let ret = lzma_strm_init(Some(strm));
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+    let cancel = strm
+        .internal
+        .borrow()
+        .as_ref()
+        .and_then(|i| i.cancel.clone());
+
+    let mut check_state = LzmaCheckState::default();
+    lzma_check_init(&mut check_state, check);
+
+    let mut mt = MtChunkedEncoder {
+        sequence: Sequence::Block,
+        options,
+        check,
+        check_state,
+        threads,
+        segment_size,
+        segment_tx: None,
+        encoded_rx: None,
+        workers: Vec::new(),
+        segment_buf: Vec::with_capacity(segment_size),
+        history: Vec::new(),
+        last_sent: false,
+        next_seq: 0,
+        out_seq: 0,
+        encoded: BTreeMap::new(),
+        out_buf,
+        out_pos: 0,
+        block_header_size: block.header_size as u64,
+        compressed_size: 0,
+        uncompressed_size: 0,
+        cancel,
+    };
+    mt.start_workers();
+
+    let mut internal = strm.internal.borrow_mut();
+    let internal = match internal.as_mut() {
+        Some(internal) => internal,
+        None => return LzmaRet::ProgError,
+    };
+
+    internal.next = Some(Box::new(LzmaNextCoder {
+        coder: Some(CoderType::MtChunkedEncoder(mt)),
+        code: Some(mt_chunked_encode),
+        end: Some(mt_chunked_end),
+        get_block: Some(mt_chunked_get_block),
+        ..Default::default()
+    }));
+
+    internal.supported_actions[LzmaAction::Run as usize] = true;
+    internal.supported_actions[LzmaAction::Finish as usize] = true;
+
+    LzmaRet::Ok
+}
+#[cfg(test)]
+mod tests {
+    use super::lzma_stream_encoder_mt_chunked;
+    use crate::api::{
+        LzmaCheck, LzmaFilter, LzmaOptionsType, LzmaRet, LzmaStream, LZMA_FILTER_LZMA2,
+        LZMA_STREAM_HEADER_SIZE,
+    };
+    use crate::common::stream_decoder::lzma_stream_decoder;
+    use crate::common::stream_encoder::lzma_stream_encoder;
+    use crate::test_util::{code_all, lzma_filters, pseudo_random};
+
+    /// 预设等级 1、字典大小为 dict_size 的 LZMA2 过滤器链
+    fn filters(dict_size: u32) -> Vec<LzmaFilter> {
+        let mut filters = lzma_filters(LZMA_FILTER_LZMA2, 1);
+        if let Some(LzmaOptionsType::LzmaOptionsLzma(opt)) = &mut filters[0].options {
+            opt.dict_size = dict_size;
+        }
+        filters
+    }
+
+    fn encode_mt(filters: &[LzmaFilter], threads: u32, input: &[u8]) -> Vec<u8> {
+        let mut strm = LzmaStream::default();
+        assert_eq!(
+            lzma_stream_encoder_mt_chunked(&mut strm, filters, LzmaCheck::Crc64, threads),
+            LzmaRet::Ok
+        );
+        code_all(&mut strm, input).unwrap()
+    }
+
+    fn encode_single(filters: &[LzmaFilter], input: &[u8]) -> Vec<u8> {
+        let mut strm = LzmaStream::default();
+        assert_eq!(
+            lzma_stream_encoder(&mut strm, filters, LzmaCheck::Crc64),
+            LzmaRet::Ok
+        );
+        code_all(&mut strm, input).unwrap()
+    }
+
+    fn decode(xz: &[u8]) -> Vec<u8> {
+        let mut strm = LzmaStream::default();
+        assert_eq!(lzma_stream_decoder(&mut strm, u64::MAX, 0), LzmaRet::Ok);
+        code_all(&mut strm, xz).unwrap()
+    }
+
+    /// Index 中的记录数，即 Block 数
+    fn block_count(xz: &[u8]) -> u8 {
+        let footer = &xz[xz.len() - LZMA_STREAM_HEADER_SIZE..];
+        let backward_size = (u32::from_le_bytes(footer[4..8].try_into().unwrap()) as usize + 1) * 4;
+        let index = &xz[xz.len() - LZMA_STREAM_HEADER_SIZE - backward_size..];
+        assert_eq!(index[0], 0);
+        index[1]
+    }
+
+    /// 每段的长度至少是字典大小的三倍，段之间的匹配要靠前一段的数据作为预设字典。
+    /// 输出只有一个 Block，解码结果与输入相同，压缩率接近单线程编码。
+    #[test]
+    fn single_block_round_trip() {
+        // 重复的 20 KiB 随机数据，只有借助前面的数据才能压缩
+        let pattern = pseudo_random(20 << 10, 3);
+        let repeated: Vec<u8> = pattern
+            .iter()
+            .cycle()
+            .take((3 << 20) + 12345)
+            .copied()
+            .collect();
+        let mut mixed = pseudo_random(1 << 20, 5);
+        mixed.extend_from_slice(&repeated[..(1 << 20) + 777]);
+
+        for (dict_size, input) in [
+            (64 << 10, &repeated),
+            (1 << 20, &repeated),
+            (64 << 10, &mixed),
+        ] {
+            let filters = filters(dict_size);
+            let single = encode_single(&filters, input);
+
+            for threads in [1, 2, 4] {
+                let xz = encode_mt(&filters, threads, input);
+                assert_eq!(block_count(&xz), 1);
+                assert!(
+                    decode(&xz) == *input,
+                    "dict {} threads {}",
+                    dict_size,
+                    threads
+                );
+                assert!(
+                    xz.len() <= single.len() + single.len() / 20 + 1024,
+                    "dict {} threads {}: {} vs {}",
+                    dict_size,
+                    threads,
+                    xz.len(),
+                    single.len()
+                );
+            }
+        }
+    }
+
+    /// 空输入和不足一段的输入
+    #[test]
+    fn short_input() {
+        let filters = filters(64 << 10);
+        for len in [0, 1, 1000, (1 << 20) - 1, 1 << 20, (1 << 20) + 1] {
+            let input = pseudo_random(len, 9);
+            let xz = encode_mt(&filters, 2, &input);
+            assert_eq!(block_count(&xz), 1);
+            assert!(decode(&xz) == input, "len {}", len);
+        }
+    }
+}
@@ -8,19 +8,22 @@ use std::collections::HashMap;
 use std::{mem::offset_of, sync::LazyLock};
 
 use crate::api::{
-    LzmaDeltaType, LzmaFilter, LzmaOptionsType, LzmaVli, LZMA_FILTERS_MAX, LZMA_STR_ALL_FILTERS,
+    LzmaDeltaType, LzmaFilter, LzmaOptionsCustom, LzmaOptionsDedup, LzmaOptionsEncrypt, LzmaOptionsType, LzmaVli, LZMA_FILTERS_MAX, LZMA_STR_ALL_FILTERS,
     LZMA_STR_DECODER, LZMA_STR_ENCODER, LZMA_STR_GETOPT_LONG, LZMA_STR_NO_SPACES,
     LZMA_STR_NO_VALIDATION, LZMA_VLI_UNKNOWN,
 };
 use crate::common::lzma_validate_chain;
+use crate::custom::{lzma_custom_filter_find, lzma_custom_filter_find_name};
 use crate::LzmaOptionsDelta;
 use crate::{
     api::{
         LzmaMatchFinder, LzmaMode, LzmaOptionsBcj, LzmaOptionsLzma, LzmaRet, LZMA_DELTA_DIST_MAX,
+        LZMA_DEDUP_CHUNK_LOG_DEFAULT, LZMA_DEDUP_CHUNK_LOG_MAX, LZMA_DEDUP_CHUNK_LOG_MIN,
         LZMA_DELTA_DIST_MIN, LZMA_DICT_SIZE_MIN, LZMA_FILTER_ARM, LZMA_FILTER_ARM64,
-        LZMA_FILTER_ARMTHUMB, LZMA_FILTER_DELTA, LZMA_FILTER_IA64, LZMA_FILTER_LZMA1,
+        LZMA_FILTER_ARMTHUMB, LZMA_FILTER_DEDUP, LZMA_FILTER_DELTA, LZMA_FILTER_ENCRYPT, LZMA_FILTER_IA64, LZMA_FILTER_LZMA1,
         LZMA_FILTER_LZMA2, LZMA_FILTER_POWERPC, LZMA_FILTER_SPARC, LZMA_FILTER_X86, LZMA_LCLP_MAX,
         LZMA_LCLP_MIN, LZMA_PB_MAX, LZMA_PB_MIN, LZMA_PRESET_DEFAULT, LZMA_PRESET_EXTREME,
+        LZMA_PRESET_FAST,
     },
     lzma::lzma_lzma_preset,
 };
@@ -252,18 +255,13 @@ pub static BCJ_OPTMAP: &[OptionMap] = &[OptionMap {
 /// 解析 BCJ 选项
 ///
 /// # 参数
-/// - `str_ptr`: 输入字符串指针，表示当前解析位置
-/// - `str_end`: 输入字符串的结束位置
+/// - `str_ptr`: 选项字符串
 /// - `filter_options`: 用于存储解析结果的过滤器选项
 ///
 /// # 返回值
 /// 如果解析成功，返回 `None`；如果解析失败，返回错误信息
-pub fn parse_bcj(
-    str_ptr: &str, // 修改为 &mut &str
-    str_end: &str,
-    filter_options: LzmaOptionsType,
-) -> Option<String> {
-    parse_options(str_ptr, str_end, filter_options, BCJ_OPTMAP).err() // 将 Result<(), String> 转换为 Option<String>
+pub fn parse_bcj(str_ptr: &str, filter_options: &mut LzmaOptionsType) -> Option<String> {
+    parse_options(str_ptr, filter_options, BCJ_OPTMAP).err() // 将 Result<(), String> 转换为 Option<String>
 }
 
 /// Delta 选项映射表
@@ -281,18 +279,13 @@ pub static DELTA_OPTMAP: &[OptionMap] = &[OptionMap {
 /// 解析 Delta 过滤器选项
 ///
 /// # 参数
-/// - `str_ptr`: 输入字符串的引用，会在解析过程中更新
-/// - `str_end`: 输入字符串的结束位置
+/// - `str_ptr`: 选项字符串
 /// - `filter_options`: 用于存储解析结果的过滤器选项
 ///
 /// # 返回值
 /// 如果解析成功，返回 `None`；如果解析失败，返回错误信息
-pub fn parse_delta(
-    str_ptr: &str,
-    str_end: &str,
-    filter_options: LzmaOptionsType,
-) -> Option<String> {
-    let mut opts: LzmaOptionsDelta = match filter_options {
+pub fn parse_delta(str_ptr: &str, filter_options: &mut LzmaOptionsType) -> Option<String> {
+    let opts = match filter_options {
         LzmaOptionsType::Delta(opts) => opts,
         _ => {
             return Some("Invalid filter options type".to_string());
@@ -303,11 +296,48 @@ pub fn parse_delta(
     opts.dist = LZMA_DELTA_DIST_MIN;
 
     // 调用通用选项解析函数
-    parse_options(str_ptr, str_end, LzmaOptionsType::Delta(opts), DELTA_OPTMAP).err()
+    parse_options(str_ptr, filter_options, DELTA_OPTMAP).err()
+}
+
+/// 长距离去重过滤器选项映射表
+pub static DEDUP_OPTMAP: &[OptionMap] = &[OptionMap {
+    name: "chunk_log",
+    offset: offset_of!(LzmaOptionsDedup, chunk_log) as u16,
+    u: OptionMapUnion::Range {
+        min: LZMA_DEDUP_CHUNK_LOG_MIN,
+        max: LZMA_DEDUP_CHUNK_LOG_MAX,
+    },
+    flags: 0,
+    type_: 0,
+}];
+
+/// 解析长距离去重过滤器选项
+///
+/// # 参数
+/// - `str_ptr`: 选项字符串
+/// - `filter_options`: 用于存储解析结果的过滤器选项
+///
+/// # 返回值
+/// 如果解析成功，返回 `None`；如果解析失败，返回错误信息
+pub fn parse_dedup(str_ptr: &str, filter_options: &mut LzmaOptionsType) -> Option<String> {
+    let opts = match filter_options {
+        LzmaOptionsType::Dedup(opts) => opts,
+        _ => {
+            return Some("Invalid filter options type".to_string());
+        }
+    };
+    opts.chunk_log = LZMA_DEDUP_CHUNK_LOG_DEFAULT;
+
+    parse_options(str_ptr, filter_options, DEDUP_OPTMAP).err()
+}
+
+/// 加密过滤器的密钥只能由应用程序给出，不能从字符串解析
+pub fn parse_encrypt(_str_ptr: &str, _filter_options: &mut LzmaOptionsType) -> Option<String> {
+    Some("The encrypt filter needs a key from the application".to_string())
 }
 
 /// LZMA1 和 LZMA2 的预设字符串
-const LZMA12_PRESET_STR: &str = "0-9[e]";
+const LZMA12_PRESET_STR: &str = "0-9[e|f]";
 
 /// 解析 LZMA12 预设字符串，返回 Ok(()) 表示成功，Err(错误信息) 表示失败
 pub fn parse_lzma12_preset(s: String, preset: &mut u32) -> Option<String> {
@@ -315,6 +345,9 @@ pub fn parse_lzma12_preset(s: String, preset: &mut u32) -> Option<String> {
     if bytes.is_empty() {
         return Some(("预设字符串为空").to_string());
     }
+    if !bytes[0].is_ascii_digit() {
+        return Some(("不支持的预设参数").to_string());
+    }
     // 取第一个字符作为预设数字
     *preset = (bytes[0] - b'0') as u32;
 
@@ -322,6 +355,7 @@ pub fn parse_lzma12_preset(s: String, preset: &mut u32) -> Option<String> {
     for &c in &bytes[1..] {
         match c as char {
             'e' => *preset |= LZMA_PRESET_EXTREME,
+            'f' => *preset |= LZMA_PRESET_FAST,
             _ => return Some(("不支持的预设标志").to_string()),
         }
     }
@@ -337,7 +371,7 @@ pub fn set_lzma12_preset(s: String, filter_options: &mut LzmaOptionsLzma) -> Opt
         return errmsg;
     }
     // 设置选项
-    if !lzma_lzma_preset(filter_options, preset) {
+    if lzma_lzma_preset(filter_options, preset) {
         return Some(("不支持的预设参数").to_string());
     }
 
@@ -364,8 +398,9 @@ const LZMA_MF_HC4: u32 = 0x04;
 const LZMA_MF_BT2: u32 = 0x12;
 const LZMA_MF_BT3: u32 = 0x13;
 const LZMA_MF_BT4: u32 = 0x14;
+const LZMA_MF_HT4: u32 = 0x24;
 // LZMA12 匹配查找器映射表
-const LZMA12_MF_MAP: &[NameValueMap; 6] = &[
+const LZMA12_MF_MAP: &[NameValueMap; 7] = &[
     NameValueMap {
         name: "hc3",
         value: LZMA_MF_HC3,
@@ -386,6 +421,10 @@ const LZMA12_MF_MAP: &[NameValueMap; 6] = &[
         name: "bt4",
         value: LZMA_MF_BT4,
     },
+    NameValueMap {
+        name: "ht4",
+        value: LZMA_MF_HT4,
+    },
     NameValueMap { name: "", value: 0 },
 ];
 
@@ -479,18 +518,13 @@ const LZMA12_OPTMAP: &[OptionMap] = &[
 /// 解析 LZMA12 过滤器选项
 ///
 /// # Arguments
-/// * `str_ptr` - 输入字符串的引用的引用，会在解析过程中更新
-/// * `str_end` - 输入字符串的结束位置
-/// * `filter_options` - LZMA 选项结构体
+/// * `str_ptr` - 选项字符串
+/// * `filter_options` - LZMA 选项结构体，解析结果写入其中
 ///
 /// # Returns
 /// * `Option<String>` - 如果解析出错，返回错误信息；如果成功，返回 None
-pub fn parse_lzma12(
-    str_ptr: &str,
-    str_end: &str,
-    filter_options: LzmaOptionsType,
-) -> Option<String> {
-    let mut opts: LzmaOptionsLzma = match filter_options {
+pub fn parse_lzma12(str_ptr: &str, filter_options: &mut LzmaOptionsType) -> Option<String> {
+    let opts = match filter_options {
         LzmaOptionsType::LzmaOptionsLzma(opts) => opts,
         _ => {
             return Some("Invalid filter options type".to_string());
@@ -498,20 +532,31 @@ pub fn parse_lzma12(
     };
     // 设置默认预设值
     // 注意：在 Rust 中，我们假设 lzma_lzma_preset 总是成功
-    let preset_ret = lzma_lzma_preset(&mut opts, LZMA_PRESET_DEFAULT);
+    let preset_ret = lzma_lzma_preset(opts, LZMA_PRESET_DEFAULT);
     assert!(!preset_ret);
+
+    // 预设会重设所有选项，所以无论 preset= 写在什么位置都先应用它，
+    // 再用其他选项覆盖预设中的值
+    if let Some(preset) = str_ptr
+        .split(',')
+        .filter_map(|opt| opt.strip_prefix("preset="))
+        .last()
+    {
+        if let Some(errmsg) = set_lzma12_preset(preset.to_string(), opts) {
+            return Some(errmsg);
+        }
+    }
+
     // 解析选项
-    parse_options(
-        str_ptr,
-        str_end,
-        LzmaOptionsType::LzmaOptionsLzma(opts.clone()),
-        LZMA12_OPTMAP,
-    )
-    .err();
+    if let Err(errmsg) = parse_options(str_ptr, filter_options, LZMA12_OPTMAP) {
+        return Some(errmsg);
+    }
 
     // 验证 lc + lp 不超过最大值
-    if opts.lc + opts.lp > LZMA_LCLP_MAX {
-        return Some("The sum of lc and lp must not exceed 4".to_string());
+    if let LzmaOptionsType::LzmaOptionsLzma(opts) = filter_options {
+        if opts.lc + opts.lp > LZMA_LCLP_MAX {
+            return Some("The sum of lc and lp must not exceed 4".to_string());
+        }
     }
 
     None
@@ -536,7 +581,7 @@ pub struct FilterNameMap {
     pub id: u64, // 对应 C 中的 `lzma_vli`
 
     /// 解析函数指针
-    pub parse: fn(&str, &str, LzmaOptionsType) -> Option<String>,
+    pub parse: fn(&str, &mut LzmaOptionsType) -> Option<String>,
 
     /// 选项映射表
     pub optmap: &'static [OptionMap],
@@ -653,13 +698,32 @@ pub static FILTER_NAME_MAP: &[FilterNameMap] = &[
         strfy_decoder: 1,
         allow_null: false,
     },
+    FilterNameMap {
+        name: "dedup",
+        opts_size: std::mem::size_of::<LzmaOptionsDedup>() as u32,
+        id: LZMA_FILTER_DEDUP,
+        parse: parse_dedup,
+        optmap: DEDUP_OPTMAP,
+        strfy_encoder: 1,
+        strfy_decoder: 1,
+        allow_null: false,
+    },
+    FilterNameMap {
+        name: "encrypt",
+        opts_size: std::mem::size_of::<LzmaOptionsEncrypt>() as u32,
+        id: LZMA_FILTER_ENCRYPT,
+        parse: parse_encrypt,
+        optmap: &[],
+        strfy_encoder: 0,
+        strfy_decoder: 0,
+        allow_null: false,
+    },
 ];
 
 /// 解析过滤器选项
 ///
 /// # 参数
-/// - `str_ptr`: 输入字符串的引用，会在解析过程中更新
-/// - `str_end`: 输入字符串的结束位置
+/// - `str_ptr`: 逗号分隔的 name=value 选项字符串
 /// - `filter_options`: 用于存储解析结果的过滤器选项
 /// - `optmap`: 选项映射表
 ///
@@ -667,54 +731,51 @@ pub static FILTER_NAME_MAP: &[FilterNameMap] = &[
 /// 如果解析成功，返回 `Ok(())`；如果解析失败，返回错误信息
 pub fn parse_options(
     mut str_ptr: &str,
-    str_end: &str,
-    mut filter_options: LzmaOptionsType,
+    filter_options: &mut LzmaOptionsType,
     optmap: &[OptionMap],
 ) -> Result<(), String> {
-    while str_ptr < str_end && !str_ptr.is_empty() {
+    while !str_ptr.is_empty() {
         // 跳过多余的逗号
         if str_ptr.starts_with(',') {
             str_ptr = &str_ptr[1..];
             continue;
         }
 
-        // 找到下一个 name=value 的结束位置
-        let name_eq_value_end = str_ptr.find(',').unwrap_or(str_end.len());
-        let equals_sign = str_ptr.find('=');
+        // 找到下一个 name=value 的结束位置，之后的部分留到下一轮处理
+        let name_eq_value_end = str_ptr.find(',').unwrap_or(str_ptr.len());
+        let name_eq_value = &str_ptr[..name_eq_value_end];
+        str_ptr = &str_ptr[name_eq_value_end..];
 
         // 如果没有找到 '=' 或选项名称为空，则返回错误
-        if equals_sign.is_none() || str_ptr.starts_with('=') {
-            return Err("选项必须是 'name=value' 格式，并用逗号分隔".to_string());
-        }
-
-        let equals_sign = equals_sign.unwrap();
-        let name_len = equals_sign;
+        let equals_sign = match name_eq_value.find('=') {
+            Some(pos) if pos > 0 => pos,
+            _ => return Err("选项必须是 'name=value' 格式，并用逗号分隔".to_string()),
+        };
 
         // 检查选项名称是否过长
-        if name_len > NAME_LEN_MAX {
+        let name = &name_eq_value[..equals_sign];
+        if name.len() > NAME_LEN_MAX {
             return Err("未知的选项名称".to_string());
         }
 
         // 在 optmap 中查找选项名称
-        let opt = optmap
-            .iter()
-            .find(|opt| opt.name.len() == name_len && opt.name == &str_ptr[..name_len]);
-
-        if opt.is_none() {
-            return Err("未知的选项名称".to_string());
-        }
-
-        let opt = opt.unwrap();
-        str_ptr = &str_ptr[equals_sign + 1..];
+        let opt = match optmap.iter().find(|opt| opt.name == name) {
+            Some(opt) => opt,
+            None => return Err("未知的选项名称".to_string()),
+        };
 
         // 检查选项值是否为空
-        let value_len = name_eq_value_end - equals_sign - 1;
-        if value_len == 0 {
+        let value = &name_eq_value[equals_sign + 1..];
+        if value.is_empty() {
             return Err("选项值不能为空".to_string());
         }
 
+        // 预设由 parse_lzma12() 在其他选项之前应用，这里只跳过它
+        if opt.type_ == OptMapType::LzmaPreset as u8 {
+            continue;
+        }
+
         // 解析选项值
-        let value = &str_ptr[..value_len];
         let parsed_value = if opt.flags & OPTMAP_USE_NAME_VALUE_MAP != 0 {
             // 从名称-值映射表中查找值
             let map = match opt.u {
@@ -722,18 +783,15 @@ pub fn parse_options(
                 _ => return Err("选项值映射表为空".to_string()),
             };
 
-            let map_entry = map.iter().find(|entry| entry.name == value);
-
-            if map_entry.is_none() {
-                return Err("无效的选项值".to_string());
+            match map
+                .iter()
+                .find(|entry| !entry.name.is_empty() && entry.name == value)
+            {
+                Some(entry) => entry.value,
+                None => return Err("无效的选项值".to_string()),
             }
-
-            map_entry.unwrap().value
-        } else if value.chars().all(|c| c.is_ascii_digit()) {
-            // 解析为整数值
-            value.parse::<u32>().map_err(|_| "值超出范围".to_string())?
         } else {
-            return Err("值不是非负整数".to_string());
+            parse_u32_value(value, opt.flags & OPTMAP_USE_BYTE_SUFFIX != 0)?
         };
 
         // 检查值是否在范围内
@@ -744,40 +802,86 @@ pub fn parse_options(
         }
 
         // 设置选项值到 filter_options
-        let offset = opt.offset as usize;
-        let ptr = &mut filter_options as *mut _ as *mut u8;
-        let target = unsafe { ptr.add(offset) as *mut u32 };
-        unsafe {
-            *target = parsed_value;
+        let written = match filter_options {
+            LzmaOptionsType::LzmaOptionsLzma(opts) => {
+                opts.write_value_at_offset(opt.offset as u32, parsed_value)
+            }
+            LzmaOptionsType::Delta(opts) => {
+                opts.write_value_at_offset(opt.offset as u32, parsed_value)
+            }
+            LzmaOptionsType::Bcj(opts) => {
+                opts.write_value_at_offset(opt.offset as u32, parsed_value)
+            }
+            LzmaOptionsType::Dedup(opts) => {
+                opts.write_value_at_offset(opt.offset as u32, parsed_value)
+            }
+            _ => false,
+        };
+        if !written {
+            return Err("Invalid filter options type".to_string());
         }
-
-        // 更新 str_ptr 到下一个选项
-        str_ptr = &str_ptr[name_eq_value_end..];
     }
 
     Ok(())
 }
 
+/// 解析非负整数选项值
+///
+/// use_byte_suffix 为 true 时允许 KiB、MiB 和 GiB 后缀（也接受 K、M、G 和
+/// k、Ki 等写法），值乘以对应的倍数后不能超过 u32::MAX。
+fn parse_u32_value(value: &str, use_byte_suffix: bool) -> Result<u32, String> {
+    let digits_end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    if digits_end == 0 {
+        return Err("值不是非负整数".to_string());
+    }
+
+    let mut v: u64 = value[..digits_end]
+        .parse()
+        .map_err(|_| "值超出范围".to_string())?;
+
+    let suffix = &value[digits_end..];
+    if !suffix.is_empty() {
+        if !use_byte_suffix {
+            return Err("值不是非负整数".to_string());
+        }
+
+        let shift = match suffix {
+            "k" | "K" | "kiB" | "KiB" | "Ki" | "KB" => 10,
+            "M" | "MiB" | "Mi" | "MB" => 20,
+            "G" | "GiB" | "Gi" | "GB" => 30,
+            _ => return Err("无效的乘数后缀（KiB、MiB 或 GiB）".to_string()),
+        };
+        v <<= shift;
+    }
+
+    u32::try_from(v).map_err(|_| "值超出范围".to_string())
+}
+
 /// 解析过滤器
 pub fn parse_filter(
-    mut s: String,
     str_end: String,
     filter: &mut LzmaFilter,
     only_xz: bool,
 ) -> Option<String> {
     // 查找过滤器名称和选项的分隔符（冒号或等号）
-    let mut name_end = str_end.clone();
-    let mut opts_start = str_end.clone();
-
-    // 假设 s 是待处理的字符串切片，代表 *str 到 str_end 的内容。
-    if let Some(pos) = s.find(|c: char| c == ':' || c == '=') {
-        // name_end 指向分隔符的位置
-        let name_end = pos;
-        // opts_start 指向分隔符后第一个字符的位置
-        let opts_start = pos + 1;
-        // 之后可根据 name_end 和 opts_start 进行后续处理
-    } else {
-        // 没有找到分隔符则不做处理
+    let (name_end, opts_start) = match str_end.find(|c: char| c == ':' || c == '=') {
+        Some(pos) => (str_end[..pos].to_string(), str_end[pos + 1..].to_string()),
+        None => (str_end.clone(), String::new()),
+    };
+
+    // 已注册的自定义过滤器的选项由过滤器自己解析。
+    // 自定义过滤器 ID 都小于 LZMA_FILTER_RESERVED_START，可以用于 .xz 格式
+    if let Some(custom) = lzma_custom_filter_find_name(&name_end) {
+        return match custom.parse_options(&opts_start) {
+            Ok(props) => {
+                filter.id = custom.id();
+                filter.options = Some(LzmaOptionsType::Custom(LzmaOptionsCustom { props }));
+                None
+            }
+            Err(errmsg) => Some(errmsg),
+        };
     }
 
     // 检查过滤器名称长度是否过长
@@ -795,17 +899,17 @@ pub fn parse_filter(
 
             // 初始化过滤器选项
             let mut options = match entry.id {
-                0x21 => LzmaOptionsType::LzmaOptionsLzma(LzmaOptionsLzma::default()),
-                0x03 => LzmaOptionsType::Delta(LzmaOptionsDelta::default()),
-                0x04 => LzmaOptionsType::Bcj(LzmaOptionsBcj::default()),
-                _ => return Some(("Unsupported filter").to_string()),
+                LZMA_FILTER_LZMA1 | LZMA_FILTER_LZMA2 => {
+                    LzmaOptionsType::LzmaOptionsLzma(LzmaOptionsLzma::default())
+                }
+                LZMA_FILTER_DELTA => LzmaOptionsType::Delta(LzmaOptionsDelta::default()),
+                LZMA_FILTER_DEDUP => LzmaOptionsType::Dedup(LzmaOptionsDedup::default()),
+                LZMA_FILTER_ENCRYPT => LzmaOptionsType::None,
+                _ => LzmaOptionsType::Bcj(LzmaOptionsBcj::default()),
             };
 
-            // 调用过滤器特定的解析函数
-            s = opts_start; // 直接赋值给 String
-            if let Some(errmsg) =
-                (entry.parse)(s.as_str(), str_end.clone().as_str(), options.clone())
-            {
+            // 调用过滤器特定的解析函数，解析结果直接写入 options
+            if let Some(errmsg) = (entry.parse)(&opts_start, &mut options) {
                 return Some(errmsg);
             }
 
@@ -842,7 +946,7 @@ pub fn str_to_filters(mut s: String, filters: &mut [LzmaFilter], flags: u32) ->
     let mut errmsg: Option<String> = None;
 
     // 跳过前导空格
-    s.trim_start().to_string();
+    s = skip_spaces(s);
 
     if s.clone().is_empty() {
         return Some(
@@ -860,12 +964,10 @@ pub fn str_to_filters(mut s: String, filters: &mut [LzmaFilter], flags: u32) ->
 
         // 忽略尾随空格
         let str_end = s.find(' ').unwrap_or_else(|| s.len());
-        let preset_str = &s[..str_end];
-        s = s[str_end..].to_string();
+        let preset_str = s[..str_end].to_string();
 
         let mut preset = 0;
-        let filter_str_owned = str_end.to_string();
-        errmsg = parse_lzma12_preset(s, &mut preset);
+        errmsg = parse_lzma12_preset(preset_str, &mut preset);
         if errmsg.is_some() {
             return errmsg;
         }
@@ -879,7 +981,10 @@ pub fn str_to_filters(mut s: String, filters: &mut [LzmaFilter], flags: u32) ->
             id: LZMA_FILTER_LZMA2,
             options: Some(LzmaOptionsType::LzmaOptionsLzma(opts)),
         };
-        filters[1] = LzmaFilter::default();
+        filters[1] = LzmaFilter {
+            id: LZMA_VLI_UNKNOWN,
+            options: None,
+        };
 
         return None;
     }
@@ -899,15 +1004,16 @@ pub fn str_to_filters(mut s: String, filters: &mut [LzmaFilter], flags: u32) ->
             s = s[2..].to_string();
         }
 
-        let filter_end = s
-            .find("--")
-            .or_else(|| s.find(' '))
-            .unwrap_or_else(|| s.len());
+        // 过滤器之间用空格或 "--" 分隔，以先出现的为准
+        let filter_end = [s.find(' '), s.find("--")]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(s.len());
         let filter_str = s[..filter_end].to_string(); // 将切片转换为 String
         s = s[filter_end..].to_string();
 
         errmsg = parse_filter(
-            s.clone(),
             filter_str.to_string(),
             &mut temp_filters[i],
             only_xz,
@@ -916,12 +1022,15 @@ pub fn str_to_filters(mut s: String, filters: &mut [LzmaFilter], flags: u32) ->
             break;
         }
 
-        skip_spaces(s.clone());
+        s = skip_spaces(s);
         i += 1;
     }
 
     if errmsg.is_none() {
-        temp_filters[i] = LzmaFilter::default();
+        temp_filters[i] = LzmaFilter {
+            id: LZMA_VLI_UNKNOWN,
+            options: None,
+        };
 
         if (flags & LZMA_STR_NO_VALIDATION) == 0 && !validate_filter_chain(&temp_filters[..i]) {
             errmsg = Some(("Invalid filter chain ('lzma2' missing at the end?)").to_string());
@@ -1018,6 +1127,15 @@ trait OptionAccess {
     /// - `Some(u32)`: 成功读取到的值，转换为u32
     /// - `None`: 偏移量无效或字段不存在
     fn read_value_at_offset(&self, offset: u32, type_: u32) -> Option<u32>;
+
+    /// 根据字节偏移量写入解析得到的选项值
+    ///
+    /// 偏移量由选项映射表中的 offset_of! 给出，枚举类型的字段由值转换为对应的变体。
+    ///
+    /// # 返回值
+    /// - `true`: 成功写入
+    /// - `false`: 偏移量无效或值不能转换为字段的类型
+    fn write_value_at_offset(&mut self, offset: u32, value: u32) -> bool;
 }
 
 /// 为 LZMA 选项实现选项访问trait
@@ -1044,6 +1162,42 @@ impl OptionAccess for LzmaOptionsLzma {
             _ => None,
         }
     }
+
+    fn write_value_at_offset(&mut self, offset: u32, value: u32) -> bool {
+        let offset = offset as usize;
+        if offset == offset_of!(LzmaOptionsLzma, dict_size) {
+            self.dict_size = value;
+        } else if offset == offset_of!(LzmaOptionsLzma, lc) {
+            self.lc = value;
+        } else if offset == offset_of!(LzmaOptionsLzma, lp) {
+            self.lp = value;
+        } else if offset == offset_of!(LzmaOptionsLzma, pb) {
+            self.pb = value;
+        } else if offset == offset_of!(LzmaOptionsLzma, mode) {
+            self.mode = match value {
+                1 => LzmaMode::Fast,
+                2 => LzmaMode::Normal,
+                _ => return false,
+            };
+        } else if offset == offset_of!(LzmaOptionsLzma, nice_len) {
+            self.nice_len = value;
+        } else if offset == offset_of!(LzmaOptionsLzma, mf) {
+            self.mf = match value {
+                LZMA_MF_HC3 => LzmaMatchFinder::LzmaMfHc3,
+                LZMA_MF_HC4 => LzmaMatchFinder::LzmaMfHc4,
+                LZMA_MF_BT2 => LzmaMatchFinder::LzmaMfBt2,
+                LZMA_MF_BT3 => LzmaMatchFinder::LzmaMfBt3,
+                LZMA_MF_BT4 => LzmaMatchFinder::LzmaMfBt4,
+                LZMA_MF_HT4 => LzmaMatchFinder::LzmaMfHt4,
+                _ => return false,
+            };
+        } else if offset == offset_of!(LzmaOptionsLzma, depth) {
+            self.depth = value;
+        } else {
+            return false;
+        }
+        true
+    }
 }
 
 /// 为 Delta 过滤器选项实现选项访问trait
@@ -1055,6 +1209,32 @@ impl OptionAccess for LzmaOptionsDelta {
             _ => None,                            // 无效偏移量
         }
     }
+
+    fn write_value_at_offset(&mut self, offset: u32, value: u32) -> bool {
+        if offset as usize != offset_of!(LzmaOptionsDelta, dist) {
+            return false;
+        }
+        self.dist = value;
+        true
+    }
+}
+
+/// 为长距离去重过滤器选项实现选项访问trait
+impl OptionAccess for LzmaOptionsDedup {
+    fn read_value_at_offset(&self, offset: u32, type_: u32) -> Option<u32> {
+        match offset {
+            0 => Some(self.chunk_log), // 平均分块大小的对数（4字节）
+            _ => None,
+        }
+    }
+
+    fn write_value_at_offset(&mut self, offset: u32, value: u32) -> bool {
+        if offset as usize != offset_of!(LzmaOptionsDedup, chunk_log) {
+            return false;
+        }
+        self.chunk_log = value;
+        true
+    }
 }
 
 /// 为 BCJ（分支/调用/跳转）过滤器选项实现选项访问trait
@@ -1065,6 +1245,14 @@ impl OptionAccess for LzmaOptionsBcj {
             _ => None,                    // BCJ选项只有一个字段
         }
     }
+
+    fn write_value_at_offset(&mut self, offset: u32, value: u32) -> bool {
+        if offset as usize != offset_of!(LzmaOptionsBcj, start_offset) {
+            return false;
+        }
+        self.start_offset = value;
+        true
+    }
 }
 
 // 然后简化主函数
@@ -1094,10 +1282,12 @@ pub fn strfy_filter(
             LzmaOptionsType::Bcj(opts) => {
                 opts.read_value_at_offset(om.offset as u32, om.type_ as u32)
             }
-            LzmaOptionsType::Lod(opts) => {
+            LzmaOptionsType::Dedup(opts) => {
                 opts.read_value_at_offset(om.offset as u32, om.type_ as u32)
             }
-            LzmaOptionsType::None => continue,
+            LzmaOptionsType::Custom(_) | LzmaOptionsType::Encrypt(_) | LzmaOptionsType::None => {
+                continue
+            }
         };
 
         let v = match v {
@@ -1215,6 +1405,25 @@ pub fn lzma_str_from_filters(
             dest.append_str("--");
         }
 
+        // 已注册的自定义过滤器使用自己的名称和选项格式
+        if let Some(custom) = lzma_custom_filter_find(filter.id) {
+            dest.append_str(custom.name());
+            if !show_opts {
+                continue;
+            }
+
+            let opts_str = match filter.options.as_ref() {
+                Some(LzmaOptionsType::Custom(opt)) => custom.options_to_str(&opt.props),
+                None => custom.options_to_str(&[]),
+                Some(_) => return LzmaRet::OptionsError,
+            };
+            if !opts_str.is_empty() {
+                dest.append_str(opt_delim);
+                dest.append_str(&opts_str);
+            }
+            continue;
+        }
+
         // 9. 内部循环：查找 filter_name_map 中与当前 filter.id 匹配的条目
         let mut entry_found = None;
         for entry in FILTER_NAME_MAP.iter() {
@@ -1410,3 +1619,181 @@ pub fn lzma_str_from_filters(
 
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Once};
+
+    use super::str_to_filters;
+    use crate::api::{
+        Filter, FilterCoder, LzmaAction, LzmaCheck, LzmaFilter, LzmaOptionsType, LzmaRet,
+        LzmaStream, LzmaVli, LZMA_FILTERS_MAX, LZMA_FILTER_CUSTOM_MIN, LZMA_FILTER_DELTA,
+        LZMA_FILTER_LZMA2, LZMA_VLI_UNKNOWN,
+    };
+    use crate::common::stream_decoder::lzma_stream_decoder;
+    use crate::common::stream_encoder::lzma_stream_encoder;
+    use crate::custom::lzma_filter_register;
+    use crate::test_util::{code_all, pseudo_random, raw_code};
+
+    /// 测试用的自定义过滤器：把每个字节与 k 异或
+    struct XorFilter;
+
+    struct XorCoder(u8);
+
+    impl Filter for XorFilter {
+        fn id(&self) -> LzmaVli {
+            LZMA_FILTER_CUSTOM_MIN + 0x40
+        }
+
+        fn name(&self) -> &str {
+            "xor"
+        }
+
+        fn encoder(&self, props: &[u8]) -> Result<Box<dyn FilterCoder>, LzmaRet> {
+            match props {
+                [] => Ok(Box::new(XorCoder(0x5A))),
+                [k] => Ok(Box::new(XorCoder(*k))),
+                _ => Err(LzmaRet::OptionsError),
+            }
+        }
+
+        fn decoder(&self, props: &[u8]) -> Result<Box<dyn FilterCoder>, LzmaRet> {
+            self.encoder(props)
+        }
+
+        fn parse_options(&self, options: &str) -> Result<Vec<u8>, String> {
+            if options.is_empty() {
+                return Ok(Vec::new());
+            }
+            options
+                .strip_prefix("k=")
+                .and_then(|k| k.parse().ok())
+                .map(|k| vec![k])
+                .ok_or_else(|| "k 必须是 0 到 255 之间的整数".to_string())
+        }
+    }
+
+    impl FilterCoder for XorCoder {
+        fn code(
+            &mut self,
+            input: &[u8],
+            in_pos: &mut usize,
+            output: &mut [u8],
+            out_pos: &mut usize,
+            action: LzmaAction,
+        ) -> LzmaRet {
+            let n = (input.len() - *in_pos).min(output.len() - *out_pos);
+            for i in 0..n {
+                output[*out_pos + i] = input[*in_pos + i] ^ self.0;
+            }
+            *in_pos += n;
+            *out_pos += n;
+
+            if action != LzmaAction::Run && *in_pos == input.len() {
+                LzmaRet::StreamEnd
+            } else {
+                LzmaRet::Ok
+            }
+        }
+    }
+
+    fn register_xor() {
+        static REGISTER: Once = Once::new();
+        REGISTER.call_once(|| {
+            assert_eq!(lzma_filter_register(Arc::new(XorFilter)), LzmaRet::Ok);
+        });
+    }
+
+    /// 解析过滤器链字符串，返回包括结束标记在内的过滤器
+    fn parse(s: &str) -> Result<Vec<LzmaFilter>, String> {
+        let mut filters = vec![LzmaFilter::default(); LZMA_FILTERS_MAX + 1];
+        if let Some(errmsg) = str_to_filters(s.to_string(), &mut filters, 0) {
+            return Err(errmsg);
+        }
+        let len = filters
+            .iter()
+            .position(|f| f.id == LZMA_VLI_UNKNOWN)
+            .unwrap();
+        filters.truncate(len + 1);
+        Ok(filters)
+    }
+
+    fn dict_size(filter: &LzmaFilter) -> u32 {
+        filter
+            .options
+            .as_ref()
+            .and_then(|opts| opts.as_lzma_options_lzma())
+            .unwrap()
+            .dict_size
+    }
+
+    /// 选项值写入返回的过滤器链，预设先于其他选项应用
+    #[test]
+    fn str_to_filters_options() {
+        let filters = parse("delta:dist=4 lzma2:dict=1MiB").unwrap();
+        assert_eq!(filters.len(), 3);
+        assert_eq!(filters[0].id, LZMA_FILTER_DELTA);
+        match &filters[0].options {
+            Some(LzmaOptionsType::Delta(opts)) => assert_eq!(opts.dist, 4),
+            opts => panic!("{:?}", opts),
+        }
+        assert_eq!(filters[1].id, LZMA_FILTER_LZMA2);
+        assert_eq!(dict_size(&filters[1]), 1 << 20);
+
+        assert_eq!(dict_size(&parse("lzma2:preset=6").unwrap()[0]), 8 << 20);
+        assert_eq!(dict_size(&parse("lzma2:preset=1").unwrap()[0]), 1 << 20);
+        assert_eq!(dict_size(&parse("lzma2").unwrap()[0]), 8 << 20);
+        assert_eq!(
+            dict_size(&parse("lzma2:dict=64KiB,preset=1").unwrap()[0]),
+            64 << 10
+        );
+        assert_eq!(dict_size(&parse("--lzma2=dict=4096").unwrap()[0]), 4096);
+        assert_eq!(dict_size(&parse("3").unwrap()[0]), 4 << 20);
+
+        for bad in [
+            "lzma2:dict=1XiB",
+            "lzma2:dict=",
+            "lzma2:dict=8GiB",
+            "lzma2:nice=1",
+            "lzma2:lc=4,lp=1",
+            "lzma2:preset=x",
+            "lzma2:foo=1",
+            "delta:dist=0 lzma2",
+            "delta",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    /// 用字符串给出的自定义过滤器链做 raw 和 .xz 往返
+    #[test]
+    fn custom_filter_round_trip() {
+        register_xor();
+        let data = pseudo_random(100_000, 4);
+
+        for chain in [
+            "xor:k=7 lzma2:dict=1MiB",
+            "xor lzma2",
+            "xor--lzma2:preset=1",
+        ] {
+            let filters = parse(chain).unwrap();
+            assert_eq!(filters[0].id, LZMA_FILTER_CUSTOM_MIN + 0x40);
+
+            let encoded = raw_code(&filters, &data, true).unwrap();
+            assert_eq!(raw_code(&filters, &encoded, false).unwrap(), data);
+
+            let mut strm = LzmaStream::default();
+            assert_eq!(
+                lzma_stream_encoder(&mut strm, &filters, LzmaCheck::Crc64),
+                LzmaRet::Ok
+            );
+            let encoded = code_all(&mut strm, &data).unwrap();
+
+            let mut strm = LzmaStream::default();
+            assert_eq!(lzma_stream_decoder(&mut strm, u64::MAX, 0), LzmaRet::Ok);
+            assert_eq!(code_all(&mut strm, &encoded).unwrap(), data);
+        }
+
+        assert!(parse("xor:k=256 lzma2").is_err());
+    }
+}
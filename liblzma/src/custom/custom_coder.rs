@@ -0,0 +1,273 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use std::fmt;
+
+use crate::{
+    api::{FilterCoder, LzmaAction, LzmaFilter, LzmaOptionsCustom, LzmaOptionsType, LzmaRet},
+    common::{
+        lzma_next_end, lzma_next_filter_init, lzma_next_filter_update, CoderType, LzmaFilterInfo,
+        LzmaNextCoder,
+    },
+};
+
+use super::lzma_custom_filter_find;
+
+/// 从链中下一个过滤器读取数据时使用的缓冲区大小
+pub const CUSTOM_BUF_SIZE: usize = 64 << 10;
+
+/// 把自定义过滤器的 FilterCoder 接入过滤器链
+pub struct LzmaCustomCoder {
+    next: Box<LzmaNextCoder>,
+    coder: Box<dyn FilterCoder>,
+    is_encoder: bool,
+
+    /// 从下一个过滤器读入、尚未交给 coder 的数据
+    buf: Vec<u8>,
+    pos: usize,
+    size: usize,
+
+    /// 下一个过滤器已返回 LZMA_STREAM_END
+    end_was_reached: bool,
+}
+
+impl fmt::Debug for LzmaCustomCoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LzmaCustomCoder")
+            .field("next", &self.next)
+            .field("is_encoder", &self.is_encoder)
+            .field("pos", &self.pos)
+            .field("size", &self.size)
+            .field("end_was_reached", &self.end_was_reached)
+            .finish_non_exhaustive()
+    }
+}
+
+fn custom_code(
+    coder_ptr: &mut CoderType,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::CustomCoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    // 链中没有下一个过滤器时直接转换调用者的输入
+    let code = match coder.next.code {
+        Some(code) => code,
+        None => {
+            let ret = coder.coder.code(
+                &in_[..in_size],
+                in_pos,
+                &mut out[..out_size],
+                out_pos,
+                action,
+            );
+            if ret == LzmaRet::StreamEnd && action == LzmaAction::Run {
+                return LzmaRet::ProgError;
+            }
+            return ret;
+        }
+    };
+
+    loop {
+        if coder.pos == coder.size && !coder.end_was_reached {
+            coder.pos = 0;
+            coder.size = 0;
+            let ret = code(
+                coder.next.coder.as_mut().unwrap(),
+                in_,
+                in_pos,
+                in_size,
+                &mut coder.buf,
+                &mut coder.size,
+                CUSTOM_BUF_SIZE,
+                action,
+            );
+            match ret {
+                LzmaRet::StreamEnd => coder.end_was_reached = true,
+                LzmaRet::Ok => {}
+                ret => return ret,
+            }
+            if coder.size == 0 && !coder.end_was_reached {
+                return LzmaRet::Ok;
+            }
+        }
+
+        // 解码器的下一个过滤器结束时就是整个数据的结束
+        let coder_action = match (coder.end_was_reached, coder.is_encoder) {
+            (false, _) => LzmaAction::Run,
+            (true, true) => action,
+            (true, false) => LzmaAction::Finish,
+        };
+
+        let pos = coder.pos;
+        let out_start = *out_pos;
+        let ret = coder.coder.code(
+            &coder.buf[..coder.size],
+            &mut coder.pos,
+            &mut out[..out_size],
+            out_pos,
+            coder_action,
+        );
+        match ret {
+            LzmaRet::StreamEnd if coder_action != LzmaAction::Run => {
+                // flush 之后编码器可以继续处理更多数据
+                coder.end_was_reached = false;
+                return LzmaRet::StreamEnd;
+            }
+            LzmaRet::StreamEnd => return LzmaRet::ProgError,
+            LzmaRet::Ok => {}
+            ret => return ret,
+        }
+
+        if *out_pos == out_size {
+            return LzmaRet::Ok;
+        }
+
+        // 输出空间还有剩余时 coder 必须读取全部输入，结束时必须继续输出直到
+        // 返回 LZMA_STREAM_END，否则会一直没有进展
+        if coder.pos == pos
+            && *out_pos == out_start
+            && (coder.pos < coder.size || coder.end_was_reached)
+        {
+            return LzmaRet::ProgError;
+        }
+    }
+}
+
+fn custom_coder_end(coder_ptr: &mut CoderType) {
+    if let CoderType::CustomCoder(ref mut coder) = coder_ptr {
+        lzma_next_end(&mut coder.next);
+    }
+}
+
+fn custom_coder_update(
+    coder_ptr: &mut CoderType,
+    _filters: Option<&[LzmaFilter]>,
+    reversed_filters: &[LzmaFilter],
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::CustomCoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+    // 自定义过滤器的选项不能更新，只更新链中的下一个过滤器
+    lzma_next_filter_update(&mut coder.next, &reversed_filters[1..])
+}
+
+fn custom_coder_init(
+    next: &mut LzmaNextCoder,
+    filters: &[LzmaFilterInfo],
+    is_encoder: bool,
+) -> LzmaRet {
+    let filter = match lzma_custom_filter_find(filters[0].id) {
+        Some(filter) => filter,
+        None => return LzmaRet::OptionsError,
+    };
+
+    let props: &[u8] = match filters[0].options.as_ref() {
+        Some(LzmaOptionsType::Custom(opt)) => &opt.props,
+        None => &[],
+        Some(_) => return LzmaRet::OptionsError,
+    };
+
+    let ret = if is_encoder {
+        filter.encoder(props)
+    } else {
+        filter.decoder(props)
+    };
+    let filter_coder = match ret {
+        Ok(c) => c,
+        Err(ret) => return ret,
+    };
+
+    match next.coder.as_mut() {
+        Some(CoderType::CustomCoder(coder)) => {
+            coder.coder = filter_coder;
+            coder.is_encoder = is_encoder;
+            coder.pos = 0;
+            coder.size = 0;
+            coder.end_was_reached = false;
+        }
+        _ => {
+            next.coder = Some(CoderType::CustomCoder(LzmaCustomCoder {
+                next: Box::default(),
+                coder: filter_coder,
+                is_encoder,
+                buf: vec![0; CUSTOM_BUF_SIZE],
+                pos: 0,
+                size: 0,
+                end_was_reached: false,
+            }));
+            next.code = Some(custom_code);
+            next.end = Some(custom_coder_end);
+            next.update = Some(custom_coder_update);
+        }
+    }
+
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::CustomCoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+    lzma_next_filter_init(&mut coder.next, &filters[1..])
+}
+
+pub fn lzma_custom_encoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
+    custom_coder_init(next, filters, true)
+}
+
+pub fn lzma_custom_decoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
+    custom_coder_init(next, filters, false)
+}
+
+/// 内存用量只计入缓冲区，FilterCoder 自己使用的内存无法得知
+pub fn lzma_custom_memusage(options: &LzmaOptionsType) -> u64 {
+    match options {
+        LzmaOptionsType::Custom(_) | LzmaOptionsType::None => {
+            (std::mem::size_of::<LzmaCustomCoder>() + CUSTOM_BUF_SIZE) as u64
+        }
+        _ => u64::MAX,
+    }
+}
+
+pub fn lzma_custom_props_size(size: &mut u32, options: &LzmaOptionsType) -> LzmaRet {
+    *size = match options {
+        LzmaOptionsType::Custom(opt) => match u32::try_from(opt.props.len()) {
+            Ok(len) => len,
+            Err(_) => return LzmaRet::OptionsError,
+        },
+        LzmaOptionsType::None => 0,
+        _ => return LzmaRet::ProgError,
+    };
+    LzmaRet::Ok
+}
+
+pub fn lzma_custom_props_encode(options: &LzmaOptionsType, out: &mut [u8]) -> LzmaRet {
+    match options {
+        LzmaOptionsType::Custom(opt) => {
+            out[..opt.props.len()].copy_from_slice(&opt.props);
+            LzmaRet::Ok
+        }
+        LzmaOptionsType::None => LzmaRet::Ok,
+        _ => LzmaRet::ProgError,
+    }
+}
+
+pub fn lzma_custom_props_decode(
+    props: &[u8],
+    props_size: usize,
+) -> (LzmaRet, Option<LzmaOptionsType>) {
+    let opt = LzmaOptionsCustom {
+        props: props[..props_size].to_vec(),
+    };
+    (LzmaRet::Ok, Some(LzmaOptionsType::Custom(opt)))
+}
@@ -0,0 +1,83 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use std::sync::{Arc, RwLock};
+
+use crate::api::{Filter, LzmaRet, LzmaVli, LZMA_FILTER_CUSTOM_MAX, LZMA_FILTER_CUSTOM_MIN};
+use crate::common::{
+    lzma_filter_decoder_is_supported, lzma_filter_encoder_is_supported,
+    string_conversion::FILTER_NAME_MAP,
+};
+
+/// 已注册的自定义过滤器
+static CUSTOM_FILTERS: RwLock<Vec<Arc<dyn Filter>>> = RwLock::new(Vec::new());
+
+/// 注册自定义过滤器
+///
+/// ID 不在自定义过滤器的范围内、ID 或名称已被其他过滤器使用时返回
+/// LzmaRet::OptionsError。注册对之后创建的编码器和解码器生效。
+pub fn lzma_filter_register(filter: Arc<dyn Filter>) -> LzmaRet {
+    let id = filter.id();
+    if !(LZMA_FILTER_CUSTOM_MIN..=LZMA_FILTER_CUSTOM_MAX).contains(&id) || filter.name().is_empty()
+    {
+        return LzmaRet::OptionsError;
+    }
+
+    // 内置过滤器（例如去重过滤器）也使用自定义范围内的 ID。
+    // 这里会查找已注册的过滤器，所以要在获取写锁之前检查
+    if lzma_filter_encoder_is_supported(id)
+        || lzma_filter_decoder_is_supported(id)
+        || FILTER_NAME_MAP
+            .iter()
+            .any(|entry| entry.name == filter.name())
+    {
+        return LzmaRet::OptionsError;
+    }
+
+    let mut filters = CUSTOM_FILTERS.write().unwrap();
+    if filters
+        .iter()
+        .any(|f| f.id() == id || f.name() == filter.name())
+    {
+        return LzmaRet::OptionsError;
+    }
+
+    filters.push(filter);
+    LzmaRet::Ok
+}
+
+/// 取消注册自定义过滤器，没有注册该 ID 时返回 false
+///
+/// 已经创建的编码器和解码器不受影响。
+pub fn lzma_filter_unregister(id: LzmaVli) -> bool {
+    let mut filters = CUSTOM_FILTERS.write().unwrap();
+    let len = filters.len();
+    filters.retain(|f| f.id() != id);
+    filters.len() != len
+}
+
+/// 按 ID 查找已注册的自定义过滤器
+pub fn lzma_custom_filter_find(id: LzmaVli) -> Option<Arc<dyn Filter>> {
+    if !(LZMA_FILTER_CUSTOM_MIN..=LZMA_FILTER_CUSTOM_MAX).contains(&id) {
+        return None;
+    }
+    CUSTOM_FILTERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|f| f.id() == id)
+        .cloned()
+}
+
+/// 按名称查找已注册的自定义过滤器
+pub fn lzma_custom_filter_find_name(name: &str) -> Option<Arc<dyn Filter>> {
+    CUSTOM_FILTERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|f| f.name() == name)
+        .cloned()
+}
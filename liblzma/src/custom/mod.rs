@@ -0,0 +1,11 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+mod custom_coder;
+mod custom_registry;
+
+pub use custom_coder::*;
+pub use custom_registry::*;
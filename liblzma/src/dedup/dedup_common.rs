@@ -0,0 +1,227 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use crate::{
+    api::{
+        LzmaAction, LzmaOptionsDedup, LzmaOptionsType, LzmaRet, LZMA_DEDUP_CHUNK_LOG_MAX,
+        LZMA_DEDUP_CHUNK_LOG_MIN,
+    },
+    check::{lzma_sha256_finish, lzma_sha256_init, lzma_sha256_update, LzmaCheckState},
+    common::{lzma_bufcpy, LzmaNextCoder},
+};
+
+/// 字面量记号：VLI 长度 + 原样的数据
+pub const DEDUP_TAG_LITERAL: u8 = 0x00;
+
+/// 引用记号：VLI 偏移量 + VLI 长度，偏移量相对于本过滤器输出的开头
+pub const DEDUP_TAG_COPY: u8 = 0x01;
+
+/// 记号头部的最大长度：标记字节加两个 VLI
+pub const DEDUP_HEADER_MAX: usize = 1 + 2 * 9;
+
+/// 从应用程序或链中下一个过滤器读取数据时使用的缓冲区大小
+pub const DEDUP_BUF_SIZE: usize = 64 << 10;
+
+/// 解码器从窗口文件读取被引用数据时每次读取的大小
+pub const DEDUP_COPY_SIZE: usize = 64 << 10;
+
+/// 解码器的输出在内存中累积到这个大小后写入窗口文件
+pub const DEDUP_WINDOW_BUF_SIZE: usize = 1 << 20;
+
+/// Gear 滚动哈希使用的随机表，由 splitmix64 生成，编码器和解码器不依赖它的具体值
+pub const DEDUP_GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6922_C107_CD00_0001;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// 从应用程序输入或链中的下一个过滤器读入的数据
+#[derive(Debug, Default)]
+pub struct DedupInput {
+    pub buf: Vec<u8>,
+    pub pos: usize,
+    /// 已读到输入的末尾（编码器为本次 flush 或 finish 的末尾）
+    pub end: bool,
+}
+
+impl DedupInput {
+    pub fn avail(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    /// 在缓冲区已用完时读入更多数据
+    pub fn fill(
+        &mut self,
+        next: &mut LzmaNextCoder,
+        in_: &[u8],
+        in_pos: &mut usize,
+        in_size: usize,
+        action: LzmaAction,
+    ) -> LzmaRet {
+        debug_assert!(self.is_empty());
+        self.buf.resize(DEDUP_BUF_SIZE, 0);
+        self.pos = 0;
+        let mut filled = 0;
+
+        let ret = match next.code {
+            None => {
+                lzma_bufcpy(
+                    in_,
+                    in_pos,
+                    in_size,
+                    &mut self.buf,
+                    &mut filled,
+                    DEDUP_BUF_SIZE,
+                );
+                if action != LzmaAction::Run && *in_pos == in_size {
+                    LzmaRet::StreamEnd
+                } else {
+                    LzmaRet::Ok
+                }
+            }
+            Some(code) => code(
+                next.coder.as_mut().unwrap(),
+                in_,
+                in_pos,
+                in_size,
+                &mut self.buf,
+                &mut filled,
+                DEDUP_BUF_SIZE,
+                action,
+            ),
+        };
+        self.buf.truncate(filled);
+
+        match ret {
+            LzmaRet::StreamEnd => {
+                self.end = true;
+                LzmaRet::Ok
+            }
+            ret => ret,
+        }
+    }
+}
+
+/// 以 .xz 的 VLI 格式追加一个整数
+pub fn dedup_put_vli(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// 从 buf 的 *pos 处读取一个 VLI，数据不完整时返回 Ok(None)
+pub fn dedup_get_vli(buf: &[u8], pos: &mut usize) -> Result<Option<u64>, LzmaRet> {
+    let mut value: u64 = 0;
+    let mut i = 0;
+    loop {
+        let byte = match buf.get(*pos + i) {
+            Some(&b) => b,
+            None => return Ok(None),
+        };
+        value |= ((byte & 0x7F) as u64) << (i * 7);
+        i += 1;
+
+        if byte & 0x80 == 0 {
+            // 与 lzma_vli_decode 一样，不允许多余的前导零字节
+            if byte == 0 && i > 1 {
+                return Err(LzmaRet::DataError);
+            }
+            *pos += i;
+            return Ok(Some(value));
+        }
+
+        if i == 9 {
+            return Err(LzmaRet::DataError);
+        }
+    }
+}
+
+/// 计算分块的指纹：SHA-256 的前 128 位
+pub fn dedup_digest(data: &[u8]) -> u128 {
+    let mut check = LzmaCheckState::default();
+    lzma_sha256_init(&mut check);
+    lzma_sha256_update(data, data.len(), &mut check);
+    lzma_sha256_finish(&mut check);
+
+    let state = &check.state.sha256.state;
+    ((state[0] as u128) << 96)
+        | ((state[1] as u128) << 64)
+        | ((state[2] as u128) << 32)
+        | state[3] as u128
+}
+
+pub fn dedup_options(options: &LzmaOptionsType) -> Option<&LzmaOptionsDedup> {
+    match options {
+        LzmaOptionsType::Dedup(opt)
+            if opt.chunk_log >= LZMA_DEDUP_CHUNK_LOG_MIN
+                && opt.chunk_log <= LZMA_DEDUP_CHUNK_LOG_MAX =>
+        {
+            Some(opt)
+        }
+        _ => None,
+    }
+}
+
+/// 编码器的内存用量，不含随输入增长的分块索引（每个不重复的分块约 48 字节）
+pub fn lzma_dedup_encoder_memusage(options: &LzmaOptionsType) -> u64 {
+    match dedup_options(options) {
+        // 正在累积的分块和待输出的字面量记号各需要一个最大分块大小
+        Some(opt) => (2 * (4u64 << opt.chunk_log)) + DEDUP_BUF_SIZE as u64,
+        None => u64::MAX,
+    }
+}
+
+/// 解码器的内存用量，被引用的数据保存在窗口文件中，不计入内存用量
+pub fn lzma_dedup_decoder_memusage(options: &LzmaOptionsType) -> u64 {
+    match dedup_options(options) {
+        Some(_) => (DEDUP_BUF_SIZE + DEDUP_COPY_SIZE + DEDUP_WINDOW_BUF_SIZE) as u64,
+        None => u64::MAX,
+    }
+}
+
+pub fn lzma_dedup_props_encode(options: &LzmaOptionsType, out: &mut [u8]) -> LzmaRet {
+    match dedup_options(options) {
+        Some(opt) => {
+            out[0] = opt.chunk_log as u8;
+            LzmaRet::Ok
+        }
+        None => LzmaRet::ProgError,
+    }
+}
+
+pub fn lzma_dedup_props_decode(
+    props: &[u8],
+    props_size: usize,
+) -> (LzmaRet, Option<LzmaOptionsType>) {
+    if props_size != 1 {
+        return (LzmaRet::OptionsError, None);
+    }
+
+    let opt = LzmaOptionsType::Dedup(LzmaOptionsDedup {
+        chunk_log: props[0] as u32,
+        ..Default::default()
+    });
+    if dedup_options(&opt).is_none() {
+        return (LzmaRet::OptionsError, None);
+    }
+
+    (LzmaRet::Ok, Some(opt))
+}
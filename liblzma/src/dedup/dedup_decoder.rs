@@ -0,0 +1,345 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{FileExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{
+    api::{LzmaAction, LzmaRet},
+    common::{lzma_next_end, lzma_next_filter_init, CoderType, LzmaFilterInfo, LzmaNextCoder},
+};
+
+use super::{
+    dedup_get_vli, dedup_options, DedupInput, DEDUP_COPY_SIZE, DEDUP_HEADER_MAX, DEDUP_TAG_COPY,
+    DEDUP_TAG_LITERAL, DEDUP_WINDOW_BUF_SIZE,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Sequence {
+    #[default]
+    Header,
+    Literal {
+        left: u64,
+    },
+    Copy {
+        offset: u64,
+        left: u64,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct LzmaDedupDecoder {
+    pub next: Box<LzmaNextCoder>,
+    pub input: DedupInput,
+    pub sequence: Sequence,
+    /// 尚不完整的记号头部
+    pub header: Vec<u8>,
+
+    /// 窗口文件，保存已输出的数据以便解码引用；第一次需要写入时才创建
+    pub window: Option<File>,
+    /// 创建窗口文件的目录，None 表示临时目录
+    pub window_dir: Option<PathBuf>,
+    /// 窗口中最多保存的字节数
+    pub window_limit: u64,
+    /// 尚未写入窗口文件的输出
+    pub window_buf: Vec<u8>,
+    /// 已写入窗口文件的字节数
+    pub window_flushed: u64,
+
+    /// 从窗口文件读出、尚未输出的被引用数据
+    pub copy_buf: Vec<u8>,
+    pub copy_pos: usize,
+}
+
+/// 在 dir 中创建没有名字的窗口文件，文件在解码器结束或进程退出时由系统回收
+///
+/// 文件系统不支持 O_TMPFILE 时创建一个只有本用户可读写的文件，并立即删除目录项。
+fn window_create(dir: &Path) -> io::Result<File> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .mode(0o600)
+        .custom_flags(libc::O_TMPFILE)
+        .open(dir)
+    {
+        Ok(file) => return Ok(file),
+        Err(e) if !matches!(e.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EISDIR)) => {
+            return Err(e)
+        }
+        Err(_) => {}
+    }
+
+    loop {
+        let path = dir.join(format!(
+            ".lzma-dedup-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(file) => {
+                let _ = fs::remove_file(&path);
+                return Ok(file);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn window_flush(coder: &mut LzmaDedupDecoder) -> io::Result<()> {
+    if coder.window_buf.is_empty() {
+        return Ok(());
+    }
+
+    if coder.window.is_none() {
+        let dir = match &coder.window_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::temp_dir(),
+        };
+        coder.window = Some(window_create(&dir)?);
+    }
+    coder
+        .window
+        .as_mut()
+        .unwrap()
+        .write_all(&coder.window_buf)?;
+    coder.window_flushed += coder.window_buf.len() as u64;
+    coder.window_buf.clear();
+    Ok(())
+}
+
+/// 把刚输出的数据追加到窗口
+fn window_append(coder: &mut LzmaDedupDecoder, data: &[u8]) -> LzmaRet {
+    let total = coder.window_flushed + (coder.window_buf.len() + data.len()) as u64;
+    if total > coder.window_limit {
+        return LzmaRet::MemlimitError;
+    }
+
+    if coder.window_buf.len() + data.len() > DEDUP_WINDOW_BUF_SIZE && window_flush(coder).is_err() {
+        return LzmaRet::MemError;
+    }
+    coder.window_buf.extend_from_slice(data);
+    LzmaRet::Ok
+}
+
+/// 读取被引用的下一段数据到 copy_buf
+fn window_read(coder: &mut LzmaDedupDecoder, offset: u64, left: u64) -> io::Result<()> {
+    let size = left.min(DEDUP_COPY_SIZE as u64) as usize;
+    coder.copy_buf.resize(size, 0);
+    coder.copy_pos = 0;
+
+    // 被引用的数据可能还在 window_buf 中
+    if offset >= coder.window_flushed {
+        let start = (offset - coder.window_flushed) as usize;
+        coder
+            .copy_buf
+            .copy_from_slice(&coder.window_buf[start..start + size]);
+        return Ok(());
+    }
+
+    if offset + size as u64 > coder.window_flushed {
+        window_flush(coder)?;
+    }
+    coder
+        .window
+        .as_ref()
+        .unwrap()
+        .read_exact_at(&mut coder.copy_buf, offset)
+}
+
+/// 解析 header 中的记号头部，数据不完整时返回 Ok(None)
+fn parse_header(header: &[u8]) -> Result<Option<Sequence>, LzmaRet> {
+    let mut pos = 1;
+    match header[0] {
+        DEDUP_TAG_LITERAL => match dedup_get_vli(header, &mut pos)? {
+            Some(0) => Err(LzmaRet::DataError),
+            Some(left) => Ok(Some(Sequence::Literal { left })),
+            None => Ok(None),
+        },
+        DEDUP_TAG_COPY => {
+            let offset = match dedup_get_vli(header, &mut pos)? {
+                Some(offset) => offset,
+                None => return Ok(None),
+            };
+            match dedup_get_vli(header, &mut pos)? {
+                Some(0) => Err(LzmaRet::DataError),
+                Some(left) => Ok(Some(Sequence::Copy { offset, left })),
+                None => Ok(None),
+            }
+        }
+        _ => Err(LzmaRet::DataError),
+    }
+}
+
+fn dedup_decode(
+    coder_ptr: &mut CoderType,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::DedupDecoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    while *out_pos < out_size {
+        // 字面量和记号头部需要更多输入
+        if coder.input.is_empty() && !matches!(coder.sequence, Sequence::Copy { .. }) {
+            if coder.input.end {
+                // 输入只能在两个记号之间结束
+                if coder.sequence == Sequence::Header && coder.header.is_empty() {
+                    return LzmaRet::StreamEnd;
+                }
+                return LzmaRet::DataError;
+            }
+
+            let ret = coder
+                .input
+                .fill(&mut coder.next, in_, in_pos, in_size, action);
+            if ret != LzmaRet::Ok {
+                return ret;
+            }
+            if coder.input.is_empty() && !coder.input.end {
+                return LzmaRet::Ok;
+            }
+            continue;
+        }
+
+        match coder.sequence {
+            Sequence::Header => {
+                let byte = coder.input.buf[coder.input.pos];
+                coder.input.pos += 1;
+                coder.header.push(byte);
+
+                let seq = match parse_header(&coder.header) {
+                    Ok(Some(seq)) => seq,
+                    Ok(None) if coder.header.len() < DEDUP_HEADER_MAX => continue,
+                    Ok(None) => return LzmaRet::DataError,
+                    Err(ret) => return ret,
+                };
+                coder.header.clear();
+
+                // 引用只能指向已经输出的数据
+                if let Sequence::Copy { offset, left } = seq {
+                    let total = coder.window_flushed + coder.window_buf.len() as u64;
+                    if offset > total || left > total - offset {
+                        return LzmaRet::DataError;
+                    }
+                    coder.copy_buf.clear();
+                    coder.copy_pos = 0;
+                }
+                coder.sequence = seq;
+            }
+
+            Sequence::Literal { left } => {
+                let size = (left as usize)
+                    .min(coder.input.buf.len() - coder.input.pos)
+                    .min(out_size - *out_pos);
+                let start = coder.input.pos;
+                out[*out_pos..*out_pos + size]
+                    .copy_from_slice(&coder.input.buf[start..start + size]);
+                *out_pos += size;
+                coder.input.pos += size;
+
+                let ret = window_append(coder, &out[*out_pos - size..*out_pos]);
+                if ret != LzmaRet::Ok {
+                    return ret;
+                }
+
+                coder.sequence = if left == size as u64 {
+                    Sequence::Header
+                } else {
+                    Sequence::Literal {
+                        left: left - size as u64,
+                    }
+                };
+            }
+
+            Sequence::Copy { offset, left } => {
+                if coder.copy_pos == coder.copy_buf.len()
+                    && window_read(coder, offset, left).is_err()
+                {
+                    return LzmaRet::MemError;
+                }
+
+                let size = (coder.copy_buf.len() - coder.copy_pos).min(out_size - *out_pos);
+                out[*out_pos..*out_pos + size]
+                    .copy_from_slice(&coder.copy_buf[coder.copy_pos..coder.copy_pos + size]);
+                *out_pos += size;
+                coder.copy_pos += size;
+
+                let ret = window_append(coder, &out[*out_pos - size..*out_pos]);
+                if ret != LzmaRet::Ok {
+                    return ret;
+                }
+
+                coder.sequence = if left == size as u64 {
+                    Sequence::Header
+                } else {
+                    Sequence::Copy {
+                        offset: offset + size as u64,
+                        left: left - size as u64,
+                    }
+                };
+            }
+        }
+    }
+
+    LzmaRet::Ok
+}
+
+fn dedup_decoder_end(coder_ptr: &mut CoderType) {
+    if let CoderType::DedupDecoder(ref mut coder) = coder_ptr {
+        lzma_next_end(&mut coder.next);
+        coder.window = None;
+    }
+}
+
+pub fn lzma_dedup_decoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
+    let (window_dir, window_limit) = match filters[0].options.as_ref().and_then(dedup_options) {
+        Some(opt) => (opt.window_dir.clone(), opt.window_limit),
+        None => return LzmaRet::OptionsError,
+    };
+
+    if next.coder.is_none() {
+        next.coder = Some(CoderType::DedupDecoder(LzmaDedupDecoder::default()));
+        next.code = Some(dedup_decode);
+        next.end = Some(dedup_decoder_end);
+    }
+
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::DedupDecoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    coder.input = DedupInput::default();
+    coder.sequence = Sequence::Header;
+    coder.header.clear();
+    coder.window = None;
+    coder.window_dir = window_dir;
+    coder.window_limit = window_limit;
+    coder.window_buf.clear();
+    coder.window_flushed = 0;
+    coder.copy_buf.clear();
+    coder.copy_pos = 0;
+
+    lzma_next_filter_init(&mut coder.next, &filters[1..])
+}
@@ -0,0 +1,216 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+    api::{LzmaAction, LzmaRet},
+    common::{
+        lzma_bufcpy, lzma_next_end, lzma_next_filter_init, CoderType, LzmaFilterInfo, LzmaNextCoder,
+    },
+};
+
+use super::{
+    dedup_digest, dedup_options, dedup_put_vli, DedupInput, DEDUP_GEAR, DEDUP_TAG_COPY,
+    DEDUP_TAG_LITERAL,
+};
+
+#[derive(Debug, Default)]
+pub struct LzmaDedupEncoder {
+    pub next: Box<LzmaNextCoder>,
+    pub input: DedupInput,
+
+    /// 正在累积的分块
+    pub chunk: Vec<u8>,
+    /// 分块的 Gear 滚动哈希
+    pub hash: u64,
+    pub min_size: usize,
+    pub max_size: usize,
+    /// 哈希值与 mask 相与为零的位置是分块边界
+    pub mask: u64,
+
+    /// 已输出为字面量的分块的指纹及其在未压缩数据中的偏移量
+    pub index: HashMap<u128, u64>,
+    /// 当前分块在未压缩数据中的偏移量
+    pub pos: u64,
+    /// 尚未输出的引用，相邻的重复分块合并为一个引用
+    pub pending_copy: Option<(u64, u64)>,
+
+    /// 已编码、尚未复制到 out[] 的数据
+    pub out_buf: Vec<u8>,
+    pub out_pos: usize,
+    /// 本次 flush 或 finish 的数据已全部编码
+    pub flushed: bool,
+}
+
+fn put_pending_copy(coder: &mut LzmaDedupEncoder) {
+    if let Some((offset, len)) = coder.pending_copy.take() {
+        coder.out_buf.push(DEDUP_TAG_COPY);
+        dedup_put_vli(&mut coder.out_buf, offset);
+        dedup_put_vli(&mut coder.out_buf, len);
+    }
+}
+
+/// 输出一个完整的分块：之前出现过的输出为引用，否则输出为字面量并加入索引
+fn put_chunk(coder: &mut LzmaDedupEncoder) {
+    let len = coder.chunk.len() as u64;
+    let digest = dedup_digest(&coder.chunk);
+
+    match coder.index.get(&digest) {
+        Some(&offset) => match coder.pending_copy {
+            Some((start, size)) if start + size == offset => {
+                coder.pending_copy = Some((start, size + len));
+            }
+            _ => {
+                put_pending_copy(coder);
+                coder.pending_copy = Some((offset, len));
+            }
+        },
+        None => {
+            coder.index.insert(digest, coder.pos);
+            put_pending_copy(coder);
+            coder.out_buf.push(DEDUP_TAG_LITERAL);
+            dedup_put_vli(&mut coder.out_buf, len);
+            coder.out_buf.extend_from_slice(&coder.chunk);
+        }
+    }
+
+    coder.pos += len;
+    coder.chunk.clear();
+    coder.hash = 0;
+}
+
+/// 把输入追加到当前分块，找到分块边界时返回 true
+fn find_boundary(coder: &mut LzmaDedupEncoder) -> bool {
+    let avail = coder.input.avail();
+    let mut hash = coder.hash;
+    let mut used = avail.len();
+    let mut cut = false;
+
+    for (i, &b) in avail.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(DEDUP_GEAR[b as usize]);
+        let len = coder.chunk.len() + i + 1;
+        if len >= coder.max_size || (len >= coder.min_size && hash & coder.mask == 0) {
+            used = i + 1;
+            cut = true;
+            break;
+        }
+    }
+
+    coder.chunk.extend_from_slice(&avail[..used]);
+    coder.input.pos += used;
+    coder.hash = hash;
+    cut
+}
+
+fn dedup_encode(
+    coder_ptr: &mut CoderType,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::DedupEncoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    loop {
+        // 先输出已编码的数据
+        if coder.out_pos < coder.out_buf.len() {
+            let out_len = coder.out_buf.len();
+            lzma_bufcpy(
+                &coder.out_buf,
+                &mut coder.out_pos,
+                out_len,
+                out,
+                out_pos,
+                out_size,
+            );
+            if coder.out_pos < out_len {
+                return LzmaRet::Ok;
+            }
+        }
+        coder.out_buf.clear();
+        coder.out_pos = 0;
+
+        if coder.flushed {
+            // flush 之后可以继续编码，为下一次 flush 或 finish 重置状态
+            coder.flushed = false;
+            coder.input.end = false;
+            return LzmaRet::StreamEnd;
+        }
+
+        if coder.input.is_empty() && !coder.input.end {
+            let ret = coder
+                .input
+                .fill(&mut coder.next, in_, in_pos, in_size, action);
+            if ret != LzmaRet::Ok {
+                return ret;
+            }
+            if coder.input.is_empty() && !coder.input.end {
+                return LzmaRet::Ok;
+            }
+        }
+
+        if find_boundary(coder) {
+            put_chunk(coder);
+        } else if coder.input.end {
+            // 没有更多输入：把不完整的分块和未输出的引用都写出去，
+            // 这样 flush 之后解码器能得到全部数据
+            if !coder.chunk.is_empty() {
+                put_chunk(coder);
+            }
+            put_pending_copy(coder);
+            coder.flushed = true;
+        }
+    }
+}
+
+fn dedup_encoder_end(coder_ptr: &mut CoderType) {
+    if let CoderType::DedupEncoder(ref mut coder) = coder_ptr {
+        lzma_next_end(&mut coder.next);
+    }
+}
+
+pub fn lzma_dedup_encoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
+    let chunk_log = match filters[0].options.as_ref().and_then(dedup_options) {
+        Some(opt) => opt.chunk_log,
+        None => return LzmaRet::OptionsError,
+    };
+
+    if next.coder.is_none() {
+        next.coder = Some(CoderType::DedupEncoder(LzmaDedupEncoder::default()));
+        next.code = Some(dedup_encode);
+        next.end = Some(dedup_encoder_end);
+    }
+
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::DedupEncoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    let avg_size = 1usize << chunk_log;
+    coder.min_size = avg_size / 4;
+    coder.max_size = avg_size * 4;
+    // Gear 哈希的低位只取决于最近几个字节，所以用高位判断边界
+    coder.mask = ((1u64 << chunk_log) - 1) << (64 - chunk_log);
+
+    coder.input = DedupInput::default();
+    coder.chunk = Vec::with_capacity(coder.max_size);
+    coder.hash = 0;
+    coder.index.clear();
+    coder.pos = 0;
+    coder.pending_copy = None;
+    coder.out_buf.clear();
+    coder.out_pos = 0;
+    coder.flushed = false;
+
+    lzma_next_filter_init(&mut coder.next, &filters[1..])
+}
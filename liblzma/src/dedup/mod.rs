@@ -0,0 +1,156 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+mod dedup_common;
+mod dedup_decoder;
+mod dedup_encoder;
+
+pub use dedup_common::*;
+pub use dedup_decoder::*;
+pub use dedup_encoder::*;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::DEDUP_WINDOW_BUF_SIZE;
+    use crate::api::{
+        LzmaFilter, LzmaOptionsDedup, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream,
+        LZMA_FILTER_DEDUP, LZMA_FILTER_LZMA2, LZMA_VLI_UNKNOWN,
+    };
+    use crate::common::lzma_raw_encoder;
+    use crate::lzma::lzma_lzma_preset;
+    use crate::test_util::{pseudo_random, raw_code};
+
+    /// 字典为 256 KiB 的 LZMA2 过滤器链，dedup 为 Some 时在前面加上去重过滤器
+    fn dedup_filters(dedup: Option<u32>) -> Vec<LzmaFilter> {
+        let mut lzma = LzmaOptionsLzma::default();
+        assert!(!lzma_lzma_preset(&mut lzma, 6));
+        lzma.dict_size = 256 << 10;
+
+        let mut filters = Vec::new();
+        if let Some(chunk_log) = dedup {
+            filters.push(LzmaFilter {
+                id: LZMA_FILTER_DEDUP,
+                options: Some(LzmaOptionsType::Dedup(LzmaOptionsDedup {
+                    chunk_log,
+                    ..Default::default()
+                })),
+            });
+        }
+        filters.push(LzmaFilter {
+            id: LZMA_FILTER_LZMA2,
+            options: Some(LzmaOptionsType::LzmaOptionsLzma(lzma)),
+        });
+        filters.push(LzmaFilter {
+            id: LZMA_VLI_UNKNOWN,
+            options: None,
+        });
+        filters
+    }
+
+    /// 重复数据相距远超过 LZMA2 字典大小
+    #[test]
+    fn far_duplicates_round_trip() {
+        let block = pseudo_random(512 << 10, 1);
+        let mut data = block.clone();
+        data.extend_from_slice(&pseudo_random(768 << 10, 2));
+        data.extend_from_slice(&block);
+        data.extend_from_slice(b"tail");
+
+        let plain = raw_code(&dedup_filters(None), &data, true).unwrap();
+
+        for chunk_log in [12, 16] {
+            let filters = dedup_filters(Some(chunk_log));
+            let encoded = raw_code(&filters, &data, true).unwrap();
+            assert!(encoded.len() + (256 << 10) < plain.len());
+
+            let decoded = raw_code(&filters, &encoded, false).unwrap();
+            assert_eq!(decoded, data);
+        }
+
+        // 空输入和不含重复的输入
+        let filters = dedup_filters(Some(16));
+        for data in [Vec::new(), pseudo_random(100_000, 3)] {
+            let encoded = raw_code(&filters, &data, true).unwrap();
+            assert_eq!(raw_code(&filters, &encoded, false).unwrap(), data);
+        }
+    }
+
+    /// 拒绝指向尚未输出的数据的引用
+    #[test]
+    fn invalid_reference() {
+        // 字面量 "abc"，之后引用偏移量 1、长度 5，超出已输出的数据
+        let tokens = [0x00, 0x03, b'a', b'b', b'c', 0x01, 0x01, 0x05];
+        let encoded = raw_code(&dedup_filters(None), &tokens, true).unwrap();
+        assert_eq!(
+            raw_code(&dedup_filters(Some(16)), &encoded, false),
+            Err(LzmaRet::DataError)
+        );
+
+        // 引用已输出的数据是合法的
+        let tokens = [0x00, 0x03, b'a', b'b', b'c', 0x01, 0x01, 0x02];
+        let encoded = raw_code(&dedup_filters(None), &tokens, true).unwrap();
+        assert_eq!(
+            raw_code(&dedup_filters(Some(16)), &encoded, false).unwrap(),
+            b"abcbc"
+        );
+
+        // 不支持的分块大小
+        let mut strm = LzmaStream::default();
+        assert_eq!(
+            lzma_raw_encoder(&mut strm, &dedup_filters(Some(30))),
+            LzmaRet::OptionsError
+        );
+    }
+
+    /// 把 filters 中去重过滤器的解码器选项设置为 window
+    fn with_window(mut filters: Vec<LzmaFilter>, window: LzmaOptionsDedup) -> Vec<LzmaFilter> {
+        if let Some(LzmaOptionsType::Dedup(opt)) = &mut filters[0].options {
+            opt.window_dir = window.window_dir;
+            opt.window_limit = window.window_limit;
+        }
+        filters
+    }
+
+    /// 窗口文件创建在指定的目录中，不留下目录项；输出超过上限时解码失败
+    #[test]
+    fn window_dir_and_limit() {
+        // 窗口超过 DEDUP_WINDOW_BUF_SIZE 后才写入窗口文件
+        let block = pseudo_random(DEDUP_WINDOW_BUF_SIZE, 4);
+        let data = [&block[..], &block[..]].concat();
+        let filters = dedup_filters(Some(12));
+        let encoded = raw_code(&filters, &data, true).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("lzma-dedup-window-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+
+        for (limit, expected) in [
+            (data.len() as u64, Ok(data.clone())),
+            (data.len() as u64 - 1, Err(LzmaRet::MemlimitError)),
+            (1000, Err(LzmaRet::MemlimitError)),
+        ] {
+            let window = LzmaOptionsDedup {
+                window_dir: Some(dir.clone()),
+                window_limit: limit,
+                ..Default::default()
+            };
+            let decoded = raw_code(&with_window(filters.clone(), window), &encoded, false);
+            assert!(decoded == expected, "limit {}", limit);
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        }
+
+        // 目录不存在时不能创建窗口文件
+        let window = LzmaOptionsDedup {
+            window_dir: Some(dir.join("missing")),
+            ..Default::default()
+        };
+        assert!(raw_code(&with_window(filters, window), &encoded, false) == Err(LzmaRet::MemError));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -27,30 +27,36 @@ fn delta_coder_end(coder_ptr: &mut CoderType) {
 }
 
 pub fn lzma_delta_coder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
-    let mut coder = LzmaDeltaCoder::default();
     if next.coder.is_none() {
-        let coder_ = LzmaDeltaCoder::default();
         next.end = Some(delta_coder_end);
-        coder.next = Box::new(LzmaNextCoder::default());
-        next.coder = Some(CoderType::DeltaCoder(coder_));
+        next.coder = Some(CoderType::DeltaCoder(LzmaDeltaCoder::default()));
     }
 
-    if lzma_delta_coder_memusage(&filters[0].options.clone().unwrap()) == u64::MAX {
+    let options = match filters[0].options.as_ref() {
+        Some(options) => options,
+        None => return LzmaRet::OptionsError,
+    };
+    if lzma_delta_coder_memusage(options) == u64::MAX {
         return LzmaRet::OptionsError;
     }
 
-    let opt = match filters[0].options.as_ref().unwrap() {
+    let opt = match options {
         LzmaOptionsType::Delta(c) => c,
         _ => return LzmaRet::ProgError, // 如果不是 AloneDecoder 类型，则返回错误
     };
 
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::DeltaCoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
     coder.distance = opt.dist as usize;
 
     coder.pos = 0;
 
     memzero(&mut coder.history);
 
-    lzma_next_filter_init(&mut coder.next, std::slice::from_ref(&filters[1]))
+    lzma_next_filter_init(&mut coder.next, &filters[1..])
 }
 
 pub fn lzma_delta_coder_memusage(mut options: &LzmaOptionsType) -> u64 {
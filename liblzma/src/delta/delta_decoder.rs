@@ -5,13 +5,15 @@
  */
 
 use crate::{
-    api::{LzmaAction, LzmaDeltaType, LzmaOptionsDelta, LzmaOptionsType, LzmaRet},
+    api::{
+        LzmaAction, LzmaDeltaType, LzmaOptionsDelta, LzmaOptionsType, LzmaRet, LZMA_DELTA_DIST_MIN,
+    },
     common::{CoderType, LzmaFilterInfo, LzmaNextCoder},
 };
 
 use super::{lzma_delta_coder_init, LzmaDeltaCoder};
 
-fn decode_buffer(coder: &mut LzmaDeltaCoder, buffer: &mut Vec<u8>, size: usize) {
+fn decode_buffer(coder: &mut LzmaDeltaCoder, buffer: &mut [u8], size: usize) {
     let distance = coder.distance;
 
     for i in 0..size {
@@ -57,9 +59,7 @@ fn delta_decode(
 
     let size = *out_pos - out_start;
     if size > 0 {
-        // 创建从 out_start 开始的切片
-        let out_slice = &mut out[out_start..*out_pos];
-        decode_buffer(coder, &mut out_slice.to_vec(), size);
+        decode_buffer(coder, &mut out[out_start..], size);
     }
     ret
 }
@@ -78,12 +78,11 @@ pub fn lzma_delta_props_decode(
         return (LzmaRet::OptionsError, None);
     }
 
-    let opt = &mut LzmaOptionsDelta::default();
-
-    opt.type_ = LzmaDeltaType::Byte;
-    opt.dist = props[0] as u32 + 1;
-
-    // 将 opt 包装成 Box<dyn Any>
+    let opt = LzmaOptionsDelta {
+        type_: LzmaDeltaType::Byte,
+        dist: props[0] as u32 + LZMA_DELTA_DIST_MIN,
+        ..Default::default()
+    };
 
-    (LzmaRet::Ok, Some(LzmaOptionsType::Lod(opt.clone())))
+    (LzmaRet::Ok, Some(LzmaOptionsType::Delta(opt)))
 }
@@ -0,0 +1,130 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 估计 Delta 过滤器的最佳距离
+//!
+//! 对每个候选距离计算 Delta 变换后字节的零阶熵，熵最低的距离通常就是采样宽度
+//! 或定长记录的宽度。记录宽度的整数倍的熵往往也同样低，所以在接近最低值的距离中
+//! 取最小的一个。
+
+use crate::api::{LZMA_DELTA_DIST_MAX, LZMA_DELTA_DIST_MIN};
+
+/// 最多分析这么多字节，较大的缓冲区从中均匀取几个窗口
+const DELTA_ANALYZE_MAX: usize = 256 << 10;
+
+/// 较大的缓冲区分析的窗口数
+const DELTA_ANALYZE_WINDOWS: usize = 4;
+
+/// Delta 变换后的熵至少降低这么多（按比例）才建议使用
+const DELTA_MIN_GAIN: f64 = 0.10;
+
+/// 熵与最低值相差不超过这么多（按比例）的距离视为同样好
+const DELTA_TIE_TOLERANCE: f64 = 0.02;
+
+/// 零阶熵（比特/字节）
+fn entropy(hist: &[u64; 256]) -> f64 {
+    let total: u64 = hist.iter().sum();
+    hist.iter()
+        .filter(|&&n| n != 0)
+        .map(|&n| {
+            let p = n as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// 分析 buf，建议 Delta 过滤器的距离（1 到 256）
+///
+/// Delta 变换不能明显降低数据的熵时返回 None，此时不值得使用 Delta 过滤器。
+pub fn lzma_delta_suggest_dist(buf: &[u8]) -> Option<u32> {
+    let max_dist = LZMA_DELTA_DIST_MAX as usize;
+
+    let windows: Vec<&[u8]> = if buf.len() <= DELTA_ANALYZE_MAX {
+        vec![buf]
+    } else {
+        let size = DELTA_ANALYZE_MAX / DELTA_ANALYZE_WINDOWS;
+        let step = (buf.len() - size) / (DELTA_ANALYZE_WINDOWS - 1);
+        (0..DELTA_ANALYZE_WINDOWS)
+            .map(|i| &buf[i * step..i * step + size])
+            .collect()
+    };
+
+    // 每个窗口的前 max_dist 字节只作为历史，所有距离统计的是同一批字节
+    if windows[0].len() <= max_dist * 4 {
+        return None;
+    }
+
+    let mut hist = [0u64; 256];
+    for window in &windows {
+        for &b in &window[max_dist..] {
+            hist[b as usize] += 1;
+        }
+    }
+    let base = entropy(&hist);
+
+    let entropies: Vec<f64> = (LZMA_DELTA_DIST_MIN..=LZMA_DELTA_DIST_MAX)
+        .map(|dist| {
+            let mut hist = [0u64; 256];
+            for window in &windows {
+                for i in max_dist..window.len() {
+                    hist[window[i].wrapping_sub(window[i - dist as usize]) as usize] += 1;
+                }
+            }
+            entropy(&hist)
+        })
+        .collect();
+
+    let min = entropies.iter().cloned().fold(f64::INFINITY, f64::min);
+    if min >= base * (1.0 - DELTA_MIN_GAIN) {
+        return None;
+    }
+
+    entropies
+        .iter()
+        .position(|&e| e <= min + min.max(0.01) * DELTA_TIE_TOLERANCE)
+        .map(|i| i as u32 + LZMA_DELTA_DIST_MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lzma_delta_suggest_dist, DELTA_ANALYZE_MAX};
+    use crate::test_util::{pcm16, pseudo_random, words};
+
+    #[test]
+    fn pcm_sample_width() {
+        assert_eq!(lzma_delta_suggest_dist(&pcm16(2, 16 << 10)), Some(4));
+        assert_eq!(lzma_delta_suggest_dist(&pcm16(1, 16 << 10)), Some(2));
+        assert_eq!(lzma_delta_suggest_dist(&pcm16(3, 16 << 10)), Some(6));
+
+        // 较大的缓冲区只分析其中几个窗口
+        let big = pcm16(2, DELTA_ANALYZE_MAX);
+        assert_eq!(lzma_delta_suggest_dist(&big), Some(4));
+    }
+
+    /// 定长记录：每条 12 字节，其中的计数器逐条递增
+    #[test]
+    fn fixed_size_records() {
+        let records: Vec<u8> = (0..10_000u32)
+            .flat_map(|i| {
+                let mut record = [0u8; 12];
+                record[..4].copy_from_slice(&(i * 3).to_le_bytes());
+                record[4..8].copy_from_slice(&(i * 7 + 1000).to_le_bytes());
+                record[8..].copy_from_slice(&(i / 5).to_le_bytes());
+                record
+            })
+            .collect();
+        assert_eq!(lzma_delta_suggest_dist(&records), Some(12));
+    }
+
+    /// 随机数据、文本和太短的输入不建议使用 Delta 过滤器
+    #[test]
+    fn not_worth_it() {
+        assert_eq!(lzma_delta_suggest_dist(&pseudo_random(64 << 10, 1)), None);
+        assert_eq!(lzma_delta_suggest_dist(&words(64 << 10)), None);
+        assert_eq!(lzma_delta_suggest_dist(&pcm16(2, 256)), None);
+        assert_eq!(lzma_delta_suggest_dist(&[]), None);
+    }
+}
@@ -62,7 +62,7 @@ fn delta_encode(
         let size = my_min(in_avail, out_avail);
 
         if size > 0 {
-            copy_and_encode(coder, in_, out, size);
+            copy_and_encode(coder, &in_[*in_pos..], &mut out[*out_pos..], size);
         }
 
         *in_pos += size;
@@ -91,7 +91,7 @@ fn delta_encode(
 
         let size = *out_pos - out_start;
         if size > 0 {
-            encode_in_place(coder, out, size);
+            encode_in_place(coder, &mut out[out_start..], size);
         }
     }
 
@@ -109,7 +109,7 @@ fn delta_encoder_update(
         _ => return LzmaRet::ProgError, // 如果不是 AloneDecoder 类型，则返回错误
     };
 
-    return lzma_next_filter_update(&mut coder.next, std::slice::from_ref(&reversed_filters[1]));
+    return lzma_next_filter_update(&mut coder.next, &reversed_filters[1..]);
 }
 
 pub fn lzma_delta_encoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
@@ -6,10 +6,12 @@
 
 mod delta_common;
 mod delta_decoder;
+mod delta_detect;
 mod delta_encoder;
 mod delta_private;
 
 pub use delta_common::*;
 pub use delta_decoder::*;
+pub use delta_detect::*;
 pub use delta_encoder::*;
 pub use delta_private::*;
@@ -0,0 +1,107 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! ChaCha20-Poly1305 认证加密（RFC 8439 2.8 节）
+
+use super::{chacha20_block, chacha20_xor, Poly1305, CHACHA20_NONCE_SIZE, POLY1305_TAG_SIZE};
+
+/// 计算附加数据 aad 和密文 ct 的认证标签
+fn aead_tag(
+    key: &[u8; 32],
+    nonce: &[u8; CHACHA20_NONCE_SIZE],
+    aad: &[u8],
+    ct: &[u8],
+) -> [u8; POLY1305_TAG_SIZE] {
+    // Poly1305 的一次性密钥是计数器为 0 的密钥流块的前 32 字节
+    let block = chacha20_block(key, 0, nonce);
+    let mut mac = Poly1305::new(block[..32].try_into().unwrap());
+    mac.update(aad);
+    mac.pad16();
+    mac.update(ct);
+    mac.pad16();
+    mac.update(&(aad.len() as u64).to_le_bytes());
+    mac.update(&(ct.len() as u64).to_le_bytes());
+    mac.finish()
+}
+
+/// 原地加密 data 并返回认证标签
+pub fn chacha20_poly1305_seal(
+    key: &[u8; 32],
+    nonce: &[u8; CHACHA20_NONCE_SIZE],
+    aad: &[u8],
+    data: &mut [u8],
+) -> [u8; POLY1305_TAG_SIZE] {
+    chacha20_xor(key, 1, nonce, data);
+    aead_tag(key, nonce, aad, data)
+}
+
+/// 验证认证标签后原地解密 data，标签不匹配时返回 false，data 不变
+pub fn chacha20_poly1305_open(
+    key: &[u8; 32],
+    nonce: &[u8; CHACHA20_NONCE_SIZE],
+    aad: &[u8],
+    data: &mut [u8],
+    tag: &[u8; POLY1305_TAG_SIZE],
+) -> bool {
+    let expected = aead_tag(key, nonce, aad, data);
+
+    // 比较所用的时间与内容无关
+    if expected
+        .iter()
+        .zip(tag)
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        != 0
+    {
+        return false;
+    }
+
+    chacha20_xor(key, 1, nonce, data);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chacha20_poly1305_open, chacha20_poly1305_seal};
+    use crate::test_util::hex;
+
+    /// RFC 8439 2.8.2 节
+    #[test]
+    fn rfc_8439_aead() {
+        let key: [u8; 32] = core::array::from_fn(|i| 0x80 + i as u8);
+        let nonce = hex("070000004041424344454647");
+        let nonce = nonce[..].try_into().unwrap();
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+            only one tip for the future, sunscreen would be it.";
+        let ciphertext = hex(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116",
+        );
+
+        let mut data = plaintext.to_vec();
+        let tag = chacha20_poly1305_seal(&key, nonce, &aad, &mut data);
+        assert_eq!(data, ciphertext);
+        assert_eq!(tag[..], hex("1ae10b594f09e26a7e902ecbd0600691"));
+
+        // 标签或附加数据不对时不解密
+        let mut bad = tag;
+        bad[0] ^= 1;
+        assert!(!chacha20_poly1305_open(&key, nonce, &aad, &mut data, &bad));
+        assert!(!chacha20_poly1305_open(
+            &key,
+            nonce,
+            &aad[1..],
+            &mut data,
+            &tag
+        ));
+        assert_eq!(data, ciphertext);
+
+        assert!(chacha20_poly1305_open(&key, nonce, &aad, &mut data, &tag));
+        assert_eq!(data, plaintext);
+    }
+}
@@ -0,0 +1,425 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! Argon2id 口令散列（RFC 9106）
+//!
+//! 加密过滤器只使用一个 lane，不带密钥和附加数据；多个 lane 依次计算，
+//! 用于验证 RFC 9106 的测试向量。
+//!
+//! 需要的 BLAKE2b（RFC 7693）也在这里实现，只支持不带密钥的散列。
+
+/// Argon2 版本 1.3
+const ARGON2_VERSION: u32 = 0x13;
+
+/// Argon2 的类型：2 表示 Argon2id
+const ARGON2_TYPE_ID: u32 = 2;
+
+/// 一个内存块有 128 个 64 位字（1 KiB）
+const ARGON2_QWORDS_IN_BLOCK: usize = 128;
+
+/// 每个 lane 分为 4 个 slice
+const ARGON2_SYNC_POINTS: usize = 4;
+
+type Block = [u64; ARGON2_QWORDS_IN_BLOCK];
+
+const BLAKE2B_BLOCK_SIZE: usize = 128;
+const BLAKE2B_OUT_MAX: usize = 64;
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6A09_E667_F3BC_C908,
+    0xBB67_AE85_84CA_A73B,
+    0x3C6E_F372_FE94_F82B,
+    0xA54F_F53A_5F1D_36F1,
+    0x510E_527F_ADE6_82D1,
+    0x9B05_688C_2B3E_6C1F,
+    0x1F83_D9AB_FB41_BD6B,
+    0x5BE0_CD19_137E_2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+struct Blake2b {
+    h: [u64; 8],
+    /// 已压缩的字节数
+    t: u128,
+    buf: [u8; BLAKE2B_BLOCK_SIZE],
+    buf_len: usize,
+    out_len: usize,
+}
+
+impl Blake2b {
+    fn new(out_len: usize) -> Self {
+        debug_assert!(out_len >= 1 && out_len <= BLAKE2B_OUT_MAX);
+        let mut h = BLAKE2B_IV;
+        h[0] ^= 0x0101_0000 ^ out_len as u64;
+        Blake2b {
+            h,
+            t: 0,
+            buf: [0; BLAKE2B_BLOCK_SIZE],
+            buf_len: 0,
+            out_len,
+        }
+    }
+
+    fn compress(&mut self, last: bool) {
+        let mut m = [0u64; 16];
+        for (i, w) in m.iter_mut().enumerate() {
+            *w = u64::from_le_bytes(self.buf[8 * i..8 * i + 8].try_into().unwrap());
+        }
+
+        let mut v = [0u64; 16];
+        v[..8].copy_from_slice(&self.h);
+        v[8..].copy_from_slice(&BLAKE2B_IV);
+        v[12] ^= self.t as u64;
+        v[13] ^= (self.t >> 64) as u64;
+        if last {
+            v[14] = !v[14];
+        }
+
+        for round in 0..12 {
+            let s = &BLAKE2B_SIGMA[round % 10];
+            let mut g = |a: usize, b: usize, c: usize, d: usize, x: u64, y: u64| {
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+                v[d] = (v[d] ^ v[a]).rotate_right(32);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right(24);
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+                v[d] = (v[d] ^ v[a]).rotate_right(16);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right(63);
+            };
+            g(0, 4, 8, 12, m[s[0]], m[s[1]]);
+            g(1, 5, 9, 13, m[s[2]], m[s[3]]);
+            g(2, 6, 10, 14, m[s[4]], m[s[5]]);
+            g(3, 7, 11, 15, m[s[6]], m[s[7]]);
+            g(0, 5, 10, 15, m[s[8]], m[s[9]]);
+            g(1, 6, 11, 12, m[s[10]], m[s[11]]);
+            g(2, 7, 8, 13, m[s[12]], m[s[13]]);
+            g(3, 4, 9, 14, m[s[14]], m[s[15]]);
+        }
+
+        for i in 0..8 {
+            self.h[i] ^= v[i] ^ v[i + 8];
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // 最后一个分组要带标记压缩，所以缓冲区满了也要等有更多数据时才压缩
+            if self.buf_len == BLAKE2B_BLOCK_SIZE {
+                self.t += BLAKE2B_BLOCK_SIZE as u128;
+                self.compress(false);
+                self.buf_len = 0;
+            }
+            let n = (BLAKE2B_BLOCK_SIZE - self.buf_len).min(data.len());
+            self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+        }
+    }
+
+    fn finish(mut self, out: &mut [u8]) {
+        self.t += self.buf_len as u128;
+        self.buf[self.buf_len..].fill(0);
+        self.compress(true);
+
+        let mut bytes = [0u8; BLAKE2B_OUT_MAX];
+        for (i, w) in self.h.iter().enumerate() {
+            bytes[8 * i..8 * i + 8].copy_from_slice(&w.to_le_bytes());
+        }
+        out.copy_from_slice(&bytes[..self.out_len]);
+    }
+}
+
+/// 可变长度的散列 H'（RFC 9106 3.3 节），输入是 parts 的串联
+fn blake2b_long(out: &mut [u8], parts: &[&[u8]]) {
+    let out_len = out.len();
+    let mut hash = Blake2b::new(out_len.min(BLAKE2B_OUT_MAX));
+    hash.update(&(out_len as u32).to_le_bytes());
+    for part in parts {
+        hash.update(part);
+    }
+
+    if out_len <= BLAKE2B_OUT_MAX {
+        hash.finish(out);
+        return;
+    }
+
+    // 每个中间结果只输出前一半，最后一个完整输出
+    let mut v = [0u8; BLAKE2B_OUT_MAX];
+    hash.finish(&mut v);
+    out[..32].copy_from_slice(&v[..32]);
+    let mut pos = 32;
+    while out_len - pos > BLAKE2B_OUT_MAX {
+        let mut hash = Blake2b::new(BLAKE2B_OUT_MAX);
+        hash.update(&v);
+        hash.finish(&mut v);
+        out[pos..pos + 32].copy_from_slice(&v[..32]);
+        pos += 32;
+    }
+    let mut hash = Blake2b::new(out_len - pos);
+    hash.update(&v);
+    hash.finish(&mut out[pos..]);
+}
+
+#[inline(always)]
+fn fblamka(x: u64, y: u64) -> u64 {
+    let m = (x as u32 as u64) * (y as u32 as u64);
+    x.wrapping_add(y).wrapping_add(m.wrapping_mul(2))
+}
+
+/// BLAKE2b 轮函数的变体，乘法增加了计算时间的下限
+#[inline(always)]
+fn permute(v: &mut [u64; 128], idx: [usize; 16]) {
+    let mut gb = |a: usize, b: usize, c: usize, d: usize| {
+        let (a, b, c, d) = (idx[a], idx[b], idx[c], idx[d]);
+        v[a] = fblamka(v[a], v[b]);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = fblamka(v[c], v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = fblamka(v[a], v[b]);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = fblamka(v[c], v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    };
+    gb(0, 4, 8, 12);
+    gb(1, 5, 9, 13);
+    gb(2, 6, 10, 14);
+    gb(3, 7, 11, 15);
+    gb(0, 5, 10, 15);
+    gb(1, 6, 11, 12);
+    gb(2, 7, 8, 13);
+    gb(3, 4, 9, 14);
+}
+
+/// 压缩函数 G：next = G(prev, reference)，with_xor 时再与 next 原来的值异或
+fn fill_block(prev: &Block, reference: &Block, next: &mut Block, with_xor: bool) {
+    let mut r = [0u64; ARGON2_QWORDS_IN_BLOCK];
+    for i in 0..ARGON2_QWORDS_IN_BLOCK {
+        r[i] = prev[i] ^ reference[i];
+    }
+    let mut tmp = r;
+    if with_xor {
+        for i in 0..ARGON2_QWORDS_IN_BLOCK {
+            tmp[i] ^= next[i];
+        }
+    }
+
+    // 先按 16 个字一行处理 8 行，再按每行取 2 个字处理 8 列
+    for i in 0..8 {
+        permute(&mut r, core::array::from_fn(|j| 16 * i + j));
+    }
+    for i in 0..8 {
+        permute(
+            &mut r,
+            core::array::from_fn(|j| 2 * i + (j & 1) + 16 * (j >> 1)),
+        );
+    }
+
+    for i in 0..ARGON2_QWORDS_IN_BLOCK {
+        next[i] = tmp[i] ^ r[i];
+    }
+}
+
+/// 数据无关寻址时生成下一组 128 个伪随机数
+fn next_addresses(address: &mut Block, input: &mut Block) {
+    let zero = [0u64; ARGON2_QWORDS_IN_BLOCK];
+    input[6] += 1;
+    let mut tmp = [0u64; ARGON2_QWORDS_IN_BLOCK];
+    fill_block(&zero, input, &mut tmp, false);
+    fill_block(&zero, &tmp, address, false);
+}
+
+/// 用 Argon2id 从口令 password 和盐 salt 派生 out.len() 字节
+///
+/// time_cost 是迭代次数，memory_kib 是所用内存（KiB），至少为 8。
+pub fn argon2id(password: &[u8], salt: &[u8], time_cost: u32, memory_kib: u32, out: &mut [u8]) {
+    argon2id_lanes(password, salt, &[], &[], time_cost, memory_kib, 1, out);
+}
+
+/// 完整参数的 Argon2id：带密钥 secret、附加数据 ad 和 lanes 个 lane
+///
+/// 各个 lane 依次计算，结果与并行计算相同。memory_kib 至少为 8 * lanes。
+#[allow(clippy::too_many_arguments)]
+fn argon2id_lanes(
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    ad: &[u8],
+    time_cost: u32,
+    memory_kib: u32,
+    lanes: u32,
+    out: &mut [u8],
+) {
+    let lanes_n = lanes as usize;
+    let segment_length = memory_kib as usize / (ARGON2_SYNC_POINTS * lanes_n);
+    let lane_length = segment_length * ARGON2_SYNC_POINTS;
+    debug_assert!(segment_length >= 2 && time_cost >= 1);
+
+    let mut h0 = [0u8; BLAKE2B_OUT_MAX];
+    let mut hash = Blake2b::new(BLAKE2B_OUT_MAX);
+    for value in [
+        lanes,
+        out.len() as u32,
+        memory_kib,
+        time_cost,
+        ARGON2_VERSION,
+        ARGON2_TYPE_ID,
+    ] {
+        hash.update(&value.to_le_bytes());
+    }
+    for part in [password, salt, secret, ad] {
+        hash.update(&(part.len() as u32).to_le_bytes());
+        hash.update(part);
+    }
+    hash.finish(&mut h0);
+
+    let mut memory: Vec<Block> = vec![[0u64; ARGON2_QWORDS_IN_BLOCK]; lane_length * lanes_n];
+    let mut bytes = [0u8; 1024];
+    for lane in 0..lanes_n {
+        for i in 0..2 {
+            blake2b_long(
+                &mut bytes,
+                &[&h0, &(i as u32).to_le_bytes(), &(lane as u32).to_le_bytes()],
+            );
+            let block = &mut memory[lane * lane_length + i];
+            for (j, w) in block.iter_mut().enumerate() {
+                *w = u64::from_le_bytes(bytes[8 * j..8 * j + 8].try_into().unwrap());
+            }
+        }
+    }
+
+    for pass in 0..time_cost as usize {
+        for slice in 0..ARGON2_SYNC_POINTS {
+            for lane in 0..lanes_n {
+                // Argon2id 的第一遍的前半部分用数据无关寻址，以抵抗旁路攻击
+                let data_independent = pass == 0 && slice < ARGON2_SYNC_POINTS / 2;
+                let mut address = [0u64; ARGON2_QWORDS_IN_BLOCK];
+                let mut input = [0u64; ARGON2_QWORDS_IN_BLOCK];
+                if data_independent {
+                    input[0] = pass as u64;
+                    input[1] = lane as u64;
+                    input[2] = slice as u64;
+                    input[3] = memory.len() as u64;
+                    input[4] = time_cost as u64;
+                    input[5] = ARGON2_TYPE_ID as u64;
+                }
+
+                let start = if pass == 0 && slice == 0 {
+                    if data_independent {
+                        next_addresses(&mut address, &mut input);
+                    }
+                    2
+                } else {
+                    0
+                };
+
+                for index in start..segment_length {
+                    let pos = slice * segment_length + index;
+                    let curr = lane * lane_length + pos;
+                    let prev = if pos == 0 {
+                        curr + lane_length - 1
+                    } else {
+                        curr - 1
+                    };
+
+                    let pseudo_rand = if data_independent {
+                        if index % ARGON2_QWORDS_IN_BLOCK == 0 {
+                            next_addresses(&mut address, &mut input);
+                        }
+                        address[index % ARGON2_QWORDS_IN_BLOCK]
+                    } else {
+                        memory[prev][0]
+                    };
+
+                    // 第一遍的第一个 slice 只能参考同一个 lane
+                    let ref_lane = if pass == 0 && slice == 0 {
+                        lane
+                    } else {
+                        (pseudo_rand >> 32) as usize % lanes_n
+                    };
+
+                    // 其他 lane 中只能参考已经完成的 slice
+                    let finished = if pass == 0 {
+                        slice * segment_length
+                    } else {
+                        lane_length - segment_length
+                    };
+                    let ref_area = if ref_lane == lane {
+                        finished + index - 1
+                    } else if index == 0 {
+                        finished - 1
+                    } else {
+                        finished
+                    } as u64;
+                    let mut rel = pseudo_rand & 0xFFFF_FFFF;
+                    rel = (rel * rel) >> 32;
+                    let rel = ref_area - 1 - ((ref_area * rel) >> 32);
+                    let start_pos = if pass == 0 || slice == ARGON2_SYNC_POINTS - 1 {
+                        0
+                    } else {
+                        (slice + 1) * segment_length
+                    };
+                    let reference =
+                        ref_lane * lane_length + (start_pos + rel as usize) % lane_length;
+
+                    let prev_block = memory[prev];
+                    let ref_block = memory[reference];
+                    fill_block(&prev_block, &ref_block, &mut memory[curr], pass > 0);
+                }
+            }
+        }
+    }
+
+    // 各个 lane 的最后一块异或后得到输出
+    let mut last = memory[lane_length - 1];
+    for lane in 1..lanes_n {
+        for (w, x) in last.iter_mut().zip(memory[(lane + 1) * lane_length - 1].iter()) {
+            *w ^= x;
+        }
+    }
+    for (j, w) in last.iter().enumerate() {
+        bytes[8 * j..8 * j + 8].copy_from_slice(&w.to_le_bytes());
+    }
+    blake2b_long(out, &[&bytes]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::argon2id_lanes;
+    use crate::test_util::hex;
+
+    /// RFC 9106 5.3 节
+    #[test]
+    fn rfc_9106_argon2id() {
+        let mut out = [0u8; 32];
+        argon2id_lanes(
+            &[0x01; 32],
+            &[0x02; 16],
+            &[0x03; 8],
+            &[0x04; 12],
+            3,
+            32,
+            4,
+            &mut out,
+        );
+        assert_eq!(
+            out[..],
+            hex("0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659")
+        );
+    }
+}
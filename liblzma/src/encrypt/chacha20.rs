@@ -0,0 +1,126 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! ChaCha20 流密码（RFC 8439 2.4 节），96 位 nonce、32 位块计数器
+
+/// ChaCha20 的块大小
+pub const CHACHA20_BLOCK_SIZE: usize = 64;
+
+/// ChaCha20 的 nonce 长度
+pub const CHACHA20_NONCE_SIZE: usize = 12;
+
+#[inline(always)]
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+/// 计算一个密钥流块
+pub fn chacha20_block(
+    key: &[u8; 32],
+    counter: u32,
+    nonce: &[u8; CHACHA20_NONCE_SIZE],
+) -> [u8; CHACHA20_BLOCK_SIZE] {
+    let mut state = [0u32; 16];
+    // "expand 32-byte k"
+    state[0] = 0x6170_7865;
+    state[1] = 0x3320_646E;
+    state[2] = 0x7962_2D32;
+    state[3] = 0x6B20_6574;
+    for i in 0..8 {
+        state[4 + i] = u32::from_le_bytes(key[4 * i..4 * i + 4].try_into().unwrap());
+    }
+    state[12] = counter;
+    for i in 0..3 {
+        state[13 + i] = u32::from_le_bytes(nonce[4 * i..4 * i + 4].try_into().unwrap());
+    }
+
+    let mut x = state;
+    for _ in 0..10 {
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+
+    let mut out = [0u8; CHACHA20_BLOCK_SIZE];
+    for i in 0..16 {
+        out[4 * i..4 * i + 4].copy_from_slice(&x[i].wrapping_add(state[i]).to_le_bytes());
+    }
+    out
+}
+
+/// 用从 counter 开始的密钥流与 data 异或，加密和解密相同
+pub fn chacha20_xor(
+    key: &[u8; 32],
+    mut counter: u32,
+    nonce: &[u8; CHACHA20_NONCE_SIZE],
+    data: &mut [u8],
+) {
+    for chunk in data.chunks_mut(CHACHA20_BLOCK_SIZE) {
+        let stream = chacha20_block(key, counter, nonce);
+        for (b, s) in chunk.iter_mut().zip(stream.iter()) {
+            *b ^= s;
+        }
+        counter = counter.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chacha20_block, chacha20_xor};
+    use crate::test_util::hex;
+
+    fn key() -> [u8; 32] {
+        core::array::from_fn(|i| i as u8)
+    }
+
+    /// RFC 8439 2.3.2 节
+    #[test]
+    fn rfc_8439_block() {
+        let nonce = hex("000000090000004a00000000");
+        let block = chacha20_block(&key(), 1, nonce[..].try_into().unwrap());
+        assert_eq!(
+            block[..],
+            hex(
+                "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+                 d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+            )
+        );
+    }
+
+    /// RFC 8439 2.4.2 节
+    #[test]
+    fn rfc_8439_encrypt() {
+        let nonce = hex("000000000000004a00000000");
+        let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+            only one tip for the future, sunscreen would be it.";
+        let mut data = plaintext.to_vec();
+        chacha20_xor(&key(), 1, nonce[..].try_into().unwrap(), &mut data);
+        assert_eq!(
+            data,
+            hex(
+                "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+                 f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+                 07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+                 5af90bbf74a35be6b40b8eedf2785e42874d"
+            )
+        );
+
+        chacha20_xor(&key(), 1, nonce[..].try_into().unwrap(), &mut data);
+        assert_eq!(data, plaintext);
+    }
+}
@@ -0,0 +1,351 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 加密过滤器的数据格式、密钥环和 Filter Properties
+//!
+//! 过滤器输出的开头是 16 字节的随机盐，之后是若干记录。每个记录由 4 字节的
+//! 记录头（小端序，低 31 位是长度，最高位表示最后一个记录）、加密后的 LZMA2
+//! 数据和 16 字节的认证标签组成。记录的密钥是 HMAC-SHA256(密钥, 盐)，nonce 的
+//! 后 8 字节是记录的序号，记录头作为附加数据参与认证，所以记录不能被重排、
+//! 删除或截断。每个 Block 有自己的盐，可以独立解密。
+
+use std::fs::File;
+use std::io::Read;
+use std::sync::Mutex;
+
+use crate::{
+    api::{
+        LzmaEncryptSecretFn, LzmaOptionsEncrypt, LzmaOptionsType, LzmaRet, LZMA_ENCRYPT_KEY_SIZE,
+        LZMA_ENCRYPT_MEMORY_COST_LOG_MAX, LZMA_ENCRYPT_MEMORY_COST_LOG_MIN, LZMA_ENCRYPT_SALT_SIZE,
+        LZMA_ENCRYPT_TIME_COST_MAX,
+    },
+    check::{lzma_hmac_sha256_finish, lzma_hmac_sha256_init, lzma_hmac_sha256_update},
+    lzma::{
+        lzma_lzma2_decoder_memusage, lzma_lzma2_encoder_memusage, lzma_lzma2_props_decode,
+        lzma_lzma2_props_encode,
+    },
+};
+
+use super::{argon2id, CHACHA20_NONCE_SIZE, POLY1305_TAG_SIZE};
+
+/// 每个 Block 的加密数据开头的随机盐的长度
+pub const ENCRYPT_SALT_SIZE: usize = 16;
+
+/// 记录头的长度
+pub const ENCRYPT_HEADER_SIZE: usize = 4;
+
+/// 记录中 LZMA2 数据的最大长度
+pub const ENCRYPT_RECORD_SIZE: usize = 64 << 10;
+
+/// 记录头中表示最后一个记录的位
+pub const ENCRYPT_FINAL: u32 = 1 << 31;
+
+/// 认证标签的长度
+pub const ENCRYPT_TAG_SIZE: usize = POLY1305_TAG_SIZE;
+
+/// Filter Properties 的版本
+const ENCRYPT_PROPS_VERSION: u8 = 0;
+
+/// Filter Properties 的长度：版本、LZMA2 字典大小、Argon2id 的两个参数、
+/// Argon2id 的盐和 8 字节的密钥标识
+pub const ENCRYPT_PROPS_SIZE: u32 = 4 + LZMA_ENCRYPT_SALT_SIZE as u32 + 8;
+
+/// 计算密钥标识时 HMAC 的消息，长度与盐不同，不会与记录密钥相同
+const ENCRYPT_KEY_ID_INFO: &[u8] = b"utxz encrypt key id";
+
+/// 密钥环中的口令由 Argon2id 派生出的密钥
+struct DerivedKey {
+    secret: usize,
+    salt: [u8; LZMA_ENCRYPT_SALT_SIZE],
+    time_cost: u32,
+    memory_cost_log: u32,
+    key: [u8; LZMA_ENCRYPT_KEY_SIZE],
+}
+
+/// 解码时可以使用的口令
+struct Keyring {
+    secrets: Vec<Vec<u8>>,
+    /// Argon2id 很慢，同一文件的各个 Block 使用相同的参数，派生出的密钥保存下来
+    derived: Vec<DerivedKey>,
+    callback: Option<LzmaEncryptSecretFn>,
+    /// 回调函数已经返回了 None
+    exhausted: bool,
+}
+
+static KEYRING: Mutex<Keyring> = Mutex::new(Keyring {
+    secrets: Vec::new(),
+    derived: Vec::new(),
+    callback: None,
+    exhausted: false,
+});
+
+/// 把口令或密钥文件的内容加入密钥环，解码器用它解密数据
+pub fn lzma_encrypt_secret_add(secret: &[u8]) {
+    KEYRING.lock().unwrap().secrets.push(secret.to_vec());
+}
+
+/// 设置密钥环中没有匹配的口令时调用的函数，例如向用户询问口令
+///
+/// 调用回调函数时不持有密钥环的锁，回调函数返回的口令加入密钥环。
+pub fn lzma_encrypt_secret_callback(callback: Option<LzmaEncryptSecretFn>) {
+    let mut keyring = KEYRING.lock().unwrap();
+    keyring.callback = callback;
+    keyring.exhausted = false;
+}
+
+/// 从操作系统的随机数生成器读取随机数据
+pub fn encrypt_random(buf: &mut [u8]) -> bool {
+    File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(buf))
+        .is_ok()
+}
+
+/// 用选项中的 Argon2id 参数派生 secret 的密钥
+fn key_derive(options: &LzmaOptionsEncrypt, secret: &[u8]) -> [u8; LZMA_ENCRYPT_KEY_SIZE] {
+    let mut key = [0u8; LZMA_ENCRYPT_KEY_SIZE];
+    argon2id(
+        secret,
+        &options.salt,
+        options.time_cost,
+        1 << options.memory_cost_log,
+        &mut key,
+    );
+    key
+}
+
+/// 计算密钥的标识
+pub fn encrypt_key_id(key: &[u8; LZMA_ENCRYPT_KEY_SIZE]) -> u64 {
+    let mut hmac = lzma_hmac_sha256_init(key);
+    lzma_hmac_sha256_update(&mut hmac, ENCRYPT_KEY_ID_INFO);
+    let mac = lzma_hmac_sha256_finish(hmac);
+    u64::from_le_bytes(mac[..8].try_into().unwrap())
+}
+
+/// 计算一个 Block 的记录密钥
+pub fn encrypt_record_key(
+    key: &[u8; LZMA_ENCRYPT_KEY_SIZE],
+    salt: &[u8; ENCRYPT_SALT_SIZE],
+) -> [u8; LZMA_ENCRYPT_KEY_SIZE] {
+    let mut hmac = lzma_hmac_sha256_init(key);
+    lzma_hmac_sha256_update(&mut hmac, salt);
+    lzma_hmac_sha256_finish(hmac)
+}
+
+/// 序号为 counter 的记录的 nonce
+pub fn encrypt_nonce(counter: u64) -> [u8; CHACHA20_NONCE_SIZE] {
+    let mut nonce = [0u8; CHACHA20_NONCE_SIZE];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+/// 为编码生成新的 Argon2id 盐，并由 secret 派生密钥
+///
+/// 调用前先设置好 Argon2id 的参数。随机数生成器不可用时返回 LzmaRet::ProgError。
+pub fn lzma_encrypt_key_set(options: &mut LzmaOptionsEncrypt, secret: &[u8]) -> LzmaRet {
+    if !kdf_is_valid(options) {
+        return LzmaRet::OptionsError;
+    }
+    if !encrypt_random(&mut options.salt) {
+        return LzmaRet::ProgError;
+    }
+
+    let key = key_derive(options, secret);
+    options.key_id = encrypt_key_id(&key);
+    options.key = Some(key);
+    LzmaRet::Ok
+}
+
+/// 在密钥环中查找标识与选项相同的密钥
+///
+/// 选项中已有密钥时直接使用它。找不到时返回 LzmaRet::KeyError。
+pub fn encrypt_key_find(
+    options: &LzmaOptionsEncrypt,
+) -> Result<[u8; LZMA_ENCRYPT_KEY_SIZE], LzmaRet> {
+    if let Some(key) = options.key {
+        return Ok(key);
+    }
+
+    let mut keyring = KEYRING.lock().unwrap();
+    let mut secret = 0;
+    loop {
+        while secret < keyring.secrets.len() {
+            let cached = keyring.derived.iter().find(|d| {
+                d.secret == secret
+                    && d.salt == options.salt
+                    && d.time_cost == options.time_cost
+                    && d.memory_cost_log == options.memory_cost_log
+            });
+            let key = match cached {
+                Some(d) => d.key,
+                None => {
+                    let key = key_derive(options, &keyring.secrets[secret]);
+                    keyring.derived.push(DerivedKey {
+                        secret,
+                        salt: options.salt,
+                        time_cost: options.time_cost,
+                        memory_cost_log: options.memory_cost_log,
+                        key,
+                    });
+                    key
+                }
+            };
+            if encrypt_key_id(&key) == options.key_id {
+                return Ok(key);
+            }
+            secret += 1;
+        }
+
+        // 密钥环中的口令都不匹配时向回调函数要新的口令。回调函数可能要等待
+        // 用户输入，调用期间不持有锁，返回后重新检查其他线程加入的口令。
+        let callback = match keyring.callback {
+            Some(callback) if !keyring.exhausted => callback,
+            _ => return Err(LzmaRet::KeyError),
+        };
+        drop(keyring);
+        let new_secret = callback();
+        keyring = KEYRING.lock().unwrap();
+        match new_secret {
+            Some(new_secret) => keyring.secrets.push(new_secret),
+            None => keyring.exhausted = true,
+        }
+    }
+}
+
+fn kdf_is_valid(opt: &LzmaOptionsEncrypt) -> bool {
+    (1..=LZMA_ENCRYPT_TIME_COST_MAX).contains(&opt.time_cost)
+        && (LZMA_ENCRYPT_MEMORY_COST_LOG_MIN..=LZMA_ENCRYPT_MEMORY_COST_LOG_MAX)
+            .contains(&opt.memory_cost_log)
+}
+
+pub fn encrypt_options(options: &LzmaOptionsType) -> Option<&LzmaOptionsEncrypt> {
+    match options {
+        LzmaOptionsType::Encrypt(opt) if kdf_is_valid(opt) => Some(opt),
+        _ => None,
+    }
+}
+
+/// 最多 size 字节的 LZMA2 数据加密后增加的字节数
+pub fn encrypt_overhead(size: u64) -> u64 {
+    let records = size / ENCRYPT_RECORD_SIZE as u64 + 1;
+    ENCRYPT_SALT_SIZE as u64 + records * (ENCRYPT_HEADER_SIZE + ENCRYPT_TAG_SIZE) as u64
+}
+
+/// 编码器的内存用量：LZMA2 编码器和一个记录的缓冲区
+pub fn lzma_encrypt_encoder_memusage(options: &LzmaOptionsType) -> u64 {
+    let opt = match encrypt_options(options) {
+        Some(opt) => opt,
+        None => return u64::MAX,
+    };
+    let lzma_mem = lzma_lzma2_encoder_memusage(&LzmaOptionsType::LzmaOptionsLzma(opt.lzma.clone()));
+    if lzma_mem == u64::MAX {
+        return u64::MAX;
+    }
+    lzma_mem + 2 * ENCRYPT_RECORD_SIZE as u64
+}
+
+/// 解码器的内存用量：LZMA2 解码器、一个记录的缓冲区，以及派生密钥时 Argon2id 所用的内存
+pub fn lzma_encrypt_decoder_memusage(options: &LzmaOptionsType) -> u64 {
+    let opt = match encrypt_options(options) {
+        Some(opt) => opt,
+        None => return u64::MAX,
+    };
+    let lzma_mem = lzma_lzma2_decoder_memusage(&LzmaOptionsType::LzmaOptionsLzma(opt.lzma.clone()));
+    let kdf_mem = if opt.key.is_none() {
+        1024u64 << opt.memory_cost_log
+    } else {
+        0
+    };
+    lzma_mem + (ENCRYPT_RECORD_SIZE + ENCRYPT_HEADER_SIZE + ENCRYPT_TAG_SIZE) as u64 + kdf_mem
+}
+
+pub fn lzma_encrypt_props_encode(options: &LzmaOptionsType, out: &mut [u8]) -> LzmaRet {
+    let opt = match encrypt_options(options) {
+        Some(opt) => opt,
+        None => return LzmaRet::ProgError,
+    };
+    let key_id = match opt.key {
+        Some(key) => encrypt_key_id(&key),
+        None => return LzmaRet::KeyError,
+    };
+
+    out[0] = ENCRYPT_PROPS_VERSION;
+    let ret = lzma_lzma2_props_encode(
+        &LzmaOptionsType::LzmaOptionsLzma(opt.lzma.clone()),
+        &mut out[1..2],
+    );
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+    out[2] = opt.time_cost as u8;
+    out[3] = opt.memory_cost_log as u8;
+    out[4..4 + LZMA_ENCRYPT_SALT_SIZE].copy_from_slice(&opt.salt);
+    out[4 + LZMA_ENCRYPT_SALT_SIZE..ENCRYPT_PROPS_SIZE as usize]
+        .copy_from_slice(&key_id.to_le_bytes());
+    LzmaRet::Ok
+}
+
+pub fn lzma_encrypt_props_decode(
+    props: &[u8],
+    props_size: usize,
+) -> (LzmaRet, Option<LzmaOptionsType>) {
+    if props_size != ENCRYPT_PROPS_SIZE as usize || props[0] != ENCRYPT_PROPS_VERSION {
+        return (LzmaRet::OptionsError, None);
+    }
+
+    let lzma = match lzma_lzma2_props_decode(&props[1..2], 1) {
+        (LzmaRet::Ok, Some(LzmaOptionsType::LzmaOptionsLzma(lzma))) => lzma,
+        (LzmaRet::Ok, _) => return (LzmaRet::ProgError, None),
+        (ret, _) => return (ret, None),
+    };
+
+    let opt = LzmaOptionsType::Encrypt(LzmaOptionsEncrypt {
+        lzma,
+        salt: props[4..4 + LZMA_ENCRYPT_SALT_SIZE].try_into().unwrap(),
+        time_cost: props[2] as u32,
+        memory_cost_log: props[3] as u32,
+        key: None,
+        key_id: u64::from_le_bytes(
+            props[4 + LZMA_ENCRYPT_SALT_SIZE..props_size]
+                .try_into()
+                .unwrap(),
+        ),
+    });
+    if encrypt_options(&opt).is_none() {
+        return (LzmaRet::OptionsError, None);
+    }
+
+    (LzmaRet::Ok, Some(opt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encrypt_key_find, lzma_encrypt_key_set, lzma_encrypt_secret_add,
+        lzma_encrypt_secret_callback,
+    };
+    use crate::api::{LzmaOptionsEncrypt, LzmaRet, LZMA_ENCRYPT_MEMORY_COST_LOG_MIN};
+
+    /// 回调函数在锁外调用，可以自己把口令加入密钥环
+    fn secret_add() -> Option<Vec<u8>> {
+        lzma_encrypt_secret_add(b"callback");
+        None
+    }
+
+    #[test]
+    fn callback_adds_secret() {
+        let mut opt = LzmaOptionsEncrypt {
+            time_cost: 1,
+            memory_cost_log: LZMA_ENCRYPT_MEMORY_COST_LOG_MIN,
+            ..Default::default()
+        };
+        assert_eq!(lzma_encrypt_key_set(&mut opt, b"callback"), LzmaRet::Ok);
+        let key = opt.key.take().unwrap();
+
+        lzma_encrypt_secret_callback(Some(secret_add));
+        assert_eq!(encrypt_key_find(&opt), Ok(key));
+        lzma_encrypt_secret_callback(None);
+    }
+}
@@ -0,0 +1,334 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use crate::{
+    api::{LzmaAction, LzmaOptionsType, LzmaRet, LZMA_ENCRYPT_KEY_SIZE, LZMA_FILTER_LZMA2},
+    common::{
+        lzma_bufcpy, lzma_next_end, lzma_next_filter_init, CoderType, LzmaFilterInfo, LzmaNextCoder,
+    },
+    lzma::lzma_lzma2_decoder_init,
+};
+
+use super::{
+    chacha20_poly1305_open, encrypt_key_find, encrypt_nonce, encrypt_options, encrypt_record_key,
+    ENCRYPT_FINAL, ENCRYPT_HEADER_SIZE, ENCRYPT_RECORD_SIZE, ENCRYPT_SALT_SIZE, ENCRYPT_TAG_SIZE,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Sequence {
+    #[default]
+    Salt,
+    Header,
+    Body {
+        len: usize,
+        last: bool,
+    },
+    Plain,
+    End,
+}
+
+#[derive(Debug, Default)]
+pub struct LzmaEncryptDecoder {
+    /// 提供加密数据的下一个过滤器，本过滤器在链的最后时为空
+    pub next: Box<LzmaNextCoder>,
+    /// 解压缩记录内容的 LZMA2 解码器
+    pub lzma: Box<LzmaNextCoder>,
+    /// 属性中的密钥标识对应的密钥
+    pub key: [u8; LZMA_ENCRYPT_KEY_SIZE],
+    /// 本 Block 的记录密钥
+    pub record_key: [u8; LZMA_ENCRYPT_KEY_SIZE],
+    /// 下一个记录的序号
+    pub counter: u64,
+    pub sequence: Sequence,
+
+    /// 盐、记录头或整个记录，解密后是记录头和明文
+    pub buf: Vec<u8>,
+    pub buf_pos: usize,
+    /// 尚未交给 LZMA2 解码器的明文
+    pub plain_pos: usize,
+    pub plain_end: usize,
+    /// 已读到最后一个记录
+    pub last_seen: bool,
+    /// LZMA2 解码器已结束
+    pub lzma_done: bool,
+    /// 下一个过滤器已结束
+    pub next_done: bool,
+}
+
+/// 从下一个过滤器或 in[] 读入数据直到 coder.buf 中有 need 字节
+///
+/// 在链的最后时只从 in[] 复制需要的字节，之后的 Block Padding 和校验值留给
+/// Block 解码器。数据已够时返回 true。
+fn fill(
+    coder: &mut LzmaEncryptDecoder,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    need: usize,
+    action: LzmaAction,
+) -> Result<bool, LzmaRet> {
+    if coder.buf.len() < need {
+        coder.buf.resize(need, 0);
+    }
+
+    match coder.next.code {
+        None => {
+            lzma_bufcpy(
+                in_,
+                in_pos,
+                in_size,
+                &mut coder.buf,
+                &mut coder.buf_pos,
+                need,
+            );
+        }
+        // 加密数据在最后一个记录之前结束
+        Some(_) if coder.next_done => return Err(LzmaRet::DataError),
+        Some(code) => {
+            let ret = code(
+                coder.next.coder.as_mut().unwrap(),
+                in_,
+                in_pos,
+                in_size,
+                &mut coder.buf,
+                &mut coder.buf_pos,
+                need,
+                action,
+            );
+            match ret {
+                LzmaRet::Ok => {}
+                LzmaRet::StreamEnd if coder.buf_pos < need => return Err(LzmaRet::DataError),
+                LzmaRet::StreamEnd => coder.next_done = true,
+                ret => return Err(ret),
+            }
+        }
+    }
+
+    Ok(coder.buf_pos == need)
+}
+
+fn encrypt_decode(
+    coder_ptr: &mut CoderType,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::EncryptDecoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    loop {
+        match coder.sequence {
+            Sequence::Salt => {
+                match fill(coder, in_, in_pos, in_size, ENCRYPT_SALT_SIZE, action) {
+                    Ok(true) => {}
+                    Ok(false) => return LzmaRet::Ok,
+                    Err(ret) => return ret,
+                }
+                let salt = coder.buf[..ENCRYPT_SALT_SIZE].try_into().unwrap();
+                coder.record_key = encrypt_record_key(&coder.key, &salt);
+                coder.buf_pos = 0;
+                coder.sequence = Sequence::Header;
+            }
+
+            Sequence::Header => {
+                match fill(coder, in_, in_pos, in_size, ENCRYPT_HEADER_SIZE, action) {
+                    Ok(true) => {}
+                    Ok(false) => return LzmaRet::Ok,
+                    Err(ret) => return ret,
+                }
+                let header =
+                    u32::from_le_bytes(coder.buf[..ENCRYPT_HEADER_SIZE].try_into().unwrap());
+                let len = (header & !ENCRYPT_FINAL) as usize;
+                if len > ENCRYPT_RECORD_SIZE {
+                    return LzmaRet::DataError;
+                }
+                coder.sequence = Sequence::Body {
+                    len,
+                    last: header & ENCRYPT_FINAL != 0,
+                };
+            }
+
+            Sequence::Body { len, last } => {
+                let need = ENCRYPT_HEADER_SIZE + len + ENCRYPT_TAG_SIZE;
+                match fill(coder, in_, in_pos, in_size, need, action) {
+                    Ok(true) => {}
+                    Ok(false) => return LzmaRet::Ok,
+                    Err(ret) => return ret,
+                }
+
+                let (header, rest) = coder.buf.split_at_mut(ENCRYPT_HEADER_SIZE);
+                let (data, tag) = rest.split_at_mut(len);
+                let tag = tag[..ENCRYPT_TAG_SIZE].try_into().unwrap();
+                if !chacha20_poly1305_open(
+                    &coder.record_key,
+                    &encrypt_nonce(coder.counter),
+                    header,
+                    data,
+                    tag,
+                ) {
+                    return LzmaRet::DataError;
+                }
+
+                coder.counter += 1;
+                coder.buf_pos = 0;
+                coder.plain_pos = ENCRYPT_HEADER_SIZE;
+                coder.plain_end = ENCRYPT_HEADER_SIZE + len;
+                coder.last_seen = last;
+                coder.sequence = Sequence::Plain;
+            }
+
+            Sequence::Plain => {
+                if coder.plain_pos < coder.plain_end {
+                    // LZMA2 数据结束后不能还有数据
+                    if coder.lzma_done {
+                        return LzmaRet::DataError;
+                    }
+
+                    let plain_start = coder.plain_pos;
+                    let out_start = *out_pos;
+                    let lzma = &mut coder.lzma;
+                    let ret = (lzma.code.unwrap())(
+                        lzma.coder.as_mut().unwrap(),
+                        &coder.buf,
+                        &mut coder.plain_pos,
+                        coder.plain_end,
+                        out,
+                        out_pos,
+                        out_size,
+                        action,
+                    );
+                    match ret {
+                        LzmaRet::Ok => {}
+                        LzmaRet::StreamEnd => coder.lzma_done = true,
+                        ret => return ret,
+                    }
+
+                    // 输出缓冲区已满时仍继续读取，Block 解码器要求输出全部数据后
+                    // 输入也已用完，所以 LZMA2 的结束标记和最后一个记录要尽早读完
+                    if coder.plain_pos == plain_start && *out_pos == out_start && !coder.lzma_done {
+                        return LzmaRet::Ok;
+                    }
+                    continue;
+                }
+
+                coder.sequence = if coder.last_seen {
+                    Sequence::End
+                } else {
+                    Sequence::Header
+                };
+            }
+
+            Sequence::End => {
+                // LZMA2 解码器可能还有尚未输出的数据
+                if !coder.lzma_done {
+                    let out_start = *out_pos;
+                    let lzma = &mut coder.lzma;
+                    let ret = (lzma.code.unwrap())(
+                        lzma.coder.as_mut().unwrap(),
+                        &coder.buf,
+                        &mut coder.plain_pos,
+                        coder.plain_end,
+                        out,
+                        out_pos,
+                        out_size,
+                        action,
+                    );
+                    match ret {
+                        LzmaRet::Ok if *out_pos == out_size => return LzmaRet::Ok,
+                        // 输出空间足够却没有结束，说明 LZMA2 数据不完整
+                        LzmaRet::Ok if *out_pos == out_start => return LzmaRet::DataError,
+                        LzmaRet::Ok => continue,
+                        LzmaRet::StreamEnd => coder.lzma_done = true,
+                        ret => return ret,
+                    }
+                }
+
+                // 最后一个记录之后下一个过滤器也必须结束
+                if let (Some(code), false) = (coder.next.code, coder.next_done) {
+                    let mut extra = [0u8; 1];
+                    let mut extra_pos = 0;
+                    let ret = code(
+                        coder.next.coder.as_mut().unwrap(),
+                        in_,
+                        in_pos,
+                        in_size,
+                        &mut extra,
+                        &mut extra_pos,
+                        1,
+                        action,
+                    );
+                    if extra_pos != 0 {
+                        return LzmaRet::DataError;
+                    }
+                    return ret;
+                }
+
+                return LzmaRet::StreamEnd;
+            }
+        }
+    }
+}
+
+fn encrypt_decoder_end(coder_ptr: &mut CoderType) {
+    if let CoderType::EncryptDecoder(ref mut coder) = coder_ptr {
+        lzma_next_end(&mut coder.next);
+        lzma_next_end(&mut coder.lzma);
+    }
+}
+
+pub fn lzma_encrypt_decoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
+    let opt = match filters[0].options.as_ref().and_then(encrypt_options) {
+        Some(opt) => opt,
+        None => return LzmaRet::OptionsError,
+    };
+    let key = match encrypt_key_find(opt) {
+        Ok(key) => key,
+        Err(ret) => return ret,
+    };
+
+    if next.coder.is_none() {
+        next.coder = Some(CoderType::EncryptDecoder(LzmaEncryptDecoder::default()));
+        next.code = Some(encrypt_decode);
+        next.end = Some(encrypt_decoder_end);
+    }
+
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::EncryptDecoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    coder.key = key;
+    coder.counter = 0;
+    coder.sequence = Sequence::Salt;
+    coder.buf_pos = 0;
+    coder.plain_pos = 0;
+    coder.plain_end = 0;
+    coder.last_seen = false;
+    coder.lzma_done = false;
+    coder.next_done = false;
+
+    // 记录内容由 LZMA2 解码器从 coder.buf 读取
+    let lzma_filters = [
+        LzmaFilterInfo {
+            id: LZMA_FILTER_LZMA2,
+            init: Some(lzma_lzma2_decoder_init),
+            options: Some(LzmaOptionsType::LzmaOptionsLzma(opt.lzma.clone())),
+        },
+        LzmaFilterInfo::default(),
+    ];
+    let ret = lzma_next_filter_init(&mut coder.lzma, &lzma_filters);
+    if ret != LzmaRet::Ok {
+        return ret;
+    }
+
+    lzma_next_filter_init(&mut coder.next, &filters[1..])
+}
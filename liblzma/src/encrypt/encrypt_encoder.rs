@@ -0,0 +1,194 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+use crate::{
+    api::{LzmaAction, LzmaOptionsType, LzmaRet, LZMA_ENCRYPT_KEY_SIZE, LZMA_FILTER_LZMA2},
+    common::{
+        lzma_bufcpy, lzma_next_end, lzma_next_filter_init, CoderType, LzmaFilterInfo, LzmaNextCoder,
+    },
+    lzma::lzma_lzma2_encoder_init,
+};
+
+use super::{
+    chacha20_poly1305_seal, encrypt_nonce, encrypt_options, encrypt_random, encrypt_record_key,
+    ENCRYPT_FINAL, ENCRYPT_HEADER_SIZE, ENCRYPT_RECORD_SIZE, ENCRYPT_SALT_SIZE, ENCRYPT_TAG_SIZE,
+};
+
+#[derive(Debug, Default)]
+pub struct LzmaEncryptEncoder {
+    /// 压缩数据的 LZMA2 编码器，它从链中的下一个过滤器或应用程序读取数据
+    pub lzma: Box<LzmaNextCoder>,
+    /// 本 Block 的记录密钥
+    pub key: [u8; LZMA_ENCRYPT_KEY_SIZE],
+    /// 下一个记录的序号
+    pub counter: u64,
+
+    /// 正在累积的记录，内容是 LZMA2 编码器的输出
+    pub record: Vec<u8>,
+    pub record_len: usize,
+    /// LZMA2 编码器已完成本次 flush 或 finish
+    pub lzma_end: bool,
+
+    /// 已加密、尚未复制到 out[] 的数据
+    pub out_buf: Vec<u8>,
+    pub out_pos: usize,
+    /// 本次 flush 或 finish 的数据已全部加密
+    pub flushed: bool,
+}
+
+/// 加密当前记录并放入输出缓冲区
+fn seal_record(coder: &mut LzmaEncryptEncoder, last: bool) {
+    let len = coder.record_len;
+    let header = (len as u32 | if last { ENCRYPT_FINAL } else { 0 }).to_le_bytes();
+
+    let start = coder.out_buf.len();
+    coder.out_buf.extend_from_slice(&header);
+    coder.out_buf.extend_from_slice(&coder.record[..len]);
+    let nonce = encrypt_nonce(coder.counter);
+    let tag = chacha20_poly1305_seal(
+        &coder.key,
+        &nonce,
+        &header,
+        &mut coder.out_buf[start + ENCRYPT_HEADER_SIZE..],
+    );
+    coder.out_buf.extend_from_slice(&tag);
+
+    coder.counter += 1;
+    coder.record_len = 0;
+}
+
+fn encrypt_encode(
+    coder_ptr: &mut CoderType,
+    in_: &[u8],
+    in_pos: &mut usize,
+    in_size: usize,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+    action: LzmaAction,
+) -> LzmaRet {
+    let coder = match coder_ptr {
+        CoderType::EncryptEncoder(ref mut c) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    loop {
+        // 先输出已加密的数据
+        if coder.out_pos < coder.out_buf.len() {
+            let out_len = coder.out_buf.len();
+            lzma_bufcpy(
+                &coder.out_buf,
+                &mut coder.out_pos,
+                out_len,
+                out,
+                out_pos,
+                out_size,
+            );
+            if coder.out_pos < out_len {
+                return LzmaRet::Ok;
+            }
+        }
+        coder.out_buf.clear();
+        coder.out_pos = 0;
+
+        if coder.flushed {
+            // flush 之后可以继续编码
+            coder.flushed = false;
+            return LzmaRet::StreamEnd;
+        }
+
+        if !coder.lzma_end {
+            let lzma = &mut coder.lzma;
+            let ret = (lzma.code.unwrap())(
+                lzma.coder.as_mut().unwrap(),
+                in_,
+                in_pos,
+                in_size,
+                &mut coder.record,
+                &mut coder.record_len,
+                ENCRYPT_RECORD_SIZE,
+                action,
+            );
+            match ret {
+                LzmaRet::Ok => {}
+                LzmaRet::StreamEnd => coder.lzma_end = true,
+                ret => return ret,
+            }
+        }
+
+        if coder.lzma_end {
+            // 解码器据最后一个记录的标记判断数据是否完整，所以即使为空也要输出它；
+            // flush 时没有新数据就不必输出空记录
+            let last = action == LzmaAction::Finish;
+            if last || coder.record_len > 0 {
+                seal_record(coder, last);
+            }
+            coder.lzma_end = false;
+            coder.flushed = true;
+        } else if coder.record_len == ENCRYPT_RECORD_SIZE {
+            seal_record(coder, false);
+        } else {
+            return LzmaRet::Ok;
+        }
+    }
+}
+
+fn encrypt_encoder_end(coder_ptr: &mut CoderType) {
+    if let CoderType::EncryptEncoder(ref mut coder) = coder_ptr {
+        lzma_next_end(&mut coder.lzma);
+    }
+}
+
+pub fn lzma_encrypt_encoder_init(next: &mut LzmaNextCoder, filters: &[LzmaFilterInfo]) -> LzmaRet {
+    let opt = match filters[0].options.as_ref().and_then(encrypt_options) {
+        Some(opt) => opt,
+        None => return LzmaRet::OptionsError,
+    };
+    let key = match opt.key {
+        Some(key) => key,
+        None => return LzmaRet::KeyError,
+    };
+
+    // 每个 Block 使用新的随机盐，所以各个 Block 的记录密钥不同
+    let mut salt = [0u8; ENCRYPT_SALT_SIZE];
+    if !encrypt_random(&mut salt) {
+        return LzmaRet::ProgError;
+    }
+
+    // LZMA2 编码器接在本过滤器和链中的下一个过滤器之间
+    let mut chain = vec![LzmaFilterInfo {
+        id: LZMA_FILTER_LZMA2,
+        init: Some(lzma_lzma2_encoder_init),
+        options: Some(LzmaOptionsType::LzmaOptionsLzma(opt.lzma.clone())),
+    }];
+    chain.extend_from_slice(&filters[1..]);
+
+    if next.coder.is_none() {
+        next.coder = Some(CoderType::EncryptEncoder(LzmaEncryptEncoder::default()));
+        next.code = Some(encrypt_encode);
+        next.end = Some(encrypt_encoder_end);
+    }
+
+    let coder = match next.coder.as_mut() {
+        Some(CoderType::EncryptEncoder(c)) => c,
+        _ => return LzmaRet::ProgError,
+    };
+
+    coder.key = encrypt_record_key(&key, &salt);
+    coder.counter = 0;
+    coder.record.resize(ENCRYPT_RECORD_SIZE, 0);
+    coder.record_len = 0;
+    coder.lzma_end = false;
+    coder.out_buf.clear();
+    coder
+        .out_buf
+        .reserve(ENCRYPT_HEADER_SIZE + ENCRYPT_RECORD_SIZE + ENCRYPT_TAG_SIZE);
+    coder.out_buf.extend_from_slice(&salt);
+    coder.out_pos = 0;
+    coder.flushed = false;
+
+    lzma_next_filter_init(&mut coder.lzma, &chain)
+}
@@ -0,0 +1,21 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+mod aead;
+mod argon2;
+mod chacha20;
+mod encrypt_common;
+mod encrypt_decoder;
+mod encrypt_encoder;
+mod poly1305;
+
+pub use aead::*;
+pub use argon2::*;
+pub use chacha20::*;
+pub use encrypt_common::*;
+pub use encrypt_decoder::*;
+pub use encrypt_encoder::*;
+pub use poly1305::*;
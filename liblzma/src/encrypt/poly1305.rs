@@ -0,0 +1,204 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! Poly1305 消息认证码（RFC 8439 2.5 节）
+//!
+//! 130 位的累加器和 r 都用 5 个 26 位的分量表示，乘积不会溢出 u64。
+
+/// Poly1305 的分组大小
+const POLY1305_BLOCK_SIZE: usize = 16;
+
+/// 认证标签的长度
+pub const POLY1305_TAG_SIZE: usize = 16;
+
+const MASK26: u32 = 0x3FF_FFFF;
+
+#[derive(Debug, Clone)]
+pub struct Poly1305 {
+    r: [u32; 5],
+    s: [u32; 4],
+    h: [u32; 5],
+    /// 不满一个分组的输入
+    buf: [u8; POLY1305_BLOCK_SIZE],
+    buf_len: usize,
+}
+
+#[inline(always)]
+fn le32(b: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(b[pos..pos + 4].try_into().unwrap())
+}
+
+impl Poly1305 {
+    /// 用 32 字节的一次性密钥初始化，前 16 字节是 r，后 16 字节是 s
+    pub fn new(key: &[u8; 32]) -> Self {
+        // r 按规范清除某些位
+        let r = [
+            le32(key, 0) & 0x3FF_FFFF,
+            (le32(key, 3) >> 2) & 0x3FF_FF03,
+            (le32(key, 6) >> 4) & 0x3FF_C0FF,
+            (le32(key, 9) >> 6) & 0x3F0_3FFF,
+            (le32(key, 12) >> 8) & 0x00F_FFFF,
+        ];
+        let s = [le32(key, 16), le32(key, 20), le32(key, 24), le32(key, 28)];
+
+        Poly1305 {
+            r,
+            s,
+            h: [0; 5],
+            buf: [0; POLY1305_BLOCK_SIZE],
+            buf_len: 0,
+        }
+    }
+
+    /// 处理一个分组，hibit 是附加在分组后面的 1 所在的位（不完整的最后分组已自行附加）
+    fn block(&mut self, m: &[u8; POLY1305_BLOCK_SIZE], hibit: u32) {
+        let [r0, r1, r2, r3, r4] = self.r.map(|v| v as u64);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+        let h0 = (self.h[0] + (le32(m, 0) & MASK26)) as u64;
+        let h1 = (self.h[1] + ((le32(m, 3) >> 2) & MASK26)) as u64;
+        let h2 = (self.h[2] + ((le32(m, 6) >> 4) & MASK26)) as u64;
+        let h3 = (self.h[3] + ((le32(m, 9) >> 6) & MASK26)) as u64;
+        let h4 = (self.h[4] + ((le32(m, 12) >> 8) | hibit)) as u64;
+
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        // 部分进位，结果小于 2^130 的若干倍即可
+        d1 += d0 >> 26;
+        d2 += d1 >> 26;
+        d3 += d2 >> 26;
+        d4 += d3 >> 26;
+        let h0 = (d0 & MASK26 as u64) + (d4 >> 26) * 5;
+        let h1 = (d1 & MASK26 as u64) + (h0 >> 26);
+
+        self.h = [
+            h0 as u32 & MASK26,
+            h1 as u32,
+            d2 as u32 & MASK26,
+            d3 as u32 & MASK26,
+            d4 as u32 & MASK26,
+        ];
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        if self.buf_len > 0 {
+            let n = (POLY1305_BLOCK_SIZE - self.buf_len).min(data.len());
+            self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+            if self.buf_len < POLY1305_BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.block(&block, 1 << 24);
+            self.buf_len = 0;
+        }
+
+        let mut chunks = data.chunks_exact(POLY1305_BLOCK_SIZE);
+        for chunk in &mut chunks {
+            self.block(chunk.try_into().unwrap(), 1 << 24);
+        }
+        let rest = chunks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buf_len = rest.len();
+    }
+
+    /// 用零填充到分组边界，ChaCha20-Poly1305 在附加数据和密文之后都这样做
+    pub fn pad16(&mut self) {
+        if self.buf_len > 0 {
+            let zeros = [0u8; POLY1305_BLOCK_SIZE];
+            self.update(&zeros[..POLY1305_BLOCK_SIZE - self.buf_len]);
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; POLY1305_TAG_SIZE] {
+        if self.buf_len > 0 {
+            let mut block = [0u8; POLY1305_BLOCK_SIZE];
+            block[..self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
+            block[self.buf_len] = 1;
+            self.block(&block, 0);
+        }
+
+        // 完全进位
+        let [mut h0, mut h1, mut h2, mut h3, mut h4] = self.h;
+        h2 += h1 >> 26;
+        h1 &= MASK26;
+        h3 += h2 >> 26;
+        h2 &= MASK26;
+        h4 += h3 >> 26;
+        h3 &= MASK26;
+        h0 += (h4 >> 26) * 5;
+        h4 &= MASK26;
+        h1 += h0 >> 26;
+        h0 &= MASK26;
+
+        // 计算 h + -p，不小于 p 时取它
+        let mut g0 = h0.wrapping_add(5);
+        let mut g1 = h1.wrapping_add(g0 >> 26);
+        g0 &= MASK26;
+        let mut g2 = h2.wrapping_add(g1 >> 26);
+        g1 &= MASK26;
+        let mut g3 = h3.wrapping_add(g2 >> 26);
+        g2 &= MASK26;
+        let mut g4 = h4.wrapping_add(g3 >> 26).wrapping_sub(1 << 26);
+        g3 &= MASK26;
+
+        let mask = (g4 >> 31).wrapping_sub(1);
+        g0 &= mask;
+        g1 &= mask;
+        g2 &= mask;
+        g3 &= mask;
+        g4 &= mask;
+        let mask = !mask;
+        h0 = (h0 & mask) | g0;
+        h1 = (h1 & mask) | g1;
+        h2 = (h2 & mask) | g2;
+        h3 = (h3 & mask) | g3;
+        h4 = (h4 & mask) | g4;
+
+        // h 转为 4 个 32 位的字后加上 s
+        let words = [
+            h0 | (h1 << 26),
+            (h1 >> 6) | (h2 << 20),
+            (h2 >> 12) | (h3 << 14),
+            (h3 >> 18) | (h4 << 8),
+        ];
+        let mut tag = [0u8; POLY1305_TAG_SIZE];
+        let mut carry = 0u64;
+        for i in 0..4 {
+            let f = words[i] as u64 + self.s[i] as u64 + carry;
+            tag[4 * i..4 * i + 4].copy_from_slice(&(f as u32).to_le_bytes());
+            carry = f >> 32;
+        }
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Poly1305;
+    use crate::test_util::hex;
+
+    /// RFC 8439 2.5.2 节，分成不同长度的片段加入得到相同的结果
+    #[test]
+    fn rfc_8439_mac() {
+        let key = hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        let msg = b"Cryptographic Forum Research Group";
+        let expected = hex("a8061dc1305136c6c22b8baf0c0127a9");
+
+        for step in [1, 5, 16, 17, msg.len()] {
+            let mut mac = Poly1305::new(key[..].try_into().unwrap());
+            for chunk in msg.chunks(step) {
+                mac.update(chunk);
+            }
+            assert_eq!(mac.finish()[..], expected);
+        }
+    }
+}
@@ -22,7 +22,10 @@ pub mod common;
 
 pub mod api;
 pub mod check;
+pub mod custom;
+pub mod dedup;
 pub mod delta;
+pub mod encrypt;
 pub mod lz;
 pub mod lzma;
 
@@ -31,4 +34,7 @@ pub mod rangecoder;
 
 pub mod simple;
 
+#[cfg(test)]
+mod test_util;
+
 use crate::api::*;
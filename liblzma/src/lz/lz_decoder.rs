@@ -184,21 +184,20 @@ pub fn dict_repeat(dict: &mut LzmaDict, distance: usize, len: &mut usize) -> boo
     if distance < left {
         if distance < dict.pos {
             // 源和目标区域重叠，但未发生环绕。
-            // 使用 unsafe ptr::copy 消除边界检查。
+            // 重复的内容以 distance + 1 为周期，每次复制源和目标之间
+            // 已有的不重叠部分，源起点固定，因此每轮可复制的长度成倍增长。
+            // 使用 unsafe ptr::copy_nonoverlapping 消除边界检查。
             let src = dict.buf.as_ptr();
             let dst = dict.buf.as_mut_ptr();
             let start = dict.pos - distance - 1;
-            // 前 distance 个字节：源 (start..start+distance) 与目标 (dict.pos..dict.pos+distance) 不重叠
-            unsafe {
-                ptr::copy_nonoverlapping(src.add(start), dst.add(dict.pos), distance);
-            }
-            dict.pos += distance;
-            left -= distance;
-            // 剩余部分可能重叠，使用 ptr::copy (memmove 语义)
-            unsafe {
-                ptr::copy(src.add(start), dst.add(dict.pos), left);
+            while left > 0 {
+                let chunk = my_min(left, dict.pos - start);
+                unsafe {
+                    ptr::copy_nonoverlapping(src.add(start), dst.add(dict.pos), chunk);
+                }
+                dict.pos += chunk;
+                left -= chunk;
             }
-            dict.pos += left;
         } else {
             // 环绕情况下的重叠复制，逐字节复制
             while left > 0 {
@@ -579,9 +578,7 @@ pub fn lzma_lz_decoder_init(
     if !lz_options.preset_dict.is_empty() && lz_options.preset_dict_size > 0 {
         let copy_size = my_min(lz_options.preset_dict_size, lz_options.dict_size);
         let offset = lz_options.preset_dict_size - copy_size;
-        coder
-            .dict
-            .buf
+        coder.dict.buf[..copy_size]
             .copy_from_slice(&lz_options.preset_dict[offset..offset + copy_size]);
         coder.dict.pos = copy_size;
         coder.dict.full = copy_size;
@@ -600,3 +597,26 @@ pub fn lzma_lz_decoder_init(
 pub fn lzma_lz_decoder_memusage(dictionary_size: usize) -> u64 {
     std::mem::size_of::<LzmaDecoder>() as u64 + dictionary_size as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::LZMA_FILTER_LZMA2;
+    use crate::test_util::{lzma_filters, pseudo_random, raw_code};
+
+    /// 距离小于长度的匹配：复制的源和目标重叠，结果要以距离为周期重复
+    #[test]
+    fn overlapping_repeat() {
+        let filters = lzma_filters(LZMA_FILTER_LZMA2, 6);
+        let mut data = Vec::new();
+        for period in 1..=20 {
+            data.extend_from_slice(&pseudo_random(100, period as u64));
+            let unit = pseudo_random(period, 1000 + period as u64);
+            for i in 0..1000 {
+                data.push(unit[i % period]);
+            }
+        }
+
+        let encoded = raw_code(&filters, &data, true).unwrap();
+        assert_eq!(raw_code(&filters, &encoded, false), Ok(data));
+    }
+}
@@ -18,6 +18,7 @@ use crate::{
     lz::{
         lzma_mf_bt2_find, lzma_mf_bt2_skip, lzma_mf_bt3_find, lzma_mf_bt3_skip, lzma_mf_bt4_find,
         lzma_mf_bt4_skip, lzma_mf_hc3_find, lzma_mf_hc3_skip, lzma_mf_hc4_find, lzma_mf_hc4_skip,
+        lzma_mf_ht4_find, lzma_mf_ht4_skip, HT4_WAYS,
     },
     lzma::{LzmaLzma1Encoder, LzmaLzma2Encoder},
 };
@@ -438,6 +439,11 @@ fn lz_encoder_prepare(mf: &mut LzmaMf, lz_options: &LzmaLzOptions) -> bool {
             mf.find = Some(lzma_mf_bt4_find);
             mf.skip = Some(lzma_mf_bt4_skip);
         }
+
+        LzmaMatchFinder::LzmaMfHt4 => {
+            mf.find = Some(lzma_mf_ht4_find);
+            mf.skip = Some(lzma_mf_ht4_skip);
+        }
         _ => {
             return true;
         }
@@ -448,6 +454,7 @@ fn lz_encoder_prepare(mf: &mut LzmaMf, lz_options: &LzmaLzOptions) -> bool {
     assert!(hash_bytes <= mf.nice_len);
 
     let is_bt = (lz_options.match_finder.clone() as u32 & 0x10) != 0;
+    let is_ht = (lz_options.match_finder.clone() as u32 & 0x20) != 0;
     let mut hs;
 
     if hash_bytes == 2 {
@@ -473,17 +480,22 @@ fn lz_encoder_prepare(mf: &mut LzmaMf, lz_options: &LzmaLzOptions) -> bool {
     mf.hash_mask = hs as u32;
 
     hs += 1;
-    if hash_bytes > 2 {
-        hs += HASH_2_SIZE;
-    }
-    if hash_bytes > 3 {
-        hs += HASH_3_SIZE;
+    if is_ht {
+        // HT 没有 2/3 字节哈希表和 son 数组，每个哈希值对应一个桶
+        hs *= HT4_WAYS;
+    } else {
+        if hash_bytes > 2 {
+            hs += HASH_2_SIZE;
+        }
+        if hash_bytes > 3 {
+            hs += HASH_3_SIZE;
+        }
     }
 
     let old_hash_count = mf.hash_count;
     let old_sons_count = mf.sons_count;
     mf.hash_count = hs as u32;
-    mf.sons_count = mf.cyclic_size;
+    mf.sons_count = if is_ht { 0 } else { mf.cyclic_size };
     if is_bt {
         mf.sons_count *= 2;
     }
@@ -529,7 +541,7 @@ fn lz_encoder_init(mf: &mut LzmaMf, lz_options: &LzmaLzOptions) -> bool {
         mf.hash = vec![0u32; mf.hash_count as usize];
         mf.son = vec![0u32; mf.sons_count as usize];
 
-        if mf.hash.is_empty() || mf.son.is_empty() {
+        if mf.hash.is_empty() || (mf.son.is_empty() && mf.sons_count != 0) {
             mf.hash.clear();
             mf.son.clear();
 
@@ -697,7 +709,13 @@ pub fn lzma_lz_encoder_init(
 }
 
 pub fn lzma_mf_is_supported(mf: LzmaMatchFinder) -> bool {
-    match mf {
-        _ => false,
-    }
+    matches!(
+        mf,
+        LzmaMatchFinder::LzmaMfHc3
+            | LzmaMatchFinder::LzmaMfHc4
+            | LzmaMatchFinder::LzmaMfBt2
+            | LzmaMatchFinder::LzmaMfBt3
+            | LzmaMatchFinder::LzmaMfBt4
+            | LzmaMatchFinder::LzmaMfHt4
+    )
 }
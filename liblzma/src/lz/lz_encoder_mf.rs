@@ -254,7 +254,7 @@ pub fn lzma_mf_hc3_find(mf: &mut LzmaMf, matches: &mut [LzmaMatch]) -> u32 {
 
     // 调用查找函数，进一步更新匹配记录
     // hc_find_func 返回匹配记录数组尾指针与 matches 数组起始指针的偏移量（即匹配数）
-    matches_count = hc_find_func(
+    matches_count += hc_find_func(
         len_limit,
         pos,
         &mf.buffer,
@@ -277,6 +277,7 @@ pub fn lzma_mf_hc3_skip(mf: &mut LzmaMf, mut amount: u32) {
         // 如果可用字节数不足 3，则调用 move_pending 更新状态后继续循环
         if mf_avail(mf) < 3 {
             move_pending(mf);
+            amount -= 1;
             continue;
         }
         // 获取当前数据切片
@@ -402,7 +403,7 @@ pub fn lzma_mf_hc4_find(mf: &mut LzmaMf, matches: &mut [LzmaMatch]) -> u32 {
     let additional: usize = hc_find_func(
         len_limit,
         pos,
-        cur,
+        &mf.buffer,
         cur_index,
         cur_match,
         mf.depth,
@@ -1032,3 +1033,88 @@ pub fn lzma_mf_bt4_skip(mf: &mut LzmaMf, mut amount: u32) {
 //         return matches_count;
 //     };
 // }
+
+/// HT4 每个哈希桶中保存的位置数
+pub const HT4_WAYS: usize = 4;
+
+/// 计算 HT4 的哈希桶在 mf.hash 中的起始下标
+fn ht4_bucket(mf: &LzmaMf, cur: &[u8]) -> usize {
+    let temp: u32 = CRC32_TABLE[cur[0] as usize] ^ (cur[1] as u32);
+    let hash_value: u32 =
+        (temp ^ ((cur[2] as u32) << 8) ^ (CRC32_TABLE[cur[3] as usize] << 5)) & mf.hash_mask;
+    hash_value as usize * HT4_WAYS
+}
+
+/// 把 pos 插入哈希桶的最前面，桶中最旧的位置被丢弃
+fn ht4_insert(mf: &mut LzmaMf, bucket: usize, pos: u32) {
+    mf.hash
+        .copy_within(bucket..bucket + HT4_WAYS - 1, bucket + 1);
+    mf.hash[bucket] = pos;
+}
+
+/// 根据 HT4 算法查找匹配项
+///
+/// HT4 是分桶的哈希表匹配查找器：每个 4 字节哈希值对应一个桶，桶中只保存
+/// 最近的 HT4_WAYS 个位置，不维护 son 数组，因此每个位置最多只比较
+/// HT4_WAYS 次。与 HC4 相比压缩率较低，但速度快得多，适合 -0 fast 这类
+/// 以吞吐量为主的场景。只报告长度至少为 4 的匹配。
+pub fn lzma_mf_ht4_find(mf: &mut LzmaMf, matches: &mut [LzmaMatch]) -> u32 {
+    let mut len_limit = mf_avail(mf);
+    if mf.nice_len <= len_limit {
+        len_limit = mf.nice_len;
+    } else if len_limit < 4 {
+        move_pending(mf);
+        return 0;
+    }
+
+    let cur_index: usize = mf.cur_offset() as usize;
+    let pos: u32 = mf.read_pos + mf.offset;
+    let bucket = ht4_bucket(mf, &mf.buffer[cur_index..]);
+
+    let mut matches_count: u32 = 0;
+    let mut len_best: u32 = 3;
+    for i in 0..HT4_WAYS {
+        let delta: u32 = pos.wrapping_sub(mf.hash[bucket + i]);
+        if delta == 0 || delta >= mf.cyclic_size || delta as usize > cur_index {
+            continue;
+        }
+
+        // 先比较当前最佳长度处的字节，不可能更长的候选不必完整比较
+        let pb = cur_index - delta as usize;
+        if mf.buffer[pb + len_best as usize] != mf.buffer[cur_index + len_best as usize] {
+            continue;
+        }
+
+        let len = lzma_memcmplen(&mf.buffer[pb..], &mf.buffer[cur_index..], 0, len_limit);
+        if len > len_best {
+            len_best = len;
+            matches[matches_count as usize].len = len;
+            matches[matches_count as usize].dist = delta - 1;
+            matches_count += 1;
+            if len == len_limit {
+                break;
+            }
+        }
+    }
+
+    ht4_insert(mf, bucket, pos);
+    move_pos(mf);
+    matches_count
+}
+
+/// 根据 HT4 算法跳过匹配查找过程中的指定数量
+pub fn lzma_mf_ht4_skip(mf: &mut LzmaMf, mut amount: u32) {
+    while amount != 0 {
+        if mf_avail(mf) < 4 {
+            move_pending(mf);
+            amount -= 1;
+            continue;
+        }
+
+        let pos: u32 = mf.read_pos + mf.offset;
+        let bucket = ht4_bucket(mf, mf_ptr(mf));
+        ht4_insert(mf, bucket, pos);
+        move_pos(mf);
+        amount -= 1;
+    }
+}
@@ -9,10 +9,14 @@ use std::mem;
 
 use crate::{
     api::{
-        LzmaAction, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaVli,
-        LZMA_DICT_SIZE_MIN, LZMA_FILTER_LZMA2, LZMA_LCLP_MAX, LZMA_PB_MAX,
+        LzmaAction, LzmaCancelToken, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet,
+        LzmaVli, LZMA_DICT_SIZE_MIN, LZMA_FILTER_LZMA2, LZMA_LCLP_MAX, LZMA_PB_MAX,
+        LZMA_VLI_UNKNOWN,
+    },
+    common::{
+        lzma_bufcpy, lzma_next_end, lzma_raw_encoder_init, LzmaFilterInfo, LzmaNextCoder,
+        LZMA_CANCEL_CHECK_SIZE,
     },
-    common::{lzma_bufcpy, LzmaFilterInfo, LzmaNextCoder},
     lz::{
         lzma_lz_encoder_init, mf_read, mf_unencoded, LzEncoderType, LzmaLzDecoder, LzmaLzEncoder,
         LzmaLzOptions, LzmaMf,
@@ -465,3 +469,95 @@ pub fn lzma_lzma2_block_size(options: &mut Box<dyn std::any::Any>) -> u64 {
     // Use at least 1 MiB to keep compression ratio better.
     my_max((opt.dict_size as u64) * 3, 1 << 20)
 }
+
+/// 把 data 编码为一段 LZMA2 块，可以直接接在前面数据的 LZMA2 块之后
+///
+/// window 是 data 之前的数据（最多 dict_size 字节），它作为预设字典装入编码器，
+/// 所以这一段的第一个块只重置状态并写出属性，不重置字典，匹配可以引用 window
+/// 中的内容。window 为空时这一段从字典重置开始，即一段普通的 LZMA2 数据。
+///
+/// 只有 last 为 true 时输出才以 LZMA2 结束标记结尾。cancel 被取消时返回
+/// `LzmaRet::Canceled`。
+pub fn lzma_lzma2_encode_segment(
+    options: &LzmaOptionsLzma,
+    window: &[u8],
+    data: &[u8],
+    last: bool,
+    cancel: Option<&LzmaCancelToken>,
+) -> Result<Vec<u8>, LzmaRet> {
+    let mut opt = options.clone();
+    if window.is_empty() {
+        opt.preset_dict = None;
+        opt.preset_dict_size = 0;
+    } else {
+        let start = window.len().saturating_sub(opt.dict_size as usize);
+        opt.preset_dict = Some(window[start..].to_vec());
+        opt.preset_dict_size = (window.len() - start) as u32;
+    }
+
+    let filters = [
+        LzmaFilter {
+            id: LZMA_FILTER_LZMA2,
+            options: Some(LzmaOptionsType::LzmaOptionsLzma(opt)),
+        },
+        LzmaFilter {
+            id: LZMA_VLI_UNKNOWN,
+            options: None,
+        },
+    ];
+
+    let mut next = LzmaNextCoder::default();
+    let ret = lzma_raw_encoder_init(&mut next, &filters);
+    if ret != LzmaRet::Ok {
+        lzma_next_end(&mut next);
+        return Err(ret);
+    }
+
+    // 不可压缩的数据每 64 KiB 多出 3 字节的块头部
+    let mut out = vec![0u8; data.len() + data.len() / 1024 + 64];
+    let mut out_pos = 0;
+    let mut in_pos = 0;
+    let ret = loop {
+        let code = match next.code {
+            Some(code) => code,
+            None => break LzmaRet::ProgError,
+        };
+        // 有取消令牌时分段送入输入，每段之前检查一次令牌
+        let (in_end, action) = match cancel {
+            Some(cancel) if cancel.is_canceled() => break LzmaRet::Canceled,
+            Some(_) if data.len() - in_pos > LZMA_CANCEL_CHECK_SIZE => {
+                (in_pos + LZMA_CANCEL_CHECK_SIZE, LzmaAction::Run)
+            }
+            _ => (data.len(), LzmaAction::Finish),
+        };
+        let out_size = out.len();
+        let ret = code(
+            next.coder.as_mut().unwrap(),
+            data,
+            &mut in_pos,
+            in_end,
+            &mut out,
+            &mut out_pos,
+            out_size,
+            action,
+        );
+        if ret != LzmaRet::Ok {
+            break ret;
+        }
+        if out_pos == out.len() {
+            out.resize(out.len() * 2, 0);
+        }
+    };
+    lzma_next_end(&mut next);
+
+    if ret != LzmaRet::StreamEnd {
+        return Err(ret);
+    }
+
+    out.truncate(out_pos);
+    if !last {
+        // 去掉结束标记，后面还有其他段
+        assert_eq!(out.pop(), Some(0x00));
+    }
+    Ok(out)
+}
@@ -1298,12 +1298,7 @@ pub fn lzma_lzma_props_decode(
 
     // 分配 LzmaOptionsLzma 结构体
     let mut opt = LzmaOptionsLzma::default();
-    if Some(opt.clone()).is_none() {
-        return (LzmaRet::MemError, None);
-    }
-
-    let opt_clone = opt.clone();
-    if lzma_lzma_lclppb_decode(&mut opt.clone(), props[0]) {
+    if lzma_lzma_lclppb_decode(&mut opt, props[0]) {
         return (LzmaRet::OptionsError, None);
     }
 
@@ -1314,8 +1309,5 @@ pub fn lzma_lzma_props_decode(
     opt.preset_dict = None;
     opt.preset_dict_size = 0;
 
-    // 将解码后的选项赋值给 options
-    let options = Some(LzmaOptionsType::LzmaOptionsLzma(opt.clone()));
-
-    (LzmaRet::Ok, options)
+    (LzmaRet::Ok, Some(LzmaOptionsType::LzmaOptionsLzma(opt)))
 }
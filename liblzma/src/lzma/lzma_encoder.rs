@@ -635,6 +635,18 @@ fn encode_eopm(
 
 pub const LOOP_INPUT_MAX: usize = OPTS + 1;
 
+/// 编码一个符号最多写出的字节数（不含 rc_pending() 中已经挂起的字节）。
+/// 每个比特最多调用一次 rc_shift_low()，一个匹配最多约 50 个比特。
+const SYMBOL_OUT_MAX: usize = 64;
+
+/// LZMA1 每次编码到 out_buf 中的数据量
+const LZMA1_OUT_BUF_SIZE: usize = 4096;
+
+/// LZMA1 只在输出缓冲区足够容纳 extra 个字节时才继续编码
+fn lzma1_out_full(coder: &LzmaLzma1Encoder, out_pos: usize, out_size: usize, extra: usize) -> bool {
+    out_pos + rc_pending(&coder.rc) as usize + extra > out_size
+}
+
 pub fn lzma_lzma_encode(
     coder: &mut LzmaLzma1Encoder,
     mf: &mut LzmaMf,
@@ -678,6 +690,10 @@ pub fn lzma_lzma_encode(
             }
         }
 
+        if limit == u32::MAX && lzma1_out_full(coder, *out_pos, out_size, SYMBOL_OUT_MAX) {
+            return LzmaRet::Ok;
+        }
+
         if coder.fast_mode {
             lzma_lzma_optimum_fast(coder, mf, &mut back, &mut len)
         } else {
@@ -708,6 +724,11 @@ pub fn lzma_lzma_encode(
         coder.uncomp_size_ptr = Some(coder.uncomp_size);
     }
 
+    // 结束标记和刷新范围编码器
+    if limit == u32::MAX && lzma1_out_full(coder, *out_pos, out_size, 2 * SYMBOL_OUT_MAX) {
+        return LzmaRet::Ok;
+    }
+
     if coder.use_eopm {
         if encode_eopm(coder, coder.uncomp_size as u32, out, out_pos, out_size) {
             return LzmaRet::Ok;
@@ -737,7 +758,44 @@ fn lzma_encode(
         LzEncoderType::LzmaEncoderPrivate(coder) => coder,
         _ => panic!("Invalid coder type"),
     };
-    lzma_lzma_encode(coder, mf, out, out_pos, out_size, u32::MAX)
+
+    // 先输出上次留下的数据
+    if !lzma1_copy_out(coder, out, out_pos, out_size) {
+        return LzmaRet::Ok;
+    }
+
+    let mut buf = std::mem::take(&mut coder.out_buf);
+    buf.clear();
+    buf.resize(LZMA1_OUT_BUF_SIZE + rc_pending(&coder.rc) as usize, 0);
+    let mut buf_size = 0;
+    let buf_len = buf.len();
+    let ret = lzma_lzma_encode(coder, mf, &mut buf, &mut buf_size, buf_len, u32::MAX);
+    buf.truncate(buf_size);
+    coder.out_buf = buf;
+    coder.out_buf_pos = 0;
+
+    if !lzma1_copy_out(coder, out, out_pos, out_size) && ret == LzmaRet::StreamEnd {
+        // 下次调用时输出剩下的数据后再返回 LZMA_STREAM_END
+        coder.is_flushed = true;
+        return LzmaRet::Ok;
+    }
+
+    ret
+}
+
+/// 把 out_buf 中的数据复制到 out，全部复制完时返回 true
+fn lzma1_copy_out(
+    coder: &mut LzmaLzma1Encoder,
+    out: &mut [u8],
+    out_pos: &mut usize,
+    out_size: usize,
+) -> bool {
+    let n = (coder.out_buf.len() - coder.out_buf_pos).min(out_size - *out_pos);
+    out[*out_pos..*out_pos + n]
+        .copy_from_slice(&coder.out_buf[coder.out_buf_pos..coder.out_buf_pos + n]);
+    *out_pos += n;
+    coder.out_buf_pos += n;
+    coder.out_buf_pos == coder.out_buf.len()
 }
 
 fn lzma_lzma_set_out_limit(
@@ -907,6 +965,8 @@ pub fn lzma_lzma_encoder_create(
 
     coder.is_initialized = options.preset_dict.is_some() && options.preset_dict_size > 0;
     coder.is_flushed = false;
+    coder.out_buf.clear();
+    coder.out_buf_pos = 0;
     coder.uncomp_size = 0;
     coder.uncomp_size_ptr = None;
 
@@ -939,7 +999,11 @@ pub fn lzma_encoder_init(
     lz.code = Some(lzma_encode);
     lz.set_out_limit = Some(lzma_lzma_set_out_limit);
     let options = options.as_lzma_options_lzma().unwrap();
-    lzma_lzma_encoder_create(Some(lz.coder.as_mut().unwrap()), id, options, lz_options)
+    // LZMA1 没有外层的编码器，第一次初始化时在这里创建 LZMA 编码器
+    let coder = lz
+        .coder
+        .get_or_insert_with(|| LzEncoderType::LzmaEncoderPrivate(LzmaLzma1Encoder::new()));
+    lzma_lzma_encoder_create(Some(coder), id, options, lz_options)
 }
 
 pub fn lzma_lzma_encoder_memusage(options: &LzmaOptionsType) -> u64 {
@@ -995,3 +1059,28 @@ pub fn lzma_lzma_props_encode(options: &LzmaOptionsType, out: &mut [u8]) -> Lzma
 pub fn lzma_mode_is_supported(mode: LzmaMode) -> bool {
     mode == LzmaMode::Fast || mode == LzmaMode::Normal
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::LZMA_FILTER_LZMA1;
+    use crate::test_util::{lzma_filters, pseudo_random, raw_code};
+
+    /// LZMA1 直接写入调用者的输出缓冲区，不可压缩的数据使输出多次填满缓冲区，
+    /// 填满时正在编码的符号不能丢失
+    #[test]
+    fn lzma1_output_buffer_full() {
+        for preset in [0, 6] {
+            let filters = lzma_filters(LZMA_FILTER_LZMA1, preset);
+            for len in [4000, 4096, 5000, 50000] {
+                let data = pseudo_random(len, len as u64);
+                let encoded = raw_code(&filters, &data, true).unwrap();
+                assert!(
+                    raw_code(&filters, &encoded, false) == Ok(data),
+                    "preset {}, length {}",
+                    preset,
+                    len
+                );
+            }
+        }
+    }
+}
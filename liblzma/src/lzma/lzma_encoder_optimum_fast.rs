@@ -29,7 +29,7 @@ pub fn lzma_lzma_optimum_fast(
     let mut len_main;
     let mut matches_count = 0;
     if mf.read_ahead == 0 {
-        len_main = MF_FIND(&mut mf.clone(), &mut matches_count, &mut coder.matches);
+        len_main = MF_FIND(mf, &mut matches_count, &mut coder.matches);
     } else {
         assert!(mf.read_ahead == 1);
         len_main = coder.longest_match_length;
@@ -37,7 +37,7 @@ pub fn lzma_lzma_optimum_fast(
     }
 
     // const uint8_t *buf = mf_ptr(mf) - 1; 有 -1操作
-    let mut buf = &mf.buffer[mf.mf_ptr(1)..];
+    let cur = mf.mf_ptr(1);
     let buf_avail = my_min(mf_avail(mf) + 1, MATCH_LEN_MAX as u32);
 
     if buf_avail < 2 {
@@ -50,7 +50,13 @@ pub fn lzma_lzma_optimum_fast(
     let mut rep_index = 0;
 
     for i in 0..REPS {
-        let buf_back = &buf[..buf.len() - coder.reps[i] as usize - 1];
+        // buf_back = buf - reps[i] - 1
+        let back = cur.wrapping_sub(coder.reps[i] as usize + 1);
+        if back > cur {
+            continue;
+        }
+        let buf = &mf.buffer[cur..];
+        let buf_back = &mf.buffer[back..];
 
         if not_equal_16!(buf, buf_back) {
             continue;
@@ -115,11 +121,7 @@ pub fn lzma_lzma_optimum_fast(
         return;
     }
 
-    coder.longest_match_length = MF_FIND(
-        &mut mf.clone(),
-        &mut coder.matches_count,
-        &mut coder.matches,
-    );
+    coder.longest_match_length = MF_FIND(mf, &mut coder.matches_count, &mut coder.matches);
 
     if coder.longest_match_length >= 2 {
         let new_dist = coder.matches[coder.matches_count as usize - 1].dist;
@@ -137,12 +139,13 @@ pub fn lzma_lzma_optimum_fast(
         }
     }
 
-    buf = &buf[1..];
-
-    let limit = my_max(2, len_main - 1);
+    // ++buf，下一个位置若能以任一重复距离匹配 limit 个字节，则本位置输出字面量
+    let next = cur + 1;
+    let limit = my_max(2, len_main - 1) as usize;
 
     for i in 0..REPS {
-        if buf.starts_with(&buf[..buf.len() - coder.reps[i] as usize - 1]) {
+        let back = next.wrapping_sub(coder.reps[i] as usize + 1);
+        if back <= next && mf.buffer[next..next + limit] == mf.buffer[back..back + limit] {
             *back_res = u32::MAX;
             *len_res = 1;
             return;
@@ -153,3 +156,65 @@ pub fn lzma_lzma_optimum_fast(
     *len_res = len_main;
     mf_skip(mf, len_main - 2);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA2, LZMA_PRESET_FAST};
+    use crate::check::lzma_crc32;
+    use crate::test_util::{lzma_filters, pseudo_random, raw_code, words};
+
+    /// 0-3 级和 --fast 使用本文件的快速模式，编码结果必须能正确解码
+    #[test]
+    fn round_trip() {
+        let mut inputs = vec![words(20000), pseudo_random(50000, 7)];
+        // 重复距离匹配：同一段数据以几个固定的距离反复出现
+        let block = pseudo_random(300, 3);
+        inputs.push((0..200).flat_map(|i| block[i % 7..].to_vec()).collect());
+
+        for id in [LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA2] {
+            for preset in 0..=3 {
+                for flags in [0, LZMA_PRESET_FAST] {
+                    let filters = lzma_filters(id, preset | flags);
+                    for data in &inputs {
+                        let encoded = raw_code(&filters, data, true).unwrap();
+                        assert!(
+                            raw_code(&filters, &encoded, false).as_ref() == Ok(data),
+                            "filter {:#x}, preset {}, flags {:#x}",
+                            id,
+                            preset,
+                            flags
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// 0-3 级的输出与 xz 5.8 相同，期望值是 xz -T1 --format=raw --lzma1=preset=N
+    /// 和 --lzma2=preset=N 压缩 words(20000) 的长度和 CRC32
+    #[test]
+    fn same_as_xz() {
+        let expected = [
+            (LZMA_FILTER_LZMA1, 0, 18255, 0x230361fc),
+            (LZMA_FILTER_LZMA2, 0, 18257, 0xea62d6e4),
+            (LZMA_FILTER_LZMA1, 1, 17635, 0xaed34803),
+            (LZMA_FILTER_LZMA2, 1, 17636, 0xd390681e),
+            (LZMA_FILTER_LZMA1, 2, 16026, 0xc6c0c32f),
+            (LZMA_FILTER_LZMA2, 2, 16027, 0xd471b12e),
+            (LZMA_FILTER_LZMA1, 3, 15310, 0x876c2755),
+            (LZMA_FILTER_LZMA2, 3, 15311, 0x60fd2755),
+        ];
+
+        let data = words(20000);
+        for (id, preset, len, crc) in expected {
+            let encoded = raw_code(&lzma_filters(id, preset), &data, true).unwrap();
+            assert_eq!(
+                (encoded.len(), lzma_crc32(&encoded, encoded.len(), 0)),
+                (len, crc),
+                "filter {:#x}, preset {}",
+                id,
+                preset
+            );
+        }
+    }
+}
@@ -6,18 +6,23 @@
 
 use crate::api::{
     LzmaMatchFinder, LzmaMode, LzmaOptionsLzma, LZMA_LC_DEFAULT, LZMA_LP_DEFAULT, LZMA_PB_DEFAULT,
-    LZMA_PRESET_EXTREME, LZMA_PRESET_LEVEL_MASK,
+    LZMA_PRESET_EXTREME, LZMA_PRESET_FAST, LZMA_PRESET_LEVEL_MASK,
 };
 
 pub fn lzma_lzma_preset(options: &mut LzmaOptionsLzma, preset: u32) -> bool {
     let level = preset & LZMA_PRESET_LEVEL_MASK;
     let flags = preset & !LZMA_PRESET_LEVEL_MASK;
-    let supported_flags = LZMA_PRESET_EXTREME;
+    let supported_flags = LZMA_PRESET_EXTREME | LZMA_PRESET_FAST;
 
     if level > 9 || (flags & !supported_flags) != 0 {
         return true;
     }
 
+    // 快速预设只用于 0-3 级，且不能与 EXTREME 同时使用
+    if flags & LZMA_PRESET_FAST != 0 && (level > 3 || flags & LZMA_PRESET_EXTREME != 0) {
+        return true;
+    }
+
     options.preset_dict = None;
     options.preset_dict_size = 0;
 
@@ -61,5 +66,12 @@ pub fn lzma_lzma_preset(options: &mut LzmaOptionsLzma, preset: u32) -> bool {
         }
     }
 
+    // HT4 每个位置只比较一个哈希桶，depth 不起作用
+    if flags & LZMA_PRESET_FAST != 0 {
+        options.mf = LzmaMatchFinder::LzmaMfHt4;
+        options.nice_len = if level == 0 { 16 } else { 64 };
+        options.depth = 0;
+    }
+
     false
 }
@@ -124,6 +124,13 @@ pub struct LzmaLzma1Encoder {
     /// 如果将写入有效载荷结束标记，则为真。
     pub use_eopm: bool,
 
+    /// LZMA1 的输出先写入这里再复制到调用者的输出缓冲区。范围编码器直接输出，
+    /// 编码到一半的符号不能在输出缓冲区满时暂停，LZMA2 则有自己的块缓冲区。
+    pub out_buf: Vec<u8>,
+
+    /// out_buf 中已经复制到调用者输出缓冲区的字节数
+    pub out_buf_pos: usize,
+
     pub pos_mask: u32,
     pub literal_context_bits: u32,
     pub literal_pos_mask: u32,
@@ -173,6 +180,8 @@ impl LzmaLzma1Encoder {
             is_initialized: false,
             is_flushed: false,
             use_eopm: false,
+            out_buf: Vec::new(),
+            out_buf_pos: 0,
             pos_mask: 0,
             literal_context_bits: 0,
             literal_pos_mask: 0,
@@ -192,6 +192,14 @@ impl LzmaRangeEncoder {
 
     /// Flush the range encoder. Returns true if output buffer became full.
     pub fn rc_flush(&mut self, out: &mut [u8], out_pos: &mut usize, out_size: usize) -> bool {
+        // Normalize first like any other symbol, otherwise the last byte is lost
+        // when the range happens to be below RC_TOP_VALUE.
+        if self.range < RC_TOP_VALUE {
+            if self.rc_shift_low(out, out_pos, out_size) {
+                return true;
+            }
+        }
+
         self.range = u32::MAX;
         for _ in 0..5 {
             if self.rc_shift_low(out, out_pos, out_size) {
@@ -279,3 +287,27 @@ pub fn rc_bit(
 ) -> bool {
     rc.rc_bit(prob, bit, out, out_pos, out_size)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA2};
+    use crate::test_util::{lzma_filters, pseudo_random, raw_code};
+
+    /// 刷新时 range 经常小于 RC_TOP_VALUE，逐个长度检查最后一个字节没有丢失
+    #[test]
+    fn flush_keeps_last_byte() {
+        for id in [LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA2] {
+            let filters = lzma_filters(id, 1);
+            for len in 0..300 {
+                let data = pseudo_random(len, len as u64);
+                let encoded = raw_code(&filters, &data, true).unwrap();
+                assert_eq!(
+                    raw_code(&filters, &encoded, false),
+                    Ok(data),
+                    "length {}",
+                    len
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 根据可执行文件头部选择 BCJ 过滤器
+//!
+//! 识别 ELF、PE 和 Mach-O 头部中的体系结构字段。BCJ 过滤器把相对地址转换为
+//! “起始偏移 + 文件位置”，起始偏移取代码段的虚拟地址与文件偏移之差时，
+//! 转换结果就是真实的虚拟地址，能与文件中其他绝对地址匹配。
+
+use crate::api::{
+    LzmaVli, LZMA_FILTER_ARM, LZMA_FILTER_ARM64, LZMA_FILTER_ARMTHUMB, LZMA_FILTER_IA64,
+    LZMA_FILTER_POWERPC, LZMA_FILTER_SPARC, LZMA_FILTER_X86,
+};
+
+/// 识别可执行文件头部时建议读取的字节数
+pub const LZMA_BCJ_DETECT_SIZE: usize = 64 << 10;
+
+const ELF_PT_LOAD: u32 = 1;
+const ELF_PF_X: u32 = 1;
+const PE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+const MACHO_LC_SEGMENT: u32 = 0x01;
+const MACHO_LC_SEGMENT_64: u32 = 0x19;
+
+fn read_u16(buf: &[u8], pos: usize, big_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = buf.get(pos..pos.checked_add(2)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(buf: &[u8], pos: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = buf.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_u64(buf: &[u8], pos: usize, big_endian: bool) -> Option<u64> {
+    let bytes: [u8; 8] = buf.get(pos..pos.checked_add(8)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+/// Mach-O 头部的字节序和位数
+fn macho_header(buf: &[u8]) -> Option<(bool, bool)> {
+    match read_u32(buf, 0, false)? {
+        0xFEED_FACE => Some((false, false)),
+        0xFEED_FACF => Some((false, true)),
+        0xCEFA_EDFE => Some((true, false)),
+        0xCFFA_EDFE => Some((true, true)),
+        _ => None,
+    }
+}
+
+/// PE 头部（"PE\0\0"）的位置
+fn pe_header(buf: &[u8]) -> Option<usize> {
+    if !buf.starts_with(b"MZ") {
+        return None;
+    }
+    let pe = read_u32(buf, 0x3C, false)? as usize;
+    if buf.get(pe..pe.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+    Some(pe)
+}
+
+/// 根据 ELF、PE 或 Mach-O 头部的体系结构字段选择 BCJ 过滤器
+///
+/// buf 应当是文件的开头；无法识别时返回 None。
+pub fn lzma_detect_bcj(buf: &[u8]) -> Option<LzmaVli> {
+    // ELF：e_machine 位于偏移 18，字节序由 EI_DATA 决定
+    if buf.starts_with(b"\x7fELF") {
+        let big_endian = *buf.get(5)? == 2;
+        return match read_u16(buf, 18, big_endian)? {
+            3 | 62 => Some(LZMA_FILTER_X86),
+            40 => Some(LZMA_FILTER_ARM),
+            183 => Some(LZMA_FILTER_ARM64),
+            20 | 21 if big_endian => Some(LZMA_FILTER_POWERPC),
+            2 | 18 | 43 => Some(LZMA_FILTER_SPARC),
+            50 => Some(LZMA_FILTER_IA64),
+            _ => None,
+        };
+    }
+
+    // PE：Machine 字段紧随 "PE\0\0"
+    if let Some(pe) = pe_header(buf) {
+        return match read_u16(buf, pe + 4, false)? {
+            0x014C | 0x8664 => Some(LZMA_FILTER_X86),
+            0x01C0 => Some(LZMA_FILTER_ARM),
+            0x01C2 | 0x01C4 => Some(LZMA_FILTER_ARMTHUMB),
+            0xAA64 => Some(LZMA_FILTER_ARM64),
+            0x0200 => Some(LZMA_FILTER_IA64),
+            _ => None,
+        };
+    }
+
+    // Mach-O：cputype 紧随魔数
+    let (big_endian, _) = macho_header(buf)?;
+    match read_u32(buf, 4, big_endian)? {
+        0x0000_0007 | 0x0100_0007 => Some(LZMA_FILTER_X86),
+        0x0000_000C => Some(LZMA_FILTER_ARM),
+        0x0100_000C => Some(LZMA_FILTER_ARM64),
+        0x0000_0012 if big_endian => Some(LZMA_FILTER_POWERPC),
+        _ => None,
+    }
+}
+
+/// ELF 中第一个可执行 PT_LOAD 段的虚拟地址与文件偏移之差
+fn elf_code_base(buf: &[u8]) -> Option<u64> {
+    let is_64 = *buf.get(4)? == 2;
+    let be = *buf.get(5)? == 2;
+
+    let (phoff, phentsize, phnum) = if is_64 {
+        (
+            read_u64(buf, 0x20, be)?,
+            read_u16(buf, 0x36, be)?,
+            read_u16(buf, 0x38, be)?,
+        )
+    } else {
+        (
+            read_u32(buf, 0x1C, be)? as u64,
+            read_u16(buf, 0x2A, be)?,
+            read_u16(buf, 0x2C, be)?,
+        )
+    };
+
+    for i in 0..phnum as u64 {
+        let ph = usize::try_from(phoff.checked_add(i * phentsize as u64)?).ok()?;
+        if ph >= buf.len() {
+            return None;
+        }
+        let (flags, offset, vaddr) = if is_64 {
+            (
+                read_u32(buf, ph + 4, be)?,
+                read_u64(buf, ph + 8, be)?,
+                read_u64(buf, ph + 16, be)?,
+            )
+        } else {
+            (
+                read_u32(buf, ph + 24, be)?,
+                read_u32(buf, ph + 4, be)? as u64,
+                read_u32(buf, ph + 8, be)? as u64,
+            )
+        };
+        if read_u32(buf, ph, be)? == ELF_PT_LOAD && flags & ELF_PF_X != 0 {
+            return Some(vaddr.wrapping_sub(offset));
+        }
+    }
+    None
+}
+
+/// PE 中第一个可执行节的虚拟地址（含 ImageBase）与文件偏移之差
+fn pe_code_base(buf: &[u8], pe: usize) -> Option<u64> {
+    let nsections = read_u16(buf, pe + 6, false)?;
+    let opt_size = read_u16(buf, pe + 20, false)? as usize;
+    let opt = pe + 24;
+    let image_base = match read_u16(buf, opt, false)? {
+        0x010B => read_u32(buf, opt + 28, false)? as u64,
+        0x020B => read_u64(buf, opt + 24, false)?,
+        _ => return None,
+    };
+
+    for i in 0..nsections as usize {
+        let sec = opt + opt_size + i * 40;
+        let vaddr = read_u32(buf, sec + 12, false)? as u64;
+        let offset = read_u32(buf, sec + 20, false)? as u64;
+        if read_u32(buf, sec + 36, false)? & PE_SCN_MEM_EXECUTE != 0 && offset != 0 {
+            return Some(image_base.wrapping_add(vaddr).wrapping_sub(offset));
+        }
+    }
+    None
+}
+
+/// Mach-O 中 __TEXT 段的虚拟地址与文件偏移之差
+fn macho_code_base(buf: &[u8]) -> Option<u64> {
+    let (be, is_64) = macho_header(buf)?;
+    let ncmds = read_u32(buf, 16, be)?;
+    let mut pos = if is_64 { 32 } else { 28 };
+
+    for _ in 0..ncmds {
+        if pos >= buf.len() {
+            return None;
+        }
+        let cmd = read_u32(buf, pos, be)?;
+        let cmdsize = read_u32(buf, pos + 4, be)? as usize;
+        let segname = buf.get(pos + 8..pos + 24)?;
+        if segname.starts_with(b"__TEXT\0") {
+            match cmd {
+                MACHO_LC_SEGMENT_64 => {
+                    let vmaddr = read_u64(buf, pos + 24, be)?;
+                    return Some(vmaddr.wrapping_sub(read_u64(buf, pos + 40, be)?));
+                }
+                MACHO_LC_SEGMENT => {
+                    let vmaddr = read_u32(buf, pos + 24, be)? as u64;
+                    return Some(vmaddr.wrapping_sub(read_u32(buf, pos + 32, be)? as u64));
+                }
+                _ => {}
+            }
+        }
+        if cmdsize == 0 {
+            return None;
+        }
+        pos = pos.checked_add(cmdsize)?;
+    }
+    None
+}
+
+/// 为 lzma_detect_bcj() 选出的过滤器计算 start_offset
+///
+/// 取代码段的虚拟地址与文件偏移之差的低 32 位，并向下对齐到过滤器要求的边界。
+/// 找不到代码段时返回 0。
+pub fn lzma_bcj_start_offset(buf: &[u8], id: LzmaVli) -> u32 {
+    let alignment: u32 = match id {
+        LZMA_FILTER_X86 => 1,
+        LZMA_FILTER_ARMTHUMB => 2,
+        LZMA_FILTER_IA64 => 16,
+        _ => 4,
+    };
+
+    let base = if buf.starts_with(b"\x7fELF") {
+        elf_code_base(buf)
+    } else if let Some(pe) = pe_header(buf) {
+        pe_code_base(buf, pe)
+    } else {
+        macho_code_base(buf)
+    };
+
+    base.map_or(0, |base| base as u32 & !(alignment - 1))
+}
+#[cfg(test)]
+mod tests {
+    use super::{lzma_bcj_start_offset, lzma_detect_bcj};
+    use crate::api::{
+        LZMA_FILTER_ARM, LZMA_FILTER_ARM64, LZMA_FILTER_ARMTHUMB, LZMA_FILTER_POWERPC,
+        LZMA_FILTER_X86,
+    };
+    use crate::test_util::pseudo_random;
+
+    fn put(buf: &mut [u8], pos: usize, bytes: &[u8]) {
+        buf[pos..pos + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// 64 位小端 ELF，一个可执行的 PT_LOAD 段
+    fn elf64(machine: u16, offset: u64, vaddr: u64) -> Vec<u8> {
+        let mut buf = vec![0u8; 256];
+        put(&mut buf, 0, b"\x7fELF\x02\x01\x01");
+        put(&mut buf, 18, &machine.to_le_bytes());
+        put(&mut buf, 0x20, &64u64.to_le_bytes());
+        put(&mut buf, 0x36, &56u16.to_le_bytes());
+        put(&mut buf, 0x38, &2u16.to_le_bytes());
+        // 第一个段不可执行
+        put(&mut buf, 64, &1u32.to_le_bytes());
+        put(&mut buf, 64 + 4, &4u32.to_le_bytes());
+        put(&mut buf, 120, &1u32.to_le_bytes());
+        put(&mut buf, 120 + 4, &5u32.to_le_bytes());
+        put(&mut buf, 120 + 8, &offset.to_le_bytes());
+        put(&mut buf, 120 + 16, &vaddr.to_le_bytes());
+        buf
+    }
+
+    /// 32 位大端 ELF，一个可执行的 PT_LOAD 段
+    fn elf32_be(machine: u16, offset: u32, vaddr: u32) -> Vec<u8> {
+        let mut buf = vec![0u8; 128];
+        put(&mut buf, 0, b"\x7fELF\x01\x02\x01");
+        put(&mut buf, 18, &machine.to_be_bytes());
+        put(&mut buf, 0x1C, &52u32.to_be_bytes());
+        put(&mut buf, 0x2A, &32u16.to_be_bytes());
+        put(&mut buf, 0x2C, &1u16.to_be_bytes());
+        put(&mut buf, 52, &1u32.to_be_bytes());
+        put(&mut buf, 52 + 4, &offset.to_be_bytes());
+        put(&mut buf, 52 + 8, &vaddr.to_be_bytes());
+        put(&mut buf, 52 + 24, &5u32.to_be_bytes());
+        buf
+    }
+
+    /// PE32+，一个可执行节
+    fn pe64(machine: u16, image_base: u64, vaddr: u32, offset: u32) -> Vec<u8> {
+        let mut buf = vec![0u8; 512];
+        put(&mut buf, 0, b"MZ");
+        put(&mut buf, 0x3C, &0x80u32.to_le_bytes());
+        put(&mut buf, 0x80, b"PE\0\0");
+        put(&mut buf, 0x84, &machine.to_le_bytes());
+        put(&mut buf, 0x86, &1u16.to_le_bytes());
+        put(&mut buf, 0x94, &0xF0u16.to_le_bytes());
+        put(&mut buf, 0x98, &0x020Bu16.to_le_bytes());
+        put(&mut buf, 0x98 + 24, &image_base.to_le_bytes());
+        let sec = 0x98 + 0xF0;
+        put(&mut buf, sec, b".text\0\0\0");
+        put(&mut buf, sec + 12, &vaddr.to_le_bytes());
+        put(&mut buf, sec + 20, &offset.to_le_bytes());
+        put(&mut buf, sec + 36, &0x6000_0020u32.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn elf() {
+        let x86 = elf64(62, 0x1000, 0x40_1000);
+        assert_eq!(lzma_detect_bcj(&x86), Some(LZMA_FILTER_X86));
+        assert_eq!(lzma_bcj_start_offset(&x86, LZMA_FILTER_X86), 0x40_0000);
+
+        let arm64 = elf64(183, 0x1000, 0x40_1002);
+        assert_eq!(lzma_detect_bcj(&arm64), Some(LZMA_FILTER_ARM64));
+        assert_eq!(lzma_bcj_start_offset(&arm64, LZMA_FILTER_ARM64), 0x40_0000);
+
+        assert_eq!(lzma_detect_bcj(&elf64(40, 0, 0)), Some(LZMA_FILTER_ARM));
+
+        let ppc = elf32_be(20, 0x100, 0x1000_0100);
+        assert_eq!(lzma_detect_bcj(&ppc), Some(LZMA_FILTER_POWERPC));
+        assert_eq!(
+            lzma_bcj_start_offset(&ppc, LZMA_FILTER_POWERPC),
+            0x1000_0000
+        );
+
+        // 小端 PowerPC 没有对应的过滤器，RISC-V 也没有
+        assert_eq!(lzma_detect_bcj(&elf64(21, 0, 0)), None);
+        assert_eq!(lzma_detect_bcj(&elf64(243, 0, 0)), None);
+    }
+
+    #[test]
+    fn pe() {
+        let x86 = pe64(0x8664, 0x1_4000_0000, 0x1000, 0x400);
+        assert_eq!(lzma_detect_bcj(&x86), Some(LZMA_FILTER_X86));
+        assert_eq!(lzma_bcj_start_offset(&x86, LZMA_FILTER_X86), 0x4000_0C00);
+
+        let arm64 = pe64(0xAA64, 0x1_4000_0000, 0x1000, 0x402);
+        assert_eq!(lzma_detect_bcj(&arm64), Some(LZMA_FILTER_ARM64));
+        assert_eq!(
+            lzma_bcj_start_offset(&arm64, LZMA_FILTER_ARM64),
+            0x4000_0BFC
+        );
+
+        let thumb = pe64(0x01C4, 0x40_0000, 0x1000, 0x400);
+        assert_eq!(lzma_detect_bcj(&thumb), Some(LZMA_FILTER_ARMTHUMB));
+
+        // 只有 MZ 头部的 DOS 程序
+        let mut dos = x86.clone();
+        put(&mut dos, 0x80, b"NE\0\0");
+        assert_eq!(lzma_detect_bcj(&dos), None);
+    }
+
+    #[test]
+    fn mach_o() {
+        let mut buf = vec![0u8; 64];
+        put(&mut buf, 0, &0xFEED_FACFu32.to_le_bytes());
+        put(&mut buf, 4, &0x0100_000Cu32.to_le_bytes());
+        assert_eq!(lzma_detect_bcj(&buf), Some(LZMA_FILTER_ARM64));
+        put(&mut buf, 4, &0x0100_0007u32.to_le_bytes());
+        assert_eq!(lzma_detect_bcj(&buf), Some(LZMA_FILTER_X86));
+    }
+
+    /// 其他数据和被截断的头部不会被识别，也不会越界
+    #[test]
+    fn not_executable() {
+        assert_eq!(lzma_detect_bcj(&pseudo_random(4096, 1)), None);
+        assert_eq!(lzma_detect_bcj(b""), None);
+        assert_eq!(lzma_bcj_start_offset(b"", LZMA_FILTER_X86), 0);
+
+        for buf in [elf64(62, 0x1000, 0x40_1000), pe64(0x8664, 0, 0x1000, 0x400)] {
+            for len in 0..buf.len() {
+                let _ = lzma_detect_bcj(&buf[..len]);
+                let _ = lzma_bcj_start_offset(&buf[..len], LZMA_FILTER_X86);
+            }
+        }
+
+        // 程序头表的位置超出文件
+        let mut bad = elf64(62, 0, 0);
+        put(&mut bad, 0x20, &u64::MAX.to_le_bytes());
+        assert_eq!(lzma_bcj_start_offset(&bad, LZMA_FILTER_X86), 0);
+    }
+}
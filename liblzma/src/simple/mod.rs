@@ -6,6 +6,7 @@
 
 mod arm;
 mod arm64;
+mod bcj_detect;
 mod armthumb;
 mod ia64;
 mod powerpc;
@@ -18,6 +19,7 @@ mod x86;
 
 pub use arm::*;
 pub use arm64::*;
+pub use bcj_detect::*;
 pub use armthumb::*;
 pub use ia64::*;
 pub use powerpc::*;
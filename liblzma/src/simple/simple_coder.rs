@@ -79,13 +79,13 @@ fn copy_or_code(
 /// 调用过滤器
 fn call_filter(coder: &mut LzmaSimpleCoder, buffer: &mut [u8], size: usize) -> usize {
     let filtered = (coder.filter.unwrap())(
-        &mut coder.simple.clone(),
+        &mut coder.simple,
         coder.now_pos,
         coder.is_encoder,
         buffer,
         size,
     );
-    coder.now_pos += filtered as u32;
+    coder.now_pos = coder.now_pos.wrapping_add(filtered as u32);
     filtered
 }
 
@@ -198,40 +198,45 @@ fn simple_code(
 
     debug_assert!(coder.pos == 0);
 
-    // 处理非空缓冲区
-    let mut output = coder.buffer.clone();
+    // 缓冲区不为空时尝试填满它，过滤后把已过滤的数据复制到out[]
     if coder.size > 0 {
-        let mut out_pos_local = coder.size;
+        // 暂时取出缓冲区，以便同时借用编码器
+        let mut buffer = std::mem::take(&mut coder.buffer);
+        let mut size = coder.size;
+        let allocated = coder.allocated;
         let ret = copy_or_code(
             coder,
             input,
             in_pos,
             in_size,
-            output.as_mut(),
-            &mut out_pos_local,
-            coder.allocated,
-            action.clone(), // 如果 action 已被 move，应重新 clone 或使用引用
+            &mut buffer,
+            &mut size,
+            allocated,
+            action.clone(),
         );
-        debug_assert!(ret != LzmaRet::StreamEnd);
+        coder.size = size;
         if ret != LzmaRet::Ok {
+            coder.buffer = buffer;
+            debug_assert!(ret != LzmaRet::StreamEnd);
             return ret;
         }
 
-        let mut coder_buffer = coder.buffer.clone();
-        coder.filtered = call_filter(coder, &mut coder_buffer, coder.size);
+        coder.filtered = call_filter(coder, &mut buffer, size);
+        coder.buffer = buffer;
 
         if coder.end_was_reached {
             coder.filtered = coder.size;
         }
 
         // 尽可能多地刷新
-        let copy_size = min(coder.filtered - coder.pos, out_size - *out_pos);
-        if copy_size > 0 {
-            output[*out_pos..*out_pos + copy_size]
-                .copy_from_slice(&coder.buffer[coder.pos..coder.pos + copy_size]);
-        }
-        coder.pos += copy_size;
-        *out_pos += copy_size;
+        lzma_bufcpy(
+            &coder.buffer,
+            &mut coder.pos,
+            coder.filtered,
+            output,
+            out_pos,
+            out_size,
+        );
     }
 
     // 检查是否完成所有工作
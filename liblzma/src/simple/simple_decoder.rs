@@ -33,9 +33,13 @@ pub fn lzma_simple_props_decode(
     props: &[u8],
     props_size: usize,
 ) -> (LzmaRet, Option<LzmaOptionsType>) {
-    // 如果没有属性数据，直接返回成功
+    // 没有属性数据表示起始偏移量为 0，与编码器接受的选项保持一致，
+    // 仍然返回选项结构体
     if props_size == 0 {
-        return (LzmaRet::Ok, None);
+        return (
+            LzmaRet::Ok,
+            Some(LzmaOptionsType::Bcj(LzmaOptionsBcj::default())),
+        );
     }
 
     // 检查属性大小是否正确
@@ -49,13 +53,5 @@ pub fn lzma_simple_props_decode(
     // 从小端字节序读取起始偏移量
     opt.start_offset = LittleEndian::read_u32(props);
 
-    // 如果起始偏移量为0，不保存选项结构体
-    let mut options: Option<LzmaOptionsType> = None;
-    if opt.start_offset == 0 {
-        options = None;
-    } else {
-        options = Some(LzmaOptionsType::Bcj(opt.clone()));
-    }
-
-    (LzmaRet::Ok, options)
+    (LzmaRet::Ok, Some(LzmaOptionsType::Bcj(opt)))
 }
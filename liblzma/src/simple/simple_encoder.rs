@@ -30,14 +30,11 @@ use crate::api::{LzmaOptionsBcj, LzmaOptionsType, LzmaRet};
 /// * `LzmaRet` - 操作结果
 pub fn lzma_simple_props_size(size: &mut u32, options: &LzmaOptionsType) -> LzmaRet {
     // 如果选项为空或起始偏移量为0，则不需要存储任何选项
-    let opt = match options {
-        LzmaOptionsType::Bcj(c) => c,
+    *size = match options {
+        LzmaOptionsType::Bcj(opt) if opt.start_offset != 0 => 4,
+        LzmaOptionsType::Bcj(_) | LzmaOptionsType::None => 0,
         _ => return LzmaRet::ProgError,
     };
-    *size = match Some(opt) {
-        Some(opt) if opt.start_offset != 0 => 4,
-        _ => 0,
-    };
 
     LzmaRet::Ok
 }
@@ -55,14 +52,15 @@ pub fn lzma_simple_props_encode(options: &LzmaOptionsType, out: &mut [u8]) -> Lz
     // 否则我们不需要存储任何选项
     let opt = match options {
         LzmaOptionsType::Bcj(c) => c,
+        LzmaOptionsType::None => return LzmaRet::Ok,
         _ => return LzmaRet::ProgError,
     };
 
-    if Some(opt).is_none() || opt.start_offset == 0 {
+    if opt.start_offset == 0 {
         return LzmaRet::Ok;
     }
 
-    write32le(&mut out[1..], opt.start_offset);
+    write32le(out, opt.start_offset);
 
     return LzmaRet::Ok;
 }
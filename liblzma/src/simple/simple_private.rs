@@ -69,7 +69,7 @@ impl LzmaSimpleCoder {
             pos: 0,
             filtered: 0,
             size: 0,
-            buffer: Vec::with_capacity(size),
+            buffer: vec![0; size],
         }
     }
 }
@@ -57,8 +57,8 @@ fn x86_code(
         return 0;
     }
 
-    if now_pos - prev_pos > 5 {
-        prev_pos = now_pos - 5;
+    if now_pos.wrapping_sub(prev_pos) > 5 {
+        prev_pos = now_pos.wrapping_sub(5);
     }
 
     let limit = size - 5;
@@ -71,8 +71,8 @@ fn x86_code(
             continue;
         }
 
-        let offset = now_pos + buffer_pos as u32 - prev_pos;
-        prev_pos = now_pos + buffer_pos as u32;
+        let offset = now_pos.wrapping_add(buffer_pos as u32).wrapping_sub(prev_pos);
+        prev_pos = now_pos.wrapping_add(buffer_pos as u32);
 
         if offset > 5 {
             prev_mask = 0;
@@ -97,9 +97,9 @@ fn x86_code(
             let mut dest;
             loop {
                 dest = if is_encoder {
-                    src + (now_pos + buffer_pos as u32 + 5)
+                    src.wrapping_add(now_pos.wrapping_add(buffer_pos as u32 + 5))
                 } else {
-                    src - (now_pos + buffer_pos as u32 + 5)
+                    src.wrapping_sub(now_pos.wrapping_add(buffer_pos as u32 + 5))
                 };
 
                 if prev_mask == 0 {
@@ -113,10 +113,10 @@ fn x86_code(
                     break;
                 }
 
-                src = dest ^ ((1u32 << (32 - i * 8)) - 1);
+                src = dest ^ 1u32.wrapping_shl(32 - i * 8).wrapping_sub(1);
             }
 
-            buffer[buffer_pos + 4] = !(dest >> 24 & 1) as u8;
+            buffer[buffer_pos + 4] = !((dest >> 24) & 1).wrapping_sub(1) as u8;
             buffer[buffer_pos + 3] = (dest >> 16) as u8;
             buffer[buffer_pos + 2] = (dest >> 8) as u8;
             buffer[buffer_pos + 1] = dest as u8;
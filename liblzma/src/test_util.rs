@@ -0,0 +1,131 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 单元测试共用的辅助函数
+
+use crate::api::{
+    LzmaAction, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream, LzmaVli,
+    LZMA_VLI_UNKNOWN,
+};
+use crate::common::{lzma_code, lzma_raw_decoder, lzma_raw_encoder};
+use crate::lzma::lzma_lzma_preset;
+
+/// 用 strm 处理整个输入，每次只提供一小段输入和输出缓冲区
+///
+/// strm 必须已经初始化为编码器或解码器。
+pub fn code_all<'a>(strm: &mut LzmaStream<'a>, input: &'a [u8]) -> Result<Vec<u8>, LzmaRet> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    loop {
+        let chunk = &input[pos..(pos + 8192).min(input.len())];
+        strm.next_in = chunk;
+        strm.avail_in.set(chunk.len());
+        let action = if pos + chunk.len() == input.len() {
+            LzmaAction::Finish
+        } else {
+            LzmaAction::Run
+        };
+
+        loop {
+            strm.next_out.borrow_mut().clear();
+            strm.next_out.borrow_mut().resize(4096, 0);
+            strm.next_out_pos = 0;
+            strm.avail_out.set(4096);
+            let ret = lzma_code(strm, action);
+            out.extend_from_slice(&strm.next_out.borrow()[..strm.next_out_pos as usize]);
+            match ret {
+                LzmaRet::StreamEnd => return Ok(out),
+                LzmaRet::Ok => {}
+                ret => return Err(ret),
+            }
+            if strm.avail_in.get() == 0 && strm.avail_out.get() != 0 {
+                break;
+            }
+        }
+        pos += chunk.len();
+    }
+}
+
+/// 用 raw 编码器或解码器处理整个输入
+pub fn raw_code(filters: &[LzmaFilter], input: &[u8], encode: bool) -> Result<Vec<u8>, LzmaRet> {
+    let mut strm = LzmaStream::default();
+    let ret = if encode {
+        lzma_raw_encoder(&mut strm, filters)
+    } else {
+        lzma_raw_decoder(&mut strm, filters)
+    };
+    if ret != LzmaRet::Ok {
+        return Err(ret);
+    }
+    code_all(&mut strm, input)
+}
+
+/// 由 seed 确定的伪随机数据
+pub fn pseudo_random(len: usize, mut seed: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 56) as u8
+        })
+        .collect()
+}
+
+/// 由 len 个随机选取的单词组成的文本，有大量匹配和重复距离匹配
+pub fn words(len: usize) -> Vec<u8> {
+    const WORDS: [&[u8]; 9] = [
+        b"the ", b"quick ", b"brown ", b"fox ", b"jumps ", b"over ", b"lazy ", b"dog ", b"\n",
+    ];
+    pseudo_random(len, 1)
+        .iter()
+        .flat_map(|&b| WORDS[b as usize % WORDS.len()].iter().copied())
+        .collect()
+}
+
+/// 把测试向量中的十六进制字符串转换为字节
+pub fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// 只有一个 LZMA1 或 LZMA2 过滤器的过滤器链，选项取自预设等级 preset
+pub fn lzma_filters(id: LzmaVli, preset: u32) -> Vec<LzmaFilter> {
+    let mut opt = LzmaOptionsLzma::default();
+    assert!(!lzma_lzma_preset(&mut opt, preset));
+    vec![
+        LzmaFilter {
+            id,
+            options: Some(LzmaOptionsType::LzmaOptionsLzma(opt)),
+        },
+        LzmaFilter {
+            id: LZMA_VLI_UNKNOWN,
+            options: None,
+        },
+    ]
+}
+
+/// 16 位小端 PCM，各声道的采样交错排列
+///
+/// 每个声道是两个周期不同的正弦波之和，再加上少量噪声。周期都长于 128 个采样，
+/// 数据不会在 Delta 过滤器的距离范围内重复。
+pub fn pcm16(channels: usize, samples: usize) -> Vec<u8> {
+    let noise = pseudo_random(samples * channels, 11);
+    (0..samples)
+        .flat_map(|i| {
+            let noise = &noise;
+            (0..channels).flat_map(move |c| {
+                let t = i as f64 * std::f64::consts::TAU;
+                let v = 5000.0 * (t / (211.7 + 61.7 * c as f64)).sin()
+                    + 3000.0 * (t / (139.1 + 17.3 * c as f64)).sin()
+                    + (noise[i * channels + c] as f64 - 128.0) / 4.0;
+                (v as i16).to_le_bytes()
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,67 @@
+#!/bin/sh
+#
+# SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+#
+# SPDX-License-Identifier: GPL-2.0-or-later
+#
+# 在 tmpfs 上比较 read/write 和 io_uring 两种 I/O 方式的压缩与解压缩耗时
+#
+# 用法：sh utxz/benches/io_uring.sh [输入文件] [次数]
+#
+# 不给输入文件时生成 256 MiB 压缩率较高的测试数据。使用 -0 和 --no-sync，
+# 使 I/O 在总耗时中占的比例尽可能大。
+
+set -eu
+
+ROOT=$(cd "$(dirname "$0")/../.." && pwd)
+TMP=${TMPDIR_BENCH:-/dev/shm}/utxz-bench.$$
+RUNS=${2:-5}
+
+trap 'rm -rf "$TMP"' EXIT INT TERM
+mkdir -p "$TMP"
+
+echo "构建 ..."
+cargo build --release -q -p utxz --manifest-path "$ROOT/Cargo.toml" \
+	--target-dir "$ROOT/target/bench-rw"
+cargo build --release -q -p utxz --manifest-path "$ROOT/Cargo.toml" \
+	--target-dir "$ROOT/target/bench-uring" --features io_uring
+
+if [ -n "${1:-}" ]; then
+	cp "$1" "$TMP/input"
+else
+	# 重复的文本加少量随机数据
+	yes "utxz io_uring benchmark $(date)" | head -c 240M > "$TMP/input"
+	head -c 16M /dev/urandom >> "$TMP/input"
+fi
+
+# time_ms 命令...：输出命令运行的毫秒数
+time_ms() {
+	start=$(date +%s%N)
+	"$@"
+	end=$(date +%s%N)
+	echo $(((end - start) / 1000000))
+}
+
+run() {
+	name=$1
+	bin=$2
+	total_c=0
+	total_d=0
+	i=0
+	while [ "$i" -lt "$RUNS" ]; do
+		cp "$TMP/input" "$TMP/data"
+		c=$(time_ms "$bin" -0 -f --no-sync "$TMP/data")
+		d=$(time_ms "$bin" -d -f --no-sync "$TMP/data.xz")
+		cmp -s "$TMP/input" "$TMP/data" || { echo "$name: 解压缩结果不一致" >&2; exit 1; }
+		total_c=$((total_c + c))
+		total_d=$((total_d + d))
+		i=$((i + 1))
+	done
+	printf '%-10s 压缩 %6d ms  解压缩 %6d ms\n' "$name" \
+		$((total_c / RUNS)) $((total_d / RUNS))
+}
+
+size=$(wc -c < "$TMP/input")
+echo "输入 $size 字节，每项运行 $RUNS 次取平均"
+run read/write "$ROOT/target/bench-rw/release/utxz"
+run io_uring "$ROOT/target/bench-uring/release/utxz"
@@ -6,21 +6,37 @@
 
 use std::fs::File;
 use std::io::{self, BufReader};
+use std::os::unix::io::RawFd;
 use std::path::Path;
 // use std::process::Command;
+use crate::catalog::{tr, tr_str, Msg};
 use crate::coder::{
-    coder_set_compression_settings, get_opt_format, set_opt_format, set_opt_mode, FormatType,
-    OperationMode, CHECK, OPT_BLOCK_LIST, OPT_FORMAT, OPT_MODE,
+    coder_add_filter, coder_set_compression_settings, coder_set_fast, coder_set_preset,
+    get_opt_format, set_opt_block_size, set_opt_format, set_opt_mode, FormatType, OperationMode,
+    CHECK, OPT_BLOCK_LIST, OPT_FORMAT, OPT_MODE,
 };
+use crate::dict::{dict_load, DICT_TRAIN_SIZE_DEFAULT};
+use crate::encrypt::encrypt_decoder_init;
 use crate::hardware::{hardware_memlimit_set, hardware_threads_set};
-use crate::message::message_help;
-use crate::suffix::suffix_is_set;
+use crate::message::{
+    message_fatal, message_help, message_progress_json_set_fd, message_verbosity_increase,
+};
+use crate::options::{options_delta, DELTA_DIST_AUTO};
+use crate::sign::sign_key_load;
+use crate::suffix::{suffix_is_set, suffix_set};
 use crate::util::str_to_uint64;
+use crate::verify::{verify_mode_parse, VerifyMode};
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use lazy_static::lazy_static;
+use liblzma::api::{
+    LzmaOptionsDelta, LzmaOptionsType, LZMA_DEDUP_CHUNK_LOG_MAX, LZMA_DEDUP_CHUNK_LOG_MIN,
+    LZMA_FILTER_DELTA,
+};
+use liblzma::common::LzmaAutoTarget;
 use std::error::Error;
 use std::str;
 use std::sync::Mutex;
+use utxz_sys::fcntl as sys_fcntl;
 
 /// 定义文件分隔符常量
 pub const DEFAULT_FILES_DELIM: char = '\n'; // 假设默认分隔符为换行符
@@ -79,10 +95,57 @@ lazy_static! {
     pub static ref OPT_STDOUT: Mutex<bool> = Mutex::new(false);
     pub static ref OPT_FORCE: Mutex<bool> = Mutex::new(false);
     pub static ref OPT_KEEP_ORIGINAL: Mutex<bool> = Mutex::new(false);
+    /// 先写入同目录下的临时文件，fsync 后再原子地重命名为目标文件；--no-sync 关闭
+    pub static ref OPT_SYNC: Mutex<bool> = Mutex::new(true);
+    /// --xattrs/--no-xattrs：是否复制扩展属性，包括 POSIX ACL 和文件权能
+    pub static ref OPT_XATTRS: Mutex<bool> = Mutex::new(true);
+    /// 用 mmap 读取较大的普通文件，避免复制到输入缓冲区；--no-mmap 关闭
+    pub static ref OPT_MMAP: Mutex<bool> = Mutex::new(true);
     pub static ref OPT_ROBOT: Mutex<bool> = Mutex::new(false);
     pub static ref OPT_IGNORE_CHECK: Mutex<bool> = Mutex::new(false);
+    /// --jobs 指定的并行处理文件数，1 表示逐个处理
+    pub static ref OPT_JOBS: Mutex<u32> = Mutex::new(1);
+    /// -r/--recursive：递归处理目录中的文件
+    pub static ref OPT_RECURSIVE: Mutex<bool> = Mutex::new(false);
+    /// --follow-symlinks：递归时进入指向目录的符号链接，并处理指向文件的符号链接
+    pub static ref OPT_FOLLOW_SYMLINKS: Mutex<bool> = Mutex::new(false);
+    /// --tar：创建、解开或列出 .tar.xz 归档
+    pub static ref OPT_TAR: Mutex<bool> = Mutex::new(false);
+    /// --lzma-strict：按 LZMA Utils 的规则严格检查 .lzma 头部，且不允许已知大小的数据带结束标记
+    pub static ref OPT_LZMA_STRICT: Mutex<bool> = Mutex::new(false);
+    /// --lzma-lax：接受任意字典大小和未压缩大小的 .lzma 头部，并忽略尾随数据
+    pub static ref OPT_LZMA_LAX: Mutex<bool> = Mutex::new(false);
+    /// --dict=FILE：raw 格式压缩和解压缩时使用的预设字典
+    pub static ref OPT_DICT: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+    /// --train-dict=FILE：从样本文件训练预设字典并写入 FILE
+    pub static ref OPT_TRAIN_DICT: Mutex<Option<String>> = Mutex::new(None);
+    /// --train-size=SIZE：训练出的字典的最大大小
+    pub static ref OPT_TRAIN_SIZE: Mutex<u64> = Mutex::new(DICT_TRAIN_SIZE_DEFAULT);
+    /// --exclude=GLOB：递归时跳过匹配的文件和目录
+    pub static ref OPT_EXCLUDE: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    /// --dedup[=SIZE]：在 LZMA2 之前加入长距离去重过滤器，值为平均分块大小的对数
+    pub static ref OPT_DEDUP: Mutex<Option<u32>> = Mutex::new(None);
+    /// --single-block：多线程压缩时只生成一个块，在块内并行编码 LZMA2 块
+    pub static ref OPT_SINGLE_BLOCK: Mutex<bool> = Mutex::new(false);
+    /// --auto-level、--target-speed、--target-ratio：按目标为每个文件自动选择预设和过滤器链
+    pub static ref OPT_AUTO_TARGET: Mutex<Option<LzmaAutoTarget>> = Mutex::new(None);
+    /// --auto-bcj：压缩可执行文件时自动加上对应体系结构的 BCJ 过滤器
+    pub static ref OPT_AUTO_BCJ: Mutex<bool> = Mutex::new(false);
+    /// --delta[=OPTS]：在 LZMA2 之前加入 Delta 过滤器，值为距离，DELTA_DIST_AUTO 表示按文件自动选择
+    pub static ref OPT_DELTA: Mutex<Option<u64>> = Mutex::new(None);
+    /// --verify=MODE：不完整解压，只检查 .xz 文件的结构，或另外并行验证各 Block 的校验值
+    pub static ref OPT_VERIFY: Mutex<Option<VerifyMode>> = Mutex::new(None);
+    /// --sign-key=FILE：HMAC-SHA256 签名的密钥，压缩时写入 .sig 文件，测试和解压缩时验证
+    pub static ref OPT_SIGN_KEY: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+    /// --encrypt：压缩时用加密过滤器代替 LZMA2
+    pub static ref OPT_ENCRYPT: Mutex<bool> = Mutex::new(false);
+    /// --key-file=FILE：加密和解密用的口令，不给出时在终端上询问
+    pub static ref OPT_KEY_FILE: Mutex<Option<Vec<u8>>> = Mutex::new(None);
 }
 
+/// 只给出 --auto-level 时的目标速度
+const AUTO_TARGET_SPEED_DEFAULT: u64 = 10 << 20;
+
 /// 解析内存限制参数
 ///
 /// # 参数
@@ -128,7 +191,7 @@ fn parse_memlimit(
 fn parse_block_list(str_const: &str) {
     // 验证输入有效性
     if str_const.is_empty() || str_const.starts_with(',') {
-        panic!("无效的块列表参数: {}", str_const);
+        message_fatal(&tr(Msg::InvalidBlockList, &[&str_const]), format_args!(""));
     }
 
     // 分割字符串并计算块数
@@ -137,7 +200,7 @@ fn parse_block_list(str_const: &str) {
 
     // 防止溢出
     if count > usize::MAX / std::mem::size_of::<u64>() - 1 {
-        panic!("块列表参数过多: {}", str_const);
+        message_fatal(&tr(Msg::TooManyBlocks, &[&str_const]), format_args!(""));
     }
 
     // 分配内存并解析每个块
@@ -154,7 +217,7 @@ fn parse_block_list(str_const: &str) {
 
             // 0 只能作为最后一个元素
             if v == 0 && i != count - 1 {
-                panic!("0 只能用作块列表的最后一个元素");
+                message_fatal(tr_str(Msg::BlockListZero), format_args!(""));
             }
 
             if v == 0 {
@@ -175,10 +238,19 @@ fn parse_block_list(str_const: &str) {
     *OPT_BLOCK_LIST.lock().unwrap() = Some(opt_block_list);
 }
 
+/// -0 ... -9 预设级别选项的参数名，下标即预设级别
+const PRESET_LEVEL_ARGS: [&str; 10] = [
+    "preset-0", "preset-1", "preset-2", "preset-3", "preset-4", "preset-5", "preset-6", "preset-7",
+    "preset-8", "preset-9",
+];
+
+/// --fast 可用的最高预设级别
+const PRESET_FAST_LEVEL_MAX: u32 = 3;
+
 /// 解析命令行参数
 /// 解析命令行参数
 pub fn parse_real(args: &mut ArgsInfo) -> ArgMatches {
-    let commands = Command::new("utxz")
+    let mut commands = Command::new("utxz")
         .version("1.0")
         .author("Your Name")
         .about("A Rust implementation of utxz")
@@ -197,18 +269,180 @@ pub fn parse_real(args: &mut ArgsInfo) -> ArgMatches {
                 .long("decompress")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("test")
+                .short('t')
+                .long("test")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("list")
                 .short('l')
                 .long("list")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .action(ArgAction::Set)
+                .value_name("MODE"),
+        )
         .arg(
             Arg::new("force")
                 .short('f')
                 .long("force")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("follow-symlinks")
+                .long("follow-symlinks")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .action(ArgAction::Append)
+                .value_name("GLOB"),
+        )
+        .arg(
+            Arg::new("no-sync")
+                .long("no-sync")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("xattrs")
+                .long("xattrs")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no-xattrs"),
+        )
+        .arg(
+            Arg::new("no-xattrs")
+                .long("no-xattrs")
+                .action(ArgAction::SetTrue)
+                .overrides_with("xattrs"),
+        )
+        .arg(
+            Arg::new("no-mmap")
+                .long("no-mmap")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(Arg::new("tar").long("tar").action(ArgAction::SetTrue))
+        .arg(
+            Arg::new("lzma-strict")
+                .long("lzma-strict")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("lzma-lax")
+                .long("lzma-lax")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("format")
+                .short('F')
+                .long("format")
+                .action(ArgAction::Set)
+                .value_name("FMT"),
+        )
+        .arg(
+            Arg::new("suffix")
+                .short('S')
+                .long("suffix")
+                .action(ArgAction::Set)
+                .value_name(".SUF"),
+        )
+        .arg(
+            Arg::new("dict")
+                .long("dict")
+                .action(ArgAction::Set)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("sign-key")
+                .long("sign-key")
+                .action(ArgAction::Set)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("encrypt")
+                .long("encrypt")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("key-file")
+                .long("key-file")
+                .action(ArgAction::Set)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("train-dict")
+                .long("train-dict")
+                .action(ArgAction::Set)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("train-size")
+                .long("train-size")
+                .action(ArgAction::Set)
+                .value_name("SIZE"),
+        )
+        .arg(
+            Arg::new("dedup")
+                .long("dedup")
+                .action(ArgAction::Set)
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("64KiB")
+                .value_name("SIZE"),
+        )
+        .arg(
+            Arg::new("single-block")
+                .long("single-block")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto-level")
+                .long("auto-level")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto-bcj")
+                .long("auto-bcj")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("delta")
+                .long("delta")
+                .action(ArgAction::Set)
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("")
+                .value_name("OPTS"),
+        )
+        .arg(
+            Arg::new("target-speed")
+                .long("target-speed")
+                .action(ArgAction::Set)
+                .value_name("SPEED"),
+        )
+        .arg(
+            Arg::new("target-ratio")
+                .long("target-ratio")
+                .action(ArgAction::Set)
+                .value_name("RATIO"),
+        )
+        .arg(
+            Arg::new("block-size")
+                .long("block-size")
+                .action(ArgAction::Set)
+                .value_name("SIZE"),
+        )
         .arg(
             Arg::new("help")
                 .short('h')
@@ -228,12 +462,45 @@ pub fn parse_real(args: &mut ArgsInfo) -> ArgMatches {
                 .action(ArgAction::Set)
                 .value_name("NUM"),
         )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .action(ArgAction::Set)
+                .value_name("N"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::Count),
+        )
+        .arg(Arg::new("robot").long("robot").action(ArgAction::SetTrue))
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .action(ArgAction::Set)
+                .value_name("FMT"),
+        )
+        .arg(
+            Arg::new("progress-fd")
+                .long("progress-fd")
+                .action(ArgAction::Set)
+                .value_name("N"),
+        )
         .arg(
             Arg::new("files")
                 .action(ArgAction::Append)
                 .num_args(0..)
                 .value_name("FILE"),
+        )
+        .arg(Arg::new("fast").long("fast").action(ArgAction::SetTrue));
+    for (level, id) in PRESET_LEVEL_ARGS.iter().enumerate() {
+        commands = commands.arg(
+            Arg::new(*id)
+                .short(char::from(b'0' + level as u8))
+                .action(ArgAction::SetTrue),
         );
+    }
 
     let matches: clap::ArgMatches = match commands.try_get_matches() {
         Ok(matches) => matches,
@@ -252,10 +519,25 @@ pub fn parse_real(args: &mut ArgsInfo) -> ArgMatches {
         set_opt_mode(OperationMode::Decompress);
     }
 
+    if matches.get_flag("test") {
+        set_opt_mode(OperationMode::Test);
+    }
+
     if matches.get_flag("list") {
         set_opt_mode(OperationMode::List);
     }
 
+    if let Some(mode_str) = matches.get_one::<String>("verify") {
+        match verify_mode_parse(mode_str) {
+            Some(mode) => *OPT_VERIFY.lock().unwrap() = Some(mode),
+            None => {
+                message_fatal(&tr(Msg::UnknownVerifyMode, &[&mode_str]), format_args!(""));
+                return matches;
+            }
+        }
+        set_opt_mode(OperationMode::Test);
+    }
+
     // 从 files 位置参数获取文件列表
     if let Some(files) = matches.get_many::<String>("files") {
         args.arg_names = files.map(|s| s.to_string()).collect();
@@ -275,6 +557,206 @@ pub fn parse_real(args: &mut ArgsInfo) -> ArgMatches {
         *OPT_FORCE.lock().unwrap() = true;
     }
 
+    for _ in 0..matches.get_count("verbose") {
+        message_verbosity_increase();
+    }
+    if matches.get_flag("robot") {
+        *OPT_ROBOT.lock().unwrap() = true;
+    }
+
+    // --progress-fd 只输出 JSON；单独的 --progress=json 输出到标准错误
+    let progress_json = match matches.get_one::<String>("progress").map(String::as_str) {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(fmt) => {
+            message_fatal(&tr(Msg::UnknownProgressFormat, &[&fmt]), format_args!(""));
+            return matches;
+        }
+    };
+    if let Some(fd_str) = matches.get_one::<String>("progress-fd") {
+        let fd = str_to_uint64("progress-fd", fd_str, 0, i32::MAX as u64) as RawFd;
+        if sys_fcntl::fcntl_getfl(fd).is_err() {
+            message_fatal(&tr(Msg::InvalidProgressFd, &[&fd]), format_args!(""));
+        }
+        message_progress_json_set_fd(fd);
+    } else if progress_json {
+        message_progress_json_set_fd(libc::STDERR_FILENO);
+    }
+
+    if matches.get_flag("recursive") {
+        *OPT_RECURSIVE.lock().unwrap() = true;
+    }
+    if matches.get_flag("follow-symlinks") {
+        *OPT_FOLLOW_SYMLINKS.lock().unwrap() = true;
+    }
+    if let Some(patterns) = matches.get_many::<String>("exclude") {
+        OPT_EXCLUDE
+            .lock()
+            .unwrap()
+            .extend(patterns.map(|s| s.to_string()));
+    }
+
+    if matches.get_flag("no-sync") {
+        *OPT_SYNC.lock().unwrap() = false;
+    }
+
+    if matches.get_flag("xattrs") {
+        *OPT_XATTRS.lock().unwrap() = true;
+    }
+    if matches.get_flag("no-xattrs") {
+        *OPT_XATTRS.lock().unwrap() = false;
+    }
+
+    if matches.get_flag("no-mmap") {
+        *OPT_MMAP.lock().unwrap() = false;
+    }
+
+    if matches.get_flag("tar") {
+        *OPT_TAR.lock().unwrap() = true;
+    }
+    if matches.get_flag("lzma-strict") && matches.get_flag("lzma-lax") {
+        message_fatal(
+            &tr(Msg::CannotUseWith, &[&"--lzma-strict", &"--lzma-lax"]),
+            format_args!(""),
+        );
+        return matches;
+    }
+    *OPT_LZMA_STRICT.lock().unwrap() = matches.get_flag("lzma-strict");
+    *OPT_LZMA_LAX.lock().unwrap() = matches.get_flag("lzma-lax");
+    if let Some(format) = matches.get_one::<String>("format") {
+        let format = match format.as_str() {
+            "auto" => FormatType::Auto,
+            "xz" => FormatType::Xz,
+            "lzma" | "alone" => FormatType::Lzma,
+            "lzip" => FormatType::Lzip,
+            "raw" => FormatType::Raw,
+            _ => {
+                message_fatal(&tr(Msg::UnknownFileFormat, &[&format]), format_args!(""));
+                return matches;
+            }
+        };
+        set_opt_format(format);
+    }
+    if let Some(suffix) = matches.get_one::<String>("suffix") {
+        suffix_set(suffix);
+    }
+    if let Some(path) = matches.get_one::<String>("dict") {
+        *OPT_DICT.lock().unwrap() = Some(dict_load(path));
+    }
+    if let Some(path) = matches.get_one::<String>("sign-key") {
+        *OPT_SIGN_KEY.lock().unwrap() = Some(sign_key_load(path));
+    }
+    if matches.get_flag("encrypt") {
+        *OPT_ENCRYPT.lock().unwrap() = true;
+    }
+    if let Some(path) = matches.get_one::<String>("key-file") {
+        *OPT_KEY_FILE.lock().unwrap() = Some(sign_key_load(path));
+    }
+    if let Some(path) = matches.get_one::<String>("train-dict") {
+        *OPT_TRAIN_DICT.lock().unwrap() = Some(path.to_string());
+    }
+    if let Some(size_str) = matches.get_one::<String>("train-size") {
+        *OPT_TRAIN_SIZE.lock().unwrap() =
+            str_to_uint64("train-size", size_str, 1, u32::MAX as u64);
+    }
+    // 同时给出多个预设级别时，以命令行中最后出现的为准
+    let preset_level = PRESET_LEVEL_ARGS
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| matches.get_flag(id))
+        .max_by_key(|(_, id)| matches.index_of(id))
+        .map(|(level, _)| level as u32);
+    if let Some(level) = preset_level {
+        coder_set_preset(level);
+    }
+    if matches.get_flag("fast") {
+        // 单独的 --fast 即 "-0 fast"
+        let level = preset_level.unwrap_or(0);
+        if level > PRESET_FAST_LEVEL_MAX {
+            message_fatal(
+                &tr(Msg::FastLevel, &[&PRESET_FAST_LEVEL_MAX]),
+                format_args!(""),
+            );
+            return matches;
+        }
+        coder_set_preset(level);
+        coder_set_fast();
+    }
+    if let Some(size_str) = matches.get_one::<String>("dedup") {
+        let size = str_to_uint64(
+            "dedup",
+            size_str,
+            1 << LZMA_DEDUP_CHUNK_LOG_MIN,
+            1 << LZMA_DEDUP_CHUNK_LOG_MAX,
+        );
+        if !size.is_power_of_two() {
+            message_fatal(&tr(Msg::DedupChunkSize, &[&size_str]), format_args!(""));
+            return matches;
+        }
+        *OPT_DEDUP.lock().unwrap() = Some(size.trailing_zeros());
+    }
+    if matches.get_flag("single-block") {
+        *OPT_SINGLE_BLOCK.lock().unwrap() = true;
+    }
+    if matches.get_flag("auto-level") {
+        *OPT_AUTO_TARGET.lock().unwrap() = Some(LzmaAutoTarget::Speed(AUTO_TARGET_SPEED_DEFAULT));
+    }
+    if matches.get_flag("auto-bcj") {
+        *OPT_AUTO_BCJ.lock().unwrap() = true;
+    }
+    if let Some(delta_str) = matches.get_one::<String>("delta") {
+        match options_delta(Some(delta_str)) {
+            Ok(options) => *OPT_DELTA.lock().unwrap() = Some(options.dist),
+            Err(e) => {
+                message_fatal(&format!("--delta={}: {}", delta_str, e), format_args!(""));
+                return matches;
+            }
+        }
+    }
+    if let Some(speed_str) = matches.get_one::<String>("target-speed") {
+        // 接受 "100MiB/s" 这样的写法
+        let size_str = speed_str.strip_suffix("/s").unwrap_or(speed_str);
+        let speed = str_to_uint64("target-speed", size_str, 1, u64::MAX);
+        *OPT_AUTO_TARGET.lock().unwrap() = Some(LzmaAutoTarget::Speed(speed));
+    }
+    if let Some(ratio_str) = matches.get_one::<String>("target-ratio") {
+        if matches.get_one::<String>("target-speed").is_some() {
+            message_fatal(tr_str(Msg::TargetSpeedRatio), format_args!(""));
+            return matches;
+        }
+        // 接受 "0.3" 或 "30%"
+        let ratio = match ratio_str.strip_suffix('%') {
+            Some(percent) => percent.parse::<f64>().map(|p| p / 100.0),
+            None => ratio_str.parse::<f64>(),
+        };
+        match ratio {
+            Ok(ratio) if ratio > 0.0 && ratio.is_finite() => {
+                *OPT_AUTO_TARGET.lock().unwrap() = Some(LzmaAutoTarget::Ratio(ratio));
+            }
+            _ => {
+                message_fatal(
+                    &tr(Msg::TargetRatioInvalid, &[&ratio_str]),
+                    format_args!(""),
+                );
+                return matches;
+            }
+        }
+    }
+    if let Some(size_str) = matches.get_one::<String>("block-size") {
+        set_opt_block_size(str_to_uint64("block-size", size_str, 1, u64::MAX));
+    }
+
+    if let Some(jobs_str) = matches.get_one::<String>("jobs") {
+        // 0 表示使用与处理器核心数相同的并行数
+        let mut jobs = str_to_uint64("jobs", jobs_str, 0, u32::MAX as u64) as u32;
+        if jobs == 0 {
+            jobs = std::thread::available_parallelism()
+                .map(|n| n.get() as u32)
+                .unwrap_or(1);
+        }
+        *OPT_JOBS.lock().unwrap() = jobs;
+    }
+
     if let Some(threads_str) = matches.get_one::<String>("threads") {
         let threads = str_to_uint64("threads", threads_str, 0, u32::MAX as u64) as u32;
         hardware_threads_set(threads);
@@ -310,7 +792,7 @@ pub fn parse_environment(args: &mut ArgsInfo, argv0: &str, varname: &str) {
 
             // 防止参数数量溢出
             if argc == std::cmp::min(i32::MAX, (usize::MAX / std::mem::size_of::<&str>()) as i32) {
-                panic!("环境变量 {} 包含过多参数", varname);
+                message_fatal(&tr(Msg::EnvTooManyArgs, &[&varname]), format_args!(""));
             }
         }
     }
@@ -386,7 +868,7 @@ pub fn args_parse(args: &mut ArgsInfo, argc: i32, argv: Vec<&str>) {
     if *OPT_MODE.lock().unwrap() == OperationMode::Compress
         && *OPT_FORMAT.lock().unwrap() == FormatType::Lzip
     {
-        panic!("不支持压缩 Lzip 文件 (.lz)");
+        message_fatal(tr_str(Msg::LzipCompress), format_args!(""));
     }
 
     // 如果输出到标准输出或测试模式，保留原始文件
@@ -402,19 +884,128 @@ pub fn args_parse(args: &mut ArgsInfo, argc: i32, argv: Vec<&str>) {
         *OPT_FORMAT.lock().unwrap() = FormatType::Xz;
     }
 
+    // .lzma 格式只能包含一个 LZMA1 过滤器
+    if OPT_DEDUP.lock().unwrap().is_some() && *OPT_FORMAT.lock().unwrap() == FormatType::Lzma {
+        message_fatal(&tr(Msg::CannotUseWithLzma, &[&"--dedup"]), format_args!(""));
+    }
+
+    // 块内并行编码只支持单独的 LZMA2 过滤器
+    if *OPT_SINGLE_BLOCK.lock().unwrap() && OPT_DEDUP.lock().unwrap().is_some() {
+        message_fatal(
+            &tr(Msg::CannotUseWith, &[&"--single-block", &"--dedup"]),
+            format_args!(""),
+        );
+    }
+
+    // 自动选择的过滤器链记录在 .xz 的块头部中，其他格式解压缩时无从得知
+    if OPT_AUTO_TARGET.lock().unwrap().is_some()
+        && *OPT_MODE.lock().unwrap() == OperationMode::Compress
+    {
+        if *OPT_FORMAT.lock().unwrap() != FormatType::Xz {
+            message_fatal(&tr(Msg::OnlyWithXz, &[&"--auto-level"]), format_args!(""));
+        }
+        if OPT_DEDUP.lock().unwrap().is_some() {
+            message_fatal(
+                &tr(Msg::CannotUseWith, &[&"--auto-level", &"--dedup"]),
+                format_args!(""),
+            );
+        }
+        if *OPT_SINGLE_BLOCK.lock().unwrap() {
+            message_fatal(
+                &tr(Msg::CannotUseWith, &[&"--auto-level", &"--single-block"]),
+                format_args!(""),
+            );
+        }
+    }
+
+    // 加密过滤器只能记录在 .xz 的块头部中，块内并行编码只支持单独的 LZMA2 过滤器
+    if *OPT_ENCRYPT.lock().unwrap() && *OPT_MODE.lock().unwrap() == OperationMode::Compress {
+        if *OPT_FORMAT.lock().unwrap() != FormatType::Xz {
+            message_fatal(&tr(Msg::OnlyWithXz, &[&"--encrypt"]), format_args!(""));
+        }
+        if *OPT_SINGLE_BLOCK.lock().unwrap() {
+            message_fatal(
+                &tr(Msg::CannotUseWith, &[&"--encrypt", &"--single-block"]),
+                format_args!(""),
+            );
+        }
+    }
+
+    if *OPT_AUTO_BCJ.lock().unwrap() && *OPT_MODE.lock().unwrap() == OperationMode::Compress {
+        if *OPT_FORMAT.lock().unwrap() != FormatType::Xz {
+            message_fatal(&tr(Msg::OnlyWithXz, &[&"--auto-bcj"]), format_args!(""));
+        }
+        if *OPT_SINGLE_BLOCK.lock().unwrap() {
+            message_fatal(
+                &tr(Msg::CannotUseWith, &[&"--auto-bcj", &"--single-block"]),
+                format_args!(""),
+            );
+        }
+    }
+
+    if let Some(dist) = *OPT_DELTA.lock().unwrap() {
+        let format = OPT_FORMAT.lock().unwrap().clone();
+        let mode = OPT_MODE.lock().unwrap().clone();
+        if OPT_AUTO_TARGET.lock().unwrap().is_some() {
+            message_fatal(
+                &tr(Msg::CannotUseWith, &[&"--auto-level", &"--delta"]),
+                format_args!(""),
+            );
+        }
+        if format == FormatType::Lzma {
+            message_fatal(&tr(Msg::CannotUseWithLzma, &[&"--delta"]), format_args!(""));
+        }
+        if *OPT_SINGLE_BLOCK.lock().unwrap() {
+            message_fatal(
+                &tr(Msg::CannotUseWith, &[&"--single-block", &"--delta"]),
+                format_args!(""),
+            );
+        }
+        // 自动选择的距离只记录在 .xz 的块头部中
+        if dist == DELTA_DIST_AUTO
+            && format != FormatType::Xz
+            && (mode == OperationMode::Compress || format == FormatType::Raw)
+        {
+            message_fatal(&tr(Msg::OnlyWithXz, &[&"--delta=auto"]), format_args!(""));
+        }
+    }
+
     if *OPT_MODE.lock().unwrap() == OperationMode::Compress
         || (*OPT_FORMAT.lock().unwrap() == FormatType::Raw
             && *OPT_MODE.lock().unwrap() != OperationMode::List)
     {
+        // 固定距离的 Delta 过滤器放在预设的 LZMA 过滤器之前
+        if let Some(dist) = *OPT_DELTA.lock().unwrap() {
+            if dist != DELTA_DIST_AUTO {
+                coder_add_filter(
+                    LZMA_FILTER_DELTA,
+                    Some(LzmaOptionsType::Delta(LzmaOptionsDelta {
+                        dist: dist as u32,
+                        ..Default::default()
+                    })),
+                );
+            }
+        }
         coder_set_compression_settings();
     }
 
+    if *OPT_MODE.lock().unwrap() != OperationMode::Compress {
+        encrypt_decoder_init();
+    }
+
+    // 预设字典无法记录在 .xz 或 .lzma 文件中，解压缩时无从得知
+    if OPT_DICT.lock().unwrap().is_some() && *OPT_FORMAT.lock().unwrap() != FormatType::Raw {
+        message_fatal(tr_str(Msg::DictOnlyRaw), format_args!(""));
+    }
+
     // 如果使用 Raw 格式且未设置后缀，且不是输出到标准输出，报错
+    // 从标准输入读取时总是写入标准输出
     if *OPT_FORMAT.lock().unwrap() == FormatType::Raw
         && !suffix_is_set()
         && !*OPT_STDOUT.lock().unwrap()
+        && args.arg_names.iter().any(|name| name != "-")
     {
-        panic!("使用 --format=raw 时，必须指定 --suffix=.SUF 或输出到标准输出");
+        message_fatal(tr_str(Msg::RawNeedsSuffix), format_args!(""));
     }
 
     // println!("args_names{:#?}", args);
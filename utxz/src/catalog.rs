@@ -0,0 +1,860 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 消息目录
+//!
+//! 面向用户的诊断信息和帮助文字都用 `Msg` 标识，按 LC_ALL、LC_MESSAGES、LANG
+//! 的顺序取第一个非空的值选择语言，规则与 gettext 相同。以 "zh" 开头时使用
+//! 简体中文，其余情况（包括未设置、C 和 POSIX）使用英文，脚本可以用 LC_ALL=C
+//! 得到稳定的英文输出。
+//!
+//! 消息文字中的 `{}` 按顺序替换为参数，`{0}`、`{1}` 等按位置替换，
+//! 供语序不同的翻译使用。
+
+use std::env;
+use std::fmt::Display;
+use std::io;
+use std::sync::OnceLock;
+
+use utxz_sys::locale as sys_locale;
+
+/// 消息语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    ZhCn,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// 消息 ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // message.rs
+    InternalError,
+    SignalHandler,
+    StrmNoCheck,
+    StrmUnsupportedCheck,
+    StrmMemError,
+    StrmMemlimit,
+    StrmFormat,
+    StrmOptions,
+    StrmData,
+    StrmBuf,
+    StrmKey,
+    MemNeededNoLimit,
+    MemNeeded,
+    TryHelp,
+    ProgressWriteError,
+    HelpUsage,
+    HelpOptions,
+    HelpHelp,
+    HelpVersion,
+    HelpStdin,
+
+    // args.rs、options.rs、util.rs
+    ValueOutOfRange,
+    InvalidBlockList,
+    TooManyBlocks,
+    BlockListZero,
+    EnvTooManyArgs,
+    LzipCompress,
+    UnknownProgressFormat,
+    InvalidProgressFd,
+    UnknownFileFormat,
+    FastLevel,
+    DedupChunkSize,
+    TargetSpeedRatio,
+    TargetRatioInvalid,
+    OnlyWithXz,
+    CannotUseWith,
+    CannotUseWithLzma,
+    DictOnlyRaw,
+    RawNeedsSuffix,
+    OptionNotPair,
+    OptionInvalidName,
+    OptionInvalidValue,
+    UnsupportedPreset,
+    LcLpSum,
+    StartOffsetInvalid,
+    DeltaDistMissing,
+    UnknownVerifyMode,
+    TtyRead,
+    TtyWrite,
+
+    // main.rs
+    FilesNoHandle,
+    FilesUnexpectedEnd,
+    FilesNulChar,
+    FilesReadError,
+    JobsModes,
+    JobsStdout,
+    JobsStdin,
+    RobotUnsupported,
+    StdinFilesConflict,
+
+    // file_io.rs
+    PipeCreate,
+    PipeNonblock,
+    PollFailed,
+    FileMoved,
+    CannotRemove,
+    CannotSetOwner,
+    CannotSetGroup,
+    CannotSetPermissions,
+    XattrList,
+    XattrGroupNotCopied,
+    XattrRead,
+    XattrSet,
+    StdinFlags,
+    StdinFlagsRestore,
+    StdoutFlags,
+    StdoutAppendRestore,
+    SymlinkSkipped,
+    DirectorySkipped,
+    NotRegularSkipped,
+    SetuidSkipped,
+    StickySkipped,
+    HardlinkSkipped,
+    NameNotUtf8,
+    EmptyFilename,
+    TempFileFailed,
+    DirSyncFailed,
+    SyncFailed,
+    CloseFailed,
+    SparseSeekFailed,
+    WriteError,
+    ReadError,
+    ReadTruncated,
+    SeekError,
+    UnexpectedEof,
+
+    // suffix.rs
+    UnknownSuffix,
+    HasSuffix,
+    InvalidSuffix,
+
+    // coder.rs
+    MemlimitTooLow,
+    DictSizeAdjusted,
+    TooManyFilters,
+    LzmaOnlyLzma1,
+    Lzma1WithXz,
+    FlushTimeoutFilters,
+    UnsupportedFilters,
+    RawPresetDiscouraged,
+    RawPresetVaries,
+    DecompressMemory,
+    AutoLevelNoSample,
+    AutoLevelChosen,
+    AutoLevelNoCandidate,
+    AutoBcj,
+    AutoDelta,
+    AutoDeltaNone,
+    DictMismatch,
+
+    // dict.rs
+    DictEmpty,
+    TrainStdin,
+    TrainNoSamples,
+    FileExists,
+    TrainDone,
+
+    // sign.rs
+    SignKeyEmpty,
+    SignNotRegular,
+    SignInvalid,
+    SignMismatch,
+    SignChanged,
+
+    // encrypt.rs
+    EncryptPrompt,
+    EncryptPromptAgain,
+    EncryptMismatch,
+    EncryptEmpty,
+    EncryptNoKey,
+    EncryptRandom,
+
+    // list.rs
+    FileEmpty,
+    TooSmallXz,
+    TooSmallLzma,
+    TooSmallLz,
+    BlockHeaderInvalid,
+    BlockHeaderSize,
+    LzipMemberSize,
+    ListRaw,
+    ListStdin,
+
+    // tar.rs
+    TarHeaderInvalid,
+    TarUnexpectedEnd,
+    TarFileShrank,
+    TarNotRegular,
+    TarNoFiles,
+    TarUnsafeName,
+    TarSymlinkInPath,
+    TarExtTooLarge,
+    TarUnsupportedType,
+    TarXzOnly,
+    TarNeedsArchive,
+    TarSummary,
+
+    // verify.rs
+    VerifyFormat,
+    VerifyStdin,
+    VerifyBlockError,
+}
+
+/// 返回消息的英文和简体中文文字
+fn catalog_entry(id: Msg) -> (&'static str, &'static str) {
+    match id {
+        Msg::InternalError => ("Internal error (bug)", "内部错误（Bug）"),
+        Msg::SignalHandler => ("Cannot establish signal handlers", "无法建立信号处理程序"),
+        Msg::StrmNoCheck => (
+            "No integrity check; not verifying file integrity",
+            "未进行完整性检查；未验证文件完整性",
+        ),
+        Msg::StrmUnsupportedCheck => (
+            "Unsupported type of integrity check; not verifying file integrity",
+            "不支持的完整性检查类型；未验证文件完整性",
+        ),
+        Msg::StrmMemError => ("Memory allocation failed", "内存不足"),
+        Msg::StrmMemlimit => ("Memory usage limit reached", "已达到内存使用限制"),
+        Msg::StrmFormat => ("File format not recognized", "文件格式无法识别"),
+        Msg::StrmOptions => ("Unsupported options", "不支持的选项"),
+        Msg::StrmData => ("Compressed data is corrupt", "压缩数据已损坏"),
+        Msg::StrmBuf => ("Unexpected end of input", "输入意外结束"),
+        Msg::StrmKey => (
+            "No key can decrypt the data; give the right passphrase or --key-file",
+            "没有能解密数据的密钥；请输入正确的口令或用 --key-file 给出密钥",
+        ),
+        Msg::MemNeededNoLimit => (
+            "{} MiB of memory is required. The limiter is disabled.",
+            "需要 {} MiB 内存。内存限制器已禁用。",
+        ),
+        Msg::MemNeeded => (
+            "{} MiB of memory is required. The limit is {}.",
+            "需要 {} MiB 内存。限制是 {}。",
+        ),
+        Msg::TryHelp => (
+            "Try `{} --help' for more information.",
+            "尝试 `{} --help` 获取更多信息。",
+        ),
+        Msg::ProgressWriteError => (
+            "Error writing progress information: {}",
+            "写入进度信息时出错: {}",
+        ),
+        Msg::HelpUsage => (
+            "Usage: {} [OPTION]... [FILE]...\nCompress or decompress FILEs in the .xz format.\n",
+            "用法: {} [选项]... [文件]...\n以 .xz 格式压缩或解压文件\n",
+        ),
+        Msg::HelpOptions => (
+            "    -z, --compress     force compression\n\
+             \x20   -d, --decompress   force decompression\n\
+             \x20   -f, --force        force overwrite of output file and (de)compress links\n\
+             \x20   -t, --test         test compressed file integrity\n\
+             \x20   -l, --list         list information about .xz files\n\
+             \x20       --verify MODE  check .xz files: structure checks the headers, Index and\n\
+             \x20                       padding without decompressing; full also decodes -T Blocks\n\
+             \x20                       at a time to verify their integrity checks\n\
+             \x20   -0 ... -9          compression preset (default: 6)\n\
+             \x20       --fast        fast mode using the HT4 match finder, only with -0 to -3;\n\
+             \x20                       alone it means -0 --fast\n\
+             \x20       --auto-level  trial-compress a sample of each file and pick the preset,\n\
+             \x20                       BCJ and Delta filters (default target: --target-speed=10MiB/s)\n\
+             \x20       --target-speed SPEED  pick the best ratio at no less than SPEED (e.g. 100MiB/s)\n\
+             \x20       --target-ratio RATIO  pick the fastest setting with ratio at most RATIO (e.g. 0.3 or 30%)\n\
+             \x20       --auto-bcj    add the matching BCJ filter when compressing ELF, PE or Mach-O executables\n\
+             \x20       --delta[=OPTS]  add a Delta filter before LZMA2; OPTS is dist=1..256,\n\
+             \x20                       auto or dist=auto picks the distance from a sample of each file\n\
+             \x20   -T, --threads N   use N threads for compression (default: 1)\n\
+             \x20                       -T0 uses as many threads as there are CPU threads\n\
+             \x20       --jobs N      test or decompress N files at a time (default: 1)\n\
+             \x20                       --jobs=0 uses as many jobs as there are CPU threads\n\
+             \x20   -r, --recursive    operate recursively on directories\n\
+             \x20       --follow-symlinks  follow symbolic links when recursing\n\
+             \x20       --exclude GLOB  skip files and directories matching GLOB when recursing\n\
+             \x20       --no-sync     write the target file directly without a temporary file and fsync;\n\
+             \x20                       faster, but power loss or a killed process may leave it incomplete\n\
+             \x20       --no-xattrs   do not copy extended attributes, ACLs and file capabilities\n\
+             \x20                       (copied by default, --xattrs)\n\
+             \x20       --no-mmap     read large regular files with read() instead of mmap\n\
+             \x20       --progress FMT  progress format: text (default) or json; json writes one\n\
+             \x20                       JSON line per second to standard error, with file start and end events\n\
+             \x20       --progress-fd N  write JSON progress to file descriptor N\n\
+             \x20       --block-size SIZE  start a new .xz block after every SIZE bytes of input\n\
+             \x20       --single-block  with multithreaded compression produce a single block encoded\n\
+             \x20                       in parallel, with a ratio close to single-threaded mode\n\
+             \x20       --dedup[=SIZE]  remove repeated data farther apart than the dictionary size\n\
+             \x20                       before compression; the average chunk SIZE is a power of two\n\
+             \x20                       from 4KiB to 4MiB (default: 64KiB)\n\
+             \x20       --tar         archive mode: the first file is a .tar.xz archive;\n\
+             \x20                       -z creates, -d extracts, -l or -t lists members\n\
+             \x20       --lzma-strict  check .lzma headers strictly; no end marker when the size is known\n\
+             \x20       --lzma-lax    accept non-standard .lzma headers and ignore trailing data\n\
+             \x20   -F, --format FMT   file format: auto, xz, lzma, lzip or raw\n\
+             \x20   -S, --suffix .SUF  use the suffix .SUF on compressed files (needed with --format=raw)\n\
+             \x20       --dict FILE   use FILE as the preset dictionary (only with --format=raw)\n\
+             \x20       --sign-key FILE  sign the compressed file with HMAC-SHA256 using the key in FILE\n\
+             \x20                       and write the signature next to it with a .sig suffix;\n\
+             \x20                       -t, -d and --verify check the signature first\n\
+             \x20       --encrypt     compress with LZMA2 and encrypt with ChaCha20-Poly1305 (.xz only);\n\
+             \x20                       the key is derived with Argon2id from a passphrase asked\n\
+             \x20                       on the terminal or from --key-file\n\
+             \x20       --key-file FILE  use the contents of FILE as the passphrase for --encrypt\n\
+             \x20                       and for decrypting instead of asking on the terminal\n\
+             \x20       --train-dict FILE  train a dictionary from the sample files given as arguments\n\
+             \x20                       and write it to FILE\n\
+             \x20       --train-size SIZE  make the trained dictionary at most SIZE bytes (default: 64KiB)",
+            "    -z, --compress     强制压缩\n\
+             \x20   -d, --decompress   强制解压\n\
+             \x20   -f, --force        强制覆盖输出文件和(解)压缩链接\n\
+             \x20   -t, --test         测试压缩文件的完整性\n\
+             \x20   -l, --list         列出关于 .xz 文件的信息\n\
+             \x20       --verify MODE  检查 .xz 文件：structure 不解压，只检查头部、Index 和填充；\n\
+             \x20                       full 另外同时解码 -T 个 Block，验证它们的完整性校验值\n\
+             \x20   -0 ... -9          压缩预设级别（缺省：6）\n\
+             \x20       --fast        快速模式，使用 HT4 匹配查找器，只能与 -0 到 -3\n\
+             \x20                       一起使用，单独使用即 -0 --fast\n\
+             \x20       --auto-level  在每个文件的样本上试压，自动选择预设、BCJ 和 Delta\n\
+             \x20                       过滤器（缺省目标：--target-speed=10MiB/s）\n\
+             \x20       --target-speed SPEED  选择速度不低于 SPEED（如 100MiB/s）的最高压缩率\n\
+             \x20       --target-ratio RATIO  选择压缩率不高于 RATIO（如 0.3 或 30%）的最快设置\n\
+             \x20       --auto-bcj    压缩 ELF、PE 或 Mach-O 可执行文件时自动加上对应的 BCJ 过滤器\n\
+             \x20       --delta[=OPTS]  在 LZMA2 之前加入 Delta 过滤器；OPTS 为 dist=1..256，\n\
+             \x20                       auto 或 dist=auto 按每个文件的样本自动选择距离\n\
+             \x20   -T, --threads N   使用 N 个线程进行压缩（缺省：1）\n\
+             \x20                       -T0 使用系统所有 CPU 线程\n\
+             \x20       --jobs N      同时测试或解压 N 个文件（缺省：1）\n\
+             \x20                       --jobs=0 使用系统所有 CPU 线程\n\
+             \x20   -r, --recursive    递归处理目录中的文件\n\
+             \x20       --follow-symlinks  递归时跟随符号链接\n\
+             \x20       --exclude GLOB  递归时跳过匹配 GLOB 的文件和目录\n\
+             \x20       --no-sync     直接写入目标文件，不经过临时文件和 fsync；更快，但\n\
+             \x20                       断电或进程被杀时可能留下不完整的目标文件\n\
+             \x20       --no-xattrs   不复制扩展属性、ACL 和文件权能（缺省复制，--xattrs）\n\
+             \x20       --no-mmap     不用 mmap 读取大的普通文件，改用 read()\n\
+             \x20       --progress FMT  进度格式：text（缺省）或 json；json 每秒在标准错误\n\
+             \x20                       输出一行 JSON，包含文件开始和结束事件\n\
+             \x20       --progress-fd N  把 JSON 进度写入文件描述符 N\n\
+             \x20       --block-size SIZE  每输入 SIZE 字节开始一个新的 .xz 块\n\
+             \x20       --single-block  多线程压缩时只生成一个块，在块内并行编码，\n\
+             \x20                       压缩率接近单线程\n\
+             \x20       --dedup[=SIZE]  在压缩前消除相距超过字典大小的重复数据，\n\
+             \x20                       平均分块 SIZE 为 4KiB 到 4MiB 的 2 的幂（缺省：64KiB）\n\
+             \x20       --tar         归档模式：第一个文件为 .tar.xz 归档，\n\
+             \x20                       -z 创建、-d 解开、-l 或 -t 列出成员\n\
+             \x20       --lzma-strict  严格检查 .lzma 头部，已知大小时不允许结束标记\n\
+             \x20       --lzma-lax    接受非标准的 .lzma 头部并忽略尾随数据\n\
+             \x20   -F, --format FMT   文件格式：auto、xz、lzma、lzip 或 raw\n\
+             \x20   -S, --suffix .SUF  压缩文件使用 .SUF 后缀（--format=raw 时必须指定）\n\
+             \x20       --dict FILE   以 FILE 为预设字典（仅用于 --format=raw）\n\
+             \x20       --sign-key FILE  用 FILE 中的密钥以 HMAC-SHA256 签名压缩文件，签名写入\n\
+             \x20                       压缩文件名加 .sig 的文件；-t、-d 和 --verify 先验证签名\n\
+             \x20       --encrypt     用 LZMA2 压缩后以 ChaCha20-Poly1305 加密（仅用于 .xz）；\n\
+             \x20                       密钥由 Argon2id 从终端上输入的口令或 --key-file 派生\n\
+             \x20       --key-file FILE  以 FILE 的内容为 --encrypt 和解密用的口令，\n\
+             \x20                       不在终端上询问\n\
+             \x20       --train-dict FILE  从作为参数给出的样本文件训练字典并写入 FILE\n\
+             \x20       --train-size SIZE  训练出的字典最大为 SIZE 字节（缺省：64KiB）",
+        ),
+        Msg::HelpHelp => (
+            "    -h, --help        display this short help and exit",
+            "    -h, --help        显示此简短帮助并退出",
+        ),
+        Msg::HelpVersion => (
+            "    -V, --version     display the version number and exit",
+            "    -V, --version     显示版本号并退出",
+        ),
+        Msg::HelpStdin => (
+            "    With no FILE, or when FILE is -, read standard input.",
+            "    没有文件或文件为-时，从标准输入读取",
+        ),
+
+        Msg::ValueOutOfRange => (
+            "Value of the option `{}' must be in the range [{}, {}]",
+            "选项 `{}` 的值必须在 [{}, {}] 范围内",
+        ),
+        Msg::InvalidBlockList => ("{}: Invalid block list", "{}: 无效的块列表参数"),
+        Msg::TooManyBlocks => ("{}: Too many block sizes", "{}: 块列表参数过多"),
+        Msg::BlockListZero => (
+            "0 can only be used as the last element in the block list",
+            "0 只能用作块列表的最后一个元素",
+        ),
+        Msg::EnvTooManyArgs => (
+            "The environment variable {} contains too many arguments",
+            "环境变量 {} 包含过多参数",
+        ),
+        Msg::LzipCompress => (
+            "Compression of lzip files (.lz) is not supported",
+            "不支持压缩 Lzip 文件 (.lz)",
+        ),
+        Msg::UnknownProgressFormat => ("{}: Unknown progress format", "{}: 未知的进度格式"),
+        Msg::InvalidProgressFd => (
+            "--progress-fd={}: Invalid file descriptor",
+            "--progress-fd={}: 无效的文件描述符",
+        ),
+        Msg::UnknownFileFormat => ("{}: Unknown file format type", "{}: 未知的文件格式类型"),
+        Msg::FastLevel => (
+            "--fast can only be used with -0 to -{}",
+            "--fast 只能与 -0 到 -{} 一起使用",
+        ),
+        Msg::DedupChunkSize => (
+            "--dedup={}: The chunk size must be a power of two",
+            "--dedup={}: 分块大小必须是 2 的幂",
+        ),
+        Msg::TargetSpeedRatio => (
+            "Only one of --target-speed and --target-ratio can be specified",
+            "--target-speed 和 --target-ratio 只能指定一个",
+        ),
+        Msg::TargetRatioInvalid => (
+            "--target-ratio={}: The ratio must be a number greater than 0",
+            "--target-ratio={}: 压缩率必须是大于 0 的数",
+        ),
+        Msg::OnlyWithXz => (
+            "{} can only be used with the .xz format",
+            "{} 只能与 .xz 格式一起使用",
+        ),
+        Msg::CannotUseWith => ("{} cannot be used with {}", "{} 不能与 {} 一起使用"),
+        Msg::CannotUseWithLzma => (
+            "{} cannot be used with the .lzma format",
+            "{} 不能与 .lzma 格式一起使用",
+        ),
+        Msg::DictOnlyRaw => (
+            "--dict can only be used with --format=raw",
+            "--dict 只能与 --format=raw 一起使用",
+        ),
+        Msg::RawNeedsSuffix => (
+            "With --format=raw, --suffix=.SUF is required unless writing to stdout",
+            "使用 --format=raw 时，必须指定 --suffix=.SUF 或输出到标准输出",
+        ),
+        Msg::OptionNotPair => (
+            "{}: Options must be `name=value' pairs separated with commas",
+            "{}: 选项必须是以逗号分隔的 `名称=值` 对",
+        ),
+        Msg::OptionInvalidName => ("{}: Invalid option name", "{}: 无效的选项名称"),
+        Msg::OptionInvalidValue => ("{}: Invalid option value", "{}: 无效的选项值"),
+        Msg::UnsupportedPreset => (
+            "Unsupported LZMA1/LZMA2 preset: {}",
+            "不支持的 LZMA1/LZMA2 预设: {}",
+        ),
+        Msg::LcLpSum => (
+            "The sum of lc and lp must not exceed 4",
+            "lc 与 lp 之和不能超过 4",
+        ),
+        Msg::StartOffsetInvalid => ("Invalid start offset: {}", "无效的起始偏移: {}"),
+        Msg::DeltaDistMissing => ("Missing delta distance", "缺少 Delta 距离"),
+        Msg::UnknownVerifyMode => ("{}: Unknown verify mode", "{}: 未知的检查方式"),
+        Msg::TtyRead => (
+            "Compressed data cannot be read from a terminal",
+            "不能从终端读取压缩数据",
+        ),
+        Msg::TtyWrite => (
+            "Compressed data cannot be written to a terminal",
+            "不能向终端写入压缩数据",
+        ),
+
+        Msg::FilesNoHandle => ("{}: File handle does not exist", "{}: 文件句柄不存在"),
+        Msg::FilesUnexpectedEnd => (
+            "{}: Unexpected end of input when reading filenames",
+            "{}: 读取文件名时遇到意外的输入结束",
+        ),
+        Msg::FilesNulChar => (
+            "{}: Null character found when reading filenames; maybe you meant to use `--files0' instead of `--files'?",
+            "{}: 读取文件名时发现空字符；也许你应该用 \"--files0\" 而不是 \"--files\"",
+        ),
+        Msg::FilesReadError => ("{}: Error reading filenames: {}", "{}: 读取文件名时出错: {}"),
+        Msg::JobsModes => (
+            "--jobs is only supported with --test and --decompress",
+            "--jobs 仅支持 --test 和 --decompress",
+        ),
+        Msg::JobsStdout => (
+            "--jobs cannot be used when writing to standard output",
+            "--jobs 不能与写入标准输出一起使用",
+        ),
+        Msg::JobsStdin => (
+            "--jobs does not support reading from standard input",
+            "--jobs 不支持从标准输入读取",
+        ),
+        Msg::RobotUnsupported => (
+            "Compression and decompression with --robot are not supported yet.",
+            "尚不支持使用 --robot 进行压缩和解压缩。",
+        ),
+        Msg::StdinFilesConflict => (
+            "Cannot read data from standard input when reading filenames from standard input",
+            "从标准输入读取文件名时，不能从标准输入读取数据",
+        ),
+
+        Msg::PipeCreate => ("Error creating a pipe: {}", "创建管道失败: {}"),
+        Msg::PipeNonblock => (
+            "Failed to enable non-blocking mode on the pipe: {}",
+            "设置管道非阻塞失败: {}",
+        ),
+        Msg::PollFailed => ("{}: poll() failed: {}", "{}: poll() 失败: {}"),
+        Msg::FileMoved => (
+            "{}: File seems to have been moved, not removing",
+            "{}: 文件似乎已被移动，未删除",
+        ),
+        Msg::CannotRemove => ("{}: Cannot remove: {}", "{}: 无法删除: {}"),
+        Msg::CannotSetOwner => (
+            "{}: Cannot set the file owner: {}",
+            "{}: 无法设置文件所有者: {}",
+        ),
+        Msg::CannotSetGroup => ("{}: Cannot set the file group: {}", "{}: 无法设置文件组: {}"),
+        Msg::CannotSetPermissions => (
+            "{}: Cannot set the file permissions: {}",
+            "{}: 无法设置文件权限: {}",
+        ),
+        Msg::XattrList => (
+            "{}: Cannot read extended attributes: {}",
+            "{}: 无法读取扩展属性: {}",
+        ),
+        Msg::XattrGroupNotCopied => (
+            "{}: Cannot set the file group, not copying {}",
+            "{}: 无法设置文件组，未复制 {}",
+        ),
+        Msg::XattrRead => (
+            "{}: Cannot read extended attribute {}: {}",
+            "{}: 无法读取扩展属性 {}: {}",
+        ),
+        Msg::XattrSet => (
+            "{}: Cannot set extended attribute {}: {}",
+            "{}: 无法设置扩展属性 {}: {}",
+        ),
+        Msg::StdinFlags => (
+            "Error getting the file status flags from standard input: {}",
+            "无法获取标准输入的文件状态标志: {}",
+        ),
+        Msg::StdinFlagsRestore => (
+            "Error restoring the status flags to standard input: {}",
+            "恢复标准输入状态标志失败: {}",
+        ),
+        Msg::StdoutFlags => (
+            "Error getting the file status flags from standard output: {}",
+            "获取标准输出文件状态标志失败: {}",
+        ),
+        Msg::StdoutAppendRestore => (
+            "Error restoring the O_APPEND flag to standard output: {}",
+            "恢复标准输出 O_APPEND 标志失败: {}",
+        ),
+        Msg::SymlinkSkipped => ("{}: Is a symbolic link, skipping", "{}: 是符号链接，跳过"),
+        Msg::DirectorySkipped => ("{}: Is a directory, skipping", "{}: 是目录，跳过"),
+        Msg::NotRegularSkipped => ("{}: Not a regular file, skipping", "{}: 不是普通文件，跳过"),
+        Msg::SetuidSkipped => (
+            "{}: File has setuid or setgid bit set, skipping",
+            "{}: 文件设置了 setuid 或 setgid 位，跳过",
+        ),
+        Msg::StickySkipped => (
+            "{}: File has sticky bit set, skipping",
+            "{}: 文件设置了粘滞位，跳过",
+        ),
+        Msg::HardlinkSkipped => (
+            "{}: Input file has more than one hard link, skipping",
+            "{}: 输入文件有多个硬链接，跳过",
+        ),
+        Msg::NameNotUtf8 => (
+            "{}/{}: File name is not valid UTF-8, skipping",
+            "{}/{}: 文件名不是有效的 UTF-8，跳过",
+        ),
+        Msg::EmptyFilename => ("Empty filename, skipping", "文件名为空，跳过"),
+        Msg::TempFileFailed => (
+            "{}: Cannot create a temporary file",
+            "{}: 无法创建临时文件",
+        ),
+        Msg::DirSyncFailed => (
+            "{}: Cannot synchronize the directory: {}",
+            "{}: 无法同步目录: {}",
+        ),
+        Msg::SyncFailed => ("{}: Synchronizing the file failed: {}", "{}: 同步失败: {}"),
+        Msg::CloseFailed => ("{}: Closing the file failed: {}", "{}: 关闭文件失败: {}"),
+        Msg::SparseSeekFailed => (
+            "{}: Seeking failed when trying to create a sparse file: {}",
+            "{}: 创建稀疏文件时 seek 失败: {}",
+        ),
+        Msg::WriteError => ("{}: Write error: {}", "{}: 写入错误: {}"),
+        Msg::ReadError => ("{}: Read error: {}", "{}: 读取错误: {}"),
+        Msg::ReadTruncated => (
+            "{}: Read error: the file was truncated while being read",
+            "{}: 读取错误: 文件在读取期间被截断",
+        ),
+        Msg::SeekError => ("{}: Error seeking the file: {}", "{}: 定位文件出错: {}"),
+        Msg::UnexpectedEof => ("{}: Unexpected end of file", "{}: 文件意外结束"),
+
+        Msg::UnknownSuffix => (
+            "{}: Filename has an unknown suffix, skipping",
+            "{}: 文件名后缀未知，跳过",
+        ),
+        Msg::HasSuffix => (
+            "{}: File already has `{}' suffix, skipping",
+            "{}: 文件已有 `{}` 后缀，跳过",
+        ),
+        Msg::InvalidSuffix => ("{}: Invalid filename suffix", "{}: 无效的文件名后缀"),
+
+        Msg::MemlimitTooLow => (
+            "Memory usage limit is too low for the filter setup; {} bytes are required",
+            "内存使用限制过低，无法满足当前过滤器设置；需要 {} 字节",
+        ),
+        Msg::DictSizeAdjusted => (
+            "Adjusted LZMA{} dictionary size from {} MiB to {} MiB to not exceed the memory usage limit of {} MiB",
+            "将 LZMA{} 字典大小从 {} MiB 调整为 {} MiB 以满足内存限制 {} MiB",
+        ),
+        Msg::TooManyFilters => ("Maximum number of filters is {}", "过滤器数量最多为 {} 个"),
+        Msg::LzmaOnlyLzma1 => (
+            "The .lzma format supports only the LZMA1 filter",
+            ".lzma 格式仅支持 LZMA1 过滤器",
+        ),
+        Msg::Lzma1WithXz => (
+            "LZMA1 cannot be used with the .xz format",
+            "LZMA1 不能与 .xz 格式一起使用",
+        ),
+        Msg::FlushTimeoutFilters => (
+            "The filter chain is incompatible with --flush-timeout",
+            "过滤器链与 --flush-timeout 不兼容",
+        ),
+        Msg::UnsupportedFilters => (
+            "Unsupported filter chain or filter options",
+            "不支持的过滤器链或过滤器选项",
+        ),
+        Msg::RawPresetDiscouraged => (
+            "Using a preset in raw mode is discouraged.",
+            "在 raw 模式下使用预设值是不推荐的",
+        ),
+        Msg::RawPresetVaries => (
+            "The exact options of the presets may vary between software versions.",
+            "预设值的具体选项可能因软件版本而异",
+        ),
+        Msg::DecompressMemory => (
+            "Decompression will need {} MiB of memory.",
+            "解压缩需要 {} MiB 内存",
+        ),
+        Msg::AutoLevelNoSample => (
+            "{}: Cannot take a sample, using preset -{}",
+            "{}: 无法取得样本，使用预设 -{}",
+        ),
+        Msg::AutoLevelChosen => (
+            "{}: Selected -{}{} ({}), {} KiB/s on the sample, ratio {}",
+            "{}: 自动选择 -{}{}（{}），样本上速度 {} KiB/s，压缩率 {}",
+        ),
+        Msg::AutoLevelNoCandidate => (
+            "{}: No candidate meets the target, selected the closest setting",
+            "{}: 没有候选满足目标，已选择最接近的设置",
+        ),
+        Msg::AutoBcj => (
+            "{}: Executable detected, using BCJ filter {} with start offset {}",
+            "{}: 检测到可执行文件，使用 BCJ 过滤器 {}，起始偏移 {}",
+        ),
+        Msg::AutoDelta => ("{}: Selected Delta distance {}", "{}: 自动选择 Delta 距离 {}"),
+        Msg::AutoDeltaNone => (
+            "{}: Data is not suitable for the Delta filter, not using Delta",
+            "{}: 数据不适合 Delta 过滤器，不使用 Delta",
+        ),
+        Msg::DictMismatch => (
+            "{}: The dictionary used for compression differs from the one given with --dict",
+            "{}: 压缩时使用的字典与 --dict 指定的字典不同",
+        ),
+
+        Msg::DictEmpty => ("{}: The dictionary file is empty", "{}: 字典文件为空"),
+        Msg::TrainStdin => (
+            "--train-dict does not support reading samples from standard input",
+            "--train-dict 不支持从标准输入读取样本",
+        ),
+        Msg::TrainNoSamples => (
+            "No samples available for training the dictionary",
+            "没有可用于训练字典的样本",
+        ),
+        Msg::FileExists => ("{}: File exists", "{}: 文件已存在"),
+        Msg::TrainDone => (
+            "{0}: Trained a {3}-byte dictionary from {1} samples ({2} bytes)",
+            "{0}: 从 {1} 个样本（{2} 字节）训练出 {3} 字节的字典",
+        ),
+
+        Msg::FileEmpty => ("{}: File is empty", "{}: 文件为空"),
+        Msg::TooSmallXz => (
+            "{}: Too small to be a valid .xz file",
+            "{}: 文件太小，不是有效的 .xz 文件",
+        ),
+        Msg::TooSmallLzma => (
+            "{}: Too small to be a valid .lzma file",
+            "{}: 文件太小，不是有效的 .lzma 文件",
+        ),
+        Msg::TooSmallLz => (
+            "{}: Too small to be a valid .lz file",
+            "{}: 文件太小，不是有效的 .lz 文件",
+        ),
+        Msg::BlockHeaderInvalid => ("{}: Invalid Block Header", "{}: Block Header 无效"),
+        Msg::BlockHeaderSize => (
+            "{}: Block Header Size is out of range",
+            "{}: Block Header Size 超出范围",
+        ),
+        Msg::LzipMemberSize => (
+            "{}: Invalid member size; trailing data may be present",
+            "{}: 成员大小无效；可能存在尾随数据",
+        ),
+        Msg::ListRaw => (
+            "--list does not support --format=raw (only .xz, .lzma and .lz files)",
+            "--list 不支持 --format=raw (仅支持 .xz、.lzma 和 .lz 文件)",
+        ),
+        Msg::ListStdin => (
+            "--list does not support reading from standard input",
+            "--list 不支持从标准输入读取",
+        ),
+
+        Msg::TarHeaderInvalid => ("Invalid tar header", "tar 头部无效"),
+        Msg::TarUnexpectedEnd => ("Unexpected end of archive", "归档意外结束"),
+        Msg::TarFileShrank => (
+            "{}: File shrank while being archived",
+            "{}: 文件在归档过程中变短",
+        ),
+        Msg::TarNotRegular => (
+            "{}: Not a regular file, directory or symbolic link, skipping",
+            "{}: 不是常规文件、目录或符号链接，跳过",
+        ),
+        Msg::TarNoFiles => (
+            "--tar needs at least one file to archive when creating an archive",
+            "--tar 创建归档时需要至少一个要归档的文件",
+        ),
+        Msg::TarUnsafeName => ("{}: Unsafe member name, skipping", "{}: 成员名称不安全，跳过"),
+        Msg::TarSymlinkInPath => (
+            "{}: Path goes through a symbolic link, skipping",
+            "{}: 路径经过符号链接，跳过",
+        ),
+        Msg::TarExtTooLarge => ("Extended header is too large", "扩展头过大"),
+        Msg::TarUnsupportedType => (
+            "{}: Unsupported member type `{}', skipping",
+            "{}: 不支持的成员类型 '{}'，跳过",
+        ),
+        Msg::TarXzOnly => (
+            "--tar only supports the .xz format",
+            "--tar 仅支持 .xz 格式",
+        ),
+        Msg::TarNeedsArchive => ("--tar needs an archive file name", "--tar 需要一个归档文件名"),
+        Msg::TarSummary => (
+            "{}: {} members, decoded {} Blocks",
+            "{}: {} 个成员，解码了 {} 个 Block",
+        ),
+
+        Msg::SignKeyEmpty => ("{}: The key file is empty", "{}: 密钥文件为空"),
+        Msg::SignNotRegular => (
+            "{}: --sign-key only works with regular files",
+            "{}: --sign-key 只能用于普通文件",
+        ),
+        Msg::SignInvalid => ("{}: Invalid signature file", "{}: 签名文件无效"),
+        Msg::SignMismatch => (
+            "{}: Signature does not match; the file may have been tampered with",
+            "{}: 签名不匹配；文件可能被篡改",
+        ),
+        Msg::SignChanged => (
+            "{}: File was modified while it was being verified",
+            "{}: 文件在验证期间被修改",
+        ),
+
+        Msg::EncryptPrompt => ("Enter passphrase: ", "输入口令："),
+        Msg::EncryptPromptAgain => ("Repeat passphrase: ", "再次输入口令："),
+        Msg::EncryptMismatch => ("Passphrases do not match", "两次输入的口令不一致"),
+        Msg::EncryptEmpty => ("The passphrase is empty", "口令为空"),
+        Msg::EncryptNoKey => (
+            "--encrypt needs a passphrase from the terminal or --key-file",
+            "--encrypt 需要在终端上输入口令或用 --key-file 给出密钥",
+        ),
+        Msg::EncryptRandom => (
+            "Cannot read random data for the encryption key",
+            "无法读取生成加密密钥所需的随机数据",
+        ),
+
+        Msg::VerifyFormat => (
+            "--verify only supports the .xz format",
+            "--verify 仅支持 .xz 格式",
+        ),
+        Msg::VerifyStdin => (
+            "--verify does not support reading from standard input",
+            "--verify 不支持从标准输入读取",
+        ),
+        Msg::VerifyBlockError => ("{}: Block {}: {}", "{}: 第 {} 个 Block：{}"),
+    }
+}
+
+/// 由区域设置的值选择语言
+fn lang_from_locale(value: &str) -> Lang {
+    if value.starts_with("zh") {
+        Lang::ZhCn
+    } else {
+        Lang::En
+    }
+}
+
+/// 初始化消息目录
+///
+/// 应在输出任何消息之前调用。同时调用 setlocale()，使 libc 给出的错误描述
+/// 与所选语言一致。
+pub fn catalog_init() {
+    sys_locale::setlocale_from_env();
+    catalog_lang();
+}
+
+/// 当前使用的消息语言
+pub fn catalog_lang() -> Lang {
+    *LANG.get_or_init(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::En, |value| lang_from_locale(&value))
+    })
+}
+
+/// 消息在当前语言下的文字，未替换占位符
+pub fn tr_str(id: Msg) -> &'static str {
+    let (en, zh) = catalog_entry(id);
+    match catalog_lang() {
+        Lang::En => en,
+        Lang::ZhCn => zh,
+    }
+}
+
+/// 取得消息在当前语言下的文字，并用 args 替换其中的占位符
+pub fn tr(id: Msg, args: &[&dyn Display]) -> String {
+    let text = tr_str(id);
+    let mut out = String::with_capacity(text.len());
+    let mut next = 0;
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let close = match after.find('}') {
+            Some(close) => close,
+            None => {
+                rest = &rest[open..];
+                break;
+            }
+        };
+        let index = match &after[..close] {
+            "" => {
+                next += 1;
+                Some(next - 1)
+            }
+            n => n.parse::<usize>().ok(),
+        };
+        match index.and_then(|i| args.get(i)) {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str(&rest[open..open + close + 2]),
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// io::Error 的本地化描述
+///
+/// 操作系统错误用 strerror() 取得描述，不带 "(os error N)" 后缀。
+pub fn tr_io_error(e: &io::Error) -> String {
+    match e.raw_os_error() {
+        Some(errno) => sys_locale::strerror(errno),
+        None => e.to_string(),
+    }
+}
@@ -8,36 +8,53 @@
 use lazy_static::lazy_static;
 use liblzma::{
     api::{
-        LzmaAction, LzmaCheck, LzmaFilter, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream,
-        LzmaVli, LZMA_CONCATENATED, LZMA_FILTERS_MAX, LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA2,
-        LZMA_IGNORE_CHECK, LZMA_PRESET_DEFAULT, LZMA_PRESET_EXTREME, LZMA_PRESET_LEVEL_MASK,
-        LZMA_TELL_UNSUPPORTED_CHECK,
+        LzmaAction, LzmaCheck, LzmaFilter, LzmaOptionsBcj, LzmaOptionsDedup, LzmaOptionsDelta, LzmaOptionsLzma, LzmaOptionsType, LzmaRet, LzmaStream,
+        LzmaVli, LZMA_ALONE_ALLOW_TRAILING, LZMA_ALONE_ANY_DICT_SIZE, LZMA_ALONE_FORBID_EOPM,
+        LZMA_ALONE_PICKY, LZMA_CONCATENATED, LZMA_FILTERS_MAX, LZMA_FILTER_DEDUP, LZMA_FILTER_DELTA, LZMA_FILTER_ENCRYPT, LZMA_FILTER_LZMA1, LZMA_FILTER_LZMA2,
+        LZMA_IGNORE_CHECK, LZMA_PRESET_DEFAULT, LZMA_PRESET_EXTREME, LZMA_PRESET_FAST,
+        LZMA_PRESET_LEVEL_MASK, LZMA_TELL_UNSUPPORTED_CHECK,
     },
-    check::lzma_check_is_supported,
+    check::{lzma_check_is_supported, lzma_hmac_sha256_update, LzmaHmacSha256State},
     common::{
-        lzma_alone_decoder, lzma_alone_encoder, lzma_code, lzma_lzip_decoder, lzma_memusage,
-        lzma_properties_decode, lzma_raw_decoder, lzma_raw_decoder_memusage, lzma_raw_encoder,
+        lzma_alone_decoder_flags, lzma_alone_encoder, lzma_code, lzma_lzip_decoder, lzma_memusage,
+        lzma_dict_id, lzma_properties_decode, lzma_raw_decoder, lzma_raw_dict_decoder,
+        lzma_raw_dict_encoder, lzma_raw_decoder_memusage, lzma_raw_encoder,
         lzma_raw_encoder_memusage, lzma_stream_decoder, lzma_stream_encoder,
-        lzma_stream_encoder_mt,
+        lzma_stream_encoder_mt, lzma_stream_encoder_mt_chunked, lzma_auto_level,
+        string_conversion::lzma_str_from_filters, LzmaAutoTarget, LZMA_AUTO_SAMPLE_PIECES,
+        LZMA_AUTO_SAMPLE_SIZE, LZMA_DICT_ID_SIZE,
     },
+    delta::lzma_delta_suggest_dist,
     lzma::lzma_lzma_preset,
+    simple::{lzma_bcj_start_offset, lzma_detect_bcj, LZMA_BCJ_DETECT_SIZE},
 };
-use std::sync::{Arc, Mutex, Weak};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, Weak};
+use std::thread;
 
 use crate::{
-    args::{OPT_FORCE, OPT_IGNORE_CHECK, OPT_STDOUT},
+    args::{OPT_AUTO_BCJ, OPT_AUTO_TARGET, OPT_DEDUP, OPT_DELTA, OPT_DICT, OPT_ENCRYPT, OPT_FORCE, OPT_SINGLE_BLOCK, OPT_IGNORE_CHECK, OPT_LZMA_LAX, OPT_LZMA_STRICT, OPT_STDOUT, OPT_VERIFY},
+    catalog::{tr, tr_str, Msg},
     file_io::{
-        io_close, io_fix_src_pos, io_open_dest, io_open_src, io_read, io_write, FilePair, IoBuf,
-        IO_BUFFER_SIZE,
+        io_close, io_fix_src_pos, io_open_dest, io_open_src, io_read, io_read_map, io_read_sample,
+        io_src_truncated, io_write,
+        FilePair, IoBuf, IO_BUFFER_SIZE,
     },
     hardware::{hardware_memlimit_get, hardware_threads_get, hardware_threads_is_mt},
     message::{
-        message, message_error, message_filename, message_mem_needed, message_progress_end,
-        message_progress_start, message_progress_update, message_strm, MessageVerbosity,
+        message, message_capture_begin, message_capture_end, message_error, message_fatal,
+        message_filename, message_mem_needed, message_progress_end, message_progress_json_end,
+        message_progress_json_start, message_progress_json_update, message_progress_start,
+        message_progress_update, message_strm, message_warning, MessageVerbosity,
     },
     mytime::{mytime_set_start_time, OPT_FLUSH_TIMEOUT},
+    options::DELTA_DIST_AUTO,
+    encrypt::encrypt_filter_options,
+    sign::{sign_begin, sign_src_changed, sign_verify_src, sign_write},
     signals::USER_ABORT,
     util::round_up_to_mib,
+    verify::verify_file,
 };
 /// coder_init() 返回值的类型
 #[derive(Debug, PartialEq)]
@@ -70,8 +87,6 @@ pub enum FormatType {
 }
 
 lazy_static! {
-    static ref STRM: Mutex<LzmaStream<'static>> = Mutex::new(LzmaStream::default());
-
     /// 当前操作模式，默认为 MODE_COMPRESS
     pub static ref OPT_MODE: Mutex<OperationMode> = Mutex::new(OperationMode::Compress);
 
@@ -94,12 +109,6 @@ lazy_static! {
     pub static ref FILTERS: Mutex<[LzmaFilter; LZMA_FILTERS_MAX + 1]> =
         Mutex::new(core::array::from_fn(|_| LzmaFilter::default()));
 
-    /// 输入缓冲区
-    pub static ref IN_BUF: Mutex<IoBuf> = Mutex::new(IoBuf::new());
-
-    /// 输出缓冲区
-    pub static ref OUT_BUF: Mutex<IoBuf> = Mutex::new(IoBuf::new());
-
     /// 过滤器数量，零表示使用预设，默认为 0
     pub static ref FILTERS_COUNT: Mutex<u32> = Mutex::new(0);
 
@@ -111,9 +120,54 @@ lazy_static! {
 
     /// 默认完整性检查标志，当使用 --check=CHECK 选项后设为 false，默认为 true
     pub static ref CHECK_DEFAULT: Mutex<bool> = Mutex::new(true);
+}
+
+/// 处理单个文件时使用的编码器/解码器状态
+///
+/// 每个文件使用一个独立的上下文，因此多个文件可以在不同线程中同时处理。
+/// OPT_MODE 和 OPT_FORMAT 在创建时复制一份，处理过程中不再读取全局变量。
+pub struct CoderContext {
+    /// 编码器/解码器流
+    pub strm: LzmaStream<'static>,
+
+    /// 输入缓冲区，放在堆上以保证 strm.next_in 指向的地址不变
+    pub in_buf: Box<IoBuf>,
+
+    /// 当前操作模式
+    pub mode: OperationMode,
+
+    /// 当前文件格式
+    pub format: FormatType,
+
+    /// 允许存在未消费输入的标志，解码成功后生效
+    pub allow_trailing_input: bool,
+
+    /// 是否更新进度指示器，并行处理时为 false
+    pub show_progress: bool,
 
-    /// 允许存在未消费输入的标志，解码成功后生效，默认为 false
-    pub static ref ALLOW_TRAILING_INPUT: Mutex<bool> = Mutex::new(false);
+    /// 给出 --sign-key 时，压缩过程中对写出的数据计算的签名
+    pub sign: Option<LzmaHmacSha256State>,
+}
+
+impl CoderContext {
+    /// 根据当前的全局选项创建新的上下文
+    pub fn new() -> Self {
+        CoderContext {
+            strm: LzmaStream::default(),
+            in_buf: Box::new(IoBuf::new()),
+            mode: OPT_MODE.lock().unwrap().clone(),
+            format: *OPT_FORMAT.lock().unwrap(),
+            allow_trailing_input: false,
+            show_progress: true,
+            sign: None,
+        }
+    }
+}
+
+impl Default for CoderContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 pub fn set_opt_mode(mode: OperationMode) {
     // let mut opt_mode = OPT_MODE.lock().unwrap();
@@ -194,28 +248,6 @@ pub fn get_filters() -> [LzmaFilter; LZMA_FILTERS_MAX + 1] {
     f.clone()
 }
 
-// IN_BUF: IoBuf
-pub fn set_in_buf(buf: IoBuf) {
-    // let mut b = IN_BUF.lock().unwrap();
-    *IN_BUF.lock().unwrap() = buf;
-}
-
-pub fn get_in_buf() -> IoBuf {
-    let b = IN_BUF.lock().unwrap();
-    b.clone()
-}
-
-// OUT_BUF: IoBuf
-pub fn set_out_buf(buf: IoBuf) {
-    // let mut b = OUT_BUF.lock().unwrap();
-    *OUT_BUF.lock().unwrap() = buf;
-}
-
-pub fn get_out_buf() -> IoBuf {
-    let b = OUT_BUF.lock().unwrap();
-    b.clone()
-}
-
 // FILTERS_COUNT: u32
 pub fn set_filters_count(count: u32) {
     // let mut c = FILTERS_COUNT.lock().unwrap();
@@ -260,17 +292,6 @@ pub fn get_check_default() -> bool {
     *c
 }
 
-// ALLOW_TRAILING_INPUT: bool
-pub fn set_allow_trailing_input(val: bool) {
-    // let mut a = ALLOW_TRAILING_INPUT.lock().unwrap();
-    *ALLOW_TRAILING_INPUT.lock().unwrap() = val;
-}
-
-pub fn get_allow_trailing_input() -> bool {
-    let a = ALLOW_TRAILING_INPUT.lock().unwrap();
-    *a
-}
-
 /// 设置完整性检查类型 new_check，并置 check_default 为 false
 pub fn coder_set_check(new_check: LzmaCheck) {
     *CHECK.lock().unwrap() = new_check;
@@ -305,6 +326,12 @@ pub fn coder_set_extreme() {
     forget_filter_chain();
 }
 
+/// 启用快速模式：在预设值上置 LZMA_PRESET_FAST 标志，并忘记过滤器链
+pub fn coder_set_fast() {
+    *PRESET_NUMBER.lock().unwrap() |= LZMA_PRESET_FAST;
+    forget_filter_chain();
+}
+
 /// 添加过滤器到过滤器链
 ///
 /// # 参数
@@ -316,7 +343,7 @@ pub fn coder_set_extreme() {
 pub fn coder_add_filter(id: LzmaVli, options: Option<LzmaOptionsType>) {
     // let mut filters_count = FILTERS_COUNT.lock().unwrap();
     if *FILTERS_COUNT.lock().unwrap() == LZMA_FILTERS_MAX.try_into().unwrap() {
-        panic!("过滤器数量已达到最大值（最多支持 {} 个）", LZMA_FILTERS_MAX);
+        message_fatal(&tr(Msg::TooManyFilters, &[&LZMA_FILTERS_MAX]), format_args!(""));
     }
 
     let mut filters = FILTERS.lock().unwrap();
@@ -337,9 +364,8 @@ pub fn coder_add_filter(id: LzmaVli, options: Option<LzmaOptionsType>) {
 /// # 行为
 /// 输出错误信息并退出程序
 pub fn memlimit_too_small(memory_usage: u64) -> ! {
-    eprintln!("错误：内存使用限制过低，无法满足当前过滤器设置");
-    eprintln!("所需内存：{} 字节", memory_usage);
-    std::process::exit(1);
+    message_fatal(&tr(Msg::MemlimitTooLow, &[&memory_usage]), format_args!(""));
+    unreachable!();
 }
 
 /// 设置压缩参数
@@ -355,12 +381,24 @@ pub fn coder_set_compression_settings() {
         }
     }
 
-    // 如果未设置过滤器，则使用预设值
-    if get_filters_count() == 0 {
+    // 如果未设置过滤器或链中只有 BCJ 过滤器，则在末尾加上预设值
+    let count = get_filters_count() as usize;
+    let filters = get_filters();
+    if count == 0
+        || (filters[count - 1].id != LZMA_FILTER_LZMA1 && filters[count - 1].id != LZMA_FILTER_LZMA2)
+    {
         if get_opt_format() == FormatType::Raw {
             // 在 raw 模式下使用预设值是不推荐的
-            println!("警告：在 raw 模式下使用预设值是不推荐的");
-            println!("警告：预设值的具体选项可能因软件版本而异");
+            message(
+                MessageVerbosity::Warning,
+                tr_str(Msg::RawPresetDiscouraged),
+                format_args!(""),
+            );
+            message(
+                MessageVerbosity::Warning,
+                tr_str(Msg::RawPresetVaries),
+                format_args!(""),
+            );
         }
 
         // 获取 LZMA1 或 LZMA2 的预设值
@@ -372,14 +410,48 @@ pub fn coder_set_compression_settings() {
 
         // 使用 LZMA2，除非格式是 LZMA1
         let mut filters = get_filters();
-        filters[0].id = if get_opt_format() == FormatType::Lzma {
+        filters[count].id = if get_opt_format() == FormatType::Lzma {
             LZMA_FILTER_LZMA1
         } else {
             LZMA_FILTER_LZMA2
         };
-        filters[0].options = Some(LzmaOptionsType::LzmaOptionsLzma(opt_lzma));
+        filters[count].options = Some(LzmaOptionsType::LzmaOptionsLzma(opt_lzma));
+        set_filters(filters);
+        set_filters_count(count as u32 + 1);
+    }
+
+    // 长距离去重过滤器放在过滤器链的最前面，让 LZMA2 压缩去重后的数据
+    if let Some(chunk_log) = *OPT_DEDUP.lock().unwrap() {
+        let mut filters = get_filters();
+        let count = get_filters_count() as usize;
+        if count == LZMA_FILTERS_MAX {
+            message_fatal(&tr(Msg::TooManyFilters, &[&LZMA_FILTERS_MAX]), format_args!(""));
+        }
+        filters[..=count].rotate_right(1);
+        filters[0].id = LZMA_FILTER_DEDUP;
+        filters[0].options = Some(LzmaOptionsType::Dedup(LzmaOptionsDedup {
+            chunk_log,
+            ..Default::default()
+        }));
         set_filters(filters);
-        set_filters_count(1);
+        set_filters_count(count as u32 + 1);
+    }
+
+    // 加密过滤器代替末尾的 LZMA2，先用同样的选项压缩再加密
+    if *OPT_ENCRYPT.lock().unwrap() && get_opt_mode() == OperationMode::Compress {
+        let mut filters = get_filters();
+        let last = &mut filters[get_filters_count() as usize - 1];
+        if let (LZMA_FILTER_LZMA2, Some(LzmaOptionsType::LzmaOptionsLzma(opt))) =
+            (last.id, last.options.take())
+        {
+            last.id = LZMA_FILTER_ENCRYPT;
+            last.options = Some(encrypt_filter_options(opt));
+            set_filters(filters);
+
+            // Block 的完整性检查以明文保存，会泄露未压缩数据的指纹。加密过滤器
+            // 的认证标签已经保证了数据的完整性，所以不使用完整性检查。
+            *CHECK.lock().unwrap() = LzmaCheck::None;
+        }
     }
 
     // 终止过滤器数组
@@ -391,7 +463,7 @@ pub fn coder_set_compression_settings() {
     if get_opt_format() == FormatType::Lzma
         && (get_filters_count() != 1 || get_filters()[0].id != LZMA_FILTER_LZMA1)
     {
-        panic!(".lzma 格式仅支持 LZMA1 过滤器");
+        message_fatal(tr_str(Msg::LzmaOnlyLzma1), format_args!(""));
     }
 
     // 如果使用 .xz 格式，则确保没有 LZMA1 过滤器
@@ -399,7 +471,7 @@ pub fn coder_set_compression_settings() {
         for i in 0..get_filters_count() {
             let filters = &get_filters(); // 使用引用避免移动
             if filters[i as usize].id == LZMA_FILTER_LZMA1 {
-                panic!("LZMA1 不能与 .xz 格式一起使用");
+                message_fatal(tr_str(Msg::Lzma1WithXz), format_args!(""));
             }
         }
     }
@@ -413,7 +485,7 @@ pub fn coder_set_compression_settings() {
             let filters = &get_filters(); // 再次使用引用
             match filters[i as usize].id {
                 33 | 0x03 => (),
-                _ => panic!("过滤器链与 --flush-timeout 不兼容"),
+                _ => message_fatal(tr_str(Msg::FlushTimeoutFilters), format_args!("")),
             }
         }
     }
@@ -441,7 +513,7 @@ pub fn coder_set_compression_settings() {
     }
 
     if memory_usage == u64::MAX {
-        panic!("不支持的过滤器链或过滤器选项");
+        message_fatal(tr_str(Msg::UnsupportedFilters), format_args!(""));
     }
 
     // println!("调试：所需内存：{} 字节", memory_usage);
@@ -451,8 +523,8 @@ pub fn coder_set_compression_settings() {
         if decmem != u64::MAX {
             message(
                 MessageVerbosity::Debug,
-                "调试：解压缩需要 {} MiB 内存",
-                format_args!("{}", round_up_to_mib(decmem)),
+                &tr(Msg::DecompressMemory, &[&round_up_to_mib(decmem)]),
+                format_args!(""),
             );
         }
     }
@@ -561,27 +633,177 @@ pub fn coder_set_compression_settings() {
         opt.dict_size -= 1 << 20;
     }
 
-    println!(
-        "警告：将 LZMA{} 字典大小从 {} MiB 调整为 {} MiB 以满足内存限制 {} MiB",
-        if filters[i as usize].id == LZMA_FILTER_LZMA2 {
-            '2'
-        } else {
-            '1'
-        },
-        orig_dict_size >> 20,
-        opt.dict_size >> 20,
-        round_up_to_mib(memory_limit)
+    message(
+        MessageVerbosity::Warning,
+        &tr(
+            Msg::DictSizeAdjusted,
+            &[
+                &if filters[i as usize].id == LZMA_FILTER_LZMA2 {
+                    '2'
+                } else {
+                    '1'
+                },
+                &(orig_dict_size >> 20),
+                &(opt.dict_size >> 20),
+                &round_up_to_mib(memory_limit),
+            ],
+        ),
+        format_args!(""),
+    );
+}
+
+/// --auto-level：在源文件的样本上选择预设和过滤器链，替换当前的过滤器链
+///
+/// 无法取得样本（例如从标准输入读取）时使用 -0 到 -9 给出的预设。
+fn coder_auto_level(pair: &FilePair, target: LzmaAutoTarget) {
+    // 多线程压缩时各线程分别压缩不同的块，每个线程只需达到目标速度的一部分
+    let target = match target {
+        LzmaAutoTarget::Speed(speed) if hardware_threads_is_mt() => {
+            LzmaAutoTarget::Speed(speed.div_ceil(hardware_threads_get() as u64))
+        }
+        target => target,
+    };
+
+    let src_name = pair.src_name.as_deref().unwrap_or("(stdin)");
+    let choice = io_read_sample(pair, LZMA_AUTO_SAMPLE_SIZE, LZMA_AUTO_SAMPLE_PIECES)
+        .and_then(|sample| lzma_auto_level(&sample, target).ok());
+
+    set_filters_count(0);
+    match choice {
+        Some(ref choice) => {
+            let mut filters = get_filters();
+            for (i, filter) in choice.filters.iter().enumerate() {
+                filters[i] = filter.clone();
+            }
+            set_filters(filters);
+            set_filters_count(choice.filters.len() as u32 - 1);
+        }
+        None => message(
+            MessageVerbosity::Verbose,
+            &tr(
+                Msg::AutoLevelNoSample,
+                &[&src_name, &(get_preset_number() & LZMA_PRESET_LEVEL_MASK)],
+            ),
+            format_args!(""),
+        ),
+    }
+
+    // 检查过滤器链并按内存限制调整字典大小
+    coder_set_compression_settings();
+
+    let choice = match choice {
+        Some(choice) => choice,
+        None => return,
+    };
+
+    let mut chain = None;
+    lzma_str_from_filters(&mut chain, &choice.filters, 0);
+    message(
+        MessageVerbosity::Verbose,
+        &tr(
+            Msg::AutoLevelChosen,
+            &[
+                &src_name,
+                &(choice.preset & LZMA_PRESET_LEVEL_MASK),
+                &if choice.preset & LZMA_PRESET_FAST != 0 {
+                    " --fast"
+                } else {
+                    ""
+                },
+                &chain.unwrap_or_default(),
+                &(choice.speed >> 10),
+                &format!("{:.3}", choice.ratio),
+            ],
+        ),
+        format_args!(""),
     );
+    if !choice.target_met {
+        message(
+            MessageVerbosity::Warning,
+            &tr(Msg::AutoLevelNoCandidate, &[&src_name]),
+            format_args!(""),
+        );
+    }
+}
+
+/// --auto-bcj、--delta=auto：按源文件的内容为每个文件重新生成过滤器链
+///
+/// 可执行文件在链前加上对应的 BCJ 过滤器；Delta 距离由样本的熵估计选出，
+/// 样本不适合 Delta 时不使用 Delta 过滤器。
+fn coder_file_filters(pair: &FilePair) {
+    let src_name = pair.src_name.as_deref().unwrap_or("(stdin)");
+    let sample = io_read_sample(pair, LZMA_AUTO_SAMPLE_SIZE, LZMA_AUTO_SAMPLE_PIECES);
+
+    set_filters_count(0);
+
+    if *OPT_AUTO_BCJ.lock().unwrap() {
+        // 样本的第一段从文件开头开始，足以包含可执行文件头部
+        let detected = sample.as_deref().and_then(|sample| {
+            let header = &sample[..sample.len().min(LZMA_BCJ_DETECT_SIZE)];
+            lzma_detect_bcj(header).map(|id| (id, lzma_bcj_start_offset(header, id)))
+        });
+        if let Some((id, start_offset)) = detected {
+            coder_add_filter(
+                id,
+                Some(LzmaOptionsType::Bcj(LzmaOptionsBcj { start_offset })),
+            );
+            message(
+                MessageVerbosity::Verbose,
+                &tr(
+                    Msg::AutoBcj,
+                    &[
+                        &src_name,
+                        &format!("0x{:02X}", id),
+                        &format!("0x{:X}", start_offset),
+                    ],
+                ),
+                format_args!(""),
+            );
+        }
+    }
+
+    let delta = *OPT_DELTA.lock().unwrap();
+    if let Some(dist) = delta {
+        let dist = if dist == DELTA_DIST_AUTO {
+            let suggested = sample.as_deref().and_then(lzma_delta_suggest_dist);
+            match suggested {
+                Some(dist) => message(
+                    MessageVerbosity::Verbose,
+                    &tr(Msg::AutoDelta, &[&src_name, &dist]),
+                    format_args!(""),
+                ),
+                None => message(
+                    MessageVerbosity::Verbose,
+                    &tr(Msg::AutoDeltaNone, &[&src_name]),
+                    format_args!(""),
+                ),
+            }
+            suggested
+        } else {
+            Some(dist as u32)
+        };
+        if let Some(dist) = dist {
+            coder_add_filter(
+                LZMA_FILTER_DELTA,
+                Some(LzmaOptionsType::Delta(LzmaOptionsDelta {
+                    dist,
+                    ..Default::default()
+                })),
+            );
+        }
+    }
+
+    coder_set_compression_settings();
 }
 
 /// 判断输入数据是否为 XZ 格式
-fn is_format_xz(in_buf: &IoBuf, avail_in: usize) -> bool {
+pub fn is_format_xz(in_buf: &IoBuf, avail_in: usize) -> bool {
     const MAGIC: [u8; 6] = [0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00];
     avail_in >= MAGIC.len() && in_buf.data[..MAGIC.len()] == MAGIC
 }
 
 /// 判断输入数据是否为 LZMA 格式
-fn is_format_lzma(in_buf: &IoBuf, avail_in: usize) -> bool {
+pub fn is_format_lzma(in_buf: &IoBuf, avail_in: usize) -> bool {
     if avail_in < 13 {
         return false;
     }
@@ -595,6 +817,11 @@ fn is_format_lzma(in_buf: &IoBuf, avail_in: usize) -> bool {
         return false;
     }
 
+    // --lzma-lax 时只要求属性字节有效，字典大小和未压缩大小都不检查
+    if *OPT_LZMA_LAX.lock().unwrap() {
+        return true;
+    }
+
     if let Some(LzmaOptionsType::LzmaOptionsLzma(opt)) = filter.options {
         let dict_size = opt.dict_size;
         if dict_size != u32::MAX {
@@ -623,21 +850,34 @@ fn is_format_lzma(in_buf: &IoBuf, avail_in: usize) -> bool {
     true
 }
 
+/// 根据 --lzma-strict/--lzma-lax 返回 .lzma 解码器的标志
+fn lzma_alone_flags() -> u32 {
+    if *OPT_LZMA_STRICT.lock().unwrap() {
+        LZMA_ALONE_PICKY | LZMA_ALONE_FORBID_EOPM
+    } else if *OPT_LZMA_LAX.lock().unwrap() {
+        LZMA_ALONE_ANY_DICT_SIZE | LZMA_ALONE_ALLOW_TRAILING
+    } else {
+        0
+    }
+}
+
 /// 判断输入数据是否为 LZIP 格式
-fn is_format_lzip(in_buf: &IoBuf, avail_in: usize) -> bool {
+pub fn is_format_lzip(in_buf: &IoBuf, avail_in: usize) -> bool {
     const MAGIC: [u8; 4] = [0x4C, 0x5A, 0x49, 0x50];
     avail_in >= MAGIC.len() && in_buf.data[..MAGIC.len()] == MAGIC
 }
 
 /// 初始化编码器/解码器
-fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderInitRet {
+fn coder_init(pair: &FilePair, ctx: &mut CoderContext) -> CoderInitRet {
     let mut ret = LzmaRet::ProgError;
+    let strm = &mut ctx.strm;
+    let in_buf = &*ctx.in_buf;
 
     // 初始化允许尾部输入标志
-    *ALLOW_TRAILING_INPUT.lock().unwrap() = false;
+    ctx.allow_trailing_input = false;
 
-    if *OPT_MODE.lock().unwrap() == OperationMode::Compress {
-        match *OPT_FORMAT.lock().unwrap() {
+    if ctx.mode == OperationMode::Compress {
+        match ctx.format {
             FormatType::Auto => {
                 // args.c 确保不会进入此分支
                 panic!("自动格式不应在压缩模式下使用");
@@ -646,7 +886,14 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
                 let filters = FILTERS.lock().unwrap();
                 let check = CHECK.lock().unwrap().clone();
                 let filters_slice: &[LzmaFilter] = &*filters;
-                if hardware_threads_is_mt() {
+                if hardware_threads_is_mt() && *OPT_SINGLE_BLOCK.lock().unwrap() {
+                    ret = lzma_stream_encoder_mt_chunked(
+                        strm,
+                        filters_slice,
+                        check,
+                        hardware_threads_get(),
+                    );
+                } else if hardware_threads_is_mt() {
                     ret =
                         lzma_stream_encoder_mt(strm, filters_slice, check, hardware_threads_get());
                 } else {
@@ -666,7 +913,10 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
                 panic!("LZIP 格式不应在压缩模式下使用");
             }
             FormatType::Raw => {
-                ret = lzma_raw_encoder(strm, &*FILTERS.lock().unwrap());
+                ret = match OPT_DICT.lock().unwrap().as_deref() {
+                    Some(dict) => lzma_raw_dict_encoder(strm, &*FILTERS.lock().unwrap(), dict),
+                    None => lzma_raw_encoder(strm, &*FILTERS.lock().unwrap()),
+                };
             }
         }
     } else {
@@ -681,7 +931,7 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
 
         // 如果启用单流模式，则允许尾部输入
         if *OPT_SINGLE_STREAM.lock().unwrap() {
-            *ALLOW_TRAILING_INPUT.lock().unwrap() = true;
+            ctx.allow_trailing_input = true;
         } else {
             flags |= LZMA_CONCATENATED;
         }
@@ -689,7 +939,7 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
         // 使用 FORMAT_AUTO 表示未知文件格式，可能启用直通模式
         let mut init_format = FormatType::Auto;
 
-        match *OPT_FORMAT.lock().unwrap() {
+        match ctx.format {
             FormatType::Auto => {
                 // 优先检查 .xz 格式，因为 .lzma 检测更复杂（无魔数）
                 if is_format_xz(in_buf, strm.avail_in.get()) {
@@ -722,7 +972,7 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
 
         match init_format {
             FormatType::Auto => {
-                if *OPT_MODE.lock().unwrap() == OperationMode::Decompress
+                if ctx.mode == OperationMode::Decompress
                     && *OPT_STDOUT.lock().unwrap()
                     && *OPT_FORCE.lock().unwrap()
                 {
@@ -742,10 +992,14 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
                 );
             }
             FormatType::Lzma => {
-                ret = lzma_alone_decoder(strm, hardware_memlimit_get(OperationMode::Decompress));
+                ret = lzma_alone_decoder_flags(
+                    strm,
+                    hardware_memlimit_get(OperationMode::Decompress),
+                    lzma_alone_flags(),
+                );
             }
             FormatType::Lzip => {
-                *ALLOW_TRAILING_INPUT.lock().unwrap() = true;
+                ctx.allow_trailing_input = true;
                 ret = lzma_lzip_decoder(
                     strm,
                     hardware_memlimit_get(OperationMode::Decompress),
@@ -754,7 +1008,27 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
             }
             FormatType::Raw => {
                 // 内存使用已在 coder_set_compression_settings 中检查
-                ret = lzma_raw_decoder(strm, &*FILTERS.lock().unwrap());
+                match OPT_DICT.lock().unwrap().as_deref() {
+                    Some(dict) => {
+                        // 先比较开头的字典 ID，以便给出比“不支持的选项”更明确的错误信息
+                        let avail = strm.avail_in.get();
+                        if avail >= LZMA_DICT_ID_SIZE
+                            && in_buf.data[..LZMA_DICT_ID_SIZE]
+                                != lzma_dict_id(dict).to_le_bytes()
+                        {
+                            message_error(
+                                &tr(
+                                    Msg::DictMismatch,
+                                    &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+                                ),
+                                format_args!(""),
+                            );
+                            return CoderInitRet::Error;
+                        }
+                        ret = lzma_raw_dict_decoder(strm, &*FILTERS.lock().unwrap(), dict);
+                    }
+                    None => ret = lzma_raw_decoder(strm, &*FILTERS.lock().unwrap()),
+                }
             }
         }
 
@@ -766,10 +1040,13 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
                 ret = lzma_code(strm, LzmaAction::Run);
                 ret == LzmaRet::UnsupportedCheck
             } {
-                println!(
-                    "{} {} ",
-                    &pair.src_name.clone().unwrap(),
-                    &message_strm(ret).to_string()
+                message_warning(
+                    &format!(
+                        "{}: {}",
+                        pair.src_name.as_deref().unwrap_or("(unknown)"),
+                        message_strm(ret)
+                    ),
+                    &[],
                 );
             }
 
@@ -780,6 +1057,14 @@ fn coder_init(pair: &FilePair, strm: &mut LzmaStream, in_buf: &IoBuf) -> CoderIn
     }
 
     if ret != LzmaRet::Ok {
+        message_error(
+            &format!(
+                "{}: {}",
+                pair.src_name.as_deref().unwrap_or("(unknown)"),
+                message_strm(ret)
+            ),
+            format_args!(""),
+        );
         if ret == LzmaRet::MemlimitError {
             message_mem_needed(MessageVerbosity::Error, lzma_memusage(Some(&mut *strm)));
         }
@@ -839,14 +1124,22 @@ fn split_block(block_remaining: &mut u64, next_block_remaining: &mut u64, list_p
 ///
 /// # 返回值
 /// 如果写入成功，返回 `false`；如果写入失败，返回 `true`
-fn coder_write_output(pair: &mut FilePair, strm: &mut LzmaStream) -> bool {
+fn coder_write_output(
+    pair: &mut FilePair,
+    strm: &mut LzmaStream,
+    mode: &OperationMode,
+    sign: &mut Option<LzmaHmacSha256State>,
+) -> bool {
     let written_size = IO_BUFFER_SIZE - strm.avail_out.get();
 
-    if *OPT_MODE.lock().unwrap() != OperationMode::Test {
+    if *mode != OperationMode::Test {
         let next_out_ref = strm.next_out.borrow();
         if io_write(pair, &next_out_ref[..written_size], written_size) {
             return true;
         }
+        if let Some(hmac) = sign.as_mut() {
+            lzma_hmac_sha256_update(hmac, &next_out_ref[..written_size]);
+        }
     }
 
     // 复用已有的 Vec，避免每次 to_vec() 分配新内存
@@ -864,7 +1157,10 @@ fn coder_write_output(pair: &mut FilePair, strm: &mut LzmaStream) -> bool {
 }
 
 /// 执行正常的编码/解码操作
-fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf) -> bool {
+fn coder_normal(pair: &mut FilePair, ctx: &mut CoderContext) -> bool {
+    let strm = &mut ctx.strm;
+    let in_buf = &mut *ctx.in_buf;
+
     // 编码器需要知道何时已经提供了所有输入。
     // 解码器在使用 LZMA_CONCATENATED 时也需要知道。
     // 需要在这里检查 src_eof，因为如果是解压缩，第一个输入块已经被读取，
@@ -891,9 +1187,7 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
     let mut list_pos = 0;
 
     // 处理单线程模式下的 --block-size 和 --block-list 的第一步。
-    if *OPT_MODE.lock().unwrap() == OperationMode::Compress
-        && *OPT_FORMAT.lock().unwrap() == FormatType::Xz
-    {
+    if ctx.mode == OperationMode::Compress && ctx.format == FormatType::Xz {
         // 在线程模式下，--block-size 不会做任何事情，
         // 因为线程编码器会负责将块分割为固定大小。
         if !hardware_threads_is_mt() && *OPT_BLOCK_SIZE.lock().unwrap() > 0 {
@@ -930,25 +1224,34 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
 
     while !*USER_ABORT.lock().unwrap() {
         if strm.avail_in.get() == 0 && action == LzmaAction::Run {
-            let read_size = io_read(
-                pair,
-                in_buf,
-                block_remaining
-                    .min(IO_BUFFER_SIZE.try_into().unwrap())
-                    .try_into()
-                    .unwrap(),
-            );
-            // 直接使用 in_buf.data 的切片，零拷贝。
-            // 安全性：in_buf 是全局 Mutex 内的 IoBuf，MutexGuard 在 coder_run 期间一直持有。
-            let in_buf_data: &'static [u8] =
-                unsafe { std::mem::transmute::<&[u8], &'static [u8]>(&in_buf.data[..read_size]) };
-            strm.next_in = in_buf_data;
+            let map_size = block_remaining.min(IO_BUFFER_SIZE as u64);
+            let read_size = if let Some(data) = io_read_map(pair, map_size) {
+                // 映射的文件不经过 in_buf。每次给出的数据量与 io_read() 相同，
+                // 这样每处理这么多输入就会检查一次 USER_ABORT。
+                // 安全性：映射在 io_close() 之前一直有效。
+                strm.next_in = unsafe { std::mem::transmute::<&[u8], &'static [u8]>(data) };
+                strm.next_in.len()
+            } else {
+                let read_size = io_read(
+                    pair,
+                    in_buf,
+                    block_remaining
+                        .min(IO_BUFFER_SIZE.try_into().unwrap())
+                        .try_into()
+                        .unwrap(),
+                );
+                if read_size == usize::MAX {
+                    break;
+                }
+                // 直接使用 in_buf.data 的切片，零拷贝。
+                // 安全性：in_buf 是 CoderContext 持有的堆分配 IoBuf，在整个文件处理期间有效。
+                strm.next_in = unsafe {
+                    std::mem::transmute::<&[u8], &'static [u8]>(&in_buf.data[..read_size])
+                };
+                read_size
+            };
             strm.avail_in.set(read_size);
 
-            if strm.avail_in.get() == usize::MAX {
-                break;
-            }
-
             if pair.src_eof {
                 action = LzmaAction::Finish;
             } else if block_remaining != u64::MAX {
@@ -969,11 +1272,16 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
 
         ret = lzma_code(strm, action_t);
 
+        // 映射的文件被截断时读到的是零，不能写出由此得到的数据
+        if io_src_truncated(pair) {
+            break;
+        }
+
         // 如果输出缓冲区有数据，则写出。
         // 使用 < IO_BUFFER_SIZE 来确保对部分填充的缓冲区的刷新，
         // 这对多线程编码器在进入 Index 和 StreamFooter 阶段前确保有干净的输出缓冲区至关重要。
         if strm.avail_out.get() < IO_BUFFER_SIZE {
-            if coder_write_output(pair, strm) {
+            if coder_write_output(pair, strm, &ctx.mode, &mut ctx.sign) {
                 break;
             }
         }
@@ -983,7 +1291,7 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
         {
             if action == LzmaAction::SyncFlush {
                 // 刷新完成。立即写出待处理的数据，以便读取端可以解压缩所有已压缩的数据。
-                if coder_write_output(pair, strm) {
+                if coder_write_output(pair, strm, &ctx.mode, &mut ctx.sign) {
                     break;
                 }
 
@@ -1016,13 +1324,13 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
                 // 即使出现问题，也写出剩余的字节，因为这样用户可以获得尽可能多的数据，
                 // 这在尝试从损坏的文件中获取一些有用数据时可能很有用。
 
-                if coder_write_output(pair, strm) {
+                if coder_write_output(pair, strm, &ctx.mode, &mut ctx.sign) {
                     break;
                 }
             }
 
             if ret == LzmaRet::StreamEnd {
-                if *ALLOW_TRAILING_INPUT.lock().unwrap() {
+                if ctx.allow_trailing_input {
                     io_fix_src_pos(pair, strm.avail_in.get());
                     success = true;
                     break;
@@ -1067,10 +1375,17 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
                     format_args!(""),
                 );
             } else {
-                println!("警告：{:#?}: {}", pair.src_name, message_strm(ret));
+                message_warning(
+                    &format!(
+                        "{}: {}",
+                        pair.src_name.as_deref().unwrap_or("(unknown)"),
+                        message_strm(ret)
+                    ),
+                    &[],
+                );
 
                 // 压缩时，所有可能的错误都会将 stop 设置为 true。
-                assert!(*OPT_MODE.lock().unwrap() != OperationMode::Compress);
+                assert!(ctx.mode != OperationMode::Compress);
             }
 
             if ret == LzmaRet::MemlimitError {
@@ -1086,7 +1401,10 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
         }
 
         // 在某些条件下显示进度信息。
-        message_progress_update();
+        if ctx.show_progress {
+            message_progress_update();
+        }
+        message_progress_json_update(strm, false);
     }
 
     success
@@ -1094,7 +1412,10 @@ fn coder_normal(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf)
 
 /// 直通模式处理函数
 /// 将输入数据直接写入输出文件，不进行压缩或解压缩
-fn coder_passthru(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf) -> bool {
+fn coder_passthru(pair: &mut FilePair, ctx: &mut CoderContext) -> bool {
+    let strm = &mut ctx.strm;
+    let in_buf = &mut *ctx.in_buf;
+
     while strm.avail_in.get() != 0 {
         // 如果用户中断操作，则返回失败
         if *USER_ABORT.lock().unwrap() {
@@ -1118,7 +1439,10 @@ fn coder_passthru(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf
         let total_in = strm.total_in.get();
         strm.total_out.set(total_in);
 
-        message_progress_update();
+        if ctx.show_progress {
+            message_progress_update();
+        }
+        message_progress_json_update(strm, true);
 
         strm.avail_in.set(io_read(pair, in_buf, IO_BUFFER_SIZE));
         if strm.avail_in.get() == usize::MAX {
@@ -1131,77 +1455,173 @@ fn coder_passthru(pair: &mut FilePair, strm: &mut LzmaStream, in_buf: &mut IoBuf
 
 /// 运行编码器/解码器
 pub fn coder_run(filename: &str) {
-    // 重置全局编码器状态，确保多文件处理时不会残留上次的状态
-    *STRM.lock().unwrap() = LzmaStream::default();
-    *IN_BUF.lock().unwrap() = IoBuf::new();
+    // 每个文件使用新的上下文，确保多文件处理时不会残留上次的状态
+    let mut ctx = CoderContext::new();
 
     // 设置并打印文件名，用于进度信息
     message_filename(filename);
 
+    coder_run_ctx(&mut ctx, filename);
+}
+
+/// 使用给定的上下文处理单个文件
+///
+/// 不调用 message_filename()，调用者负责设置当前文件名。
+pub fn coder_run_ctx(ctx: &mut CoderContext, filename: &str) {
     // 尝试打开输入文件
-    let mut pair = io_open_src(filename);
-    if pair.is_none() {
-        return;
-    }
-    let mut pair = pair.unwrap();
+    let mut pair = match io_open_src(filename) {
+        Some(p) => p,
+        None => return,
+    };
 
     // 假设操作会失败
     let mut success = false;
 
-    // 锁定全局编码器状态，在整个文件处理期间持有锁。
-    // 由于 coder_run → coder_init → coder_normal/coder_passthru 是单线程调用链，
-    // 不存在并发访问，持锁不会造成死锁。
-    let mut strm = STRM.lock().unwrap();
-    let mut in_buf = IN_BUF.lock().unwrap();
+    if ctx.mode == OperationMode::Compress {
+        // --auto-level 自己会尝试 BCJ 过滤器
+        let auto_target = *OPT_AUTO_TARGET.lock().unwrap();
+        if let Some(target) = auto_target {
+            coder_auto_level(&pair, target);
+        } else if *OPT_AUTO_BCJ.lock().unwrap()
+            || *OPT_DELTA.lock().unwrap() == Some(DELTA_DIST_AUTO)
+        {
+            coder_file_filters(&pair);
+        }
 
-    if *OPT_MODE.lock().unwrap() == OperationMode::Compress {
         // 压缩模式下，初始化输入缓冲区为空
-        strm.next_in = &[];
-        strm.avail_in.set(0);
+        ctx.strm.next_in = &[];
+        ctx.strm.avail_in.set(0);
+        ctx.sign = sign_begin();
+    } else if sign_verify_src(&mut pair) {
+        // 签名不匹配时不解压
+        ctx.strm.avail_in.set(usize::MAX);
     } else {
         // 解压缩模式下，读取第一块输入数据以检测文件类型
-        let read_size = io_read(&mut pair, &mut in_buf, IO_BUFFER_SIZE);
-        // 直接使用 in_buf.data 的切片，零拷贝
-        let in_buf_data: &'static [u8] =
-            unsafe { std::mem::transmute::<&[u8], &'static [u8]>(&in_buf.data[..read_size]) };
-        strm.next_in = in_buf_data;
-        strm.avail_in.set(read_size);
+        let read_size = io_read(&mut pair, &mut ctx.in_buf, IO_BUFFER_SIZE);
+        if read_size == usize::MAX {
+            ctx.strm.avail_in.set(read_size);
+        } else {
+            // 直接使用 in_buf.data 的切片，零拷贝
+            let in_buf_data: &'static [u8] = unsafe {
+                std::mem::transmute::<&[u8], &'static [u8]>(&ctx.in_buf.data[..read_size])
+            };
+            ctx.strm.next_in = in_buf_data;
+            ctx.strm.avail_in.set(read_size);
+        }
     }
 
-    if strm.avail_in.get() != usize::MAX {
+    if ctx.strm.avail_in.get() != usize::MAX {
         // 初始化编码器/解码器，检测文件格式并检查内存使用情况
-        let init_ret = coder_init(&pair, &mut strm, &in_buf);
-
-        if init_ret != CoderInitRet::Error && !*USER_ABORT.lock().unwrap() {
-            // 测试模式下不打开目标文件
-            let open_ret = io_open_dest(&mut pair);
-            let om: OperationMode = (*OPT_MODE.lock().unwrap()).clone();
-            if om == OperationMode::Test || !open_ret {
-                // 记录当前时间，用于进度指示器
+        let init_ret = coder_init(&pair, ctx);
+
+        // 测试模式下不打开目标文件
+        if init_ret != CoderInitRet::Error
+            && !*USER_ABORT.lock().unwrap()
+            && (ctx.mode == OperationMode::Test || !io_open_dest(&mut pair))
+        {
+            // 记录当前时间，用于进度指示器
+            if ctx.show_progress {
                 mytime_set_start_time();
+            }
 
-                // 初始化进度指示器
-                let is_passthru = init_ret == CoderInitRet::PassThru;
-                let in_size = if pair.src_st.st_size <= 0 {
-                    0
-                } else {
-                    pair.src_st.st_size as u64
-                };
-                message_progress_start(&mut strm, is_passthru, in_size);
+            // 初始化进度指示器
+            let is_passthru = init_ret == CoderInitRet::PassThru;
+            let in_size = if pair.src_st.st_size <= 0 {
+                0
+            } else {
+                pair.src_st.st_size as u64
+            };
+            if ctx.show_progress {
+                message_progress_start(&mut ctx.strm, is_passthru, in_size);
+            }
+            message_progress_json_start(
+                pair.src_name.as_deref().unwrap_or("(unknown)"),
+                &ctx.mode,
+                in_size,
+            );
 
-                // 执行实际的编码/解码或直通操作
-                if is_passthru {
-                    success = coder_passthru(&mut pair, &mut strm, &mut in_buf);
-                } else {
-                    success = coder_normal(&mut pair, &mut strm, &mut in_buf);
+            // 执行实际的编码/解码或直通操作
+            if is_passthru {
+                success = coder_passthru(&mut pair, ctx);
+            } else {
+                success = coder_normal(&mut pair, ctx);
+            }
+
+            // 解压的数据必须是验证签名时读到的数据
+            if success && sign_src_changed(&pair) {
+                success = false;
+            }
+
+            // 目标文件写完后在它旁边写入签名
+            if success {
+                if let Some(hmac) = ctx.sign.take() {
+                    success = !sign_write(&pair, hmac);
                 }
+            }
 
-                // 结束进度指示器
+            // 结束进度指示器
+            if ctx.show_progress {
                 message_progress_end(success);
             }
+            message_progress_json_end(&ctx.mode, success, &ctx.strm);
         }
     }
 
     // 关闭文件对，根据操作是否成功决定是否删除源文件或目标文件
     io_close(&mut pair, success);
 }
+
+/// 使用 jobs 个工作线程并行测试或解压缩多个文件
+///
+/// 每个文件的诊断信息在工作线程中先被捕获，再由调用线程按文件在
+/// filenames 中的顺序输出，因此不同文件的消息不会交错。
+/// 退出状态由 set_exit_status() 在所有线程间共同累计。
+pub fn coder_run_parallel(filenames: &[String], jobs: u32) {
+    let jobs = (jobs as usize).clamp(1, filenames.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<(usize, String)>();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= filenames.len() {
+                    break;
+                }
+
+                // 用户中断后不再开始新的文件，但仍需回报以保持输出顺序
+                if !*USER_ABORT.lock().unwrap() {
+                    let mut ctx = CoderContext::new();
+                    ctx.show_progress = false;
+
+                    message_capture_begin();
+                    if OPT_VERIFY.lock().unwrap().is_some() {
+                        verify_file(&filenames[i]);
+                    } else {
+                        coder_run_ctx(&mut ctx, &filenames[i]);
+                    }
+                    if tx.send((i, message_capture_end())).is_err() {
+                        break;
+                    }
+                } else if tx.send((i, String::new())).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        // 按顺序输出已完成文件的诊断信息，先完成的后续文件暂存起来
+        let mut pending: BTreeMap<usize, String> = BTreeMap::new();
+        let mut emit = 0;
+        for (i, text) in rx {
+            pending.insert(i, text);
+            while let Some(text) = pending.remove(&emit) {
+                message_filename(&filenames[emit]);
+                eprint!("{}", text);
+                emit += 1;
+            }
+        }
+    });
+}
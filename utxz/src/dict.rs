@@ -0,0 +1,86 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 预设字典：--dict 读取字典文件，--train-dict 从样本文件训练字典
+
+use std::fs;
+use std::path::Path;
+
+use liblzma::common::lzma_dict_train;
+
+use crate::args::OPT_FORCE;
+use crate::catalog::{tr, tr_io_error, tr_str, Msg};
+use crate::message::{message, message_error, message_fatal, MessageVerbosity};
+use crate::util::uint64_to_str;
+
+/// --train-size 的缺省值
+pub const DICT_TRAIN_SIZE_DEFAULT: u64 = 64 << 10;
+
+/// 读取 --dict 指定的字典文件，失败时直接退出
+pub fn dict_load(path: &str) -> Vec<u8> {
+    let dict = match fs::read(path) {
+        Ok(dict) => dict,
+        Err(e) => {
+            message_fatal(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+            return Vec::new();
+        }
+    };
+
+    if dict.is_empty() {
+        message_fatal(&tr(Msg::DictEmpty, &[&path]), format_args!(""));
+    }
+
+    dict
+}
+
+/// --train-dict 模式：从 names 中的样本文件训练字典并写入 out
+pub fn dict_train_run(out: &str, names: &[String], dict_size: u64) {
+    let mut samples: Vec<Vec<u8>> = Vec::new();
+    for name in names {
+        if name == "-" {
+            message_error(tr_str(Msg::TrainStdin), format_args!(""));
+            continue;
+        }
+
+        match fs::read(name) {
+            Ok(data) => samples.push(data),
+            Err(e) => message_error(&format!("{}: {}", name, tr_io_error(&e)), format_args!("")),
+        }
+    }
+
+    if samples.is_empty() {
+        message_fatal(tr_str(Msg::TrainNoSamples), format_args!(""));
+        return;
+    }
+
+    if Path::new(out).exists() && !*OPT_FORCE.lock().unwrap() {
+        message_error(&tr(Msg::FileExists, &[&out]), format_args!(""));
+        return;
+    }
+
+    let refs: Vec<&[u8]> = samples.iter().map(|s| s.as_slice()).collect();
+    let dict = lzma_dict_train(&refs, dict_size as usize);
+
+    if let Err(e) = fs::write(out, &dict) {
+        message_error(&format!("{}: {}", out, tr_io_error(&e)), format_args!(""));
+        return;
+    }
+
+    let total: usize = samples.iter().map(|s| s.len()).sum();
+    message(
+        MessageVerbosity::Verbose,
+        &tr(
+            Msg::TrainDone,
+            &[
+                &out,
+                &samples.len(),
+                &uint64_to_str(total as u64, 0),
+                &dict.len(),
+            ],
+        ),
+        format_args!(""),
+    );
+}
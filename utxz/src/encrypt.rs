@@ -0,0 +1,117 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --encrypt 和 --key-file：加密压缩的 .xz 文件
+//!
+//! 压缩时用加密过滤器代替过滤器链末尾的 LZMA2，加密过滤器先用同样的 LZMA2
+//! 选项压缩，再用 ChaCha20-Poly1305 加密。密钥由 Argon2id 从终端输入的口令或
+//! 密钥文件的内容派生。文件仍是普通的 .xz 文件，Index 不加密，所以仍可以
+//! 列出、定位和多线程处理各个 Block。
+
+use liblzma::api::{LzmaOptionsEncrypt, LzmaOptionsLzma, LzmaOptionsType, LzmaRet};
+use liblzma::encrypt::{
+    lzma_encrypt_key_set, lzma_encrypt_secret_add, lzma_encrypt_secret_callback,
+};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::io::AsRawFd;
+use std::sync::Mutex;
+use utxz_sys::termios as sys_termios;
+
+use crate::args::OPT_KEY_FILE;
+use crate::catalog::{tr_str, Msg};
+use crate::message::message_fatal;
+
+/// 压缩时使用的加密选项，所有文件共用同一个派生出的密钥，只询问一次口令
+static ENCRYPT_OPTIONS: Mutex<Option<LzmaOptionsEncrypt>> = Mutex::new(None);
+
+/// 在控制终端上显示 prompt 并读取一行口令，不回显
+///
+/// 没有控制终端或读到文件结尾时返回 None。
+fn passphrase_read(prompt: &str) -> Option<Vec<u8>> {
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let fd = tty.as_raw_fd();
+
+    let saved = sys_termios::tcgetattr(fd).ok()?;
+    let mut noecho = saved;
+    noecho.c_lflag &= !libc::ECHO;
+    noecho.c_lflag |= libc::ECHONL;
+    sys_termios::tcsetattr(fd, libc::TCSAFLUSH, &noecho).ok()?;
+
+    let _ = tty.write_all(prompt.as_bytes());
+    let mut line = Vec::new();
+    let ret = BufReader::new(&tty).read_until(b'\n', &mut line);
+
+    let _ = sys_termios::tcsetattr(fd, libc::TCSAFLUSH, &saved);
+
+    match ret {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            while matches!(line.last(), Some(b'\n' | b'\r')) {
+                line.pop();
+            }
+            Some(line)
+        }
+    }
+}
+
+/// 压缩时的口令：密钥文件的内容，或在终端上输入两次的口令
+fn encrypt_secret_get() -> Vec<u8> {
+    if let Some(secret) = OPT_KEY_FILE.lock().unwrap().clone() {
+        return secret;
+    }
+
+    let secret = match passphrase_read(tr_str(Msg::EncryptPrompt)) {
+        Some(secret) => secret,
+        None => {
+            message_fatal(tr_str(Msg::EncryptNoKey), format_args!(""));
+            return Vec::new();
+        }
+    };
+    if secret.is_empty() {
+        message_fatal(tr_str(Msg::EncryptEmpty), format_args!(""));
+    }
+    if passphrase_read(tr_str(Msg::EncryptPromptAgain)).as_ref() != Some(&secret) {
+        message_fatal(tr_str(Msg::EncryptMismatch), format_args!(""));
+    }
+
+    secret
+}
+
+/// 返回压缩时代替 LZMA2 的加密过滤器选项，lzma 是原来的 LZMA2 选项
+pub fn encrypt_filter_options(lzma: LzmaOptionsLzma) -> LzmaOptionsType {
+    let mut cached = ENCRYPT_OPTIONS.lock().unwrap();
+    if cached.is_none() {
+        let mut opt = LzmaOptionsEncrypt::default();
+        if lzma_encrypt_key_set(&mut opt, &encrypt_secret_get()) != LzmaRet::Ok {
+            message_fatal(tr_str(Msg::EncryptRandom), format_args!(""));
+        }
+        *cached = Some(opt);
+    }
+
+    let mut opt = cached.clone().unwrap();
+    opt.lzma = lzma;
+    LzmaOptionsType::Encrypt(opt)
+}
+
+/// 解码器的密钥环中没有匹配的口令时在终端上询问，空行表示放弃
+fn passphrase_ask() -> Option<Vec<u8>> {
+    passphrase_read(tr_str(Msg::EncryptPrompt)).filter(|secret| !secret.is_empty())
+}
+
+/// 为解压缩、测试和 --verify 准备解密用的口令
+///
+/// 给出 --key-file 时只使用它；否则遇到加密的 Block 时才在终端上询问口令。
+pub fn encrypt_decoder_init() {
+    match OPT_KEY_FILE.lock().unwrap().as_deref() {
+        Some(secret) => lzma_encrypt_secret_add(secret),
+        None => lzma_encrypt_secret_callback(Some(passphrase_ask)),
+    }
+}
@@ -30,18 +30,29 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use std::process::exit;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use utxz_sys::{fcntl as sys_fcntl, fs as sys_fs, poll as sys_poll, unistd as sys_unistd};
+use utxz_sys::{
+    fcntl as sys_fcntl, fs as sys_fs, poll as sys_poll, unistd as sys_unistd, xattr as sys_xattr,
+};
 
 pub const IO_BUFFER_SIZE: usize = 262144;
 
 use std::mem;
 
-use crate::args::{OPT_FORCE, OPT_KEEP_ORIGINAL, OPT_STDOUT, STDIN_FILENAME};
+use crate::args::{
+    OPT_EXCLUDE, OPT_FOLLOW_SYMLINKS, OPT_FORCE, OPT_KEEP_ORIGINAL, OPT_MMAP, OPT_STDOUT,
+    OPT_SYNC, OPT_XATTRS, STDIN_FILENAME,
+};
+use crate::catalog::{tr, tr_io_error, tr_str, Msg};
 use crate::coder::{OperationMode, OPT_MODE};
-use crate::message::{message_bug, message_error, message_fatal, message_warning};
+use crate::message::{
+    message, message_bug, message_error, message_fatal, message_warning, MessageVerbosity,
+};
+use crate::mmap::IoMap;
 use crate::mytime::{mytime_get_flush_timeout, mytime_set_flush_time};
 use crate::signals::{signals_block, signals_unblock, USER_ABORT};
-use crate::suffix::suffix_get_dest_name;
+use crate::suffix::{suffix_get_dest_name, suffix_is_compressed};
+#[cfg(feature = "io_uring")]
+use crate::uring::{UringReader, UringWriter};
 use crate::E_ERROR;
 
 #[derive(Debug, Clone, Copy)]
@@ -103,9 +114,16 @@ pub struct FilePair {
     /// 如果写入标准输出，则指向静态字符串 "(stdout)"。
     pub dest_name: Option<String>,
 
+    /// 持久写入模式下实际写入的临时文件名，关闭时重命名为 dest_name。
+    /// 为 None 时直接写入 dest_name。
+    pub dest_tmp_name: Option<String>,
+
     /// 源文件的文件描述符
     pub src_fd: i32,
 
+    /// 已验证的签名文件名及其状态，删除源文件时一并删除
+    pub src_sig: Option<(String, stat)>,
+
     /// 目标文件的文件描述符
     pub dest_fd: i32,
 
@@ -130,6 +148,20 @@ pub struct FilePair {
 
     /// 目标文件的状态（如大小、权限等）
     pub dest_st: stat,
+
+    /// 较大的普通源文件被映射到内存时的映射，为 None 时使用 read()
+    pub src_map: Option<IoMap>,
+
+    /// 下一次从 src_map 读取的位置
+    pub src_map_pos: usize,
+
+    /// 源文件是普通文件时用 io_uring 预读，为 None 时使用 read()
+    #[cfg(feature = "io_uring")]
+    pub uring_src: Option<Box<UringReader>>,
+
+    /// 目标文件是普通文件时用 io_uring 写入，为 None 时使用 write()
+    #[cfg(feature = "io_uring")]
+    pub uring_dest: Option<Box<UringWriter>>,
 }
 
 impl FilePair {
@@ -151,7 +183,9 @@ impl FilePair {
         FilePair {
             src_name: src_name.map(|s| s.to_string()),
             dest_name: dest_name.map(|s| s.to_string()),
+            dest_tmp_name: None,
             src_fd,
+            src_sig: None,
             dest_fd,
             src_eof: false,
             src_has_seen_input: false,
@@ -160,6 +194,12 @@ impl FilePair {
             dest_pending_sparse: 0,
             src_st,
             dest_st,
+            src_map: None,
+            src_map_pos: 0,
+            #[cfg(feature = "io_uring")]
+            uring_src: None,
+            #[cfg(feature = "io_uring")]
+            uring_dest: None,
         }
     }
 }
@@ -207,7 +247,7 @@ pub fn io_init() {
     let pipe_fds = match sys_unistd::pipe() {
         Ok(fds) => fds,
         Err(e) => {
-            message_fatal(&format!("创建管道失败: {}", e), format_args!(""));
+            message_fatal(&tr(Msg::PipeCreate, &[&tr_io_error(&e)]), format_args!(""));
             unreachable!();
         }
     };
@@ -217,12 +257,18 @@ pub fn io_init() {
         let flags = match sys_fcntl::fcntl_getfl(pipe_fds[i]) {
             Ok(v) => v,
             Err(e) => {
-                message_fatal(&format!("设置管道非阻塞失败: {}", e), format_args!(""));
+                message_fatal(
+                    &tr(Msg::PipeNonblock, &[&tr_io_error(&e)]),
+                    format_args!(""),
+                );
                 unreachable!();
             }
         };
         if let Err(e) = sys_fcntl::fcntl_setfl(pipe_fds[i], flags | O_NONBLOCK) {
-            message_fatal(&format!("设置管道非阻塞失败: {}", e), format_args!(""));
+            message_fatal(
+                &tr(Msg::PipeNonblock, &[&tr_io_error(&e)]),
+                format_args!(""),
+            );
         }
     }
 
@@ -270,14 +316,16 @@ fn io_wait(pair: &FilePair, timeout: i32, is_reading: bool) -> IoWaitRet {
                 }
 
                 message_error(
-                    &format!(
-                        "{}: poll() 失败: {}",
-                        if is_reading {
-                            pair.src_name.as_deref().unwrap_or("(stdin)")
-                        } else {
-                            pair.dest_name.as_deref().unwrap_or("(stdout)")
-                        },
-                        e
+                    &tr(
+                        Msg::PollFailed,
+                        &[
+                            &if is_reading {
+                                pair.src_name.as_deref().unwrap_or("(stdin)")
+                            } else {
+                                pair.dest_name.as_deref().unwrap_or("(stdout)")
+                            },
+                            &tr_io_error(&e),
+                        ],
                     ),
                     format_args!(""),
                 );
@@ -311,17 +359,29 @@ fn io_unlink(name: &str, known_st: &libc::stat) {
         Ok(new_st) => {
             // 检查设备号和 inode 是否一致，避免误删
             if new_st.dev() != known_st.st_dev as u64 || new_st.ino() != known_st.st_ino as u64 {
-                eprintln!("警告：{}: 文件似乎已被移动，未删除", name);
+                message(
+                    MessageVerbosity::Warning,
+                    &tr(Msg::FileMoved, &[&name]),
+                    format_args!(""),
+                );
                 return;
             }
             // 有竞争条件，但我们已尽力避免误删
             let c_name = CString::new(name).expect("CString::new failed");
             if let Err(e) = sys_fs::unlink(&c_name) {
-                eprintln!("警告：{}: 无法删除: {}", name, e);
+                message(
+                    MessageVerbosity::Warning,
+                    &tr(Msg::CannotRemove, &[&name, &tr_io_error(&e)]),
+                    format_args!(""),
+                );
             }
         }
         Err(_) => {
-            eprintln!("警告：{}: 文件似乎已被移动，未删除", name);
+            message(
+                MessageVerbosity::Warning,
+                &tr(Msg::FileMoved, &[&name]),
+                format_args!(""),
+            );
         }
     }
 }
@@ -332,25 +392,39 @@ fn io_copy_attrs(pair: &FilePair) {
     if sys_fs::fchown(pair.dest_fd, pair.src_st.st_uid, !(0 as libc::gid_t)).is_err()
         && *WARN_FCHOWN.lock().unwrap()
     {
-        eprintln!(
-            "警告：{}: 无法设置文件所有者: {}",
-            pair.dest_name.as_deref().unwrap_or("(unknown)"),
-            std::io::Error::last_os_error()
+        message(
+            MessageVerbosity::Warning,
+            &tr(
+                Msg::CannotSetOwner,
+                &[
+                    &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                    &tr_io_error(&io::Error::last_os_error()),
+                ],
+            ),
+            format_args!(""),
         );
     }
 
     let mut mode: u32;
+    let mut group_ok = true;
 
     // 设置文件组
     if pair.dest_st.st_gid != pair.src_st.st_gid
         && sys_fs::fchown(pair.dest_fd, !(0 as libc::uid_t), pair.src_st.st_gid).is_err()
     {
-        eprintln!(
-            "警告：{}: 无法设置文件组: {}",
-            pair.dest_name.as_deref().unwrap_or("(unknown)"),
-            std::io::Error::last_os_error()
+        message(
+            MessageVerbosity::Warning,
+            &tr(
+                Msg::CannotSetGroup,
+                &[
+                    &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                    &tr_io_error(&io::Error::last_os_error()),
+                ],
+            ),
+            format_args!(""),
         );
         // 降级权限
+        group_ok = false;
         mode = ((pair.src_st.st_mode & 0o070) >> 3) & (pair.src_st.st_mode & 0o007);
         mode = (pair.src_st.st_mode & 0o700) | (mode << 3) | mode;
     } else {
@@ -360,13 +434,24 @@ fn io_copy_attrs(pair: &FilePair) {
 
     // 设置权限
     if sys_fs::fchmod(pair.dest_fd, mode).is_err() {
-        eprintln!(
-            "警告：{}: 无法设置文件权限: {}",
-            pair.dest_name.as_deref().unwrap_or("(unknown)"),
-            std::io::Error::last_os_error()
+        message(
+            MessageVerbosity::Warning,
+            &tr(
+                Msg::CannotSetPermissions,
+                &[
+                    &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                    &tr_io_error(&io::Error::last_os_error()),
+                ],
+            ),
+            format_args!(""),
         );
     }
 
+    // 扩展属性要在 fchown() 之后设置，否则文件权能会被清除
+    if *OPT_XATTRS.lock().unwrap() {
+        io_copy_xattrs(pair, group_ok);
+    }
+
     // 构造 timespec 结构体
     let ts = [
         libc::timespec {
@@ -382,6 +467,95 @@ fn io_copy_attrs(pair: &FilePair) {
     let _ = sys_fs::futimens(pair.dest_fd, &ts);
 }
 
+/// 读取名称列表或属性值，大小在两次调用之间改变时重试
+fn io_read_xattr(mut get: impl FnMut(&mut [u8]) -> io::Result<usize>) -> io::Result<Vec<u8>> {
+    loop {
+        let size = get(&mut [])?;
+        let mut buf = vec![0u8; size];
+        match get(&mut buf) {
+            Ok(len) => {
+                buf.truncate(len);
+                return Ok(buf);
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ERANGE) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 把源文件的扩展属性复制到目标文件
+///
+/// POSIX ACL（system.posix_acl_*）和文件权能（security.capability）在 Linux 上
+/// 都保存在扩展属性中。没能设置文件组时不复制 ACL，以免把权限授予错误的组。
+/// 失败只显示警告。
+fn io_copy_xattrs(pair: &FilePair, group_ok: bool) {
+    let dest_name = pair.dest_name.as_deref().unwrap_or("(unknown)");
+
+    let names = match io_read_xattr(|buf| sys_xattr::flistxattr(pair.src_fd, buf)) {
+        Ok(names) => names,
+        // 源文件所在的文件系统不支持扩展属性
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENOTSUP) | Some(libc::ENOSYS)) => return,
+        Err(e) => {
+            message_warning(
+                &tr(
+                    Msg::XattrList,
+                    &[
+                        &pair.src_name.as_deref().unwrap_or("(unknown)"),
+                        &tr_io_error(&e),
+                    ],
+                ),
+                &[],
+            );
+            return;
+        }
+    };
+
+    for name in names.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+        let name_str = String::from_utf8_lossy(name);
+        if !group_ok && name.starts_with(b"system.posix_acl_") {
+            message_warning(&tr(Msg::XattrGroupNotCopied, &[&dest_name, &name_str]), &[]);
+            continue;
+        }
+
+        let c_name = match CString::new(name) {
+            Ok(c_name) => c_name,
+            Err(_) => continue,
+        };
+
+        let value = match io_read_xattr(|buf| sys_xattr::fgetxattr(pair.src_fd, &c_name, buf)) {
+            Ok(value) => value,
+            // 属性在列出之后被删除
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => continue,
+            Err(e) => {
+                message_warning(
+                    &tr(
+                        Msg::XattrRead,
+                        &[
+                            &pair.src_name.as_deref().unwrap_or("(unknown)"),
+                            &name_str,
+                            &tr_io_error(&e),
+                        ],
+                    ),
+                    &[],
+                );
+                continue;
+            }
+        };
+
+        if let Err(e) = sys_xattr::fsetxattr(pair.dest_fd, &c_name, &value, 0) {
+            message_warning(
+                &tr(Msg::XattrSet, &[&dest_name, &name_str, &tr_io_error(&e)]),
+                &[],
+            );
+
+            // 目标文件系统不支持扩展属性时不再尝试其余属性
+            if e.raw_os_error() == Some(libc::ENOTSUP) {
+                return;
+            }
+        }
+    }
+}
+
 pub fn s_isreg(mode: u32) -> bool {
     (mode & 0o170000) == 0o100000
 }
@@ -394,7 +568,7 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
         match sys_fcntl::fcntl_getfl(STDIN_FILENO) {
             Ok(v) => *STDIN_FLAGS.lock().unwrap() = v,
             Err(e) => {
-                eprintln!("错误：无法获取标准输入的文件状态标志: {}", e);
+                message_error(&tr(Msg::StdinFlags, &[&tr_io_error(&e)]), format_args!(""));
                 return true;
             }
         }
@@ -425,7 +599,8 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
     // 是否跟随符号链接
     let follow_symlinks = *OPT_STDOUT.lock().unwrap()
         || *OPT_FORCE.lock().unwrap()
-        || *OPT_KEEP_ORIGINAL.lock().unwrap();
+        || *OPT_KEEP_ORIGINAL.lock().unwrap()
+        || *OPT_FOLLOW_SYMLINKS.lock().unwrap();
     let reg_files_only = !*OPT_STDOUT.lock().unwrap();
 
     // open() 标志
@@ -458,9 +633,9 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
 
             if was_symlink {
                 message_warning(
-                    &format!(
-                        "{}: Is a symbolic link, skipping",
-                        pair.src_name.as_deref().unwrap_or("(unknown)")
+                    &tr(
+                        Msg::SymlinkSkipped,
+                        &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
                     ),
                     &[],
                 );
@@ -469,7 +644,7 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
                     &format!(
                         "{}: {}",
                         pair.src_name.as_deref().unwrap_or("(unknown)"),
-                        err
+                        tr_io_error(&err)
                     ),
                     format_args!(""),
                 );
@@ -484,7 +659,7 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
             &format!(
                 "{}: {}",
                 pair.src_name.as_deref().unwrap_or("(unknown)"),
-                err
+                tr_io_error(&err)
             ),
             format_args!(""),
         );
@@ -495,9 +670,9 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
     // 检查目录
     if (pair.src_st.st_mode & S_IFMT) == S_IFDIR {
         message_warning(
-            &format!(
-                "{}: Is a directory, skipping",
-                pair.src_name.as_deref().unwrap_or("(unknown)")
+            &tr(
+                Msg::DirectorySkipped,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
             ),
             &[],
         );
@@ -508,9 +683,9 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
     // 只允许常规文件
     if reg_files_only && !((pair.src_st.st_mode & S_IFMT) == S_IFREG) {
         message_warning(
-            &format!(
-                "{}: Not a regular file, skipping",
-                pair.src_name.as_deref().unwrap_or("(unknown)")
+            &tr(
+                Msg::NotRegularSkipped,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
             ),
             &[],
         );
@@ -523,9 +698,9 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
         let mode = pair.src_st.st_mode;
         if (mode & S_ISUID as u32 != 0) || (mode & S_ISGID as u32 != 0) {
             message_warning(
-                &format!(
-                    "{}: File has setuid or setgid bit set, skipping",
-                    pair.src_name.as_deref().unwrap_or("(unknown)")
+                &tr(
+                    Msg::SetuidSkipped,
+                    &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
                 ),
                 &[],
             );
@@ -534,9 +709,9 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
         }
         if mode & S_ISVTX as u32 != 0 {
             message_warning(
-                &format!(
-                    "{}: File has sticky bit set, skipping",
-                    pair.src_name.as_deref().unwrap_or("(unknown)")
+                &tr(
+                    Msg::StickySkipped,
+                    &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
                 ),
                 &[],
             );
@@ -545,9 +720,9 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
         }
         if pair.src_st.st_nlink > 1 {
             message_warning(
-                &format!(
-                    "{}: Input file has more than one hard link, skipping",
-                    pair.src_name.as_deref().unwrap_or("(unknown)")
+                &tr(
+                    Msg::HardlinkSkipped,
+                    &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
                 ),
                 &[],
             );
@@ -580,13 +755,164 @@ fn io_open_src_real(pair: &mut FilePair) -> bool {
         },
     );
 
+    // --list 需要随机访问，mmap 和 io_uring 只用于顺序读取。创建失败时使用 read()
+    if pair.src_fd != STDIN_FILENO
+        && (pair.src_st.st_mode & S_IFMT) == S_IFREG
+        && *OPT_MODE.lock().unwrap() != OperationMode::List
+    {
+        if *OPT_MMAP.lock().unwrap() && pair.src_st.st_size > IO_BUFFER_SIZE as off_t {
+            if let Ok(len) = usize::try_from(pair.src_st.st_size) {
+                pair.src_map = IoMap::new(pair.src_fd, len).ok();
+            }
+        }
+
+        #[cfg(feature = "io_uring")]
+        if pair.src_map.is_none() {
+            pair.uring_src = UringReader::new(pair.src_fd, 0).ok().map(Box::new);
+        }
+    }
+
     false
 }
 
+/// 检查路径是否匹配某个 --exclude 模式
+///
+/// 模式同时与完整路径和最后一个路径分量比较，
+/// 因此 "*.gz" 和 "logs/old/*" 都能按预期工作。
+fn io_is_excluded(path: &str) -> bool {
+    let patterns = OPT_EXCLUDE.lock().unwrap();
+    if patterns.is_empty() {
+        return false;
+    }
+
+    let base = path.rsplit('/').next().unwrap_or(path);
+    let (c_path, c_base) = match (CString::new(path), CString::new(base)) {
+        (Ok(p), Ok(b)) => (p, b),
+        _ => return false,
+    };
+
+    patterns.iter().any(|pattern| match CString::new(pattern.as_str()) {
+        Ok(c_pattern) => {
+            sys_fs::fnmatch(&c_pattern, &c_path, 0) || sys_fs::fnmatch(&c_pattern, &c_base, 0)
+        }
+        Err(_) => false,
+    })
+}
+
+/// 判断名称是否为需要递归展开的目录
+///
+/// 命令行上直接给出的指向目录的符号链接总是会被跟随。
+pub fn io_is_dir(name: &str) -> bool {
+    match CString::new(name) {
+        Ok(c_name) => match sys_fs::stat(&c_name) {
+            Ok(st) => (st.st_mode & S_IFMT) == S_IFDIR,
+            Err(_) => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// 递归遍历目录，把需要处理的常规文件按名称顺序追加到 files
+///
+/// 压缩时跳过已有压缩后缀的文件，解压缩和测试时只收集带有已知后缀的文件。
+/// 只有使用 --follow-symlinks 时才会跟随符号链接，已访问过的目录
+/// （按设备号和 inode 号判断）不会再次进入，以免符号链接形成循环。
+pub fn io_walk_dir(dir: &str, files: &mut Vec<String>) {
+    if io_is_excluded(dir) {
+        return;
+    }
+
+    let mut visited: Vec<(u64, u64)> = Vec::new();
+    io_walk_dir_real(dir, files, &mut visited);
+}
+
+fn io_walk_dir_real(dir: &str, files: &mut Vec<String>, visited: &mut Vec<(u64, u64)>) {
+    let dir_meta = match metadata(dir) {
+        Ok(m) => m,
+        Err(e) => {
+            message_error(&format!("{}: {}", dir, tr_io_error(&e)), format_args!(""));
+            return;
+        }
+    };
+    let id = (dir_meta.dev(), dir_meta.ino());
+    if visited.contains(&id) {
+        return;
+    }
+    visited.push(id);
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            message_error(&format!("{}: {}", dir, tr_io_error(&e)), format_args!(""));
+            return;
+        }
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => match entry.file_name().into_string() {
+                Ok(name) => names.push(name),
+                Err(name) => {
+                    message_warning(&tr(Msg::NameNotUtf8, &[&dir, &name.to_string_lossy()]), &[])
+                }
+            },
+            Err(e) => message_error(&format!("{}: {}", dir, tr_io_error(&e)), format_args!("")),
+        }
+    }
+    names.sort();
+
+    let follow_symlinks = *OPT_FOLLOW_SYMLINKS.lock().unwrap();
+    let compressing = *OPT_MODE.lock().unwrap() == OperationMode::Compress;
+
+    for name in names {
+        if *USER_ABORT.lock().unwrap() {
+            return;
+        }
+
+        let path = if dir.ends_with('/') {
+            format!("{}{}", dir, name)
+        } else {
+            format!("{}/{}", dir, name)
+        };
+
+        if io_is_excluded(&path) {
+            continue;
+        }
+
+        let mut meta = match fs::symlink_metadata(&path) {
+            Ok(m) => m,
+            Err(e) => {
+                message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                continue;
+            }
+        };
+
+        if meta.file_type().is_symlink() {
+            if !follow_symlinks {
+                continue;
+            }
+            meta = match metadata(&path) {
+                Ok(m) => m,
+                Err(e) => {
+                    message_warning(&format!("{}: {}", path, tr_io_error(&e)), &[]);
+                    continue;
+                }
+            };
+        }
+
+        if meta.is_dir() {
+            io_walk_dir_real(&path, files, visited);
+        } else if meta.is_file() && suffix_is_compressed(&path) != compressing {
+            files.push(path);
+        }
+    }
+}
+
 /// 打开源文件，返回 Some(FilePair) 表示成功，None 表示失败
 pub fn io_open_src(src_name: &str) -> Option<FilePair> {
     if src_name.is_empty() {
-        message_error("(empty filename)", format_args!(""));
+        message_error(tr_str(Msg::EmptyFilename), format_args!(""));
         return None;
     }
 
@@ -594,7 +920,9 @@ pub fn io_open_src(src_name: &str) -> Option<FilePair> {
     let mut pair = FilePair {
         src_name: Some(src_name.to_string()),
         dest_name: None,
+        dest_tmp_name: None,
         src_fd: -1,
+        src_sig: None,
         dest_fd: -1,
         src_eof: false,
         src_has_seen_input: false,
@@ -603,6 +931,12 @@ pub fn io_open_src(src_name: &str) -> Option<FilePair> {
         dest_pending_sparse: 0,
         src_st: sys_fs::zeroed_stat(),
         dest_st: sys_fs::zeroed_stat(),
+        src_map: None,
+        src_map_pos: 0,
+        #[cfg(feature = "io_uring")]
+        uring_src: None,
+        #[cfg(feature = "io_uring")]
+        uring_dest: None,
     };
 
     // 阻塞信号
@@ -624,7 +958,10 @@ fn io_close_src(pair: &mut FilePair, success: bool) {
         assert!(pair.src_fd == STDIN_FILENO);
         *RESTORE_STDIN_FLAGS.lock().unwrap() = false;
         if let Err(e) = sys_fcntl::fcntl_setfl(STDIN_FILENO, *STDIN_FLAGS.lock().unwrap()) {
-            eprintln!("错误：恢复标准输入状态标志失败: {}", e);
+            message_error(
+                &tr(Msg::StdinFlagsRestore, &[&tr_io_error(&e)]),
+                format_args!(""),
+            );
         }
     }
 
@@ -637,6 +974,11 @@ fn io_close_src(pair: &mut FilePair, success: bool) {
                 &<Option<std::string::String> as Clone>::clone(&pair.src_name).unwrap(),
                 &pair.src_st,
             );
+
+            // 签名文件只对这个源文件有用
+            if let Some((sig_name, sig_st)) = pair.src_sig.take() {
+                io_unlink(&sig_name, &sig_st);
+            }
         }
     }
 }
@@ -651,7 +993,7 @@ fn io_open_dest_real(pair: &mut FilePair) -> bool {
         match sys_fcntl::fcntl_getfl(STDOUT_FILENO) {
             Ok(v) => *STDOUT_FLAGS.lock().unwrap() = v,
             Err(e) => {
-                eprintln!("错误：获取标准输出文件状态标志失败: {}", e);
+                message_error(&tr(Msg::StdoutFlags, &[&tr_io_error(&e)]), format_args!(""));
                 return true;
             }
         }
@@ -670,15 +1012,22 @@ fn io_open_dest_real(pair: &mut FilePair) -> bool {
             return true;
         }
 
-        // --force 先尝试删除目标文件
-        if *OPT_FORCE.lock().unwrap() {
+        // 持久写入模式先写入临时文件，关闭时再重命名为目标文件
+        if *OPT_SYNC.lock().unwrap() {
+            if io_open_dest_tmp(pair) {
+                return true;
+            }
+        } else if *OPT_FORCE.lock().unwrap() {
+            // --force 先尝试删除目标文件
             let c_dest = CString::new(pair.dest_name.as_ref().unwrap().as_str()).unwrap();
             if let Err(e) = sys_fs::unlink(&c_dest) {
                 if e.raw_os_error().unwrap_or(0) != ENOENT {
-                    eprintln!(
-                        "错误：{}: 无法删除: {}",
-                        pair.dest_name.as_ref().unwrap(),
-                        e
+                    message_error(
+                        &tr(
+                            Msg::CannotRemove,
+                            &[pair.dest_name.as_ref().unwrap(), &tr_io_error(&e)],
+                        ),
+                        format_args!(""),
                     );
                     return true;
                 }
@@ -686,17 +1035,19 @@ fn io_open_dest_real(pair: &mut FilePair) -> bool {
         }
 
         // 打开目标文件
-        let flags = O_WRONLY | 0 | O_NOCTTY | O_CREAT | O_EXCL | O_NONBLOCK;
-        let mode = (S_IRUSR | S_IWUSR) as libc::mode_t;
-        let c_dest = CString::new(pair.dest_name.as_ref().unwrap().as_str()).unwrap();
-        match sys_fcntl::open_with_mode(&c_dest, flags, mode) {
-            Ok(fd) => pair.dest_fd = fd,
-            Err(e) => {
-                message_error(
-                    &format!("{}: {}", pair.dest_name.as_ref().unwrap(), e),
-                    format_args!(""),
-                );
-                return true;
+        if pair.dest_tmp_name.is_none() {
+            let flags = O_WRONLY | 0 | O_NOCTTY | O_CREAT | O_EXCL | O_NONBLOCK;
+            let mode = (S_IRUSR | S_IWUSR) as libc::mode_t;
+            let c_dest = CString::new(pair.dest_name.as_ref().unwrap().as_str()).unwrap();
+            match sys_fcntl::open_with_mode(&c_dest, flags, mode) {
+                Ok(fd) => pair.dest_fd = fd,
+                Err(e) => {
+                    message_error(
+                        &format!("{}: {}", pair.dest_name.as_ref().unwrap(), tr_io_error(&e)),
+                        format_args!(""),
+                    );
+                    return true;
+                }
             }
         }
     }
@@ -732,6 +1083,142 @@ fn io_open_dest_real(pair: &mut FilePair) -> bool {
         pair.dest_try_sparse = true;
     }
 
+    #[cfg(feature = "io_uring")]
+    if pair.dest_fd != STDOUT_FILENO && (pair.dest_st.st_mode & S_IFMT) == S_IFREG {
+        pair.uring_dest = UringWriter::new(pair.dest_fd, 0).ok().map(Box::new);
+    }
+
+    false
+}
+
+/// 在目标文件所在目录中创建隐藏的临时文件，返回 true 表示出错
+fn io_open_dest_tmp(pair: &mut FilePair) -> bool {
+    match io_create_tmp(pair.dest_name.as_deref().unwrap()) {
+        Some((fd, tmp_name)) => {
+            pair.dest_fd = fd;
+            pair.dest_tmp_name = Some(tmp_name);
+            false
+        }
+        None => true,
+    }
+}
+
+/// 在 dest_name 所在目录中创建隐藏的临时文件，返回文件描述符和临时文件名
+///
+/// 临时文件名为 ".目标文件名.XXXXXX"。没有 --force 时先检查目标文件是否存在，
+/// 以便在写入之前就报告错误。出错时已经显示了错误信息并返回 None。
+pub fn io_create_tmp(dest_name: &str) -> Option<(RawFd, String)> {
+    if !*OPT_FORCE.lock().unwrap() {
+        let c_dest = CString::new(dest_name).unwrap();
+        let mut st = sys_fs::zeroed_stat();
+        if sys_fs::lstat(&c_dest, &mut st).is_ok() {
+            message_error(
+                &format!(
+                    "{}: {}",
+                    dest_name,
+                    tr_io_error(&io::Error::from_raw_os_error(libc::EEXIST))
+                ),
+                format_args!(""),
+            );
+            return None;
+        }
+    }
+
+    let (dir, base) = match dest_name.rfind('/') {
+        Some(pos) => (&dest_name[..=pos], &dest_name[pos + 1..]),
+        None => ("", dest_name),
+    };
+
+    // 文件名加上前缀和后缀后不能超过 NAME_MAX
+    let mut base_len = base.len().min(255 - 8);
+    while !base.is_char_boundary(base_len) {
+        base_len -= 1;
+    }
+    let base = &base[..base_len];
+
+    let flags = O_WRONLY | O_NOCTTY | O_CREAT | O_EXCL | O_NONBLOCK;
+    let mode = (S_IRUSR | S_IWUSR) as libc::mode_t;
+    let mut seed = std::process::id() as u64
+        ^ std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+
+    for _ in 0..100 {
+        let mut suffix = String::with_capacity(6);
+        for _ in 0..6 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let c = (seed >> 58) as u8 % 36;
+            suffix.push(if c < 10 { b'0' + c } else { b'a' + c - 10 } as char);
+        }
+
+        let tmp_name = format!("{}.{}.{}", dir, base, suffix);
+        let c_tmp = CString::new(tmp_name.as_str()).unwrap();
+        match sys_fcntl::open_with_mode(&c_tmp, flags, mode) {
+            Ok(fd) => return Some((fd, tmp_name)),
+            Err(e) if e.raw_os_error() == Some(libc::EEXIST) => continue,
+            Err(e) => {
+                message_error(
+                    &format!("{}: {}", tmp_name, tr_io_error(&e)),
+                    format_args!(""),
+                );
+                return None;
+            }
+        }
+    }
+
+    message_error(&tr(Msg::TempFileFailed, &[&dest_name]), format_args!(""));
+    None
+}
+
+/// 把已写完的临时文件重命名为目标文件，并同步目录，返回 true 表示出错
+///
+/// 没有 --force 时不覆盖在写入期间出现的同名文件。重命名失败时删除临时文件。
+/// 给出 --no-sync 时不同步目录。
+pub fn io_rename_dest(dest_name: &str, tmp_name: &str, tmp_st: &libc::stat) -> bool {
+    let c_dest = CString::new(dest_name).unwrap();
+    let c_tmp = CString::new(tmp_name).unwrap();
+
+    let ret = if *OPT_FORCE.lock().unwrap() {
+        sys_fs::rename(&c_tmp, &c_dest)
+    } else {
+        sys_fs::rename_noreplace(&c_tmp, &c_dest)
+    };
+    if let Err(e) = ret {
+        message_error(
+            &format!("{}: {}", dest_name, tr_io_error(&e)),
+            format_args!(""),
+        );
+        io_unlink(tmp_name, tmp_st);
+        return true;
+    }
+
+    if !*OPT_SYNC.lock().unwrap() {
+        return false;
+    }
+
+    // 同步目录，保证重命名在删除源文件之前已写入磁盘
+    let dir = match dest_name.rfind('/') {
+        Some(0) => "/",
+        Some(pos) => &dest_name[..pos],
+        None => ".",
+    };
+    let c_dir = CString::new(dir).unwrap();
+    let ret = sys_fcntl::open_with_mode(&c_dir, O_RDONLY | libc::O_DIRECTORY, 0).and_then(|fd| {
+        let ret = sys_unistd::fsync(fd);
+        let _ = sys_unistd::close(fd);
+        ret
+    });
+    if let Err(e) = ret {
+        message_error(
+            &tr(Msg::DirSyncFailed, &[&dir, &tr_io_error(&e)]),
+            format_args!(""),
+        );
+        return true;
+    }
+
     false
 }
 
@@ -750,7 +1237,10 @@ fn io_close_dest(pair: &mut FilePair, success: bool) -> bool {
         assert!(pair.dest_fd == STDOUT_FILENO);
         *RESTORE_STDOUT_FLAGS.lock().unwrap() = false;
         if let Err(e) = sys_fcntl::fcntl_setfl(STDOUT_FILENO, *STDOUT_FLAGS.lock().unwrap()) {
-            eprintln!("错误：恢复标准输出 O_APPEND 标志失败: {}", e);
+            message_error(
+                &tr(Msg::StdoutAppendRestore, &[&tr_io_error(&e)]),
+                format_args!(""),
+            );
             return true;
         }
     }
@@ -759,20 +1249,56 @@ fn io_close_dest(pair: &mut FilePair, success: bool) -> bool {
         return false;
     }
 
+    // 持久写入模式下写入的是临时文件，出错时删除的也是它
+    let tmp_name = pair.dest_tmp_name.take();
+    let written_name = tmp_name.clone().or_else(|| pair.dest_name.clone());
+
+    // 重命名之前先把数据写入磁盘
+    let mut error = false;
+    if success && tmp_name.is_some() {
+        if let Err(e) = sys_unistd::fsync(pair.dest_fd) {
+            message_error(
+                &tr(
+                    Msg::SyncFailed,
+                    &[
+                        &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                        &tr_io_error(&e),
+                    ],
+                ),
+                format_args!(""),
+            );
+            error = true;
+        }
+    }
+
     if let Err(e) = sys_unistd::close(pair.dest_fd) {
-        eprintln!("错误：关闭文件失败: {}", e);
+        message_error(
+            &tr(
+                Msg::CloseFailed,
+                &[
+                    &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                    &tr_io_error(&e),
+                ],
+            ),
+            format_args!(""),
+        );
         // 关闭失败，不能信任文件内容，删除之
-        if let Some(ref name) = pair.dest_name {
+        if let Some(ref name) = written_name {
             io_unlink(name, &pair.dest_st);
         }
         return true;
     }
 
     // 如果操作未成功，删除目标文件
-    if !success {
-        if let Some(ref name) = pair.dest_name {
+    if !success || error {
+        if let Some(ref name) = written_name {
             io_unlink(name, &pair.dest_st);
         }
+        return error;
+    }
+
+    if let (Some(tmp_name), Some(dest_name)) = (tmp_name, pair.dest_name.as_deref()) {
+        return io_rename_dest(dest_name, &tmp_name, &pair.dest_st);
     }
 
     false
@@ -783,10 +1309,16 @@ pub fn io_close(pair: &mut FilePair, mut success: bool) {
     // 处理稀疏文件结尾
     if success && pair.dest_try_sparse && pair.dest_pending_sparse > 0 {
         // 向前 seek 到空洞末尾，写一个 0 字节
-        if sys_fs::lseek(pair.dest_fd, pair.dest_pending_sparse - 1, SEEK_CUR).is_err() {
-            eprintln!(
-                "错误：创建稀疏文件时 seek 失败: {}",
-                std::io::Error::last_os_error()
+        if let Err(e) = io_skip_dest(pair, pair.dest_pending_sparse - 1) {
+            message_error(
+                &tr(
+                    Msg::SparseSeekFailed,
+                    &[
+                        &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                        &tr_io_error(&e),
+                    ],
+                ),
+                format_args!(""),
             );
             success = false;
         } else {
@@ -797,6 +1329,33 @@ pub fn io_close(pair: &mut FilePair, mut success: bool) {
         }
     }
 
+    // 等待 io_uring 写完所有数据，之后才能设置时间戳和关闭文件
+    #[cfg(feature = "io_uring")]
+    if let Some(mut writer) = pair.uring_dest.take() {
+        if let Err(e) = writer.finish() {
+            if success {
+                message_error(
+                    &tr(
+                        Msg::WriteError,
+                        &[
+                            &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                            &tr_io_error(&e),
+                        ],
+                    ),
+                    format_args!(""),
+                );
+            }
+            success = false;
+        }
+    }
+
+    #[cfg(feature = "io_uring")]
+    {
+        pair.uring_src = None;
+    }
+
+    pair.src_map = None;
+
     signals_block();
 
     // 拷贝文件属性（仅当目标文件已打开且不是标准输出）
@@ -816,22 +1375,139 @@ pub fn io_close(pair: &mut FilePair, mut success: bool) {
 }
 
 pub fn io_fix_src_pos(pair: &mut FilePair, rewind: usize) {
+    // 从映射读取时一次可以给出多于 IO_BUFFER_SIZE 字节的输入
+    if pair.src_map.is_some() {
+        assert!(rewind <= pair.src_map_pos);
+        pair.src_map_pos -= rewind;
+        return;
+    }
+
     assert!(rewind <= IO_BUFFER_SIZE);
 
     if rewind > 0 {
+        #[cfg(feature = "io_uring")]
+        if let Some(reader) = pair.uring_src.as_mut() {
+            let pos = reader.pos() - rewind as u64;
+            reader.seek(pos);
+            return;
+        }
+
         // 对于不可 seek 的 fd 忽略错误
         let _ = sys_fs::lseek(pair.src_fd, -(rewind as off_t), SEEK_CUR);
     }
 }
 
+/// 从普通源文件中均匀取出 pieces 段、共约 size 字节的样本，第一段从文件开头开始
+///
+/// 使用 pread，不改变文件位置。源文件不是普通文件或读取失败时返回 None。
+pub fn io_read_sample(pair: &FilePair, size: usize, pieces: usize) -> Option<Vec<u8>> {
+    if (pair.src_st.st_mode & S_IFMT) != S_IFREG || pair.src_st.st_size <= 0 {
+        return None;
+    }
+
+    let file_size = pair.src_st.st_size as u64;
+    let (pieces, piece_size) = if file_size <= size as u64 {
+        (1, file_size as usize)
+    } else {
+        (pieces, size / pieces)
+    };
+
+    let mut sample = vec![0u8; pieces * piece_size];
+    let mut sample_len = 0;
+    for i in 0..pieces {
+        let offset = if pieces == 1 {
+            0
+        } else {
+            (file_size - piece_size as u64) / (pieces as u64 - 1) * i as u64
+        };
+        let piece = &mut sample[sample_len..sample_len + piece_size];
+        let mut done = 0;
+        while done < piece_size {
+            let pos = (offset + done as u64) as off_t;
+            match sys_unistd::pread(pair.src_fd, &mut piece[done..], pos) {
+                Ok(0) => break,
+                Ok(n) => done += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            }
+        }
+        sample_len += done;
+    }
+
+    sample.truncate(sample_len);
+    Some(sample)
+}
+
+/// 检查映射的源文件在读取期间是否被截断，返回 true 表示已被截断
+///
+/// 被截断后从映射读到的数据不可用，调用者应当停止处理。
+pub fn io_src_truncated(pair: &FilePair) -> bool {
+    match &pair.src_map {
+        Some(map) if map.is_truncated() => {
+            message_error(
+                &tr(
+                    Msg::ReadTruncated,
+                    &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+                ),
+                format_args!(""),
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 不复制地从映射的源文件取出最多 size 字节
+///
+/// 源文件没有被映射时返回 None，这时应使用 io_read()。取出的数据在 io_close()
+/// 之前有效；使用之前要用 io_src_truncated() 检查。
+pub fn io_read_map(pair: &mut FilePair, size: u64) -> Option<&[u8]> {
+    let map = pair.src_map.as_ref()?;
+    let data = map.data();
+
+    let start = pair.src_map_pos;
+    let end = start + (data.len() - start).min(usize::try_from(size).unwrap_or(usize::MAX));
+    pair.src_map_pos = end;
+
+    if end == data.len() {
+        pair.src_eof = true;
+    }
+
+    if start < end && !pair.src_has_seen_input {
+        pair.src_has_seen_input = true;
+        mytime_set_flush_time();
+    }
+
+    Some(&data[start..end])
+}
+
 /// 从源文件读取数据，返回实际读取字节数
 pub fn io_read(pair: &mut FilePair, buf: &mut IoBuf, size: usize) -> usize {
     assert!(size < usize::MAX);
 
+    // 映射的文件直接复制
+    if pair.src_map.is_some() {
+        let data = io_read_map(pair, size as u64).unwrap();
+        let amount = data.len();
+        buf.data[..amount].copy_from_slice(data);
+        if io_src_truncated(pair) {
+            return usize::MAX;
+        }
+        return amount;
+    }
+
     let mut pos = 0;
 
     while pos < size {
-        match sys_unistd::read(pair.src_fd, &mut buf.data[pos..size]) {
+        #[cfg(feature = "io_uring")]
+        let ret = match pair.uring_src.as_mut() {
+            Some(reader) => reader.read(&mut buf.data[pos..size]),
+            None => sys_unistd::read(pair.src_fd, &mut buf.data[pos..size]),
+        };
+        #[cfg(not(feature = "io_uring"))]
+        let ret = sys_unistd::read(pair.src_fd, &mut buf.data[pos..size]);
+
+        match ret {
             Ok(0) => {
                 pair.src_eof = true;
                 break;
@@ -870,10 +1546,12 @@ pub fn io_read(pair: &mut FilePair, buf: &mut IoBuf, size: usize) -> usize {
                 }
 
                 message_error(
-                    &format!(
-                        "{}: 读取错误: {}",
-                        pair.src_name.as_deref().unwrap_or("(unknown)"),
-                        err
+                    &tr(
+                        Msg::ReadError,
+                        &[
+                            &pair.src_name.as_deref().unwrap_or("(unknown)"),
+                            &tr_io_error(&err),
+                        ],
                     ),
                     format_args!(""),
                 );
@@ -892,15 +1570,32 @@ pub fn io_seek_src(pair: &mut FilePair, pos: u64) -> bool {
         message_bug();
     }
 
+    if pair.src_map.is_some() {
+        pair.src_map_pos = pos as usize;
+        pair.src_eof = false;
+        return false;
+    }
+
     let ret = sys_fs::lseek(pair.src_fd, pos as off_t, SEEK_SET);
     if let Err(e) = ret {
         message_error(
-            &format!("{:#?}: 定位文件出错: {}", pair.src_name, e),
+            &tr(
+                Msg::SeekError,
+                &[
+                    &pair.src_name.as_deref().unwrap_or("(unknown)"),
+                    &tr_io_error(&e),
+                ],
+            ),
             format_args!(""),
         );
         return true;
     }
 
+    #[cfg(feature = "io_uring")]
+    if let Some(reader) = pair.uring_src.as_mut() {
+        reader.seek(pos);
+    }
+
     pair.src_eof = false;
     false
 }
@@ -921,7 +1616,10 @@ pub fn io_pread(pair: &mut FilePair, buf: &mut IoBuf, size: usize, pos: u64) ->
     // 如果未读满，报错
     if amount != size {
         message_error(
-            &format!("{:#?}: 文件意外结束", pair.src_name),
+            &tr(
+                Msg::UnexpectedEof,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+            ),
             format_args!(""),
         );
         return true;
@@ -947,10 +1645,38 @@ fn is_sparse(buf: &[u8]) -> bool {
     true
 }
 
+/// 在目标文件中向前跳过 size 字节，留下空洞
+fn io_skip_dest(pair: &mut FilePair, size: off_t) -> io::Result<()> {
+    #[cfg(feature = "io_uring")]
+    if let Some(writer) = pair.uring_dest.as_mut() {
+        return writer.skip(size as u64);
+    }
+
+    sys_fs::lseek(pair.dest_fd, size, SEEK_CUR).map(|_| ())
+}
+
 /// 写缓冲区到目标文件，返回 true 表示出错
 fn io_write_buf(pair: &mut FilePair, buf: &[u8], mut size: usize) -> bool {
     assert!(size < usize::MAX);
 
+    #[cfg(feature = "io_uring")]
+    if let Some(writer) = pair.uring_dest.as_mut() {
+        if let Err(err) = writer.write(&buf[..size]) {
+            message_error(
+                &tr(
+                    Msg::WriteError,
+                    &[
+                        &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                        &tr_io_error(&err),
+                    ],
+                ),
+                format_args!(""),
+            );
+            return true;
+        }
+        return false;
+    }
+
     let mut remaining = &buf[..size];
     while !remaining.is_empty() {
         match sys_unistd::write(pair.dest_fd, remaining) {
@@ -970,10 +1696,12 @@ fn io_write_buf(pair: &mut FilePair, buf: &[u8], mut size: usize) -> bool {
                 }
                 if errno != EPIPE {
                     message_error(
-                        &format!(
-                            "{}: 写入错误: {}",
-                            pair.dest_name.as_deref().unwrap_or("(unknown)"),
-                            err
+                        &tr(
+                            Msg::WriteError,
+                            &[
+                                &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                                &tr_io_error(&err),
+                            ],
                         ),
                         format_args!(""),
                     );
@@ -1004,13 +1732,15 @@ pub fn io_write(pair: &mut FilePair, buf: &[u8], size: usize) -> bool {
 
         // 非稀疏块，如果有待处理的空洞，先跳过
         if pair.dest_pending_sparse > 0 {
-            let seek_ret = sys_fs::lseek(pair.dest_fd, pair.dest_pending_sparse, SEEK_CUR);
+            let seek_ret = io_skip_dest(pair, pair.dest_pending_sparse);
             if let Err(e) = seek_ret {
                 message_error(
-                    &format!(
-                        "{}: 创建稀疏文件时 seek 失败: {}",
-                        pair.dest_name.as_deref().unwrap_or("(unknown)"),
-                        e
+                    &tr(
+                        Msg::SparseSeekFailed,
+                        &[
+                            &pair.dest_name.as_deref().unwrap_or("(unknown)"),
+                            &tr_io_error(&e),
+                        ],
                     ),
                     format_args!(""),
                 );
@@ -5,7 +5,10 @@
  */
 
 use crate::args::{OPT_FORCE, OPT_ROBOT, OPT_STDOUT, STDIN_FILENAME};
-use crate::coder::{FormatType, OperationMode, OPT_FORMAT};
+use crate::catalog::{tr, tr_str, Msg};
+use crate::coder::{
+    is_format_lzip, is_format_lzma, is_format_xz, FormatType, OperationMode, OPT_FORMAT,
+};
 use crate::file_io::{
     io_close, io_open_src, io_pread, io_read, io_seek_src, FilePair, IoBuf, IO_BUFFER_SIZE,
 };
@@ -15,11 +18,11 @@ use crate::message::{
     message_verbosity_get, MessageVerbosity,
 };
 use crate::util::{round_up_to_mib, uint64_to_nicestr, uint64_to_str, NicestrUnit};
-use common::{my_min, tuklib_mbstr_width};
+use common::{my_min, read32le, read64le, tuklib_mbstr_width};
 use lazy_static::lazy_static;
 use liblzma::api::{
-    LzmaAction, LzmaBlock, LzmaCheck, LzmaFilter, LzmaIndexIter, LzmaIndexIterMode, LzmaRet,
-    LzmaStream, LZMA_BLOCK_HEADER_SIZE_MAX, LZMA_CHECK_ID_MAX, LZMA_FILTER_ARM64,
+    LzmaAction, LzmaBlock, LzmaCheck, LzmaFilter, LzmaIndexIter, LzmaIndexIterMode,
+    LzmaOptionsLzma, LzmaRet, LzmaStream, LZMA_BLOCK_HEADER_SIZE_MAX, LZMA_CHECK_ID_MAX, LZMA_FILTER_ARM64,
     LZMA_FILTER_LZMA2, LZMA_STREAM_HEADER_SIZE, LZMA_VLI_UNKNOWN,
 };
 use liblzma::check::lzma_check_size;
@@ -31,6 +34,7 @@ use liblzma::common::{
     lzma_index_stream_count, lzma_index_uncompressed_size, lzma_memusage,
     lzma_raw_decoder_memusage, LzmaIndex,
 };
+use liblzma::lzma::lzma_lzma_lclppb_decode;
 use liblzma::lzma_block_header_size_decode;
 use std::fmt::Write;
 use std::io;
@@ -312,14 +316,23 @@ pub fn xz_ver_to_str(ver: u32) -> String {
 pub fn parse_indexes(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
     // 文件为空
     if pair.src_st.st_size <= 0 {
-        message_error(&format!("{:#?}: 文件为空", pair.src_name), format_args!(""));
+        message_error(
+            &tr(
+                Msg::FileEmpty,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+            ),
+            format_args!(""),
+        );
         return true;
     }
 
     // 文件太小
     if pair.src_st.st_size < 2 * LZMA_STREAM_HEADER_SIZE as i64 {
         message_error(
-            &format!("{:#?}: 文件太小，不是有效的 .xz 文件", pair.src_name),
+            &tr(
+                Msg::TooSmallXz,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+            ),
             format_args!(""),
         );
         return true;
@@ -339,7 +352,11 @@ pub fn parse_indexes(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
     );
     if ret != LzmaRet::Ok {
         message_error(
-            &format!("{:#?}: {}", pair.src_name, message_strm(ret)),
+            &format!(
+                "{}: {}",
+                pair.src_name.as_deref().unwrap_or("(unknown)"),
+                message_strm(ret)
+            ),
             format_args!(""),
         );
         return true;
@@ -380,14 +397,18 @@ pub fn parse_indexes(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
                 // 计算 stream_padding
                 let mut iter = LzmaIndexIter::default();
                 lzma_index_iter_init(&mut iter, Box::new(xfi.idx.as_mut().unwrap().clone()));
-                while lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Stream) {
+                while !lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Stream) {
                     xfi.stream_padding += iter.stream.padding;
                 }
                 return false;
             }
             _ => {
                 message_error(
-                    &format!("{:#?}: {:#?}", pair.src_name, message_strm(ret)),
+                    &format!(
+                        "{}: {}",
+                        pair.src_name.as_deref().unwrap_or("(unknown)"),
+                        message_strm(ret)
+                    ),
                     format_args!(""),
                 );
                 if ret == LzmaRet::MemlimitError {
@@ -401,7 +422,7 @@ pub fn parse_indexes(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
 }
 
 /// 解析 .xz Block Header，返回 true 表示出错，false 表示成功
-fn parse_block_header(
+pub fn parse_block_header(
     pair: &mut FilePair,
     iter: &LzmaIndexIter,
     bhi: &mut BlockHeaderInfo,
@@ -436,21 +457,31 @@ fn parse_block_header(
     // 检查 Block Header 是否为 0（无效块）
     if buf.data[0] == 0 {
         message_error(
-            &format!("{:#?}: Block Header 无效", pair.src_name),
+            &tr(
+                Msg::BlockHeaderInvalid,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+            ),
             format_args!(""),
         );
         return true;
     }
 
     // 初始化 block 结构体
-    let mut filters: [LzmaFilter; 5] = std::array::from_fn(|_| LzmaFilter::default());
-    let mut block = LzmaBlock::default();
+    let mut block = LzmaBlock {
+        version: 1,
+        check: iter.stream.flags.clone().unwrap().check,
+        filters: (0..5).map(|_| LzmaFilter::default()).collect(),
+        ..Default::default()
+    };
 
     // 解析 Block Header Size
     block.header_size = lzma_block_header_size_decode!(buf.data[0]);
     if block.header_size > size as u32 {
         message_error(
-            &format!("{:#?}: Block Header Size 超出范围", pair.src_name),
+            &tr(
+                Msg::BlockHeaderSize,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+            ),
             format_args!(""),
         );
         return true;
@@ -459,13 +490,13 @@ fn parse_block_header(
     // 解析 Block Header
 
     let header_size = block.header_size;
-    match lzma_block_header_decode(&mut block, &mut buf.data[..(header_size as usize)].to_vec()) {
+    match lzma_block_header_decode(&mut block, &buf.data[..header_size as usize]) {
         LzmaRet::Ok => {}
         LzmaRet::OptionsError => {
             message_error(
                 &format!(
-                    "{:#?}: {}",
-                    pair.src_name,
+                    "{}: {}",
+                    pair.src_name.as_deref().unwrap_or("(unknown)"),
                     message_strm(LzmaRet::OptionsError)
                 ),
                 format_args!(""),
@@ -474,7 +505,11 @@ fn parse_block_header(
         }
         LzmaRet::DataError => {
             message_error(
-                &format!("{:#?}: {}", pair.src_name, message_strm(LzmaRet::DataError)),
+                &format!(
+                    "{}: {}",
+                    pair.src_name.as_deref().unwrap_or("(unknown)"),
+                    message_strm(LzmaRet::DataError)
+                ),
                 format_args!(""),
             );
             return true;
@@ -508,7 +543,11 @@ fn parse_block_header(
                 && block.uncompressed_size != iter.block.uncompressed_size
             {
                 message_error(
-                    &format!("{:#?}: {}", pair.src_name, message_strm(LzmaRet::DataError)),
+                    &format!(
+                        "{}: {}",
+                        pair.src_name.as_deref().unwrap_or("(unknown)"),
+                        message_strm(LzmaRet::DataError)
+                    ),
                     format_args!(""),
                 );
                 return true;
@@ -516,7 +555,11 @@ fn parse_block_header(
         }
         LzmaRet::DataError => {
             message_error(
-                &format!("{:#?}: {}", pair.src_name, message_strm(LzmaRet::DataError)),
+                &format!(
+                    "{}: {}",
+                    pair.src_name.as_deref().unwrap_or("(unknown)"),
+                    message_strm(LzmaRet::DataError)
+                ),
                 format_args!(""),
             );
             return true;
@@ -562,7 +605,11 @@ fn parse_block_header(
         LzmaRet::Ok => bhi.filter_chain = output_str,
         ret => {
             message_error(
-                &format!("{:#?}: {}", pair.src_name, message_strm(ret)),
+                &format!(
+                    "{}: {}",
+                    pair.src_name.as_deref().unwrap_or("(unknown)"),
+                    message_strm(ret)
+                ),
                 format_args!(""),
             );
             return true;
@@ -839,11 +886,13 @@ fn print_info_adv(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
         width8 = headings[HEADING_CHECK].fw,
         width9 = headings[HEADING_PADDING].fw,
     );
+    // 打印块表头时会再次锁定 HEADINGS
+    drop(headings);
 
     // 遍历所有流
     let mut iter = LzmaIndexIter::default();
     lzma_index_iter_init(&mut iter, Box::new(xfi.idx.as_mut().unwrap().clone()));
-    while lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Stream) {
+    while !lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Stream) {
         let cols1 = [
             uint64_to_str(iter.stream.number, 0),
             uint64_to_str(iter.stream.block_count, 1),
@@ -929,7 +978,7 @@ fn print_info_adv(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
 
         // 遍历所有块
         lzma_index_iter_init(&mut iter, Box::new(xfi.idx.as_mut().unwrap().clone()));
-        while lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Block) {
+        while !lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Block) {
             let mut bhi = BlockHeaderInfo::default();
             if detailed
                 && parse_details(
@@ -1020,7 +1069,7 @@ fn print_info_robot(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
     let checks = get_check_names(lzma_index_checks(xfi.idx.as_ref().unwrap()), false);
 
     // 打印文件名
-    println!("name\t{:#?}", pair.src_name);
+    println!("name\t{}", pair.src_name.as_deref().unwrap_or(""));
 
     // 打印文件总览信息
     println!(
@@ -1043,7 +1092,7 @@ fn print_info_robot(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
         lzma_index_iter_init(&mut iter, Box::new(xfi.idx.as_mut().unwrap().clone()));
 
         // 打印每个流
-        while lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Stream) {
+        while !lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Stream) {
             let flags = iter.stream.flags.as_ref().unwrap();
             println!(
                 "stream\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
@@ -1063,7 +1112,7 @@ fn print_info_robot(xfi: &mut XzFileInfo, pair: &mut FilePair) -> bool {
         lzma_index_iter_rewind(&mut iter);
 
         // 打印每个块
-        while lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Block) {
+        while !lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Block) {
             let mut bhi = BlockHeaderInfo::default();
             let mut tmp = iter.clone();
             if message_verbosity_get() >= MessageVerbosity::Debug
@@ -1142,6 +1191,553 @@ fn update_totals(xfi: &XzFileInfo) {
     totals.all_have_sizes &= xfi.all_have_sizes;
 }
 
+/// .lzma 文件头的长度：1 字节 lc/lp/pb、4 字节字典大小、8 字节未压缩大小
+const LZMA_ALONE_HEADER_SIZE: usize = 13;
+
+/// .lz 成员头部长度："LZIP" 魔数、版本号和编码后的字典大小
+const LZIP_HEADER_SIZE: usize = 6;
+
+/// .lz 格式版本 0 的成员尾部缺少 64 位成员大小字段
+const LZIP_V0_TRAILER_SIZE: usize = 12;
+const LZIP_V1_TRAILER_SIZE: usize = 20;
+
+/// 关于 .lzma 文件的信息
+#[derive(Debug, Clone, Default)]
+pub struct LzmaAloneInfo {
+    /// 字面上下文位数
+    pub lc: u32,
+    /// 字面位置位数
+    pub lp: u32,
+    /// 位置位数
+    pub pb: u32,
+    /// 头部中声明的字典大小
+    pub dict_size: u32,
+    /// 头部中声明的未压缩大小，未知时为 LZMA_VLI_UNKNOWN
+    pub uncompressed_size: u64,
+    /// 文件大小
+    pub file_size: u64,
+}
+
+/// .lz 文件中单个成员的信息
+#[derive(Debug, Clone, Default)]
+pub struct LzipMemberInfo {
+    /// 成员在文件中的偏移
+    pub offset: u64,
+    /// 成员格式版本（0 或 1）
+    pub version: u8,
+    /// 成员头部中编码的字典大小
+    pub dict_size: u32,
+    /// 尾部中记录的未压缩数据大小
+    pub data_size: u64,
+    /// 成员总大小（包括头部和尾部）
+    pub member_size: u64,
+    /// 未压缩数据的 CRC32
+    pub crc32: u32,
+}
+
+/// 关于 .lz 文件的信息
+#[derive(Debug, Clone, Default)]
+pub struct LzipFileInfo {
+    /// 按文件中出现的顺序排列的成员
+    pub members: Vec<LzipMemberInfo>,
+    /// 文件大小
+    pub file_size: u64,
+}
+
+impl LzipFileInfo {
+    /// 所有成员的未压缩大小之和
+    pub fn data_size(&self) -> u64 {
+        self.members.iter().map(|m| m.data_size).sum()
+    }
+
+    /// 所有成员中最大的字典大小
+    pub fn dict_size_max(&self) -> u32 {
+        self.members.iter().map(|m| m.dict_size).max().unwrap_or(0)
+    }
+}
+
+/// 解码 .lz 成员头部中的字典大小字节，无效时返回 None
+///
+/// 最低 5 位是字典大小以 2 为底的对数，最高 3 位是要减去的分数部分
+/// （0/16 到 7/16）。版本 0 和 1 只允许 [4 KiB, 512 MiB]。
+fn lzip_dict_size_decode(ds: u8) -> Option<u32> {
+    let b2log = (ds & 0x1F) as u32;
+    let fracnum = (ds >> 5) as u32;
+
+    if b2log < 12 || b2log > 29 || (b2log == 12 && fracnum > 0) {
+        return None;
+    }
+
+    Some((1u32 << b2log) - (fracnum << (b2log - 4)))
+}
+
+/// 解析 .lzma 文件头，返回 true 表示出错，false 表示成功
+fn parse_lzma_header(info: &mut LzmaAloneInfo, pair: &mut FilePair) -> bool {
+    if pair.src_st.st_size < LZMA_ALONE_HEADER_SIZE as i64 {
+        message_error(
+            &tr(
+                Msg::TooSmallLzma,
+                &[&pair.src_name.as_deref().unwrap_or("(unknown)")],
+            ),
+            format_args!(""),
+        );
+        return true;
+    }
+
+    let mut buf = IoBuf {
+        data: [0; IO_BUFFER_SIZE],
+    };
+    if io_pread(pair, &mut buf, LZMA_ALONE_HEADER_SIZE, 0) {
+        return true;
+    }
+
+    let mut opt = LzmaOptionsLzma::default();
+    if lzma_lzma_lclppb_decode(&mut opt, buf.data[0]) {
+        message_error(
+            &format!(
+                "{}: {}",
+                pair.src_name.as_deref().unwrap_or("(unknown)"),
+                message_strm(LzmaRet::FormatError)
+            ),
+            format_args!(""),
+        );
+        return true;
+    }
+
+    info.lc = opt.lc;
+    info.lp = opt.lp;
+    info.pb = opt.pb;
+    info.dict_size = read32le(&buf.data[1..5]);
+    info.uncompressed_size = read64le(&buf.data[5..13]);
+    info.file_size = pair.src_st.st_size as u64;
+    false
+}
+
+/// 从文件末尾向前遍历 .lz 成员尾部，返回 true 表示出错，false 表示成功
+fn parse_lzip_members(info: &mut LzipFileInfo, pair: &mut FilePair) -> bool {
+    let name = pair.src_name.clone().unwrap_or_default();
+    let file_size = pair.src_st.st_size as u64;
+    info.file_size = file_size;
+
+    let min_member_size = (LZIP_HEADER_SIZE + LZIP_V0_TRAILER_SIZE) as u64;
+    if file_size < min_member_size {
+        message_error(&tr(Msg::TooSmallLz, &[&name]), format_args!(""));
+        return true;
+    }
+
+    let mut buf = IoBuf {
+        data: [0; IO_BUFFER_SIZE],
+    };
+
+    // 版本 0 的尾部没有成员大小字段，无法向前遍历，
+    // 此时整个文件只能是一个成员。
+    if io_pread(pair, &mut buf, LZIP_HEADER_SIZE, 0) {
+        return true;
+    }
+    if buf.data[4] == 0 {
+        let dict_size = match lzip_dict_size_decode(buf.data[5]) {
+            Some(d) => d,
+            None => {
+                message_error(
+                    &format!("{}: {}", name, message_strm(LzmaRet::DataError)),
+                    format_args!(""),
+                );
+                return true;
+            }
+        };
+
+        let trailer_pos = file_size - LZIP_V0_TRAILER_SIZE as u64;
+        if io_pread(pair, &mut buf, LZIP_V0_TRAILER_SIZE, trailer_pos) {
+            return true;
+        }
+
+        info.members.push(LzipMemberInfo {
+            offset: 0,
+            version: 0,
+            dict_size,
+            data_size: read64le(&buf.data[4..12]),
+            member_size: file_size,
+            crc32: read32le(&buf.data[0..4]),
+        });
+        return false;
+    }
+
+    let mut pos = file_size;
+    while pos > 0 {
+        if pos < (LZIP_HEADER_SIZE + LZIP_V1_TRAILER_SIZE) as u64 {
+            message_error(
+                &format!("{}: {}", name, message_strm(LzmaRet::DataError)),
+                format_args!(""),
+            );
+            return true;
+        }
+
+        // 尾部：CRC32 (4)、数据大小 (8)、成员大小 (8)，均为小端序
+        if io_pread(
+            pair,
+            &mut buf,
+            LZIP_V1_TRAILER_SIZE,
+            pos - LZIP_V1_TRAILER_SIZE as u64,
+        ) {
+            return true;
+        }
+
+        let crc32 = read32le(&buf.data[0..4]);
+        let data_size = read64le(&buf.data[4..12]);
+        let member_size = read64le(&buf.data[12..20]);
+
+        if member_size < (LZIP_HEADER_SIZE + LZIP_V1_TRAILER_SIZE) as u64 || member_size > pos {
+            message_error(&tr(Msg::LzipMemberSize, &[&name]), format_args!(""));
+            return true;
+        }
+
+        let offset = pos - member_size;
+        if io_pread(pair, &mut buf, LZIP_HEADER_SIZE, offset) {
+            return true;
+        }
+
+        if buf.data[..4] != *b"LZIP" {
+            message_error(
+                &format!("{}: {}", name, message_strm(LzmaRet::FormatError)),
+                format_args!(""),
+            );
+            return true;
+        }
+
+        // 只有版本 1 的成员才能通过成员大小字段定位
+        if buf.data[4] != 1 {
+            message_error(
+                &format!("{}: {}", name, message_strm(LzmaRet::OptionsError)),
+                format_args!(""),
+            );
+            return true;
+        }
+
+        let dict_size = match lzip_dict_size_decode(buf.data[5]) {
+            Some(d) => d,
+            None => {
+                message_error(
+                    &format!("{}: {}", name, message_strm(LzmaRet::DataError)),
+                    format_args!(""),
+                );
+                return true;
+            }
+        };
+
+        info.members.push(LzipMemberInfo {
+            offset,
+            version: 1,
+            dict_size,
+            data_size,
+            member_size,
+            crc32,
+        });
+
+        pos = offset;
+    }
+
+    // 向后遍历得到的成员是倒序的
+    info.members.reverse();
+    false
+}
+
+/// 打印 .lzma/.lz 文件在基础模式下的一行摘要
+fn print_legacy_basic(
+    streams: u64,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    check: LzmaCheck,
+    pair: &FilePair,
+) {
+    {
+        let mut displayed = HEADINGS_DISPLAYED.lock().unwrap();
+        if !*displayed {
+            *displayed = true;
+            println!("Strms  Blocks   Compressed Uncompressed  Ratio  Check   Filename");
+        }
+    }
+
+    let uncompressed = if uncompressed_size == LZMA_VLI_UNKNOWN {
+        "---"
+    } else {
+        uint64_to_nicestr(
+            uncompressed_size,
+            NicestrUnit::B,
+            NicestrUnit::TiB,
+            false,
+            3,
+        )
+    };
+    let ratio = if uncompressed_size == LZMA_VLI_UNKNOWN {
+        "---"
+    } else {
+        get_ratio(compressed_size, uncompressed_size)
+    };
+
+    println!(
+        "{:>5} {:>7}  {:>11}  {:>11}  {:>5}  {:<7} {}",
+        uint64_to_str(streams, 0),
+        "---",
+        uint64_to_nicestr(compressed_size, NicestrUnit::B, NicestrUnit::TiB, false, 2),
+        uncompressed,
+        ratio,
+        CHECK_NAMES[check as usize],
+        pair.src_name.as_deref().unwrap_or("(unknown)")
+    );
+}
+
+/// 打印 .lzma 文件信息
+fn print_lzma_info(info: &LzmaAloneInfo, pair: &FilePair) {
+    let size_known = info.uncompressed_size != LZMA_VLI_UNKNOWN;
+
+    if *OPT_ROBOT.lock().unwrap() {
+        println!("name\t{}", pair.src_name.as_deref().unwrap_or(""));
+        println!(
+            "lzma\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            info.file_size,
+            if size_known {
+                info.uncompressed_size.to_string()
+            } else {
+                "unknown".to_string()
+            },
+            if size_known {
+                get_ratio(info.file_size, info.uncompressed_size)
+            } else {
+                "---"
+            },
+            info.dict_size,
+            info.lc,
+            info.lp,
+            info.pb
+        );
+        return;
+    }
+
+    if message_verbosity_get() <= MessageVerbosity::Warning {
+        print_legacy_basic(
+            1,
+            info.file_size,
+            info.uncompressed_size,
+            LzmaCheck::None,
+            pair,
+        );
+        return;
+    }
+
+    println!("  {:<18} .lzma", "Format:");
+    println!(
+        "  {:<18} {}",
+        COLON_STRS[2],
+        uint64_to_nicestr(info.file_size, NicestrUnit::B, NicestrUnit::TiB, true, 0)
+    );
+    if size_known {
+        println!(
+            "  {:<18} {}",
+            COLON_STRS[3],
+            uint64_to_nicestr(
+                info.uncompressed_size,
+                NicestrUnit::B,
+                NicestrUnit::TiB,
+                true,
+                0
+            )
+        );
+        println!(
+            "  {:<18} {}",
+            COLON_STRS[4],
+            get_ratio(info.file_size, info.uncompressed_size)
+        );
+    } else {
+        println!("  {:<18} Unknown (end of payload marker)", COLON_STRS[3]);
+    }
+    println!(
+        "  {:<18} {}",
+        "Dictionary size:",
+        uint64_to_nicestr(
+            info.dict_size as u64,
+            NicestrUnit::B,
+            NicestrUnit::MiB,
+            true,
+            0
+        )
+    );
+    println!("  {:<18} {}/{}/{}", "lc/lp/pb:", info.lc, info.lp, info.pb);
+}
+
+/// 打印 .lz 文件信息
+fn print_lzip_info(info: &LzipFileInfo, pair: &FilePair) {
+    let data_size = info.data_size();
+
+    if *OPT_ROBOT.lock().unwrap() {
+        println!("name\t{}", pair.src_name.as_deref().unwrap_or(""));
+        println!(
+            "lzip\t{}\t{}\t{}\t{}\t{}",
+            info.members.len(),
+            info.file_size,
+            data_size,
+            get_ratio(info.file_size, data_size),
+            info.dict_size_max()
+        );
+        if message_verbosity_get() >= MessageVerbosity::Verbose {
+            for (i, m) in info.members.iter().enumerate() {
+                println!(
+                    "member\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:08x}",
+                    i + 1,
+                    m.offset,
+                    m.version,
+                    m.dict_size,
+                    m.data_size,
+                    m.member_size,
+                    get_ratio(m.member_size, m.data_size),
+                    m.crc32
+                );
+            }
+        }
+        return;
+    }
+
+    if message_verbosity_get() <= MessageVerbosity::Warning {
+        print_legacy_basic(
+            info.members.len() as u64,
+            info.file_size,
+            data_size,
+            LzmaCheck::Crc32,
+            pair,
+        );
+        return;
+    }
+
+    println!("  {:<18} .lz", "Format:");
+    println!(
+        "  {:<18} {}",
+        "Members:",
+        uint64_to_str(info.members.len() as u64, 0)
+    );
+    println!(
+        "  {:<18} {}",
+        COLON_STRS[2],
+        uint64_to_nicestr(info.file_size, NicestrUnit::B, NicestrUnit::TiB, true, 0)
+    );
+    println!(
+        "  {:<18} {}",
+        COLON_STRS[3],
+        uint64_to_nicestr(data_size, NicestrUnit::B, NicestrUnit::TiB, true, 0)
+    );
+    println!(
+        "  {:<18} {}",
+        COLON_STRS[4],
+        get_ratio(info.file_size, data_size)
+    );
+    println!("  {:<18} {}", COLON_STRS[5], CHECK_NAMES[LzmaCheck::Crc32 as usize]);
+
+    println!(
+        "  Members:\n    {:>6} {:>7} {:>15} {:>11} {:>15} {:>15}  {:>5}  {:<8}",
+        "Member", "Version", "Offset", "DictSize", "DataSize", "MemberSize", "Ratio", "CRC32"
+    );
+    for (i, m) in info.members.iter().enumerate() {
+        println!(
+            "    {:>6} {:>7} {:>15} {:>11} {:>15} {:>15}  {:>5}  {:08x}",
+            i + 1,
+            m.version,
+            m.offset,
+            uint64_to_nicestr(m.dict_size as u64, NicestrUnit::B, NicestrUnit::MiB, false, 0),
+            m.data_size,
+            m.member_size,
+            get_ratio(m.member_size, m.data_size),
+            m.crc32
+        );
+    }
+}
+
+/// 把 .lzma 或 .lz 文件计入总计
+fn update_totals_legacy(
+    streams: u64,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    check: LzmaCheck,
+    memusage: u64,
+) {
+    let mut totals = TOTALS.lock().unwrap();
+    totals.files += 1;
+    totals.streams += streams;
+    totals.compressed_size += compressed_size;
+    if uncompressed_size == LZMA_VLI_UNKNOWN {
+        totals.all_have_sizes = false;
+    } else {
+        totals.uncompressed_size += uncompressed_size;
+    }
+    totals.checks |= 1 << (check as u32);
+    if totals.memusage_max < memusage {
+        totals.memusage_max = memusage;
+    }
+}
+
+/// 列出 .lzma 文件的信息，返回 true 表示出错
+fn list_lzma_file(pair: &mut FilePair) -> bool {
+    let mut info = LzmaAloneInfo::default();
+    if parse_lzma_header(&mut info, pair) {
+        return true;
+    }
+
+    print_lzma_info(&info, pair);
+    update_totals_legacy(
+        1,
+        info.file_size,
+        info.uncompressed_size,
+        LzmaCheck::None,
+        info.dict_size as u64,
+    );
+    false
+}
+
+/// 列出 .lz 文件的信息，返回 true 表示出错
+fn list_lzip_file(pair: &mut FilePair) -> bool {
+    let mut info = LzipFileInfo::default();
+    if parse_lzip_members(&mut info, pair) {
+        return true;
+    }
+
+    print_lzip_info(&info, pair);
+    update_totals_legacy(
+        info.members.len() as u64,
+        info.file_size,
+        info.data_size(),
+        LzmaCheck::Crc32,
+        info.dict_size_max() as u64,
+    );
+    false
+}
+
+/// 根据文件开头的魔数判断要列出的文件格式
+fn list_detect_format(pair: &mut FilePair) -> Option<FormatType> {
+    let opt_format = *OPT_FORMAT.lock().unwrap();
+    if opt_format != FormatType::Auto {
+        return Some(opt_format);
+    }
+
+    let size = my_min(
+        pair.src_st.st_size.max(0) as u64,
+        LZMA_ALONE_HEADER_SIZE as u64,
+    ) as usize;
+    let mut buf = IoBuf {
+        data: [0; IO_BUFFER_SIZE],
+    };
+    if io_pread(pair, &mut buf, size, 0) || io_seek_src(pair, 0) {
+        return None;
+    }
+
+    if is_format_xz(&buf, size) {
+        Some(FormatType::Xz)
+    } else if is_format_lzip(&buf, size) {
+        Some(FormatType::Lzip)
+    } else if is_format_lzma(&buf, size) {
+        Some(FormatType::Lzma)
+    } else {
+        // 未识别的文件按 .xz 处理，由 parse_indexes 报告错误
+        Some(FormatType::Xz)
+    }
+}
+
 /// 打印总览统计信息（基础模式）
 fn print_totals_basic() {
     // 打印分隔线
@@ -1303,20 +1899,15 @@ fn init_field_widths() {
 }
 /// 列出单个文件的信息
 pub fn list_file(filename: &str) {
-    // 只支持 .xz 格式
-    if *OPT_FORMAT.lock().unwrap() != FormatType::Xz
-        && *OPT_FORMAT.lock().unwrap() != FormatType::Auto
-    {
-        message_fatal(
-            "--list 仅支持 .xz 文件 (--format=xz 或 --format=auto)",
-            format_args!(""),
-        );
+    // 支持 .xz、.lzma 和 .lz 格式
+    if *OPT_FORMAT.lock().unwrap() == FormatType::Raw {
+        message_fatal(tr_str(Msg::ListRaw), format_args!(""));
     }
 
     message_filename(filename);
 
     if filename == STDIN_FILENAME {
-        message_error("--list 不支持从标准输入读取", format_args!(""));
+        message_error(tr_str(Msg::ListStdin), format_args!(""));
         return;
     }
 
@@ -1332,26 +1923,37 @@ pub fn list_file(filename: &str) {
         None => return,
     };
 
-    // 初始化文件信息
-    let mut xfi = XzFileInfo::default();
-
-    // 解析索引
-    if !parse_indexes(&mut xfi, &mut pair) {
-        let fail = if *OPT_ROBOT.lock().unwrap() {
-            print_info_robot(&mut xfi, &mut pair)
-        } else if message_verbosity_get() <= MessageVerbosity::Warning {
-            print_info_basic(&xfi, &pair)
-        } else {
-            print_info_adv(&mut xfi, &mut pair)
-        };
-
-        // 统计汇总（只统计未出错的文件）
-        if !fail {
-            update_totals(&xfi);
+    match list_detect_format(&mut pair) {
+        Some(FormatType::Lzma) => {
+            list_lzma_file(&mut pair);
+        }
+        Some(FormatType::Lzip) => {
+            list_lzip_file(&mut pair);
         }
+        Some(_) => {
+            // 初始化文件信息
+            let mut xfi = XzFileInfo::default();
+
+            // 解析索引
+            if !parse_indexes(&mut xfi, &mut pair) {
+                let fail = if *OPT_ROBOT.lock().unwrap() {
+                    print_info_robot(&mut xfi, &mut pair)
+                } else if message_verbosity_get() <= MessageVerbosity::Warning {
+                    print_info_basic(&xfi, &pair)
+                } else {
+                    print_info_adv(&mut xfi, &mut pair)
+                };
+
+                // 统计汇总（只统计未出错的文件）
+                if !fail {
+                    update_totals(&xfi);
+                }
 
-        // 释放索引
-        if let Some(mut idx) = xfi.idx.take() {}
+                // 释放索引
+                if let Some(mut idx) = xfi.idx.take() {}
+            }
+        }
+        None => {}
     }
 
     io_close(&mut pair, false);
@@ -15,22 +15,36 @@
 #![allow(clippy::all)]
 
 mod args;
+mod catalog;
 mod coder;
+mod dict;
+mod encrypt;
 mod file_io;
 mod hardware;
 mod list;
 mod message;
+mod mmap;
 mod mytime;
 mod options;
+mod sign;
 mod signals;
 mod suffix;
+mod tar;
+#[cfg(feature = "io_uring")]
+mod uring;
 mod util;
+mod verify;
 
 use crate::args::parse_real;
-use args::{args_parse, ArgsInfo, OPT_ROBOT, OPT_STDOUT, STDIN_FILENAME};
-use coder::{coder_run, OperationMode, OPT_MODE};
+use args::{
+    args_parse, ArgsInfo, OPT_JOBS, OPT_RECURSIVE, OPT_ROBOT, OPT_STDOUT, OPT_TAR, OPT_TRAIN_DICT,
+    OPT_TRAIN_SIZE, OPT_VERIFY, STDIN_FILENAME,
+};
+use catalog::{catalog_init, tr, tr_io_error, tr_str, Msg};
+use coder::{coder_run, coder_run_parallel, OperationMode, OPT_MODE};
 use common::{tuklib_exit, PROGNAME};
-use file_io::io_init;
+use dict::dict_train_run;
+use file_io::{io_init, io_is_dir, io_walk_dir};
 use hardware::hardware_init;
 use lazy_static::lazy_static;
 use list::{list_file, list_totals};
@@ -39,6 +53,7 @@ use message::{
     message_verbosity_get, MessageVerbosity,
 };
 use signals::{signals_exit, signals_init, USER_ABORT};
+use tar::tar_run;
 use std::{
     env,
     io::{self, Read},
@@ -47,6 +62,7 @@ use std::{
     time::Duration,
 };
 use util::{is_tty_stdin, is_tty_stdout};
+use verify::verify_file;
 
 const E_SUCCESS: i32 = 0;
 const E_ERROR: i32 = 1;
@@ -97,9 +113,12 @@ fn read_name(args: &mut ArgsInfo) -> Option<String> {
         let file = match args.files_file.as_mut() {
             Some(f) => f,
             None => {
-                eprintln!(
-                    "{}: 文件句柄不存在",
-                    args.files_name.as_deref().unwrap_or("(unknown)")
+                message_error(
+                    &tr(
+                        Msg::FilesNoHandle,
+                        &[&args.files_name.as_deref().unwrap_or("(unknown)")],
+                    ),
+                    format_args!(""),
                 );
                 return None;
             }
@@ -110,9 +129,12 @@ fn read_name(args: &mut ArgsInfo) -> Option<String> {
             Ok(0) => {
                 // 遇到文件结尾
                 if !name.is_empty() {
-                    eprintln!(
-                        "{}: 读取文件名时遇到意外的输入结束",
-                        args.files_name.as_deref().unwrap_or("(unknown)")
+                    message_error(
+                        &tr(
+                            Msg::FilesUnexpectedEnd,
+                            &[&args.files_name.as_deref().unwrap_or("(unknown)")],
+                        ),
+                        format_args!(""),
                     );
                 }
                 return None;
@@ -129,9 +151,12 @@ fn read_name(args: &mut ArgsInfo) -> Option<String> {
                 }
                 // 如果读取到 '\0' 字符，而分隔符不是 '\0'，则报错
                 if c == 0 {
-                    eprintln!(
-                        "{}: 读取文件名时发现空字符；也许你应该用 \"--files0\" 而不是 \"--files\"",
-                        args.files_name.as_deref().unwrap_or("(unknown)")
+                    message_error(
+                        &tr(
+                            Msg::FilesNulChar,
+                            &[&args.files_name.as_deref().unwrap_or("(unknown)")],
+                        ),
+                        format_args!(""),
                     );
                     return None;
                 }
@@ -145,10 +170,15 @@ fn read_name(args: &mut ArgsInfo) -> Option<String> {
                 continue;
             }
             Err(e) => {
-                eprintln!(
-                    "{}: 读取文件名时出错: {}",
-                    args.files_name.as_deref().unwrap_or("(unknown)"),
-                    e
+                message_error(
+                    &tr(
+                        Msg::FilesReadError,
+                        &[
+                            &args.files_name.as_deref().unwrap_or("(unknown)"),
+                            &tr_io_error(&e),
+                        ],
+                    ),
+                    format_args!(""),
                 );
                 return None;
             }
@@ -158,6 +188,116 @@ fn read_name(args: &mut ArgsInfo) -> Option<String> {
     None
 }
 
+/// 使用 -r 时把目录展开为其中需要处理的文件，否则原样返回名称
+fn expand_name(name: &str) -> Vec<String> {
+    if *OPT_RECURSIVE.lock().unwrap() && name != STDIN_FILENAME && io_is_dir(name) {
+        let mut files = Vec::new();
+        io_walk_dir(name, &mut files);
+        files
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+/// 收集所有文件名后使用 jobs 个线程并行测试或解压缩
+///
+/// 所有文件处理完后以累计的退出状态退出程序，不会返回。
+fn run_parallel(args: &mut ArgsInfo, jobs: u32) {
+    let mode = OPT_MODE.lock().unwrap().clone();
+    if mode != OperationMode::Test && mode != OperationMode::Decompress {
+        message_fatal(tr_str(Msg::JobsModes), format_args!(""));
+    }
+
+    // 多个文件同时写入标准输出会使输出交错
+    if mode == OperationMode::Decompress && *OPT_STDOUT.lock().unwrap() {
+        message_fatal(tr_str(Msg::JobsStdout), format_args!(""));
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    for name in &args.arg_names[..args.arg_count as usize] {
+        names.extend(expand_name(name));
+    }
+    if args.files_name.is_some() {
+        while let Some(name) = read_name(args) {
+            names.extend(expand_name(&name));
+        }
+    }
+
+    if names
+        .iter()
+        .any(|name| name == "-" || name == STDIN_FILENAME)
+    {
+        message_fatal(tr_str(Msg::JobsStdin), format_args!(""));
+    }
+
+    coder_run_parallel(&names, jobs);
+
+    signals_exit();
+
+    let mut es: ExitStatusType = *EXIT_STATUS.lock().unwrap();
+    if (es == ExitStatusType::EError && *NO_WARN.lock().unwrap()) {
+        es = ExitStatusType::ESuccess;
+    }
+
+    tuklib_exit(
+        es as i32,
+        E_ERROR,
+        (message_verbosity_get() != MessageVerbosity::Silent) as i32,
+    );
+}
+
+/// --tar 模式：处理一个归档后以累计的退出状态退出程序，不会返回。
+fn run_tar(args: &mut ArgsInfo) {
+    let mode = OPT_MODE.lock().unwrap().clone();
+    let mut names: Vec<String> = args.arg_names[..args.arg_count as usize].to_vec();
+    if args.files_name.is_some() {
+        while let Some(name) = read_name(args) {
+            names.push(name);
+        }
+    }
+
+    signals_init();
+    tar_run(mode, &names);
+    signals_exit();
+
+    let mut es: ExitStatusType = *EXIT_STATUS.lock().unwrap();
+    if (es == ExitStatusType::EError && *NO_WARN.lock().unwrap()) {
+        es = ExitStatusType::ESuccess;
+    }
+
+    tuklib_exit(
+        es as i32,
+        E_ERROR,
+        (message_verbosity_get() != MessageVerbosity::Silent) as i32,
+    );
+}
+
+/// --train-dict 模式：从样本文件训练字典后以累计的退出状态退出程序，不会返回。
+fn run_train_dict(args: &mut ArgsInfo, out: &str) {
+    let mut names: Vec<String> = Vec::new();
+    for name in &args.arg_names[..args.arg_count as usize] {
+        names.extend(expand_name(name));
+    }
+    if args.files_name.is_some() {
+        while let Some(name) = read_name(args) {
+            names.extend(expand_name(&name));
+        }
+    }
+
+    dict_train_run(out, &names, *OPT_TRAIN_SIZE.lock().unwrap());
+
+    let mut es: ExitStatusType = *EXIT_STATUS.lock().unwrap();
+    if (es == ExitStatusType::EError && *NO_WARN.lock().unwrap()) {
+        es = ExitStatusType::ESuccess;
+    }
+
+    tuklib_exit(
+        es as i32,
+        E_ERROR,
+        (message_verbosity_get() != MessageVerbosity::Silent) as i32,
+    );
+}
+
 // static mut PROGNAME: Option<String> = None;
 
 /// 初始化全局程序名
@@ -185,6 +325,9 @@ fn main() {
     // Set up program name
     tuklib_progname_init();
 
+    // Select the message language before anything is printed
+    catalog_init();
+
     // Initialize file I/O (stdin, stdout, stderr)
     io_init();
 
@@ -198,14 +341,21 @@ fn main() {
     // // Handle unsupported robot mode
     if *OPT_MODE.lock().unwrap() != OperationMode::List && *OPT_ROBOT.lock().unwrap() {
         // Just an example, replace with actual condition
-        message_fatal(
-            "Compression and decompression with --robot are not supported yet.",
-            format_args!(""),
-        );
+        message_fatal(tr_str(Msg::RobotUnsupported), format_args!(""));
+    }
+
+    if *OPT_TAR.lock().unwrap() {
+        run_tar(&mut args_info);
+    }
+
+    let train_dict = OPT_TRAIN_DICT.lock().unwrap().clone();
+    if let Some(out) = train_dict {
+        run_train_dict(&mut args_info, &out);
     }
 
     // If we know the number of files, update message handling
-    if args_info.files_name.is_some() {
+    // 递归处理目录时事先不知道文件总数
+    if args_info.files_name.is_some() || *OPT_RECURSIVE.lock().unwrap() {
         message_set_files(0);
     } else {
         message_set_files(args_info.arg_count);
@@ -230,9 +380,17 @@ fn main() {
         signals_init();
     }
 
+    // --jobs 并行处理多个文件
+    let jobs = *OPT_JOBS.lock().unwrap();
+    if jobs > 1 {
+        run_parallel(&mut args_info, jobs);
+    }
+
     // 选择运行函数
     let run_fn: fn(&str) = if *OPT_MODE.lock().unwrap() == OperationMode::List {
         list_file
+    } else if OPT_VERIFY.lock().unwrap().is_some() {
+        verify_file
     } else {
         coder_run
     };
@@ -251,7 +409,7 @@ fn main() {
 
             // Handle stdin filename
             if args_info.files_name == Some(String::from("stdin")) {
-                message_error("Cannot read data from standard input when reading filenames from standard input", format_args!(""));
+                message_error(tr_str(Msg::StdinFilesConflict), format_args!(""));
                 continue;
             }
 
@@ -261,14 +419,18 @@ fn main() {
 
         // Call the run function (compression or decompression)
 
-        run_fn(&args_info.arg_names[i as usize]);
+        for name in expand_name(&args_info.arg_names[i as usize]) {
+            run_fn(&name);
+        }
     }
 
     // If --files or --files0 was used, process the filenames
 
     if let Some(files_name) = &args_info.files_name {
         while let Some(name) = read_name(&mut args_info) {
-            run_fn(&name);
+            for name in expand_name(&name) {
+                run_fn(&name);
+            }
         }
         if args_info.files_name != Some(STDIN_FILENAME.to_string()) {
             if let Some(file) = args_info.files_file.take() {
@@ -15,11 +15,15 @@ use signal_hook::iterator::Signals;
 use std::io::{self, Write};
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::cell::RefCell;
 use std::sync::{Arc, Mutex};
+use std::os::unix::io::RawFd;
 use std::thread;
+use std::time::Instant;
 
 // 或者
 use crate::args::OPT_STDOUT;
+use crate::catalog::{tr, tr_io_error, tr_str, Msg};
 use crate::coder::{OperationMode, OPT_MODE};
 use crate::hardware::hardware_memlimit_get;
 use crate::mytime::mytime_get_elapsed;
@@ -27,7 +31,7 @@ use crate::signals::{signals_block, signals_unblock};
 use crate::util::{round_up_to_mib, uint64_to_str};
 use crate::{set_exit_status, ExitStatusType, E_ERROR};
 use signal_hook::consts::signal::SIGUSR1;
-use utxz_sys::unistd as sys_unistd;
+use utxz_sys::{poll as sys_poll, unistd as sys_unistd};
 
 /// 输出详细程度
 #[derive(PartialEq, PartialOrd, Clone)]
@@ -69,6 +73,46 @@ lazy_static! {
 
 static PROGRESS_NEEDS_UPDATING: AtomicBool = AtomicBool::new(false);
 
+/// JSON progress 事件之间的最短间隔（毫秒）
+const PROGRESS_JSON_INTERVAL: u64 = 1000;
+
+lazy_static! {
+    /// --progress-fd 或 --progress=json 的输出目标，为 None 时不输出 JSON 进度
+    static ref PROGRESS_JSON_FD: Mutex<Option<RawFd>> = Mutex::new(None);
+}
+
+/// 正在处理的文件的 JSON 进度状态
+struct ProgressJsonState {
+    file: String,
+    start: Instant,
+
+    /// 上一个 progress 事件距 start 的毫秒数
+    last: u64,
+
+    expected_size: u64,
+}
+
+thread_local! {
+    /// 当前线程的诊断信息缓冲区
+    ///
+    /// 并行处理多个文件时，工作线程的消息先写入这里，
+    /// 再由主线程按文件顺序输出，避免不同文件的消息交错。
+    static CAPTURE_BUF: RefCell<Option<String>> = const { RefCell::new(None) };
+
+    /// 当前线程正在处理的文件的 JSON 进度状态，并行处理时每个工作线程各有一份
+    static PROGRESS_JSON_STATE: RefCell<Option<ProgressJsonState>> = const { RefCell::new(None) };
+}
+
+/// 开始把当前线程的消息写入缓冲区而不是标准错误
+pub fn message_capture_begin() {
+    CAPTURE_BUF.with(|buf| *buf.borrow_mut() = Some(String::new()));
+}
+
+/// 停止捕获当前线程的消息，并返回已捕获的内容
+pub fn message_capture_end() -> String {
+    CAPTURE_BUF.with(|buf| buf.borrow_mut().take().unwrap_or_default())
+}
+
 // 信号处理函数
 fn progress_signal_handler() {
     // 设置 `PROGRESS_NEEDS_UPDATING` 为 true
@@ -631,6 +675,185 @@ pub fn message_progress_end(success: bool) {
     PROGRESS_STARTED.store(false, Ordering::SeqCst);
 }
 
+/// 设置 --progress-fd 或 --progress=json 的输出目标，之后输出 JSON 进度事件
+pub fn message_progress_json_set_fd(fd: RawFd) {
+    *PROGRESS_JSON_FD.lock().unwrap() = Some(fd);
+}
+
+/// 把字符串转换为 JSON 字符串字面量
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// 把 None 转换为 JSON 的 null
+fn json_opt<T: std::fmt::Display>(val: Option<T>) -> String {
+    val.map_or_else(|| "null".to_string(), |v| v.to_string())
+}
+
+/// 输出一行 JSON，写入失败时停止输出 JSON 进度
+fn progress_json_write(line: String) {
+    let mut fd_guard = PROGRESS_JSON_FD.lock().unwrap();
+    let fd = match *fd_guard {
+        Some(fd) => fd,
+        None => return,
+    };
+
+    let line = line + "\n";
+    let mut remaining = line.as_bytes();
+    while !remaining.is_empty() {
+        match sys_unistd::write(fd, remaining) {
+            Ok(amount) => remaining = &remaining[amount..],
+            Err(err) => {
+                let errno = err.raw_os_error().unwrap_or(0);
+                if errno == libc::EINTR {
+                    continue;
+                }
+                if errno == libc::EAGAIN || errno == libc::EWOULDBLOCK {
+                    let mut pfd = [libc::pollfd {
+                        fd,
+                        events: libc::POLLOUT,
+                        revents: 0,
+                    }];
+                    if sys_poll::poll(&mut pfd, -1).is_ok() {
+                        continue;
+                    }
+                }
+
+                *fd_guard = None;
+                drop(fd_guard);
+                message_warning(&tr(Msg::ProgressWriteError, &[&tr_io_error(&err)]), &[]);
+                return;
+            }
+        }
+    }
+}
+
+/// 操作模式在 JSON 中的名称
+fn progress_json_mode(mode: &OperationMode) -> &'static str {
+    match mode {
+        OperationMode::Compress => "compress",
+        OperationMode::Decompress => "decompress",
+        OperationMode::Test => "test",
+        OperationMode::List => "list",
+    }
+}
+
+/// 输入和输出字节数、压缩率和速度，与 progress_sizes() 一样压缩率为压缩后/压缩前
+fn progress_json_sizes(mode: &OperationMode, in_pos: u64, out_pos: u64, elapsed: u64) -> String {
+    let (compressed, uncompressed) = if *mode == OperationMode::Compress {
+        (out_pos, in_pos)
+    } else {
+        (in_pos, out_pos)
+    };
+    let ratio = (uncompressed > 0).then(|| format!("{:.4}", compressed as f64 / uncompressed as f64));
+    let speed = (elapsed > 0).then(|| uncompressed * 1000 / elapsed);
+    format!(
+        "\"bytes_in\":{},\"bytes_out\":{},\"ratio\":{},\"speed\":{},\"elapsed\":{:.3}",
+        in_pos,
+        out_pos,
+        json_opt(ratio),
+        json_opt(speed),
+        elapsed as f64 / 1000.0
+    )
+}
+
+/// 开始处理文件时输出 JSON start 事件
+///
+/// 不依赖进度指示器的状态，并行处理文件时也会输出。expected_size 是输入文件的大小，
+/// 为 0 表示未知。
+pub fn message_progress_json_start(filename: &str, mode: &OperationMode, expected_size: u64) {
+    if PROGRESS_JSON_FD.lock().unwrap().is_none() {
+        return;
+    }
+
+    PROGRESS_JSON_STATE.with(|state| {
+        *state.borrow_mut() = Some(ProgressJsonState {
+            file: filename.to_string(),
+            start: Instant::now(),
+            last: 0,
+            expected_size,
+        })
+    });
+
+    progress_json_write(format!(
+        "{{\"event\":\"start\",\"file\":{},\"mode\":\"{}\",\"expected_size\":{}}}",
+        json_str(filename),
+        progress_json_mode(mode),
+        json_opt((expected_size > 0).then_some(expected_size))
+    ));
+}
+
+/// 距上一个事件至少 PROGRESS_JSON_INTERVAL 毫秒时输出 JSON progress 事件
+///
+/// JSON 进度按时间输出，不依赖 SIGALRM 和进度指示器，并行处理文件的工作线程
+/// 也会调用它，所以位置直接从 strm 读取。passthru 为 true 表示直通模式。
+pub fn message_progress_json_update(strm: &mut LzmaStream, passthru: bool) {
+    let (file, elapsed, expected_size) = match PROGRESS_JSON_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let state = state.as_mut()?;
+        let elapsed = state.start.elapsed().as_millis() as u64;
+        if elapsed < state.last + PROGRESS_JSON_INTERVAL {
+            return None;
+        }
+        state.last = elapsed;
+        Some((state.file.clone(), elapsed, state.expected_size))
+    }) {
+        Some(v) => v,
+        None => return,
+    };
+
+    let mut in_pos = strm.total_in.get();
+    let mut out_pos = strm.total_out.get();
+    if !passthru {
+        lzma_get_progress(strm, &mut in_pos, &mut out_pos);
+    }
+
+    let mode = OPT_MODE.lock().unwrap().clone();
+
+    // 与 progress_remaining() 相同，输入大小未知或已超过预期时不估计剩余时间
+    let eta = (expected_size > 0 && in_pos > 0 && in_pos <= expected_size)
+        .then(|| (expected_size - in_pos) as f64 * (elapsed as f64 / 1000.0) / in_pos as f64)
+        .map(|eta| format!("{:.1}", eta));
+
+    progress_json_write(format!(
+        "{{\"event\":\"progress\",\"file\":{},{},\"expected_size\":{},\"eta\":{}}}",
+        json_str(&file),
+        progress_json_sizes(&mode, in_pos, out_pos, elapsed),
+        json_opt((expected_size > 0).then_some(expected_size)),
+        json_opt(eta)
+    ));
+}
+
+/// 文件处理结束时输出 JSON end 事件
+pub fn message_progress_json_end(mode: &OperationMode, success: bool, strm: &LzmaStream) {
+    let state = match PROGRESS_JSON_STATE.with(|state| state.borrow_mut().take()) {
+        Some(state) => state,
+        None => return,
+    };
+    let elapsed = state.start.elapsed().as_millis() as u64;
+
+    progress_json_write(format!(
+        "{{\"event\":\"end\",\"file\":{},\"success\":{},{}}}",
+        json_str(&state.file),
+        success,
+        progress_json_sizes(mode, strm.total_in.get(), strm.total_out.get(), elapsed)
+    ));
+}
+
 // lazy_static! {
 //     static ref PROGNAME: String = std::env::args()
 //         .next()
@@ -643,6 +866,25 @@ pub fn message_progress_end(success: bool) {
 /// 内部消息打印函数 (可变参数版本)
 pub fn vmessage(verbosity: MessageVerbosity, fmt: &str, args: std::fmt::Arguments) {
     if verbosity <= message_verbosity_get() {
+        let rendered_args = args.to_string();
+        let text = if rendered_args.is_empty() {
+            fmt
+        } else {
+            rendered_args.as_str()
+        };
+
+        // 正在捕获时只写入缓冲区，不涉及进度指示器
+        let captured = CAPTURE_BUF.with(|buf| match buf.borrow_mut().as_mut() {
+            Some(buf) => {
+                buf.push_str(&format!("{}: {}\n", PROGNAME.lock().unwrap(), text));
+                true
+            }
+            None => false,
+        });
+        if captured {
+            return;
+        }
+
         signals_block();
 
         // 先刷新任何未完成的进度显示
@@ -653,13 +895,7 @@ pub fn vmessage(verbosity: MessageVerbosity, fmt: &str, args: std::fmt::Argument
         write!(io::stderr(), "{}: ", PROGNAME.lock().unwrap());
 
         // 打印实际消息内容
-        let rendered_args = args.to_string();
-        if rendered_args.is_empty() {
-            write!(io::stderr(), "{}", fmt).unwrap();
-        } else {
-            write!(io::stderr(), "{}", rendered_args).unwrap();
-        }
-        writeln!(io::stderr()).unwrap();
+        writeln!(io::stderr(), "{}", text).unwrap();
 
         signals_unblock();
     }
@@ -709,14 +945,14 @@ pub fn message_fatal(fmt: &str, args: std::fmt::Arguments) {
 ///
 /// 该函数用于处理程序内部的错误（Bug），打印错误消息后立即退出程序。
 pub fn message_bug() {
-    message_fatal("内部错误（Bug）", format_args!(""));
+    message_fatal(tr_str(Msg::InternalError), format_args!(""));
 }
 
 /// 处理信号处理程序无法建立的错误并退出程序
 ///
 /// 该函数用于处理信号处理程序无法建立的错误，打印错误消息后立即退出程序。
 pub fn message_signal_handler() {
-    message_fatal("无法建立信号处理程序", format_args!(""));
+    message_fatal(tr_str(Msg::SignalHandler), format_args!(""));
 }
 
 /// 根据 `lzma_ret` 错误码返回对应的错误消息
@@ -730,15 +966,16 @@ pub fn message_signal_handler() {
 /// 返回与错误码对应的错误消息字符串
 pub fn message_strm(code: LzmaRet) -> &'static str {
     match code {
-        LzmaRet::NoCheck => "未进行完整性检查；未验证文件完整性",
-        LzmaRet::UnsupportedCheck => "不支持的完整性检查类型；未验证文件完整性",
-        LzmaRet::MemError => "内存不足",
-        LzmaRet::MemlimitError => "已达到内存使用限制",
-        LzmaRet::FormatError => "文件格式无法识别",
-        LzmaRet::OptionsError => "不支持的选项",
-        LzmaRet::DataError => "压缩数据已损坏",
-        LzmaRet::BufError => "输入意外结束",
-        _ => "内部错误（Bug）", // 其他未处理的错误码
+        LzmaRet::NoCheck => tr_str(Msg::StrmNoCheck),
+        LzmaRet::UnsupportedCheck => tr_str(Msg::StrmUnsupportedCheck),
+        LzmaRet::MemError => tr_str(Msg::StrmMemError),
+        LzmaRet::MemlimitError => tr_str(Msg::StrmMemlimit),
+        LzmaRet::FormatError => tr_str(Msg::StrmFormat),
+        LzmaRet::OptionsError => tr_str(Msg::StrmOptions),
+        LzmaRet::DataError => tr_str(Msg::StrmData),
+        LzmaRet::BufError => tr_str(Msg::StrmBuf),
+        LzmaRet::KeyError => tr_str(Msg::StrmKey),
+        _ => tr_str(Msg::InternalError), // 其他未处理的错误码
     }
 }
 
@@ -764,8 +1001,8 @@ pub fn message_mem_needed(verbosity: MessageVerbosity, memusage: u64) {
     if memlimit == u64::MAX {
         message(
             verbosity,
-            "{} MiB 内存是必需的。内存限制器已禁用。",
-            format_args!("{}", uint64_to_str(memusage, 0)),
+            &tr(Msg::MemNeededNoLimit, &[&uint64_to_str(memusage, 0)]),
+            format_args!(""),
         );
         return;
     }
@@ -779,8 +1016,8 @@ pub fn message_mem_needed(verbosity: MessageVerbosity, memusage: u64) {
 
     message(
         verbosity,
-        "{} MiB 内存是必需的。限制是 {}。",
-        format_args!("{} {} ", uint64_to_str(memusage, 0), memlimitstr),
+        &tr(Msg::MemNeeded, &[&uint64_to_str(memusage, 0), &memlimitstr]),
+        format_args!(""),
     );
 }
 
@@ -821,8 +1058,8 @@ pub fn message_try_help() {
     // 使用警告级别而不是错误级别，防止在使用--quiet时显示
     message(
         MessageVerbosity::Warning,
-        "尝试 `{} --help` 获取更多信息。",
-        format_args!("{}", PROGNAME.lock().unwrap()),
+        &tr(Msg::TryHelp, &[&*PROGNAME.lock().unwrap()]),
+        format_args!(""),
     );
 }
 
@@ -858,27 +1095,13 @@ pub fn message_version() {
 /// - `long_help`: 是否显示详细帮助信息
 pub fn message_help(long_help: bool) {
     // 打印基本用法信息
-    println!("用法: {} [选项]... [文件]...", *PROGNAME.lock().unwrap());
-    println!("以 .xz 格式压缩或解压文件\n");
-
-    // 打印基本操作选项
-    println!(
-        "{:>4}-z, --compress     强制压缩\n\
-         {:>4}-d, --decompress   强制解压\n\
-         {:>4}-f, --force        强制覆盖输出文件和(解)压缩链接\n\
-         {:>4}-l, --list         列出关于 .xz 文件的信息\n\
-         {:>4}-T, --threads N   使用 N 个线程进行压缩（缺省：1）\n\
-         {:>4}                    -T0 使用系统所有 CPU 线程",
-        "", "", "", "", "", ""
-    );
+    println!("{}", tr(Msg::HelpUsage, &[&*PROGNAME.lock().unwrap()]));
 
-    println!("{:>4}-h, --help        显示此简短帮助并退出", "");
-
-    println!(
-        "{:>4}-V, --version     显示版本号并退出\n\n\
-          {:>4}没有文件或文件为-时，从标准输入读取",
-        "", ""
-    );
+    // 打印操作选项
+    println!("{}", tr_str(Msg::HelpOptions));
+    println!("{}", tr_str(Msg::HelpHelp));
+    println!("{}\n", tr_str(Msg::HelpVersion));
+    println!("{}", tr_str(Msg::HelpStdin));
 
     // // 打印错误报告信息
     // println!("报告错误至 <{}> (英文或芬兰文)", "utxz-bugs@example.com");
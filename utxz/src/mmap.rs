@@ -0,0 +1,198 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 用 mmap 读取普通文件
+//!
+//! 映射的文件在读取期间被截断时，访问文件末尾之后的页会收到 SIGBUS。
+//! SIGBUS 处理函数把从出错的页到映射末尾的部分替换为全零的匿名页，
+//! 并记录该映射已被截断；调用者在使用读到的数据之前用 is_truncated() 检查，
+//! 把截断作为普通的读取错误处理。
+
+use std::ffi::c_void;
+use std::fmt;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Once, OnceLock};
+
+use libc::{sighandler_t, siginfo_t};
+use utxz_sys::{mman as sys_mman, signal as sys_signal};
+
+/// 可以同时存在的映射数量，超过时调用者回退到 read()
+const MAP_SLOTS_MAX: usize = 64;
+
+/// SIGBUS 处理函数查找出错地址时使用的映射信息
+struct MapSlot {
+    in_use: AtomicBool,
+    start: AtomicUsize,
+
+    /// 按页对齐的映射长度，0 表示处理函数应忽略该槽位
+    len: AtomicUsize,
+
+    truncated: AtomicBool,
+}
+
+static MAP_SLOTS: [MapSlot; MAP_SLOTS_MAX] = [const {
+    MapSlot {
+        in_use: AtomicBool::new(false),
+        start: AtomicUsize::new(0),
+        len: AtomicUsize::new(0),
+        truncated: AtomicBool::new(false),
+    }
+}; MAP_SLOTS_MAX];
+
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(4096);
+
+/// 安装处理函数之前的 SIGBUS 处理方式
+static OLD_SIGBUS: OnceLock<libc::sigaction> = OnceLock::new();
+
+static SIGBUS_INIT: Once = Once::new();
+
+extern "C" fn sigbus_handler(_sig: i32, info: *mut siginfo_t, _ctx: *mut c_void) {
+    // 安全性：SA_SIGINFO 处理函数的 info 总是有效的
+    let addr = unsafe { (*info).si_addr() } as usize;
+    let page_size = PAGE_SIZE.load(Ordering::Relaxed);
+
+    for slot in MAP_SLOTS.iter() {
+        let len = slot.len.load(Ordering::Acquire);
+        let start = slot.start.load(Ordering::Acquire);
+        if len == 0 || addr < start || addr >= start + len {
+            continue;
+        }
+
+        // 之后的页同样在文件末尾之后，一次全部替换，避免每页一个信号
+        let page = addr & !(page_size - 1);
+        // 安全性：该范围属于此槽位的映射，映射解除前槽位不会被释放
+        if unsafe { sys_mman::mmap_zero_fixed(page as *mut c_void, start + len - page) }.is_ok() {
+            slot.truncated.store(true, Ordering::Release);
+            return;
+        }
+        break;
+    }
+
+    // 不是我们的映射：恢复原来的处理方式，返回后再次出错时按原来的方式处理
+    let old = match OLD_SIGBUS.get() {
+        Some(old) => *old,
+        None => {
+            let mut sa = sys_signal::zeroed_sigaction();
+            sa.sa_sigaction = libc::SIG_DFL;
+            sa
+        }
+    };
+    let _ = sys_signal::sigaction_set(libc::SIGBUS, &old);
+}
+
+fn sigbus_init() {
+    SIGBUS_INIT.call_once(|| {
+        PAGE_SIZE.store(sys_mman::page_size(), Ordering::Relaxed);
+
+        if let Ok(old) = sys_signal::sigaction_get(libc::SIGBUS) {
+            let _ = OLD_SIGBUS.set(old);
+        }
+
+        let mut sa = sys_signal::zeroed_sigaction();
+        sa.sa_sigaction = sigbus_handler as sighandler_t;
+        sa.sa_flags = libc::SA_SIGINFO;
+        let _ = sys_signal::sigaction_set(libc::SIGBUS, &sa);
+    });
+}
+
+/// 只读映射的普通文件
+pub struct IoMap {
+    addr: *mut c_void,
+
+    /// 文件大小
+    len: usize,
+
+    /// 按页对齐的映射长度
+    map_len: usize,
+
+    /// 在 MAP_SLOTS 中的位置
+    slot: usize,
+}
+
+impl fmt::Debug for IoMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IoMap")
+            .field("addr", &self.addr)
+            .field("len", &self.len)
+            .field("truncated", &self.is_truncated())
+            .finish()
+    }
+}
+
+impl IoMap {
+    /// 映射 fd 的前 len 字节，len 应为 fstat() 得到的文件大小
+    pub fn new(fd: RawFd, len: usize) -> io::Result<Self> {
+        if len == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        sigbus_init();
+
+        let slot = MAP_SLOTS
+            .iter()
+            .position(|s| {
+                s.in_use
+                    .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            })
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENOMEM))?;
+
+        let addr = match sys_mman::mmap_readonly(fd, len) {
+            Ok(addr) => addr,
+            Err(e) => {
+                MAP_SLOTS[slot].in_use.store(false, Ordering::Release);
+                return Err(e);
+            }
+        };
+
+        let page_size = PAGE_SIZE.load(Ordering::Relaxed);
+        let map_len = len.div_ceil(page_size) * page_size;
+
+        // 只是提示，忽略错误
+        // 安全性：范围就是刚创建的映射
+        let _ = unsafe { sys_mman::madvise(addr, map_len, libc::MADV_SEQUENTIAL) };
+
+        let s = &MAP_SLOTS[slot];
+        s.truncated.store(false, Ordering::Release);
+        s.start.store(addr as usize, Ordering::Release);
+        s.len.store(map_len, Ordering::Release);
+
+        Ok(IoMap {
+            addr,
+            len,
+            map_len,
+            slot,
+        })
+    }
+
+    /// 映射的文件内容
+    ///
+    /// 文件被截断后，原文件末尾之后的部分读出来是零，使用前要检查 is_truncated()。
+    pub fn data(&self) -> &[u8] {
+        // 安全性：映射在 self 被丢弃之前有效，SIGBUS 处理函数保证访问不会出错
+        unsafe { std::slice::from_raw_parts(self.addr as *const u8, self.len) }
+    }
+
+    /// 读取期间文件是否被截断
+    pub fn is_truncated(&self) -> bool {
+        MAP_SLOTS[self.slot].truncated.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for IoMap {
+    fn drop(&mut self) {
+        let s = &MAP_SLOTS[self.slot];
+        s.len.store(0, Ordering::Release);
+
+        // 安全性：data() 返回的切片借用 self，此时已没有引用
+        let _ = unsafe { sys_mman::munmap(self.addr, self.map_len) };
+
+        s.start.store(0, Ordering::Release);
+        s.in_use.store(false, Ordering::Release);
+    }
+}
@@ -4,12 +4,13 @@
  * SPDX-License-Identifier: GPL-2.0-or-later
  */
 
+use crate::catalog::{tr, tr_str, Msg};
 use crate::util::{str_to_uint64, xstrdup};
 use liblzma::api::{
     LzmaOptionsBcj, LzmaOptionsDelta, LzmaOptionsLzma, LZMA_DELTA_DIST_MAX, LZMA_DELTA_DIST_MIN,
     LZMA_DICT_SIZE_MIN, LZMA_LCLP_MAX, LZMA_LCLP_MIN, LZMA_MF_BT2, LZMA_MF_BT3, LZMA_MF_BT4,
-    LZMA_MF_HC3, LZMA_MF_HC4, LZMA_MODE_FAST, LZMA_MODE_NORMAL, LZMA_PB_MAX, LZMA_PB_MIN,
-    LZMA_PRESET_DEFAULT, LZMA_PRESET_EXTREME,
+    LZMA_MF_HC3, LZMA_MF_HC4, LZMA_MF_HT4, LZMA_MODE_FAST, LZMA_MODE_NORMAL, LZMA_PB_MAX,
+    LZMA_PB_MIN, LZMA_PRESET_DEFAULT, LZMA_PRESET_EXTREME, LZMA_PRESET_FAST,
 };
 
 /// `NameIdMap` 结构体，包含一个名称和一个 ID
@@ -41,16 +42,19 @@ pub fn parse_options(
 
     for option in str.split(',') {
         let mut parts = option.splitn(2, '=');
-        let name = parts.next().ok_or(format!("Invalid option: {}", option))?;
+        let name = parts
+            .next()
+            .ok_or_else(|| tr(Msg::OptionNotPair, &[&str]))?;
         let value = parts
             .next()
-            .ok_or(format!("Missing value for option: {}", name))?;
+            .ok_or_else(|| tr(Msg::OptionNotPair, &[&str]))?;
 
-        // 查找选项名称在映射表中的位置
-        let option_map = opts
+        // 查找选项名称在映射表中的位置，位置即传给 `set` 的键
+        let key = opts
             .iter()
-            .find(|opt| opt.name.map_or(false, |n| n == name))
-            .ok_or(format!("Invalid option name: {}", name))?;
+            .position(|opt| opt.name.map_or(false, |n| n == name))
+            .ok_or_else(|| tr(Msg::OptionInvalidName, &[&name]))?;
+        let option_map = &opts[key];
 
         // 处理选项值
         if let Some(map) = option_map.map {
@@ -58,11 +62,11 @@ pub fn parse_options(
             let mapped_value = map
                 .iter()
                 .find(|m| m.name.map_or(false, |n| n == value))
-                .ok_or(format!("Invalid option value: {}", value))?;
+                .ok_or_else(|| tr(Msg::OptionInvalidValue, &[&value]))?;
 
             set(
                 filter_options,
-                option_map as *const _ as usize,
+                key,
                 mapped_value.id,
                 Some(value),
             );
@@ -70,7 +74,7 @@ pub fn parse_options(
             // 值是一个特殊字符串，由 `set` 函数解析
             set(
                 filter_options,
-                option_map as *const _ as usize,
+                key,
                 0,
                 Some(value),
             );
@@ -79,7 +83,7 @@ pub fn parse_options(
             let v = str_to_uint64(name, value, option_map.min, option_map.max);
             set(
                 filter_options,
-                option_map as *const _ as usize,
+                key,
                 v,
                 Some(value),
             );
@@ -100,9 +104,13 @@ const UINT64_MAX: u64 = u64::MAX;
 // 替代 C 风格的宏定义，使用 Rust 常量
 const OPT_DIST: usize = 0;
 
+/// `--delta=auto` 或 `dist=auto`：在每个文件的样本上选择距离
+pub const DELTA_DIST_AUTO: u64 = 0;
+
 // 定义 DeltaOptions 结构体，用于封装 delta 压缩选项
 pub struct DeltaOptions {
-    dist: u64,
+    /// 距离，DELTA_DIST_AUTO 表示自动选择
+    pub dist: u64,
 }
 
 impl DeltaOptions {
@@ -110,15 +118,21 @@ impl DeltaOptions {
     pub fn set_delta(
         &mut self,
         key: usize,
-        value: u64,
-        _valuestr: Option<&str>,
+        _value: u64,
+        valuestr: Option<&str>,
     ) -> Result<(), String> {
         match key {
             OPT_DIST => {
-                if value < LZMA_DELTA_DIST_MIN.into() || value > LZMA_DELTA_DIST_MAX.into() {
-                    return Err(format!("Invalid delta distance: {}", value));
-                }
-                self.dist = value;
+                self.dist = match valuestr {
+                    Some("auto") => DELTA_DIST_AUTO,
+                    Some(s) => str_to_uint64(
+                        "dist",
+                        s,
+                        LZMA_DELTA_DIST_MIN as u64,
+                        LZMA_DELTA_DIST_MAX as u64,
+                    ),
+                    None => return Err(tr_str(Msg::DeltaDistMissing).to_string()),
+                };
                 Ok(())
             }
             _ => Err(format!("Unknown option key: {}", key)),
@@ -129,11 +143,12 @@ impl DeltaOptions {
 // 创建并配置 DeltaOptions 实例
 pub fn options_delta(str: Option<&str>) -> Result<DeltaOptions, String> {
     static OPTS: [OptionMap; 2] = [
+        // 值可以是 auto，由 set_delta 解析
         OptionMap {
             name: Some("dist"),
             map: None,
-            min: LZMA_DELTA_DIST_MIN as u64,
-            max: LZMA_DELTA_DIST_MAX as u64,
+            min: u64::MAX,
+            max: 0,
         },
         OptionMap {
             name: None,
@@ -147,6 +162,12 @@ pub fn options_delta(str: Option<&str>) -> Result<DeltaOptions, String> {
         dist: LZMA_DELTA_DIST_MIN.into(),
     };
 
+    // --delta=auto 是 --delta=dist=auto 的简写
+    let str = match str {
+        Some("auto") => Some("dist=auto"),
+        str => str,
+    };
+
     parse_options(
         str,
         &OPTS,
@@ -179,7 +200,7 @@ impl BcjOptions {
         match key {
             OPT_START_OFFSET => {
                 if value > u32::MAX as u64 {
-                    return Err(format!("Invalid start offset: {}", value));
+                    return Err(tr(Msg::StartOffsetInvalid, &[&value]));
                 }
                 self.start_offset = value;
                 Ok(())
@@ -251,7 +272,7 @@ impl LzmaOptions {
             OPT_PRESET => {
                 if let Some(s) = valuestr {
                     if s.chars().nth(0).map_or(false, |c| c < '0' || c > '9') {
-                        return Err(format!("Unsupported LZMA1/LZMA2 preset: {}", s));
+                        return Err(tr(Msg::UnsupportedPreset, &[&s]));
                     }
 
                     let mut preset = s.chars().nth(0).unwrap() as u32 - '0' as u32;
@@ -259,12 +280,14 @@ impl LzmaOptions {
                     if s.len() > 1 {
                         if s.chars().nth(1).unwrap() == 'e' {
                             preset |= LZMA_PRESET_EXTREME;
+                        } else if s.chars().nth(1).unwrap() == 'f' {
+                            preset |= LZMA_PRESET_FAST;
                         } else {
-                            return Err(format!("Unsupported LZMA1/LZMA2 preset: {}", s));
+                            return Err(tr(Msg::UnsupportedPreset, &[&s]));
                         }
 
                         if s.len() > 2 {
-                            return Err(format!("Unsupported LZMA1/LZMA2 preset: {}", s));
+                            return Err(tr(Msg::UnsupportedPreset, &[&s]));
                         }
                     }
 
@@ -354,6 +377,10 @@ pub fn options_lzma(str: Option<&str>) -> Result<LzmaOptions, String> {
             name: Some("bt4"),
             id: LZMA_MF_BT4,
         },
+        NameIdMap {
+            name: Some("ht4"),
+            id: LZMA_MF_HT4,
+        },
     ];
 
     static OPTS: [OptionMap; 9] = [
@@ -433,7 +460,7 @@ pub fn options_lzma(str: Option<&str>) -> Result<LzmaOptions, String> {
     )?;
 
     if options.lc + options.lp > LZMA_LCLP_MAX as u32 {
-        return Err("The sum of lc and lp must not exceed 4".to_string());
+        return Err(tr_str(Msg::LcLpSum).to_string());
     }
 
     Ok(options)
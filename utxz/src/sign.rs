@@ -0,0 +1,247 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --sign-key：用 HMAC-SHA256 签名压缩文件
+//!
+//! 签名保存在压缩文件旁边的 .sig 文件中，压缩文件本身不变，可以照常用
+//! 其他 xz 工具解压。签名覆盖整个压缩文件。压缩时对写出的数据计算签名；
+//! 测试和解压缩时先读取一遍源文件验证签名，不匹配时不解压。之后从同一个
+//! 文件描述符解压，验证前后和解压之后都检查源文件的大小和修改时间，
+//! 源文件在此期间被修改时报告错误。
+
+use libc::{STDOUT_FILENO, S_IFMT, S_IFREG};
+use liblzma::check::{
+    lzma_hmac_sha256_equal, lzma_hmac_sha256_finish, lzma_hmac_sha256_init,
+    lzma_hmac_sha256_update, LzmaHmacSha256State, LZMA_HMAC_SHA256_SIZE,
+};
+use std::ffi::CString;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::os::unix::io::RawFd;
+use utxz_sys::{fs as sys_fs, unistd as sys_unistd};
+
+use crate::args::{OPT_SIGN_KEY, OPT_SYNC};
+use crate::catalog::{tr, tr_io_error, Msg};
+use crate::file_io::{io_create_tmp, io_rename_dest, FilePair, IO_BUFFER_SIZE};
+use crate::message::{message_error, message_fatal};
+
+/// 签名文件名的后缀，加在压缩文件名之后
+const SIGN_SUFFIX: &str = ".sig";
+
+/// 签名文件中的算法名
+const SIGN_ALGORITHM: &str = "HMAC-SHA256";
+
+/// 读取 --sign-key 给出的密钥文件，文件的全部内容就是密钥
+pub fn sign_key_load(path: &str) -> Vec<u8> {
+    let key = match fs::read(path) {
+        Ok(key) => key,
+        Err(e) => {
+            message_fatal(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+            return Vec::new();
+        }
+    };
+
+    if key.is_empty() {
+        message_fatal(&tr(Msg::SignKeyEmpty, &[&path]), format_args!(""));
+    }
+
+    key
+}
+
+/// 给出 --sign-key 时开始计算签名，否则返回 None
+pub fn sign_begin() -> Option<LzmaHmacSha256State> {
+    OPT_SIGN_KEY
+        .lock()
+        .unwrap()
+        .as_deref()
+        .map(lzma_hmac_sha256_init)
+}
+
+/// 显示签名只能用于普通文件的错误
+fn sign_not_regular(name: &str) {
+    message_error(&tr(Msg::SignNotRegular, &[&name]), format_args!(""));
+}
+
+/// 把签名写入目标文件旁边的 .sig 文件，返回 true 表示出错
+pub fn sign_write(pair: &FilePair, hmac: LzmaHmacSha256State) -> bool {
+    let dest_name = pair.dest_name.as_deref().unwrap_or("(unknown)");
+    if pair.dest_fd == STDOUT_FILENO {
+        sign_not_regular(dest_name);
+        return true;
+    }
+
+    let mut line = String::from(SIGN_ALGORITHM);
+    line.push(' ');
+    for b in lzma_hmac_sha256_finish(hmac) {
+        write!(line, "{:02x}", b).unwrap();
+    }
+    line.push('\n');
+
+    // 与目标文件一样先写入临时文件再重命名，没有 --force 时不覆盖已有的签名文件
+    let sig_name = format!("{}{}", dest_name, SIGN_SUFFIX);
+    let Some((fd, tmp_name)) = io_create_tmp(&sig_name) else {
+        return true;
+    };
+
+    let mut tmp_st = sys_fs::zeroed_stat();
+    let mut ret = sign_write_all(fd, line.as_bytes());
+    if ret.is_ok() && *OPT_SYNC.lock().unwrap() {
+        ret = sys_unistd::fsync(fd);
+    }
+    if ret.is_ok() {
+        ret = sys_fs::fstat(fd, &mut tmp_st);
+    }
+    let close_ret = sys_unistd::close(fd);
+    if let Err(e) = ret.and(close_ret) {
+        message_error(
+            &format!("{}: {}", sig_name, tr_io_error(&e)),
+            format_args!(""),
+        );
+        let _ = sys_fs::unlink(&CString::new(tmp_name).unwrap());
+        return true;
+    }
+
+    io_rename_dest(&sig_name, &tmp_name, &tmp_st)
+}
+
+/// 把 buf 全部写入 fd
+fn sign_write_all(fd: RawFd, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match sys_unistd::write(fd, buf) {
+            Ok(n) => buf = &buf[n..],
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// 解析签名文件的内容
+fn sign_parse(text: &str) -> Option<[u8; LZMA_HMAC_SHA256_SIZE]> {
+    let mut fields = text.split_ascii_whitespace();
+    if fields.next() != Some(SIGN_ALGORITHM) {
+        return None;
+    }
+    let hex = fields.next()?;
+    if fields.next().is_some() || hex.len() != 2 * LZMA_HMAC_SHA256_SIZE {
+        return None;
+    }
+
+    let mut mac = [0u8; LZMA_HMAC_SHA256_SIZE];
+    for (i, b) in mac.iter_mut().enumerate() {
+        *b = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(mac)
+}
+
+/// 用 pread() 读取整个源文件并计算签名，不改变源文件的读取位置
+fn sign_hash_src(pair: &FilePair, hmac: &mut LzmaHmacSha256State) -> io::Result<()> {
+    let mut buf = vec![0u8; IO_BUFFER_SIZE];
+    let mut pos = 0;
+    loop {
+        match sys_unistd::pread(pair.src_fd, &mut buf, pos as libc::off_t) {
+            Ok(0) => return Ok(()),
+            Ok(n) => {
+                lzma_hmac_sha256_update(hmac, &buf[..n]);
+                pos += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 检查验证过签名的源文件在打开之后是否被修改，返回 true 表示被修改或出错
+///
+/// 签名和解压缩分别读取源文件，两次读取之后都与打开时 fstat() 的结果比较，
+/// 确认解压的是验证过的数据。修改文件内容会更新 ctime，用户无法把它改回去。
+pub fn sign_src_changed(pair: &FilePair) -> bool {
+    if pair.src_sig.is_none() {
+        return false;
+    }
+
+    let src_name = pair.src_name.as_deref().unwrap_or("(unknown)");
+    let mut st = sys_fs::zeroed_stat();
+    if let Err(e) = sys_fs::fstat(pair.src_fd, &mut st) {
+        message_error(
+            &tr(Msg::ReadError, &[&src_name, &tr_io_error(&e)]),
+            format_args!(""),
+        );
+        return true;
+    }
+
+    let old = &pair.src_st;
+    if st.st_size != old.st_size
+        || st.st_mtime != old.st_mtime
+        || st.st_mtime_nsec != old.st_mtime_nsec
+        || st.st_ctime != old.st_ctime
+        || st.st_ctime_nsec != old.st_ctime_nsec
+    {
+        message_error(&tr(Msg::SignChanged, &[&src_name]), format_args!(""));
+        return true;
+    }
+
+    false
+}
+
+/// 给出 --sign-key 时验证源文件的签名，返回 true 表示出错或签名不匹配
+///
+/// 签名文件记录在 pair 中，以便删除源文件时一并删除。解压缩或测试完成后
+/// 要用 sign_src_changed() 确认源文件没有在此期间被修改。
+pub fn sign_verify_src(pair: &mut FilePair) -> bool {
+    let Some(mut hmac) = sign_begin() else {
+        return false;
+    };
+
+    let src_name = pair.src_name.as_deref().unwrap_or("(unknown)");
+    if pair.src_st.st_mode & S_IFMT != S_IFREG {
+        sign_not_regular(src_name);
+        return true;
+    }
+
+    let sig_name = format!("{}{}", src_name, SIGN_SUFFIX);
+    let mut sig_st = sys_fs::zeroed_stat();
+    if let Err(e) = sys_fs::lstat(&CString::new(sig_name.as_str()).unwrap(), &mut sig_st) {
+        message_error(
+            &format!("{}: {}", sig_name, tr_io_error(&e)),
+            format_args!(""),
+        );
+        return true;
+    }
+    let expected = match fs::read_to_string(&sig_name) {
+        Ok(text) => match sign_parse(&text) {
+            Some(mac) => mac,
+            None => {
+                message_error(&tr(Msg::SignInvalid, &[&sig_name]), format_args!(""));
+                return true;
+            }
+        },
+        Err(e) => {
+            message_error(
+                &format!("{}: {}", sig_name, tr_io_error(&e)),
+                format_args!(""),
+            );
+            return true;
+        }
+    };
+
+    if let Err(e) = sign_hash_src(pair, &mut hmac) {
+        message_error(
+            &tr(Msg::ReadError, &[&src_name, &tr_io_error(&e)]),
+            format_args!(""),
+        );
+        return true;
+    }
+
+    if !lzma_hmac_sha256_equal(&lzma_hmac_sha256_finish(hmac), &expected) {
+        message_error(&tr(Msg::SignMismatch, &[&src_name]), format_args!(""));
+        return true;
+    }
+
+    pair.src_sig = Some((sig_name, sig_st));
+    sign_src_changed(pair)
+}
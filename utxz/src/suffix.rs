@@ -11,11 +11,12 @@ use std::slice;
 use std::str;
 use std::sync::Mutex;
 
+use crate::catalog::{tr, Msg};
 use crate::coder::FormatType;
 use crate::coder::OperationMode;
 use crate::coder::OPT_FORMAT;
 use crate::coder::OPT_MODE;
-use crate::message::{message_warning, vmessage, MessageVerbosity};
+use crate::message::{message_fatal, message_warning, vmessage, MessageVerbosity};
 use crate::set_exit_status;
 use crate::util::xstrdup;
 use crate::ExitStatusType;
@@ -121,7 +122,7 @@ fn uncompressed_name(src_name: &str, src_len: usize) -> Option<String> {
     if new_len == 0 {
         vmessage(
             MessageVerbosity::Warning,
-            &format!("{}: Filename has an unknown suffix, skipping", src_name),
+            &tr(Msg::UnknownSuffix, &[&src_name]),
             format_args!(""),
         );
         set_exit_status(ExitStatusType::EWarning);
@@ -141,10 +142,7 @@ fn uncompressed_name(src_name: &str, src_len: usize) -> Option<String> {
 fn msg_suffix(src_name: &str, suffix: &str) {
     vmessage(
         MessageVerbosity::Warning,
-        &format!(
-            "{}: File already has `{}' suffix, skipping",
-            src_name, suffix
-        ),
+        &tr(Msg::HasSuffix, &[&src_name, &suffix]),
         format_args!(""),
     );
     set_exit_status(ExitStatusType::EWarning);
@@ -161,9 +159,11 @@ fn compressed_name(src_name: &str, src_len: usize) -> Option<String> {
     //     &[".lzma", ".tlz"], // 对应 FORMAT_LZMA 格式
     //     &[],                // 对应 --format=raw 的格式
     // ];
+    // 顺序与 FormatType 中 Auto 之后的各项相同
     let all_suffixes = [
         [".xz", ".txz", "", ""],
         [".lzma", ".tlz", "", ""],
+        [".lz", "", "", ""],
         ["", "", "", ""],
     ];
     // 检查格式是否合法 (假设 `opt_format` 为 1 或 2)
@@ -222,14 +222,32 @@ pub fn suffix_get_dest_name(src_name: &str) -> Option<String> {
     }
 }
 
+/// 检查文件名是否带有已知的压缩文件后缀（包括自定义后缀），不输出任何消息
+///
+/// 递归处理目录时用它来静默跳过压缩时已压缩的文件和解压缩时不认识的文件。
+pub fn suffix_is_compressed(src_name: &str) -> bool {
+    let src_len = src_name.len();
+
+    if *OPT_FORMAT.lock().unwrap() != FormatType::Raw {
+        for suffix in [".xz", ".txz", ".lzma", ".tlz", ".lz"] {
+            if test_suffix(suffix, src_name, src_len) != 0 {
+                return true;
+            }
+        }
+    }
+
+    let custom_suffix = CUSTOM_SUFFIX.lock().unwrap();
+    !custom_suffix.is_empty() && test_suffix(&custom_suffix, src_name, src_len) != 0
+}
+
 /// 设置自定义后缀
 ///
 /// \param suffix 要设置的后缀
 /// 空后缀和包含目录分隔符的后缀会被拒绝
-fn suffix_set(suffix: &str) {
+pub fn suffix_set(suffix: &str) {
     let mut custom_suffix = CUSTOM_SUFFIX.lock().unwrap();
     if suffix.is_empty() || has_dir_sep(suffix) {
-        panic!("无效的文件名后缀");
+        message_fatal(&tr(Msg::InvalidSuffix, &[&suffix]), format_args!(""));
     }
 
     *custom_suffix = xstrdup(suffix);
@@ -239,6 +257,5 @@ fn suffix_set(suffix: &str) {
 ///
 /// \return 如果设置了自定义后缀返回true
 pub fn suffix_is_set() -> bool {
-    let custom_suffix = CUSTOM_SUFFIX.lock().unwrap();
-    Some(custom_suffix).is_some()
+    !CUSTOM_SUFFIX.lock().unwrap().is_empty()
 }
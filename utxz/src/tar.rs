@@ -0,0 +1,1392 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --tar 模式：直接创建、解开和列出 .tar.xz 归档
+//!
+//! 归档数据在内存中流经 .xz 编码器/解码器，不使用临时文件。
+//! 创建归档时 Block 的边界与成员（连同它前面的 pax 扩展头）对齐：
+//! 缺省每个成员从新的 Block 开始，指定 --block-size 时，当前 Block 达到该大小后
+//! 下一个成员从新的 Block 开始。因此列出归档时可以借助 Index 通过
+//! lzma_index_iter_locate() 直接定位到成员头所在的 Block，跳过成员数据。
+
+use liblzma::api::{
+    LzmaAction, LzmaBlock, LzmaFilter, LzmaIndexIter, LzmaRet, LzmaStream,
+    LZMA_BLOCK_HEADER_SIZE_MAX,
+};
+use liblzma::common::{
+    lzma_block_compressed_size, lzma_block_decoder, lzma_block_header_decode, lzma_code,
+    lzma_index_iter_init, lzma_index_iter_locate, lzma_stream_decoder, lzma_stream_encoder,
+};
+use liblzma::lzma_block_header_size_decode;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Component, Path};
+use utxz_sys::fs as sys_fs;
+
+use crate::args::{OPT_FORCE, OPT_STDOUT, OPT_SYNC};
+use crate::catalog::{tr, tr_io_error, tr_str, Msg};
+use crate::coder::{get_opt_block_size, FormatType, OperationMode, CHECK, FILTERS, OPT_FORMAT};
+use crate::file_io::{io_close, io_create_tmp, io_open_src, io_rename_dest, IO_BUFFER_SIZE};
+use crate::hardware::hardware_memlimit_get;
+use crate::list::{parse_indexes, XzFileInfo};
+use crate::message::{
+    message, message_error, message_fatal, message_strm, message_verbosity_get, message_warning,
+    MessageVerbosity,
+};
+use crate::signals::USER_ABORT;
+
+/// tar 记录的大小
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// ustar 数值字段能表示的最大值（11 位八进制数）
+const USTAR_MAX_OCTAL: u64 = 0o77777777777;
+
+/// 成员大小的上限，与 off_t 相同，保证计算归档中的偏移时不会溢出
+const TAR_SIZE_MAX: u64 = i64::MAX as u64;
+
+/// pax 扩展头和 GNU 长名称的最大长度，防止损坏的归档使程序分配过多内存
+const TAR_EXT_SIZE_MAX: u64 = 1 << 20;
+
+/// tar 成员的类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TarKind {
+    File,
+    Dir,
+    Symlink,
+    Other(u8),
+}
+
+/// 一个 tar 成员的元数据
+#[derive(Debug, Clone)]
+pub struct TarEntry {
+    pub path: String,
+    pub kind: TarKind,
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+    pub size: u64,
+    pub mtime: i64,
+    pub link_name: String,
+}
+
+/// 读取 tar 头部数值字段：八进制文本或 base-256 编码
+fn tar_parse_number(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        // base-256：最高位为标志位，其余部分为大端序整数
+        let mut value: u64 = (field[0] & 0x7F) as u64;
+        for &b in &field[1..] {
+            value = value.checked_mul(256)? | b as u64;
+        }
+        return Some(value);
+    }
+
+    let text: Vec<u8> = field
+        .iter()
+        .copied()
+        .skip_while(|&b| b == b' ')
+        .take_while(|&b| b != 0 && b != b' ')
+        .collect();
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(std::str::from_utf8(&text).ok()?, 8).ok()
+}
+
+/// 以 NUL 结尾的八进制文本写入 tar 头部数值字段
+fn tar_write_octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    let text = format!("{:0width$o}", value, width = width);
+    field[..width].copy_from_slice(&text.as_bytes()[text.len() - width..]);
+    field[width] = 0;
+}
+
+/// 读取以 NUL 结尾的字符串字段
+fn tar_parse_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// 计算头部校验和，校验和字段本身按空格计算
+fn tar_checksum(header: &[u8; TAR_BLOCK_SIZE]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if (148..156).contains(&i) {
+                b' ' as u64
+            } else {
+                b as u64
+            }
+        })
+        .sum()
+}
+
+/// 向上对齐到 tar 记录大小
+fn tar_round_up(size: u64) -> u64 {
+    (size + TAR_BLOCK_SIZE as u64 - 1) / TAR_BLOCK_SIZE as u64 * TAR_BLOCK_SIZE as u64
+}
+
+/// 生成一条 pax 扩展头记录 "LEN key=value\n"，LEN 包括自身的长度
+fn pax_record(key: &str, value: &str) -> String {
+    let base = key.len() + value.len() + 3;
+    let mut len = base + 1;
+    while len != base + len.to_string().len() {
+        len = base + len.to_string().len();
+    }
+    format!("{} {}={}\n", len, key, value)
+}
+
+/// 解析 pax 扩展头数据
+fn pax_parse(data: &[u8]) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let space = match data[pos..].iter().position(|&b| b == b' ') {
+            Some(s) => pos + s,
+            None => break,
+        };
+        let len: usize = match std::str::from_utf8(&data[pos..space])
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            Some(l) if l > 0 && pos + l <= data.len() => l,
+            _ => break,
+        };
+        let record = &data[space + 1..pos + len - 1];
+        if let Some(eq) = record.iter().position(|&b| b == b'=') {
+            map.insert(
+                String::from_utf8_lossy(&record[..eq]).into_owned(),
+                String::from_utf8_lossy(&record[eq + 1..]).into_owned(),
+            );
+        }
+        pos += len;
+    }
+    map
+}
+
+/// 把名称拆分为 ustar 的 prefix 和 name 字段，无法拆分时返回 None
+fn ustar_split_name(path: &str) -> Option<(&str, &str)> {
+    if path.len() <= 100 {
+        return Some(("", path));
+    }
+    if path.len() > 256 {
+        return None;
+    }
+    for (i, _) in path.match_indices('/') {
+        let (prefix, name) = (&path[..i], &path[i + 1..]);
+        if prefix.len() <= 155 && !name.is_empty() && name.len() <= 100 {
+            return Some((prefix, name));
+        }
+    }
+    None
+}
+
+/// 构造一个 ustar 头部
+fn tar_build_header(
+    entry: &TarEntry,
+    name: &str,
+    prefix: &str,
+    size: u64,
+    typeflag: u8,
+) -> [u8; TAR_BLOCK_SIZE] {
+    let mut h = [0u8; TAR_BLOCK_SIZE];
+    h[..name.len()].copy_from_slice(name.as_bytes());
+    tar_write_octal(&mut h[100..108], (entry.mode & 0o7777) as u64);
+    tar_write_octal(&mut h[108..116], entry.uid.min(0o7777777));
+    tar_write_octal(&mut h[116..124], entry.gid.min(0o7777777));
+    tar_write_octal(&mut h[124..136], size.min(USTAR_MAX_OCTAL));
+    tar_write_octal(
+        &mut h[136..148],
+        (entry.mtime.max(0) as u64).min(USTAR_MAX_OCTAL),
+    );
+    h[156] = typeflag;
+    let link = entry.link_name.as_bytes();
+    h[157..157 + link.len().min(100)].copy_from_slice(&link[..link.len().min(100)]);
+    h[257..263].copy_from_slice(b"ustar\0");
+    h[263..265].copy_from_slice(b"00");
+    h[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    let sum = tar_checksum(&h);
+    let text = format!("{:06o}\0 ", sum);
+    h[148..156].copy_from_slice(text.as_bytes());
+    h
+}
+
+/// 把 .xz 编码器的输出写入文件
+struct XzWriter {
+    strm: LzmaStream<'static>,
+    out: File,
+    /// 当前 Block 中已写入的未压缩字节数
+    block_bytes: u64,
+}
+
+impl XzWriter {
+    fn new(out: File) -> Result<Self, LzmaRet> {
+        let mut strm = LzmaStream::default();
+        let filters: Vec<LzmaFilter> = FILTERS.lock().unwrap().to_vec();
+        let check = CHECK.lock().unwrap().clone();
+        let ret = lzma_stream_encoder(&mut strm, &filters, check);
+        if ret != LzmaRet::Ok {
+            return Err(ret);
+        }
+
+        Ok(XzWriter {
+            strm,
+            out,
+            block_bytes: 0,
+        })
+    }
+
+    /// 用给定的 action 把 data 送入编码器，并写出产生的所有输出
+    fn code(&mut self, data: &[u8], action: LzmaAction) -> io::Result<()> {
+        // 安全性：next_in 只在本函数内使用，返回前会重置为空切片。
+        self.strm.next_in = unsafe { std::mem::transmute::<&[u8], &'static [u8]>(data) };
+        self.strm.avail_in.set(data.len());
+
+        let result = loop {
+            {
+                let mut next_out = self.strm.next_out.borrow_mut();
+                next_out.clear();
+                next_out.resize(IO_BUFFER_SIZE, 0);
+            }
+            self.strm.next_out_pos = 0;
+            self.strm.avail_out.set(IO_BUFFER_SIZE);
+
+            let ret = lzma_code(&mut self.strm, action.clone());
+
+            let produced = self.strm.next_out_pos as usize;
+            if produced > 0 {
+                if let Err(e) = self.out.write_all(&self.strm.next_out.borrow()[..produced]) {
+                    break Err(e);
+                }
+            }
+
+            match ret {
+                LzmaRet::StreamEnd => break Ok(()),
+                LzmaRet::Ok => {
+                    if action == LzmaAction::Run && self.strm.avail_in.get() == 0 {
+                        break Ok(());
+                    }
+                }
+                _ => break Err(io::Error::new(io::ErrorKind::Other, message_strm(ret))),
+            }
+        };
+
+        self.strm.next_in = &[];
+        self.strm.avail_in.set(0);
+        result
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.block_bytes += data.len() as u64;
+        self.code(data, LzmaAction::Run)
+    }
+
+    /// 如果当前 Block 已达到 --block-size，则结束它，使下一个成员从新的 Block 开始
+    ///
+    /// 未指定 --block-size 时每个成员都从新的 Block 开始。
+    fn member_boundary(&mut self) -> io::Result<()> {
+        if self.block_bytes == 0 || self.block_bytes < get_opt_block_size() {
+            return Ok(());
+        }
+        self.block_bytes = 0;
+        self.code(&[], LzmaAction::FullBarrier)
+    }
+
+    /// 结束 .xz 流并返回输出文件
+    fn finish(mut self) -> io::Result<File> {
+        self.code(&[], LzmaAction::Finish)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// 从文件中顺序读取并解码 .xz 数据
+struct XzReader {
+    strm: LzmaStream<'static>,
+    input: File,
+    in_buf: Box<[u8]>,
+    input_eof: bool,
+    finished: bool,
+    /// 已解码但尚未被读取的数据
+    pending: Vec<u8>,
+    pending_pos: usize,
+}
+
+impl XzReader {
+    fn new(input: File) -> Result<Self, LzmaRet> {
+        let mut strm = LzmaStream::default();
+        let ret = lzma_stream_decoder(
+            &mut strm,
+            hardware_memlimit_get(OperationMode::Decompress),
+            0,
+        );
+        if ret != LzmaRet::Ok {
+            return Err(ret);
+        }
+
+        Ok(XzReader {
+            strm,
+            input,
+            in_buf: vec![0u8; IO_BUFFER_SIZE].into_boxed_slice(),
+            input_eof: false,
+            finished: false,
+            pending: Vec::new(),
+            pending_pos: 0,
+        })
+    }
+
+    /// 解码下一段数据到 pending，没有更多数据时返回 false
+    fn fill(&mut self) -> io::Result<bool> {
+        while !self.finished {
+            tar_check_abort()?;
+            if self.strm.avail_in.get() == 0 && !self.input_eof {
+                let n = self.input.read(&mut self.in_buf)?;
+                if n == 0 {
+                    self.input_eof = true;
+                }
+                // 安全性：in_buf 在堆上，存活时间与 self 相同，且只在下次读取前被引用。
+                self.strm.next_in =
+                    unsafe { std::mem::transmute::<&[u8], &'static [u8]>(&self.in_buf[..n]) };
+                self.strm.avail_in.set(n);
+            }
+
+            {
+                let mut next_out = self.strm.next_out.borrow_mut();
+                next_out.clear();
+                next_out.resize(IO_BUFFER_SIZE, 0);
+            }
+            self.strm.next_out_pos = 0;
+            self.strm.avail_out.set(IO_BUFFER_SIZE);
+
+            let action = if self.input_eof {
+                LzmaAction::Finish
+            } else {
+                LzmaAction::Run
+            };
+            let ret = lzma_code(&mut self.strm, action);
+
+            let produced = self.strm.next_out_pos as usize;
+            match ret {
+                LzmaRet::Ok => {}
+                LzmaRet::StreamEnd => self.finished = true,
+                LzmaRet::BufError if self.input_eof => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        message_strm(LzmaRet::BufError),
+                    ))
+                }
+                _ => return Err(io::Error::new(io::ErrorKind::Other, message_strm(ret))),
+            }
+
+            if produced > 0 {
+                self.pending.clear();
+                self.pending
+                    .extend_from_slice(&self.strm.next_out.borrow()[..produced]);
+                self.pending_pos = 0;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Read for XzReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending_pos == self.pending.len() && !self.fill()? {
+            return Ok(0);
+        }
+        let n = buf.len().min(self.pending.len() - self.pending_pos);
+        buf[..n].copy_from_slice(&self.pending[self.pending_pos..self.pending_pos + n]);
+        self.pending_pos += n;
+        Ok(n)
+    }
+}
+
+/// 通过 Index 随机访问 .xz 文件中的未压缩数据
+///
+/// 每次定位时先用 lzma_index_iter_locate() 找到目标偏移所在的 Block，
+/// 再从该 Block 开头解码。如果下一次读取位于同一 Block 中更靠后的位置，
+/// 则继续使用当前的解码器。
+struct XzSeekReader {
+    input: File,
+    iter: LzmaIndexIter,
+    strm: LzmaStream<'static>,
+    /// 当前 Block 的元数据，解码器持有对它的引用，因此放在堆上
+    block: Box<LzmaBlock>,
+    active: bool,
+    /// 当前 Block 的未压缩起始偏移和结束偏移
+    block_start: u64,
+    block_end: u64,
+    /// 当前解码器的下一个输出字节对应的未压缩偏移
+    pos: u64,
+    /// 当前 Block 压缩数据中尚未读取部分的文件偏移和剩余字节数
+    in_file_pos: u64,
+    in_remaining: u64,
+    in_buf: Box<[u8]>,
+    /// 被解码出来但尚未使用的数据，起始于 pos - pending.len()
+    pending: Vec<u8>,
+    /// 已解码的 Block 数，用于 --verbose 统计
+    blocks_decoded: u64,
+}
+
+impl XzSeekReader {
+    fn new(input: File, xfi: &mut XzFileInfo) -> Self {
+        let mut iter = LzmaIndexIter::default();
+        lzma_index_iter_init(&mut iter, Box::new(xfi.idx.as_ref().unwrap().clone()));
+        XzSeekReader {
+            input,
+            iter,
+            strm: LzmaStream::default(),
+            block: Box::default(),
+            active: false,
+            block_start: 0,
+            block_end: 0,
+            pos: 0,
+            in_file_pos: 0,
+            in_remaining: 0,
+            in_buf: vec![0u8; IO_BUFFER_SIZE].into_boxed_slice(),
+            pending: Vec::new(),
+            blocks_decoded: 0,
+        }
+    }
+
+    fn data_error(ret: LzmaRet) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message_strm(ret))
+    }
+
+    /// 初始化包含 offset 的 Block 的解码器，offset 超出文件末尾时返回 false
+    fn start_block(&mut self, offset: u64) -> io::Result<bool> {
+        // 旧的解码器引用着 block，必须先于 block 被替换
+        self.strm = LzmaStream::default();
+        self.active = false;
+
+        if lzma_index_iter_locate(&mut self.iter, offset) {
+            return Ok(false);
+        }
+
+        let check = self
+            .iter
+            .stream
+            .flags
+            .clone()
+            .ok_or_else(|| Self::data_error(LzmaRet::ProgError))?
+            .check;
+
+        // 读取 Block Header
+        let mut header = [0u8; LZMA_BLOCK_HEADER_SIZE_MAX as usize];
+        self.input
+            .seek(SeekFrom::Start(self.iter.block.compressed_file_offset))?;
+        self.input.read_exact(&mut header[..1])?;
+        if header[0] == 0 {
+            return Err(Self::data_error(LzmaRet::DataError));
+        }
+        let header_size = lzma_block_header_size_decode!(header[0]);
+        self.input
+            .read_exact(&mut header[1..header_size as usize])?;
+
+        let mut block = LzmaBlock {
+            version: 1,
+            header_size,
+            check,
+            filters: (0..5).map(|_| LzmaFilter::default()).collect(),
+            ..Default::default()
+        };
+        let ret = lzma_block_header_decode(&mut block, &header[..header_size as usize]);
+        if ret != LzmaRet::Ok {
+            return Err(Self::data_error(ret));
+        }
+        let ret = lzma_block_compressed_size(&mut block, self.iter.block.unpadded_size);
+        if ret != LzmaRet::Ok {
+            return Err(Self::data_error(ret));
+        }
+
+        *self.block = block;
+        // 安全性：block 在堆上，在解码器被替换或释放前不会被修改或释放。
+        let block_ref: &'static mut LzmaBlock =
+            unsafe { &mut *(self.block.as_mut() as *mut LzmaBlock) };
+        let ret = lzma_block_decoder(&mut self.strm, block_ref);
+        if ret != LzmaRet::Ok {
+            return Err(Self::data_error(ret));
+        }
+
+        self.active = true;
+        self.blocks_decoded += 1;
+        self.block_start = self.iter.block.uncompressed_file_offset;
+        self.block_end = self.block_start + self.iter.block.uncompressed_size;
+        self.pos = self.block_start;
+        self.in_file_pos = self.iter.block.compressed_file_offset + header_size as u64;
+        self.in_remaining = self.iter.block.total_size - header_size as u64;
+        self.pending.clear();
+        Ok(true)
+    }
+
+    /// 从当前解码器再解码一段数据追加到 pending
+    fn decode_more(&mut self) -> io::Result<()> {
+        if self.strm.avail_in.get() == 0 {
+            let n = (self.in_remaining as usize).min(self.in_buf.len());
+            if n == 0 {
+                return Err(Self::data_error(LzmaRet::BufError));
+            }
+            self.input.seek(SeekFrom::Start(self.in_file_pos))?;
+            self.input.read_exact(&mut self.in_buf[..n])?;
+            self.in_file_pos += n as u64;
+            self.in_remaining -= n as u64;
+            // 安全性：in_buf 在堆上，只在下次读取前被引用。
+            self.strm.next_in =
+                unsafe { std::mem::transmute::<&[u8], &'static [u8]>(&self.in_buf[..n]) };
+            self.strm.avail_in.set(n);
+        }
+
+        {
+            let mut next_out = self.strm.next_out.borrow_mut();
+            next_out.clear();
+            next_out.resize(IO_BUFFER_SIZE, 0);
+        }
+        self.strm.next_out_pos = 0;
+        self.strm.avail_out.set(IO_BUFFER_SIZE);
+
+        let ret = lzma_code(&mut self.strm, LzmaAction::Run);
+        let produced = self.strm.next_out_pos as usize;
+        self.pending
+            .extend_from_slice(&self.strm.next_out.borrow()[..produced]);
+        self.pos += produced as u64;
+
+        match ret {
+            LzmaRet::Ok | LzmaRet::StreamEnd => Ok(()),
+            _ => Err(Self::data_error(ret)),
+        }
+    }
+
+    /// 读取从未压缩偏移 offset 开始的 buf.len() 个字节，到达文件末尾时返回 false
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<bool> {
+        let mut done = 0;
+        while done < buf.len() {
+            tar_check_abort()?;
+            let want = offset + done as u64;
+            let pending_start = self.pos - self.pending.len() as u64;
+
+            if !self.active || want < pending_start || want >= self.block_end {
+                if !self.start_block(want)? {
+                    return Ok(false);
+                }
+                continue;
+            }
+
+            if want >= self.pos {
+                // 丢弃目标位置之前的数据，避免 pending 无限增长
+                self.pending.clear();
+                self.decode_more()?;
+                let pending_start = self.pos - self.pending.len() as u64;
+                if want > pending_start {
+                    let skip = ((want - pending_start) as usize).min(self.pending.len());
+                    self.pending.drain(..skip);
+                }
+                continue;
+            }
+
+            let start = (want - pending_start) as usize;
+            let n = (buf.len() - done).min(self.pending.len() - start);
+            buf[done..done + n].copy_from_slice(&self.pending[start..start + n]);
+            done += n;
+        }
+        Ok(true)
+    }
+}
+
+/// 用户中断时返回错误，使解码循环尽快结束
+///
+/// 调用者在 USER_ABORT 已设置时不显示这个错误。
+fn tar_check_abort() -> io::Result<()> {
+    if *USER_ABORT.lock().unwrap() {
+        return Err(io::Error::new(io::ErrorKind::Other, "interrupted"));
+    }
+    Ok(())
+}
+
+/// 解析一个 ustar 头部，返回 None 表示这是结束标记（全零记录）
+fn tar_parse_header(h: &[u8; TAR_BLOCK_SIZE]) -> io::Result<Option<TarEntry>> {
+    if h.iter().all(|&b| b == 0) {
+        return Ok(None);
+    }
+
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, tr_str(Msg::TarHeaderInvalid));
+    let sum = tar_parse_number(&h[148..156]).ok_or_else(bad)?;
+    if sum != tar_checksum(h) {
+        return Err(bad());
+    }
+
+    let mut path = tar_parse_str(&h[..100]);
+    if &h[257..262] == b"ustar" {
+        let prefix = tar_parse_str(&h[345..500]);
+        if !prefix.is_empty() {
+            path = format!("{}/{}", prefix, path);
+        }
+    }
+
+    let typeflag = h[156];
+    let kind = match typeflag {
+        b'0' | 0 | b'7' => TarKind::File,
+        b'5' => TarKind::Dir,
+        b'2' => TarKind::Symlink,
+        other => TarKind::Other(other),
+    };
+
+    Ok(Some(TarEntry {
+        path,
+        kind,
+        mode: tar_parse_number(&h[100..108]).ok_or_else(bad)? as u32,
+        uid: tar_parse_number(&h[108..116]).ok_or_else(bad)?,
+        gid: tar_parse_number(&h[116..124]).ok_or_else(bad)?,
+        size: tar_parse_number(&h[124..136])
+            .filter(|&size| size <= TAR_SIZE_MAX)
+            .ok_or_else(bad)?,
+        mtime: tar_parse_number(&h[136..148]).ok_or_else(bad)? as i64,
+        link_name: tar_parse_str(&h[157..257]),
+    }))
+}
+
+/// 把 pax 扩展头和 GNU 长名称应用到随后的成员上
+fn tar_apply_overrides(
+    entry: &mut TarEntry,
+    pax: &HashMap<String, String>,
+    long_name: &Option<String>,
+    long_link: &Option<String>,
+) {
+    if let Some(name) = long_name {
+        entry.path = name.clone();
+    }
+    if let Some(link) = long_link {
+        entry.link_name = link.clone();
+    }
+    if let Some(path) = pax.get("path") {
+        entry.path = path.clone();
+    }
+    if let Some(link) = pax.get("linkpath") {
+        entry.link_name = link.clone();
+    }
+    if let Some(size) = pax
+        .get("size")
+        .and_then(|s| s.parse().ok())
+        .filter(|&size| size <= TAR_SIZE_MAX)
+    {
+        entry.size = size;
+    }
+    if let Some(mtime) = pax
+        .get("mtime")
+        .and_then(|s| s.split('.').next().and_then(|s| s.parse().ok()))
+    {
+        entry.mtime = mtime;
+    }
+    if let Some(uid) = pax.get("uid").and_then(|s| s.parse().ok()) {
+        entry.uid = uid;
+    }
+    if let Some(gid) = pax.get("gid").and_then(|s| s.parse().ok()) {
+        entry.gid = gid;
+    }
+}
+
+/// 成员名称是否被命令行上的名称选中（名称本身或其下的路径）
+fn tar_is_selected(path: &str, members: &[String]) -> bool {
+    if members.is_empty() {
+        return true;
+    }
+    let path = path.trim_end_matches('/');
+    members.iter().any(|m| {
+        let m = m.trim_end_matches('/');
+        path == m || (path.starts_with(m) && path.as_bytes().get(m.len()) == Some(&b'/'))
+    })
+}
+
+/// 把成员写入归档：必要时先写 pax 扩展头，然后写 ustar 头部
+fn tar_write_header(w: &mut XzWriter, entry: &TarEntry) -> io::Result<()> {
+    let typeflag = match entry.kind {
+        TarKind::File => b'0',
+        TarKind::Dir => b'5',
+        TarKind::Symlink => b'2',
+        TarKind::Other(t) => t,
+    };
+
+    let mut pax = String::new();
+    let split = ustar_split_name(&entry.path);
+    if split.is_none() {
+        pax.push_str(&pax_record("path", &entry.path));
+    }
+    if entry.link_name.len() > 100 {
+        pax.push_str(&pax_record("linkpath", &entry.link_name));
+    }
+    if entry.size > USTAR_MAX_OCTAL {
+        pax.push_str(&pax_record("size", &entry.size.to_string()));
+    }
+    if entry.mtime < 0 || entry.mtime as u64 > USTAR_MAX_OCTAL {
+        pax.push_str(&pax_record("mtime", &entry.mtime.to_string()));
+    }
+
+    w.member_boundary()?;
+
+    if !pax.is_empty() {
+        let pax_name = format!("PaxHeaders/{}", entry.path.rsplit('/').next().unwrap_or(""));
+        let pax_name = &pax_name[..pax_name.len().min(100)];
+        let pax_entry = TarEntry {
+            path: pax_name.to_string(),
+            kind: TarKind::Other(b'x'),
+            mode: 0o644,
+            uid: 0,
+            gid: 0,
+            size: pax.len() as u64,
+            mtime: entry.mtime.max(0),
+            link_name: String::new(),
+        };
+        w.write_all(&tar_build_header(
+            &pax_entry,
+            pax_name,
+            "",
+            pax.len() as u64,
+            b'x',
+        ))?;
+        w.write_all(pax.as_bytes())?;
+        let pad = tar_round_up(pax.len() as u64) as usize - pax.len();
+        w.write_all(&[0u8; TAR_BLOCK_SIZE][..pad])?;
+    }
+
+    let (prefix, name) = split.unwrap_or(("", ""));
+    let name = if split.is_none() {
+        &entry.path[..entry.path.len().min(100)]
+    } else {
+        name
+    };
+    w.write_all(&tar_build_header(entry, name, prefix, entry.size, typeflag))
+}
+
+/// 把一个路径（及其下的所有内容）加入归档，返回 true 表示出错
+fn tar_add_path(w: &mut XzWriter, path: &str) -> io::Result<bool> {
+    if *USER_ABORT.lock().unwrap() {
+        return Ok(true);
+    }
+
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(e) => {
+            message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+            return Ok(true);
+        }
+    };
+
+    // 归档中的名称不包含开头的 "/" 和 "./"
+    let mut name = path.trim_start_matches('/');
+    while let Some(rest) = name.strip_prefix("./") {
+        name = rest.trim_start_matches('/');
+    }
+    if name.is_empty() || name == "." {
+        name = "";
+    }
+
+    let file_type = meta.file_type();
+    let mut entry = TarEntry {
+        path: name.to_string(),
+        kind: TarKind::File,
+        mode: meta.mode(),
+        uid: meta.uid() as u64,
+        gid: meta.gid() as u64,
+        size: 0,
+        mtime: meta.mtime(),
+        link_name: String::new(),
+    };
+
+    let mut error = false;
+    if file_type.is_dir() {
+        if !name.is_empty() {
+            entry.kind = TarKind::Dir;
+            entry.path = format!("{}/", name.trim_end_matches('/'));
+            tar_write_header(w, &entry)?;
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        match fs::read_dir(path) {
+            Ok(dir) => {
+                for d in dir.flatten() {
+                    if let Ok(n) = d.file_name().into_string() {
+                        names.push(n);
+                    }
+                }
+            }
+            Err(e) => {
+                message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                return Ok(true);
+            }
+        }
+        names.sort();
+        for n in names {
+            let child = format!("{}/{}", path.trim_end_matches('/'), n);
+            error |= tar_add_path(w, &child)?;
+        }
+    } else if file_type.is_symlink() {
+        entry.kind = TarKind::Symlink;
+        entry.link_name = match fs::read_link(path) {
+            Ok(l) => l.to_string_lossy().into_owned(),
+            Err(e) => {
+                message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                return Ok(true);
+            }
+        };
+        tar_write_header(w, &entry)?;
+    } else if file_type.is_file() {
+        let mut file = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                return Ok(true);
+            }
+        };
+        entry.size = meta.len();
+        tar_write_header(w, &entry)?;
+
+        // 按头部中记录的大小写入数据；文件在此期间变短时用零补齐
+        let mut buf = vec![0u8; IO_BUFFER_SIZE];
+        let mut remaining = entry.size;
+        while remaining > 0 {
+            tar_check_abort()?;
+            let want = (remaining as usize).min(buf.len());
+            let n = file.read(&mut buf[..want])?;
+            if n == 0 {
+                message_warning(&tr(Msg::TarFileShrank, &[&path]), &[]);
+                buf[..want].fill(0);
+                w.write_all(&buf[..want])?;
+                remaining -= want as u64;
+                error = true;
+                continue;
+            }
+            w.write_all(&buf[..n])?;
+            remaining -= n as u64;
+        }
+        let pad = (tar_round_up(entry.size) - entry.size) as usize;
+        w.write_all(&[0u8; TAR_BLOCK_SIZE][..pad])?;
+    } else {
+        message_warning(&tr(Msg::TarNotRegular, &[&path]), &[]);
+    }
+
+    Ok(error)
+}
+
+/// 创建 .tar.xz 归档
+pub fn tar_create(archive: &str, paths: &[String]) {
+    if paths.is_empty() {
+        message_fatal(tr_str(Msg::TarNoFiles), format_args!(""));
+        return;
+    }
+
+    // 与普通的压缩一样先写入临时文件，完成后再重命名为归档文件，
+    // 失败时不会破坏已有的归档
+    let Some((fd, tmp_name)) = io_create_tmp(archive) else {
+        return;
+    };
+    // 安全性：fd 是刚创建的临时文件，由 File 接管
+    let out = unsafe { File::from_raw_fd(fd) };
+
+    // 临时文件以 0600 创建，改为新建文件通常的权限
+    let mask = sys_fs::umask(0o022);
+    sys_fs::umask(mask);
+    let _ = sys_fs::fchmod(fd, 0o666 & !mask);
+
+    let mut w = match XzWriter::new(out) {
+        Ok(w) => w,
+        Err(ret) => {
+            message_error(
+                &format!("{}: {}", archive, message_strm(ret)),
+                format_args!(""),
+            );
+            let _ = fs::remove_file(&tmp_name);
+            return;
+        }
+    };
+
+    let result = (|| -> io::Result<libc::stat> {
+        for path in paths {
+            tar_add_path(&mut w, path)?;
+        }
+        // 被中断时不完成归档，已有的归档保持不变
+        tar_check_abort()?;
+        // 归档以两个全零记录结束
+        w.write_all(&[0u8; 2 * TAR_BLOCK_SIZE])?;
+        let out = w.finish()?;
+        if *OPT_SYNC.lock().unwrap() {
+            out.sync_all()?;
+        }
+        let mut st = sys_fs::zeroed_stat();
+        sys_fs::fstat(out.as_raw_fd(), &mut st)?;
+        Ok(st)
+    })();
+
+    match result {
+        Ok(st) => {
+            io_rename_dest(archive, &tmp_name, &st);
+        }
+        Err(e) => {
+            // 被中断时的错误只是为了结束循环
+            if !*USER_ABORT.lock().unwrap() {
+                message_error(
+                    &format!("{}: {}", archive, tr_io_error(&e)),
+                    format_args!(""),
+                );
+            }
+            let _ = fs::remove_file(&tmp_name);
+        }
+    }
+}
+
+/// 检查解包路径是否安全：不能是绝对路径，也不能包含 ".."
+fn tar_safe_path(path: &str) -> bool {
+    let p = Path::new(path);
+    !p.is_absolute()
+        && p.components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// path 的某个上级目录是否为符号链接
+///
+/// 之前解开的符号链接可能指向目标目录之外，经过它创建文件就会写到目标目录之外，
+/// 因此逐级用 lstat 检查。
+fn tar_parent_is_symlink(path: &Path) -> bool {
+    path.ancestors()
+        .skip(1)
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p| fs::symlink_metadata(p).is_ok_and(|m| m.file_type().is_symlink()))
+}
+
+/// 设置解包出的文件的权限和修改时间
+fn tar_set_attrs(file: &File, entry: &TarEntry) {
+    let _ = file.set_permissions(fs::Permissions::from_mode(entry.mode & 0o7777));
+    let ts = [
+        libc::timespec {
+            tv_sec: 0,
+            tv_nsec: libc::UTIME_OMIT,
+        },
+        libc::timespec {
+            tv_sec: entry.mtime as libc::time_t,
+            tv_nsec: 0,
+        },
+    ];
+    let _ = sys_fs::futimens(file.as_raw_fd(), &ts);
+}
+
+/// 把一个成员解包到当前目录，数据从 r 中读取
+///
+/// 目录的权限和修改时间要等其中的内容都解开后才能设置，因此先记录到 dirs 中。
+fn tar_extract_entry<R: Read>(
+    r: &mut R,
+    entry: &TarEntry,
+    dirs: &mut Vec<TarEntry>,
+) -> io::Result<bool> {
+    let path = entry.path.trim_end_matches('/');
+    if path.is_empty() || !tar_safe_path(path) {
+        message_warning(&tr(Msg::TarUnsafeName, &[&entry.path]), &[]);
+        tar_skip(r, tar_round_up(entry.size))?;
+        return Ok(true);
+    }
+
+    if tar_parent_is_symlink(Path::new(path)) {
+        message_warning(&tr(Msg::TarSymlinkInPath, &[&entry.path]), &[]);
+        tar_skip(r, tar_round_up(entry.size))?;
+        return Ok(true);
+    }
+
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    match entry.kind {
+        TarKind::Dir => {
+            if let Err(e) = fs::create_dir(path) {
+                if e.kind() != io::ErrorKind::AlreadyExists {
+                    message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                    return Ok(true);
+                }
+                // 已存在的符号链接不能当作目录，否则之后会修改它指向的目录的属性
+                if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+                    message_warning(&tr(Msg::TarSymlinkInPath, &[&entry.path]), &[]);
+                    tar_skip(r, tar_round_up(entry.size))?;
+                    return Ok(true);
+                }
+            }
+            dirs.push(entry.clone());
+            tar_skip(r, tar_round_up(entry.size))?;
+        }
+        TarKind::Symlink => {
+            if *OPT_FORCE.lock().unwrap() {
+                let _ = fs::remove_file(path);
+            }
+            if let Err(e) = std::os::unix::fs::symlink(&entry.link_name, path) {
+                message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                return Ok(true);
+            }
+            tar_skip(r, tar_round_up(entry.size))?;
+        }
+        TarKind::File => {
+            let mut options = fs::OpenOptions::new();
+            options.write(true);
+            if *OPT_FORCE.lock().unwrap() {
+                let _ = fs::remove_file(path);
+            }
+            options.create_new(true);
+            let mut file = match options.open(path) {
+                Ok(f) => f,
+                Err(e) => {
+                    message_error(&format!("{}: {}", path, tr_io_error(&e)), format_args!(""));
+                    tar_skip(r, tar_round_up(entry.size))?;
+                    return Ok(true);
+                }
+            };
+            io::copy(&mut r.take(entry.size), &mut file)?;
+            tar_skip(r, tar_round_up(entry.size) - entry.size)?;
+            tar_set_attrs(&file, entry);
+        }
+        TarKind::Other(t) => {
+            message_warning(
+                &tr(Msg::TarUnsupportedType, &[&entry.path, &(t as char)]),
+                &[],
+            );
+            tar_skip(r, tar_round_up(entry.size))?;
+        }
+    }
+    Ok(false)
+}
+
+/// 从 r 中丢弃 n 个字节
+fn tar_skip<R: Read>(r: &mut R, n: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut r.take(n), &mut io::sink())?;
+    if skipped != n {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            tr_str(Msg::TarUnexpectedEnd),
+        ));
+    }
+    Ok(())
+}
+
+/// 检查扩展头数据（pax 或 GNU 长名称）的长度
+fn tar_ext_size(size: u64) -> io::Result<usize> {
+    if size > TAR_EXT_SIZE_MAX {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            tr_str(Msg::TarExtTooLarge),
+        ));
+    }
+    Ok(size as usize)
+}
+
+/// 读取一个完整的扩展头数据（pax 或 GNU 长名称）
+fn tar_read_ext<R: Read>(r: &mut R, size: u64) -> io::Result<Vec<u8>> {
+    let mut data = vec![0u8; tar_ext_size(size)?];
+    r.read_exact(&mut data)?;
+    tar_skip(r, tar_round_up(size) - size)?;
+    Ok(data)
+}
+
+/// 解开 .tar.xz 归档到当前目录，members 非空时只解开选中的成员
+pub fn tar_extract(archive: &str, members: &[String]) {
+    let input = match File::open(archive) {
+        Ok(f) => f,
+        Err(e) => {
+            message_error(
+                &format!("{}: {}", archive, tr_io_error(&e)),
+                format_args!(""),
+            );
+            return;
+        }
+    };
+    let mut r = match XzReader::new(input) {
+        Ok(r) => r,
+        Err(ret) => {
+            message_error(
+                &format!("{}: {}", archive, message_strm(ret)),
+                format_args!(""),
+            );
+            return;
+        }
+    };
+
+    let verbose = message_verbosity_get() >= MessageVerbosity::Verbose;
+    let mut dirs: Vec<TarEntry> = Vec::new();
+    let result = (|| -> io::Result<()> {
+        let mut pax: HashMap<String, String> = HashMap::new();
+        let mut long_name: Option<String> = None;
+        let mut long_link: Option<String> = None;
+        let mut h = [0u8; TAR_BLOCK_SIZE];
+
+        loop {
+            if *USER_ABORT.lock().unwrap() {
+                return Ok(());
+            }
+            r.read_exact(&mut h)?;
+            let mut entry = match tar_parse_header(&h)? {
+                Some(e) => e,
+                None => return Ok(()),
+            };
+
+            match entry.kind {
+                TarKind::Other(b'x') => {
+                    pax = pax_parse(&tar_read_ext(&mut r, entry.size)?);
+                    continue;
+                }
+                TarKind::Other(b'g') => {
+                    tar_read_ext(&mut r, entry.size)?;
+                    continue;
+                }
+                TarKind::Other(b'L') => {
+                    let data = tar_read_ext(&mut r, entry.size)?;
+                    long_name = Some(tar_parse_str(&data));
+                    continue;
+                }
+                TarKind::Other(b'K') => {
+                    let data = tar_read_ext(&mut r, entry.size)?;
+                    long_link = Some(tar_parse_str(&data));
+                    continue;
+                }
+                _ => {}
+            }
+
+            tar_apply_overrides(&mut entry, &pax, &long_name, &long_link);
+            pax.clear();
+            long_name = None;
+            long_link = None;
+
+            if tar_is_selected(&entry.path, members) {
+                if verbose {
+                    eprintln!("{}", entry.path);
+                }
+                tar_extract_entry(&mut r, &entry, &mut dirs)?;
+            } else {
+                tar_skip(&mut r, tar_round_up(entry.size))?;
+            }
+        }
+    })();
+
+    // 从最深的目录开始设置，避免修改子目录时改变父目录的修改时间。
+    // 目录在解包过程中可能被换成了符号链接，因此不跟随符号链接。
+    for entry in dirs.iter().rev() {
+        let path = Path::new(entry.path.trim_end_matches('/'));
+        if tar_parent_is_symlink(path) {
+            continue;
+        }
+        if let Ok(dir) = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW | libc::O_DIRECTORY)
+            .open(path)
+        {
+            tar_set_attrs(&dir, entry);
+        }
+    }
+
+    match result {
+        // 被中断时的错误只是为了结束循环
+        Err(_) if *USER_ABORT.lock().unwrap() => {}
+        Err(e) => message_error(
+            &format!("{}: {}", archive, tr_io_error(&e)),
+            format_args!(""),
+        ),
+        Ok(()) => {}
+    }
+}
+
+/// 把 Unix 时间格式化为 "YYYY-MM-DD HH:MM"（UTC）
+fn tar_format_time(mtime: i64) -> String {
+    let days = mtime.div_euclid(86400);
+    let secs = mtime.rem_euclid(86400);
+
+    // 由天数计算公历日期，算法来自 Howard Hinnant 的 civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+/// 打印一个成员，格式与 tar -tv 类似
+fn tar_print_entry(entry: &TarEntry, verbose: bool) {
+    if !verbose {
+        println!("{}", entry.path);
+        return;
+    }
+
+    let type_char = match entry.kind {
+        TarKind::Dir => 'd',
+        TarKind::Symlink => 'l',
+        _ => '-',
+    };
+    let mut perms = String::with_capacity(9);
+    for shift in [6, 3, 0] {
+        let bits = (entry.mode >> shift) & 7;
+        perms.push(if bits & 4 != 0 { 'r' } else { '-' });
+        perms.push(if bits & 2 != 0 { 'w' } else { '-' });
+        perms.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    let link = if entry.kind == TarKind::Symlink {
+        format!(" -> {}", entry.link_name)
+    } else {
+        String::new()
+    };
+    println!(
+        "{}{} {}/{} {:>12} {} {}{}",
+        type_char,
+        perms,
+        entry.uid,
+        entry.gid,
+        entry.size,
+        tar_format_time(entry.mtime),
+        entry.path,
+        link
+    );
+}
+
+/// 列出 .tar.xz 归档中的成员
+///
+/// 使用 Index 定位每个成员头所在的 Block，成员数据本身不会被解码。
+/// members 非空时只打印选中的成员。
+pub fn tar_list(archive: &str, members: &[String]) {
+    *OPT_STDOUT.lock().unwrap() = false;
+    *OPT_FORCE.lock().unwrap() = true;
+
+    let mut pair = match io_open_src(archive) {
+        Some(p) => p,
+        None => return,
+    };
+    let mut xfi = XzFileInfo::default();
+    let failed = parse_indexes(&mut xfi, &mut pair);
+    io_close(&mut pair, false);
+    if failed {
+        return;
+    }
+
+    let input = match File::open(archive) {
+        Ok(f) => f,
+        Err(e) => {
+            message_error(
+                &format!("{}: {}", archive, tr_io_error(&e)),
+                format_args!(""),
+            );
+            return;
+        }
+    };
+    let mut r = XzSeekReader::new(input, &mut xfi);
+    let verbose = message_verbosity_get() >= MessageVerbosity::Verbose;
+
+    let result = (|| -> io::Result<()> {
+        let mut offset: u64 = 0;
+        let mut pax: HashMap<String, String> = HashMap::new();
+        let mut long_name: Option<String> = None;
+        let mut long_link: Option<String> = None;
+        let mut h = [0u8; TAR_BLOCK_SIZE];
+        let mut members_seen = 0u64;
+
+        loop {
+            if *USER_ABORT.lock().unwrap() {
+                return Ok(());
+            }
+            if !r.read_at(offset, &mut h)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    tr_str(Msg::TarUnexpectedEnd),
+                ));
+            }
+            let mut entry = match tar_parse_header(&h)? {
+                Some(e) => e,
+                None => break,
+            };
+            offset += TAR_BLOCK_SIZE as u64;
+
+            let ext = matches!(entry.kind, TarKind::Other(b'x' | b'g' | b'L' | b'K'));
+            if ext {
+                let mut data = vec![0u8; tar_ext_size(entry.size)?];
+                if !r.read_at(offset, &mut data)? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        tr_str(Msg::TarUnexpectedEnd),
+                    ));
+                }
+                match entry.kind {
+                    TarKind::Other(b'x') => pax = pax_parse(&data),
+                    TarKind::Other(b'L') => long_name = Some(tar_parse_str(&data)),
+                    TarKind::Other(b'K') => long_link = Some(tar_parse_str(&data)),
+                    _ => {}
+                }
+                offset += tar_round_up(entry.size);
+                continue;
+            }
+
+            tar_apply_overrides(&mut entry, &pax, &long_name, &long_link);
+            pax.clear();
+            long_name = None;
+            long_link = None;
+            members_seen += 1;
+
+            if tar_is_selected(&entry.path, members) {
+                tar_print_entry(&entry, verbose);
+            }
+
+            // 跳过成员数据，不解码
+            offset += tar_round_up(entry.size);
+        }
+
+        message(
+            MessageVerbosity::Debug,
+            &tr(
+                Msg::TarSummary,
+                &[&archive, &members_seen, &r.blocks_decoded],
+            ),
+            format_args!(""),
+        );
+        Ok(())
+    })();
+
+    match result {
+        Err(_) if *USER_ABORT.lock().unwrap() => {}
+        Err(e) => message_error(
+            &format!("{}: {}", archive, tr_io_error(&e)),
+            format_args!(""),
+        ),
+        Ok(()) => {}
+    }
+}
+
+/// --tar 模式入口：第一个文件名是归档，其余是要归档或选择的成员
+pub fn tar_run(mode: OperationMode, names: &[String]) {
+    let format = *OPT_FORMAT.lock().unwrap();
+    if format != FormatType::Xz && format != FormatType::Auto {
+        message_fatal(tr_str(Msg::TarXzOnly), format_args!(""));
+    }
+
+    let (archive, rest) = match names.split_first() {
+        Some((a, rest)) if a != "-" => (a, rest),
+        _ => {
+            message_fatal(tr_str(Msg::TarNeedsArchive), format_args!(""));
+            return;
+        }
+    };
+
+    match mode {
+        OperationMode::Compress => tar_create(archive, rest),
+        OperationMode::Decompress => tar_extract(archive, rest),
+        OperationMode::List | OperationMode::Test => tar_list(archive, rest),
+    }
+}
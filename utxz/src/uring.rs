@@ -0,0 +1,424 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 基于 io_uring 的普通文件读写
+//!
+//! 读取时保持多个大缓冲区的读请求在内核中排队，相当于预读；写入时使用两个大缓冲区，
+//! 一个交给内核写入的同时填充另一个。只用于普通文件，创建失败时调用者回退到
+//! read() 和 write()。
+
+use std::fmt;
+use std::io;
+use std::os::unix::io::RawFd;
+
+use io_uring::{opcode, types, IoUring};
+
+/// 每个缓冲区的大小
+pub const URING_BUF_SIZE: usize = 1 << 20;
+
+/// 读取时使用的缓冲区数量，除正在交给调用者的缓冲区外其余都用于预读
+const URING_READ_BUFS: usize = 4;
+
+/// 写入时使用的缓冲区数量
+const URING_WRITE_BUFS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BufState {
+    /// 没有请求，可以提交新的请求
+    Idle,
+    /// 请求已提交，内核可能正在使用缓冲区
+    InFlight,
+    /// 请求已完成
+    Done,
+}
+
+struct UringBuf {
+    data: Box<[u8]>,
+    state: BufState,
+
+    /// 缓冲区第一个字节在文件中的位置
+    offset: u64,
+
+    /// 读取时为读到的字节数；写入时为缓冲区中的字节数
+    len: usize,
+
+    /// 写入时已经写入文件的字节数
+    done: usize,
+
+    /// 请求失败时的 errno
+    err: i32,
+}
+
+impl UringBuf {
+    fn new() -> Self {
+        UringBuf {
+            data: vec![0u8; URING_BUF_SIZE].into_boxed_slice(),
+            state: BufState::Idle,
+            offset: 0,
+            len: 0,
+            done: 0,
+            err: 0,
+        }
+    }
+}
+
+/// 提交准备好的请求并至少等待一个完成，返回完成的 (user_data, result)
+fn uring_wait(ring: &mut IoUring) -> io::Result<Vec<(u64, i32)>> {
+    ring.submit_and_wait(1)?;
+    Ok(ring
+        .completion()
+        .map(|cqe| (cqe.user_data(), cqe.result()))
+        .collect())
+}
+
+/// 等待所有请求完成，之后才能释放或重用缓冲区
+fn uring_drain(ring: &mut IoUring, bufs: &mut [UringBuf]) {
+    while bufs.iter().any(|b| b.state == BufState::InFlight) {
+        match uring_wait(ring) {
+            Ok(completed) => {
+                for (i, _) in completed {
+                    bufs[i as usize].state = BufState::Done;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            // 无法再等待时只能泄漏缓冲区，避免内核写入已释放的内存
+            Err(_) => {
+                for b in bufs.iter_mut().filter(|b| b.state == BufState::InFlight) {
+                    std::mem::forget(std::mem::take(&mut b.data));
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// 顺序读取普通文件，并预读后面的数据
+pub struct UringReader {
+    ring: IoUring,
+    fd: RawFd,
+    bufs: Vec<UringBuf>,
+
+    /// 包含 pos 的缓冲区，之后的缓冲区按文件位置依次排列
+    head: usize,
+
+    /// 下一个交给调用者的字节在文件中的位置
+    pos: u64,
+
+    /// 下一个读请求的位置
+    next_offset: u64,
+
+    /// 遇到了比请求短的读取，之后不再预读
+    eof: bool,
+}
+
+impl fmt::Debug for UringReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UringReader")
+            .field("fd", &self.fd)
+            .field("pos", &self.pos)
+            .field("next_offset", &self.next_offset)
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UringReader {
+    /// 从文件位置 pos 开始读取；内核不支持 io_uring 时返回错误
+    pub fn new(fd: RawFd, pos: u64) -> io::Result<Self> {
+        let ring = IoUring::new(URING_READ_BUFS as u32)?;
+        Ok(UringReader {
+            ring,
+            fd,
+            bufs: (0..URING_READ_BUFS).map(|_| UringBuf::new()).collect(),
+            head: 0,
+            pos,
+            next_offset: pos,
+            eof: false,
+        })
+    }
+
+    /// 为所有空闲的缓冲区提交读请求
+    fn fill(&mut self) {
+        if self.eof {
+            return;
+        }
+
+        for j in 0..URING_READ_BUFS {
+            let i = (self.head + j) % URING_READ_BUFS;
+            let buf = &mut self.bufs[i];
+            if buf.state != BufState::Idle {
+                continue;
+            }
+
+            let entry = opcode::Read::new(
+                types::Fd(self.fd),
+                buf.data.as_mut_ptr(),
+                URING_BUF_SIZE as u32,
+            )
+            .offset(self.next_offset)
+            .build()
+            .user_data(i as u64);
+
+            // 安全性：缓冲区属于 self，在请求完成之前不会被释放或重用，
+            // Drop 会等待所有请求完成。
+            if unsafe { self.ring.submission().push(&entry) }.is_err() {
+                return;
+            }
+
+            buf.state = BufState::InFlight;
+            buf.offset = self.next_offset;
+            self.next_offset += URING_BUF_SIZE as u64;
+        }
+    }
+
+    /// 等待 head 缓冲区的读请求完成
+    fn wait_head(&mut self) -> io::Result<()> {
+        while self.bufs[self.head].state == BufState::InFlight {
+            for (i, res) in uring_wait(&mut self.ring)? {
+                let buf = &mut self.bufs[i as usize];
+                buf.state = BufState::Done;
+                if res < 0 {
+                    buf.err = -res;
+                    buf.len = 0;
+                } else {
+                    buf.err = 0;
+                    buf.len = res as usize;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 丢弃预读的数据，从文件位置 pos 重新开始读取
+    pub fn seek(&mut self, pos: u64) {
+        uring_drain(&mut self.ring, &mut self.bufs);
+        for buf in self.bufs.iter_mut() {
+            buf.state = BufState::Idle;
+        }
+        self.head = 0;
+        self.pos = pos;
+        self.next_offset = pos;
+        self.eof = false;
+    }
+
+    /// 下一个交给调用者的字节在文件中的位置
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// 读取到 out，返回读取的字节数，0 表示文件结束
+    ///
+    /// 返回 ErrorKind::Interrupted 时没有读取任何数据，可以再次调用。
+    pub fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut done = 0;
+
+        while done < out.len() {
+            self.fill();
+
+            let head = self.head;
+            if self.bufs[head].state == BufState::Idle {
+                break;
+            }
+
+            if let Err(e) = self.wait_head() {
+                if done > 0 && e.kind() == io::ErrorKind::Interrupted {
+                    break;
+                }
+                return Err(e);
+            }
+
+            let buf = &mut self.bufs[head];
+            if buf.err != 0 {
+                let err = buf.err;
+                self.seek(self.pos);
+                if done > 0 {
+                    break;
+                }
+                return Err(io::Error::from_raw_os_error(err));
+            }
+
+            let start = (self.pos - buf.offset) as usize;
+            if start < buf.len {
+                let n = (buf.len - start).min(out.len() - done);
+                out[done..done + n].copy_from_slice(&buf.data[start..start + n]);
+                self.pos += n as u64;
+                done += n;
+                continue;
+            }
+
+            if buf.len < URING_BUF_SIZE {
+                // 读取比请求短，通常是到了文件末尾。从这里重新读取一次，
+                // 以免文件在读取期间变长时丢失数据。
+                if buf.len == 0 {
+                    self.seek(self.pos);
+                    self.eof = true;
+                    break;
+                }
+                self.seek(self.pos);
+                continue;
+            }
+
+            buf.state = BufState::Idle;
+            self.head = (head + 1) % URING_READ_BUFS;
+        }
+
+        Ok(done)
+    }
+}
+
+impl Drop for UringReader {
+    fn drop(&mut self) {
+        uring_drain(&mut self.ring, &mut self.bufs);
+    }
+}
+
+/// 顺序写入普通文件
+pub struct UringWriter {
+    ring: IoUring,
+    fd: RawFd,
+    bufs: Vec<UringBuf>,
+
+    /// 正在填充的缓冲区
+    cur: usize,
+
+    /// 下一个写入的字节在文件中的位置
+    offset: u64,
+}
+
+impl fmt::Debug for UringWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UringWriter")
+            .field("fd", &self.fd)
+            .field("cur", &self.cur)
+            .field("offset", &self.offset)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UringWriter {
+    /// 从文件位置 offset 开始写入；内核不支持 io_uring 时返回错误
+    pub fn new(fd: RawFd, offset: u64) -> io::Result<Self> {
+        let ring = IoUring::new(URING_WRITE_BUFS as u32)?;
+        Ok(UringWriter {
+            ring,
+            fd,
+            bufs: (0..URING_WRITE_BUFS).map(|_| UringBuf::new()).collect(),
+            cur: 0,
+            offset,
+        })
+    }
+
+    /// 提交缓冲区 i 中尚未写入的部分
+    fn submit(&mut self, i: usize) -> io::Result<()> {
+        let buf = &mut self.bufs[i];
+        let entry = opcode::Write::new(
+            types::Fd(self.fd),
+            buf.data[buf.done..].as_ptr(),
+            (buf.len - buf.done) as u32,
+        )
+        .offset(buf.offset + buf.done as u64)
+        .build()
+        .user_data(i as u64);
+
+        // 安全性：缓冲区属于 self，在请求完成之前不会被释放或修改，
+        // Drop 会等待所有请求完成。
+        unsafe { self.ring.submission().push(&entry) }
+            .map_err(|_| io::Error::from_raw_os_error(libc::EBUSY))?;
+        buf.state = BufState::InFlight;
+        Ok(())
+    }
+
+    /// 等待缓冲区 i 写完，短写时提交剩余的部分
+    fn wait(&mut self, i: usize) -> io::Result<()> {
+        while self.bufs[i].state == BufState::InFlight {
+            let completed = match uring_wait(&mut self.ring) {
+                Ok(completed) => completed,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            for (j, res) in completed {
+                let j = j as usize;
+                let buf = &mut self.bufs[j];
+                buf.state = BufState::Done;
+                if res < 0 {
+                    buf.err = -res;
+                } else if res == 0 {
+                    buf.err = libc::EIO;
+                } else {
+                    buf.done += res as usize;
+                    if buf.done < buf.len {
+                        self.submit(j)?;
+                    }
+                }
+            }
+        }
+
+        let buf = &mut self.bufs[i];
+        if buf.err != 0 {
+            return Err(io::Error::from_raw_os_error(buf.err));
+        }
+        buf.state = BufState::Idle;
+        buf.len = 0;
+        buf.done = 0;
+        Ok(())
+    }
+
+    /// 把正在填充的缓冲区交给内核，并切换到下一个缓冲区
+    fn flush_cur(&mut self) -> io::Result<()> {
+        if self.bufs[self.cur].len == 0 {
+            return Ok(());
+        }
+
+        self.submit(self.cur)?;
+        self.cur = (self.cur + 1) % URING_WRITE_BUFS;
+        self.wait(self.cur)
+    }
+
+    /// 写入 data
+    pub fn write(&mut self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let offset = self.offset;
+            let buf = &mut self.bufs[self.cur];
+            if buf.len == 0 {
+                buf.offset = offset;
+            }
+
+            let n = (URING_BUF_SIZE - buf.len).min(data.len());
+            buf.data[buf.len..buf.len + n].copy_from_slice(&data[..n]);
+            buf.len += n;
+            self.offset += n as u64;
+            data = &data[n..];
+
+            if buf.len == URING_BUF_SIZE {
+                self.flush_cur()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 跳过 size 字节，在文件中留下空洞
+    pub fn skip(&mut self, size: u64) -> io::Result<()> {
+        self.flush_cur()?;
+        self.offset += size;
+        Ok(())
+    }
+
+    /// 写入所有缓冲的数据并等待完成
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.flush_cur()?;
+        for i in 0..URING_WRITE_BUFS {
+            self.wait(i)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for UringWriter {
+    fn drop(&mut self) {
+        uring_drain(&mut self.ring, &mut self.bufs);
+    }
+}
@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: GPL-2.0-or-later
  */
 
+use crate::catalog::{tr, tr_str, Msg};
+use crate::message::message_error;
 use lazy_static::lazy_static;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::{Mutex, MutexGuard};
@@ -218,16 +220,16 @@ pub fn uint64_to_nicestr(
 }
 
 fn message_fatal(name: &str, min: u64, max: u64) {
-    panic!(
-        "Value of the option `{}` must be in the range [{}, {}]",
-        name, min, max
+    crate::message::message_fatal(
+        &tr(Msg::ValueOutOfRange, &[&name, &min, &max]),
+        format_args!(""),
     );
 }
 
 pub fn is_tty_stdin() -> bool {
     let ret = atty::is(atty::Stream::Stdin);
     if ret {
-        eprintln!("Compressed data cannot be read from a terminal");
+        message_error(tr_str(Msg::TtyRead), format_args!(""));
     }
     ret
 }
@@ -235,7 +237,7 @@ pub fn is_tty_stdin() -> bool {
 pub fn is_tty_stdout() -> bool {
     let ret = atty::is(atty::Stream::Stdout);
     if ret {
-        eprintln!("Compressed data cannot be written to a terminal");
+        message_error(tr_str(Msg::TtyWrite), format_args!(""));
     }
     ret
 }
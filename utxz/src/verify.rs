@@ -0,0 +1,325 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --verify：不完整解压即可检查 .xz 文件
+//!
+//! structure 只检查文件的结构：Stream Header 和 Stream Footer、Index、Stream
+//! Padding（由 file_info 解码器完成），以及每个 Block Header 和 Block Padding，
+//! 不解码 LZMA2 数据。full 另外用 -T 指定的线程数并行解码各个 Block，
+//! 并验证它们的完整性校验值，解码出的数据直接丢弃。
+
+use liblzma::api::{
+    LzmaAction, LzmaBlock, LzmaCheck, LzmaFilter, LzmaIndexIter, LzmaIndexIterMode, LzmaRet,
+    LzmaStream, LZMA_BLOCK_HEADER_SIZE_MAX,
+};
+use liblzma::check::lzma_check_size;
+use liblzma::common::{
+    lzma_block_compressed_size, lzma_block_decoder, lzma_block_header_decode, lzma_code,
+    lzma_index_iter_init, lzma_index_iter_next,
+};
+use liblzma::lzma_block_header_size_decode;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use utxz_sys::unistd as sys_unistd;
+
+use crate::args::{OPT_VERIFY, STDIN_FILENAME};
+use crate::catalog::{tr, tr_io_error, tr_str, Msg};
+use crate::coder::{FormatType, OPT_FORMAT};
+use crate::file_io::{io_close, io_open_src, io_pread, FilePair, IoBuf, IO_BUFFER_SIZE};
+use crate::hardware::hardware_threads_get;
+use crate::list::{parse_block_header, parse_indexes, BlockHeaderInfo, XzFileInfo};
+use crate::message::{message_error, message_fatal, message_filename, message_strm};
+use crate::sign::{sign_src_changed, sign_verify_src};
+use crate::signals::USER_ABORT;
+
+/// --verify 的检查级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// 只检查头部、Index 和填充，不解码压缩数据
+    Structure,
+    /// 另外并行解码每个 Block 并验证其完整性校验值
+    Full,
+}
+
+/// 解析 --verify 的参数
+pub fn verify_mode_parse(s: &str) -> Option<VerifyMode> {
+    match s {
+        "structure" => Some(VerifyMode::Structure),
+        "full" => Some(VerifyMode::Full),
+        _ => None,
+    }
+}
+
+/// 完整检查一个 Block 所需的信息，取自 Index
+#[derive(Debug, Clone)]
+struct VerifyBlock {
+    /// Block 在文件中的序号，从 1 开始
+    number: u64,
+    /// Block Header 在文件中的偏移
+    offset: u64,
+    total_size: u64,
+    unpadded_size: u64,
+    uncompressed_size: u64,
+    check: LzmaCheck,
+}
+
+/// 检查结构：Block Header 与 Index 一致，Block Padding 为零
+///
+/// parse_indexes() 已经检查了 Stream Header、Stream Footer、Index 和 Stream Padding。
+/// 出错时已经显示了错误信息并返回 None。
+fn verify_structure(xfi: &XzFileInfo, pair: &mut FilePair) -> Option<Vec<VerifyBlock>> {
+    let mut blocks = Vec::new();
+    let mut bhi = BlockHeaderInfo::default();
+    let mut all_have_sizes = true;
+    let mut memusage_max = 0;
+    let mut min_version = 0;
+    let mut buf = IoBuf {
+        data: [0; IO_BUFFER_SIZE],
+    };
+
+    let mut iter = LzmaIndexIter::default();
+    lzma_index_iter_init(&mut iter, Box::new(xfi.idx.as_ref().unwrap().clone()));
+    while !lzma_index_iter_next(&mut iter, LzmaIndexIterMode::Block) {
+        if *USER_ABORT.lock().unwrap() {
+            return None;
+        }
+
+        if parse_block_header(
+            pair,
+            &iter,
+            &mut bhi,
+            &mut all_have_sizes,
+            &mut memusage_max,
+            &mut min_version,
+        ) {
+            return None;
+        }
+
+        // Block Padding 位于压缩数据和 Check 之间，长度为 0 到 3 字节
+        let check = iter.stream.flags.clone().unwrap().check;
+        let padding = (iter.block.total_size - iter.block.unpadded_size) as usize;
+        if padding > 0 {
+            let pos = iter.block.compressed_file_offset + iter.block.unpadded_size
+                - lzma_check_size(check) as u64;
+            if io_pread(pair, &mut buf, padding, pos) {
+                return None;
+            }
+            if buf.data[..padding].iter().any(|&b| b != 0) {
+                verify_block_error(
+                    pair,
+                    iter.block.number_in_file,
+                    message_strm(LzmaRet::DataError),
+                );
+                return None;
+            }
+        }
+
+        blocks.push(VerifyBlock {
+            number: iter.block.number_in_file,
+            offset: iter.block.compressed_file_offset,
+            total_size: iter.block.total_size,
+            unpadded_size: iter.block.unpadded_size,
+            uncompressed_size: iter.block.uncompressed_size,
+            check,
+        });
+    }
+
+    Some(blocks)
+}
+
+/// 从 fd 的 pos 处读满 buf
+fn verify_pread(fd: i32, buf: &mut [u8], pos: u64) -> Result<(), String> {
+    let mut done = 0;
+    while done < buf.len() {
+        match sys_unistd::pread(fd, &mut buf[done..], (pos + done as u64) as libc::off_t) {
+            Ok(0) => return Err(message_strm(LzmaRet::BufError).to_string()),
+            Ok(n) => done += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(tr_io_error(&e)),
+        }
+    }
+    Ok(())
+}
+
+/// 解码一个 Block 并验证其完整性校验值，解码出的数据被丢弃
+///
+/// stop 为 true 时提前返回 Ok，调用者不使用这种情况下的结果。
+fn verify_block_data(fd: i32, vb: &VerifyBlock, stop: &AtomicBool) -> Result<(), String> {
+    let mut in_buf = vec![0u8; IO_BUFFER_SIZE];
+
+    // 读取并解码 Block Header
+    let mut header = [0u8; LZMA_BLOCK_HEADER_SIZE_MAX as usize];
+    verify_pread(fd, &mut header[..1], vb.offset)?;
+    if header[0] == 0 {
+        return Err(message_strm(LzmaRet::DataError).to_string());
+    }
+    let header_size = lzma_block_header_size_decode!(header[0]);
+    if header_size as u64 >= vb.unpadded_size {
+        return Err(message_strm(LzmaRet::DataError).to_string());
+    }
+    verify_pread(fd, &mut header[1..header_size as usize], vb.offset + 1)?;
+
+    let mut block = LzmaBlock {
+        version: 1,
+        header_size,
+        check: vb.check,
+        filters: (0..5).map(|_| LzmaFilter::default()).collect(),
+        ..Default::default()
+    };
+    let ret = lzma_block_header_decode(&mut block, &header[..header_size as usize]);
+    if ret != LzmaRet::Ok {
+        return Err(message_strm(ret).to_string());
+    }
+    let ret = lzma_block_compressed_size(&mut block, vb.unpadded_size);
+    if ret != LzmaRet::Ok {
+        return Err(message_strm(ret).to_string());
+    }
+    // Block Header 中没有未压缩大小时用 Index 中的值，使解码器也检查它
+    if block.uncompressed_size == liblzma::api::LZMA_VLI_UNKNOWN {
+        block.uncompressed_size = vb.uncompressed_size;
+    }
+
+    let mut strm = LzmaStream::default();
+    let ret = lzma_block_decoder(&mut strm, &mut block);
+    if ret != LzmaRet::Ok {
+        return Err(message_strm(ret).to_string());
+    }
+    strm.next_out.borrow_mut().resize(IO_BUFFER_SIZE, 0);
+
+    // 压缩数据、Block Padding 和 Check
+    let mut pos = vb.offset + header_size as u64;
+    let mut remaining = vb.total_size - header_size as u64;
+    loop {
+        if stop.load(Ordering::Relaxed) || *USER_ABORT.lock().unwrap() {
+            return Ok(());
+        }
+
+        if strm.avail_in.get() == 0 && remaining > 0 {
+            let n = (remaining as usize).min(IO_BUFFER_SIZE);
+            verify_pread(fd, &mut in_buf[..n], pos)?;
+            pos += n as u64;
+            remaining -= n as u64;
+            // 安全性：in_buf 在 strm 之前声明，只在下次读取前被引用。
+            unsafe {
+                strm.next_in = std::slice::from_raw_parts(in_buf.as_ptr(), n);
+            }
+            strm.avail_in.set(n);
+        }
+
+        strm.next_out_pos = 0;
+        strm.avail_out.set(IO_BUFFER_SIZE);
+        let action = if remaining == 0 {
+            LzmaAction::Finish
+        } else {
+            LzmaAction::Run
+        };
+
+        match lzma_code(&mut strm, action) {
+            LzmaRet::StreamEnd => return Ok(()),
+            LzmaRet::Ok => {
+                // 输入已经用完而解码器没有进展，说明 Block 被截断
+                if remaining == 0 && strm.avail_in.get() == 0 && strm.next_out_pos == 0 {
+                    return Err(message_strm(LzmaRet::BufError).to_string());
+                }
+            }
+            ret => return Err(message_strm(ret).to_string()),
+        }
+    }
+}
+
+/// 用多个线程检查所有 Block 的数据，返回第一个出错的 Block 的序号和错误信息
+fn verify_blocks(fd: i32, blocks: &[VerifyBlock]) -> Option<(u64, String)> {
+    let threads = (hardware_threads_get().max(1) as usize).min(blocks.len());
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let first_error: Mutex<Option<(usize, String)>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= blocks.len() || stop.load(Ordering::Relaxed) || *USER_ABORT.lock().unwrap()
+                {
+                    break;
+                }
+
+                if let Err(msg) = verify_block_data(fd, &blocks[i], &stop) {
+                    // 只保留序号最小的错误，使结果与线程数无关
+                    let mut first = first_error.lock().unwrap();
+                    if first.as_ref().is_none_or(|(j, _)| i < *j) {
+                        *first = Some((i, msg));
+                    }
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    first_error
+        .into_inner()
+        .unwrap()
+        .map(|(i, msg)| (blocks[i].number, msg))
+}
+
+/// 显示 Block 出错的信息
+fn verify_block_error(pair: &FilePair, number: u64, msg: &str) {
+    message_error(
+        &tr(
+            Msg::VerifyBlockError,
+            &[
+                &pair.src_name.as_deref().unwrap_or("(unknown)"),
+                &number,
+                &msg,
+            ],
+        ),
+        format_args!(""),
+    );
+}
+
+/// 按 --verify 检查一个文件，出错时显示错误信息
+pub fn verify_file(filename: &str) {
+    let mode = OPT_VERIFY.lock().unwrap().unwrap();
+
+    let format = *OPT_FORMAT.lock().unwrap();
+    if format != FormatType::Auto && format != FormatType::Xz {
+        message_fatal(tr_str(Msg::VerifyFormat), format_args!(""));
+    }
+
+    message_filename(filename);
+
+    // 需要随机访问
+    if filename == STDIN_FILENAME {
+        message_error(tr_str(Msg::VerifyStdin), format_args!(""));
+        return;
+    }
+
+    let mut pair = match io_open_src(filename) {
+        Some(p) => p,
+        None => return,
+    };
+    if sign_verify_src(&mut pair) {
+        io_close(&mut pair, false);
+        return;
+    }
+
+    let mut xfi = XzFileInfo::default();
+    if !parse_indexes(&mut xfi, &mut pair) {
+        if let Some(blocks) = verify_structure(&xfi, &mut pair) {
+            if mode == VerifyMode::Full && !blocks.is_empty() {
+                if let Some((number, msg)) = verify_blocks(pair.src_fd, &blocks) {
+                    verify_block_error(&pair, number, &msg);
+                }
+            }
+        }
+    }
+
+    // 检查的数据必须是验证签名时读到的数据
+    sign_src_changed(&pair);
+
+    io_close(&mut pair, false);
+}
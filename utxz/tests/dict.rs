@@ -0,0 +1,59 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --format=raw 与 --dict
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+
+#[test]
+fn raw_dict_round_trip_and_mismatch() {
+    let dir = test_dir("dict");
+    let data: String = (0..3000).map(|i| i.to_string() + " hello world\n").collect();
+    let dict_a: String = (0..2000).map(|i| i.to_string() + " hello\n").collect();
+    let dict_b: String = (0..2000).map(|i| i.to_string() + " world\n").collect();
+    fs::write(dir.join("data"), &data).unwrap();
+    fs::write(dir.join("a.dict"), dict_a).unwrap();
+    fs::write(dir.join("b.dict"), dict_b).unwrap();
+
+    let raw = ["-F", "raw", "-S", ".raw"];
+    let out = utxz(&dir, &[&raw[..], &["--dict", "a.dict", "data"]].concat());
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(dir.join("data.raw").exists());
+
+    // 字典不同时明确报错，不留下输出文件
+    let out = utxz(
+        &dir,
+        &[&raw[..], &["-d", "--dict", "b.dict", "data.raw"]].concat(),
+    );
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stderr(&out).contains("differs from the one given with --dict"));
+    assert!(!dir.join("data").exists());
+    assert!(dir.join("data.raw").exists());
+
+    let out = utxz(
+        &dir,
+        &[&raw[..], &["-d", "--dict", "a.dict", "data.raw"]].concat(),
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(fs::read_to_string(dir.join("data")).unwrap(), data);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn raw_needs_suffix() {
+    let dir = test_dir("dict-suffix");
+    fs::write(dir.join("data"), "data").unwrap();
+
+    let out = utxz(&dir, &["-F", "raw", "data"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stderr(&out).contains("--suffix=.SUF is required"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
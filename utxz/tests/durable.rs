@@ -0,0 +1,139 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 持久写入：先写临时文件再重命名，不覆盖已有的目标文件
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::Duration;
+
+/// 目录中的文件名，已排序
+fn names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+/// 在后台压缩一个 16 MiB 的空洞文件，调试版本需要一两秒
+fn spawn_compress(dir: &Path, args: &[&str]) -> Child {
+    fs::File::create(dir.join("big"))
+        .unwrap()
+        .set_len(16 << 20)
+        .unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_utxz"))
+        .args(args)
+        .arg("big")
+        .current_dir(dir)
+        .env("LC_ALL", "C")
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(300));
+    child
+}
+
+/// 没有 -f 时拒绝覆盖已有的目标文件，错误信息经过翻译
+#[test]
+fn existing_dest_refused() {
+    let dir = test_dir("durable-exists");
+    fs::write(dir.join("a"), "data").unwrap();
+    fs::write(dir.join("a.xz"), "old").unwrap();
+
+    let out = utxz(&dir, &["a"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(
+        stderr(&out).contains("a.xz: File exists\n"),
+        "{}",
+        stderr(&out)
+    );
+    assert!(!stderr(&out).contains("os error"), "{}", stderr(&out));
+    assert_eq!(fs::read(dir.join("a.xz")).unwrap(), b"old");
+    assert_eq!(names(&dir), ["a", "a.xz"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 写入期间出现的同名文件不被重命名覆盖，临时文件被删除，源文件保留
+#[test]
+fn dest_created_while_writing() {
+    let dir = test_dir("durable-race");
+    let child = spawn_compress(&dir, &["-0"]);
+
+    let tmp: Vec<_> = names(&dir)
+        .into_iter()
+        .filter(|n| n.starts_with(".big.xz."))
+        .collect();
+    assert_eq!(tmp.len(), 1, "{:?}", names(&dir));
+    assert!(!dir.join("big.xz").exists());
+    fs::write(dir.join("big.xz"), "other").unwrap();
+
+    let out = child.wait_with_output().unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    assert!(
+        stderr(&out).contains("big.xz: File exists\n"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(fs::read(dir.join("big.xz")).unwrap(), b"other");
+    assert_eq!(names(&dir), ["big", "big.xz"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 出错或被终止时删除临时文件
+#[test]
+fn temp_file_removed_on_failure() {
+    let dir = test_dir("durable-cleanup");
+
+    let child = spawn_compress(&dir, &["-0"]);
+    assert_eq!(unsafe { libc::kill(child.id() as i32, libc::SIGTERM) }, 0);
+    assert!(!child.wait_with_output().unwrap().status.success());
+    assert_eq!(names(&dir), ["big"]);
+    fs::remove_file(dir.join("big")).unwrap();
+
+    // 解压到一半发现数据损坏
+    fs::write(dir.join("a"), vec![b'x'; 100_000]).unwrap();
+    assert!(utxz(&dir, &["a"]).status.success());
+    let mut xz = fs::read(dir.join("a.xz")).unwrap();
+    let len = xz.len();
+    xz[len / 2] ^= 0x55;
+    fs::write(dir.join("a.xz"), xz).unwrap();
+    let out = utxz(&dir, &["-d", "a.xz"]);
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    assert_eq!(names(&dir), ["a.xz"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// --no-sync 直接写入目标文件，被终止时删除它
+#[test]
+fn no_sync_writes_dest_directly() {
+    let dir = test_dir("durable-no-sync");
+    let child = spawn_compress(&dir, &["--no-sync", "-0"]);
+
+    assert_eq!(names(&dir), ["big", "big.xz"]);
+    assert_eq!(unsafe { libc::kill(child.id() as i32, libc::SIGTERM) }, 0);
+    assert!(!child.wait_with_output().unwrap().status.success());
+    assert_eq!(names(&dir), ["big"]);
+
+    // 正常完成时结果相同
+    let out = utxz(&dir, &["--no-sync", "-0", "big"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(names(&dir), ["big.xz"]);
+    let out = utxz(&dir, &["-d", "big.xz"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(fs::metadata(dir.join("big")).unwrap().len(), 16 << 20);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,44 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --encrypt 和 --key-file
+
+mod common;
+
+use common::{test_dir, utxz};
+use std::fs;
+
+#[test]
+fn encrypt_without_check() {
+    let dir = test_dir("encrypt");
+    let data: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+    fs::write(dir.join("key"), b"secret").unwrap();
+    fs::write(dir.join("a"), &data).unwrap();
+    assert!(utxz(&dir, &["--encrypt", "--key-file=key", "a"])
+        .status
+        .success());
+
+    // 明文的完整性检查会泄露未压缩数据的信息
+    let out = utxz(&dir, &["--robot", "-l", "a.xz"]);
+    let list = String::from_utf8_lossy(&out.stdout);
+    let file_line = list.lines().find(|l| l.starts_with("file\t")).unwrap();
+    assert_eq!(file_line.split('\t').nth(6), Some("None"));
+
+    fs::write(dir.join("wrong"), b"other").unwrap();
+    assert_eq!(
+        utxz(&dir, &["-t", "--key-file=wrong", "a.xz"])
+            .status
+            .code(),
+        Some(1)
+    );
+
+    assert!(utxz(&dir, &["-d", "--key-file=key", "a.xz"])
+        .status
+        .success());
+    assert_eq!(fs::read(dir.join("a")).unwrap(), data);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
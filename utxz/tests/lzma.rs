@@ -0,0 +1,122 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --lzma-strict 和 --lzma-lax 对 .lzma 文件的检查
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+use std::path::Path;
+
+/// 压缩出一个 .lzma 文件，头部中的未压缩大小未知，数据以结束标记结尾
+fn lzma_data(dir: &Path) -> (Vec<u8>, Vec<u8>) {
+    let data: Vec<u8> = (0..20000u32)
+        .flat_map(|i| format!("{}\n", i).into_bytes())
+        .collect();
+    fs::write(dir.join("orig"), &data).unwrap();
+    let out = utxz(dir, &["-F", "lzma", "orig"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let lzma = fs::read(dir.join("orig.lzma")).unwrap();
+    fs::remove_file(dir.join("orig.lzma")).unwrap();
+    assert_eq!(lzma[5..13], [0xFF; 8]);
+    (data, lzma)
+}
+
+/// 用给定的选项解压 lzma，成功时返回解压结果，失败时返回错误信息
+fn decompress(dir: &Path, opts: &[&str], lzma: &[u8]) -> Result<Vec<u8>, String> {
+    fs::write(dir.join("t.lzma"), lzma).unwrap();
+    let out = utxz(dir, &[opts, &["-d", "t.lzma"]].concat());
+    if out.status.success() {
+        let data = fs::read(dir.join("t")).unwrap();
+        fs::remove_file(dir.join("t")).unwrap();
+        Ok(data)
+    } else {
+        assert_eq!(out.status.code(), Some(1));
+        assert!(!dir.join("t").exists());
+        fs::remove_file(dir.join("t.lzma")).unwrap();
+        Err(stderr(&out))
+    }
+}
+
+#[test]
+fn strict_and_lax_conflict() {
+    let dir = test_dir("lzma-conflict");
+    let (_, lzma) = lzma_data(&dir);
+
+    for opts in [
+        ["--lzma-strict", "--lzma-lax"],
+        ["--lzma-lax", "--lzma-strict"],
+    ] {
+        let err = decompress(&dir, &opts, &lzma).unwrap_err();
+        assert!(
+            err.contains("--lzma-strict cannot be used with --lzma-lax"),
+            "{}",
+            err
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 头部给出了大小而数据仍以结束标记结尾时，只有 --lzma-strict 拒绝
+#[test]
+fn strict_rejects_end_marker_with_known_size() {
+    let dir = test_dir("lzma-strict");
+    let (data, mut lzma) = lzma_data(&dir);
+    lzma[5..13].copy_from_slice(&(data.len() as u64).to_le_bytes());
+
+    assert!(decompress(&dir, &[], &lzma).unwrap() == data);
+    assert!(decompress(&dir, &["--lzma-lax"], &lzma).unwrap() == data);
+    let err = decompress(&dir, &["--lzma-strict"], &lzma).unwrap_err();
+    assert!(
+        err.contains("t.lzma: Compressed data is corrupt"),
+        "{}",
+        err
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 只有 --lzma-lax 忽略尾随数据
+#[test]
+fn lax_accepts_trailing_data() {
+    let dir = test_dir("lzma-trailing");
+    let (data, mut lzma) = lzma_data(&dir);
+    lzma.extend_from_slice(b"trailing garbage");
+
+    assert!(decompress(&dir, &["--lzma-lax"], &lzma).unwrap() == data);
+    for opts in [&[][..], &["--lzma-strict"]] {
+        let err = decompress(&dir, opts, &lzma).unwrap_err();
+        assert!(
+            err.contains("t.lzma: Compressed data is corrupt"),
+            "{}",
+            err
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 非标准的字典大小只在 --lzma-lax 时被识别为 .lzma 格式
+#[test]
+fn lax_accepts_any_dict_size() {
+    let dir = test_dir("lzma-dict");
+    let (data, mut lzma) = lzma_data(&dir);
+    lzma[1..5].copy_from_slice(&0x123456u32.to_le_bytes());
+
+    assert!(decompress(&dir, &["--lzma-lax"], &lzma).unwrap() == data);
+    for opts in [&[][..], &["--lzma-strict"]] {
+        let err = decompress(&dir, opts, &lzma).unwrap_err();
+        assert!(
+            err.contains("t.lzma: File format not recognized"),
+            "{}",
+            err
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
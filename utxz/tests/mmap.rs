@@ -0,0 +1,72 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 通过 mmap 读取的源文件在读取期间被截断
+
+mod common;
+
+use common::{stderr, test_dir};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// 16 MiB 可压缩的数据，调试版本用 -0 压缩需要几秒
+fn test_data() -> Vec<u8> {
+    let mut seed = 1u64;
+    (0..16 << 20)
+        .map(|_| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            b"abcdefgh"[(seed >> 61) as usize]
+        })
+        .collect()
+}
+
+fn names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+/// 截断后的部分不会被当作零压缩，而是报告读取错误
+#[test]
+fn truncated_while_mapped() {
+    let dir = test_dir("mmap-truncate");
+    let data = test_data();
+    fs::write(dir.join("a"), &data).unwrap();
+
+    let child = Command::new(env!("CARGO_BIN_EXE_utxz"))
+        .args(["-0", "a"])
+        .current_dir(&dir)
+        .env("LC_ALL", "C")
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(300));
+    fs::OpenOptions::new()
+        .write(true)
+        .open(dir.join("a"))
+        .unwrap()
+        .set_len(1 << 20)
+        .unwrap();
+
+    let out = child.wait_with_output().unwrap();
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("a: Read error: the file was truncated while being read\n"),
+        "{}",
+        stderr(&out)
+    );
+    assert_eq!(names(&dir), ["a"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
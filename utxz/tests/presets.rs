@@ -0,0 +1,82 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! -0 到 -3 和 --fast 的压缩结果与 xz 兼容
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// 有大量匹配的文本，后面跟着不可压缩的数据
+fn test_data() -> Vec<u8> {
+    const WORDS: [&str; 9] = [
+        "the ", "quick ", "brown ", "fox ", "jumps ", "over ", "lazy ", "dog ", "\n",
+    ];
+    let mut seed = 1u64;
+    let mut next = || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 56) as u8
+    };
+    let mut data: Vec<u8> = (0..200_000)
+        .flat_map(|_| WORDS[next() as usize % WORDS.len()].bytes())
+        .collect();
+    data.extend((0..100_000).map(|_| next()));
+    data
+}
+
+/// 用 xz 处理 input，系统中没有 xz 时返回 None
+fn xz(dir: &Path, args: &[&str], input: &str) -> Option<Vec<u8>> {
+    let out = Command::new("xz")
+        .args(args)
+        .arg("-c")
+        .arg(input)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    assert!(out.status.success(), "xz {:?}: {}", args, stderr(&out));
+    Some(out.stdout)
+}
+
+/// 压缩后能用 utxz 解压，也能用 xz 解压；-0 到 -3 的输出与单线程的 xz 相同
+#[test]
+fn fast_presets_round_trip() {
+    let dir = test_dir("presets");
+    let data = test_data();
+    fs::write(dir.join("orig"), &data).unwrap();
+
+    let levels = ["-0", "-1", "-2", "-3"];
+    let mut cases: Vec<Vec<&str>> = levels.iter().map(|l| vec![*l]).collect();
+    cases.push(vec!["--fast"]);
+    cases.extend(levels.iter().map(|l| vec![*l, "--fast"]));
+
+    for args in cases {
+        fs::write(dir.join("a"), &data).unwrap();
+        let out = utxz(&dir, &[&args[..], &["a"]].concat());
+        assert!(out.status.success(), "{:?}: {}", args, stderr(&out));
+        let compressed = fs::read(dir.join("a.xz")).unwrap();
+
+        if let Some(decoded) = xz(&dir, &["-d"], "a.xz") {
+            assert!(decoded == data, "{:?}: xz decoded different data", args);
+        }
+        if args.len() == 1 && args[0] != "--fast" {
+            if let Some(expected) = xz(&dir, &["-T1", args[0]], "orig") {
+                assert!(compressed == expected, "{:?}: differs from xz", args);
+            }
+        }
+
+        let out = utxz(&dir, &["-d", "a.xz"]);
+        assert!(out.status.success(), "{:?}: {}", args, stderr(&out));
+        assert!(fs::read(dir.join("a")).unwrap() == data, "{:?}", args);
+        fs::remove_file(dir.join("a")).unwrap();
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,77 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --progress=json 的事件输出
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::ffi::CString;
+use std::fs;
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+/// --jobs 的工作线程不显示进度指示器，但仍要输出 JSON progress 事件
+#[test]
+fn json_progress_with_jobs() {
+    let dir = test_dir("progress-jobs");
+    let mut seed = 1u64;
+    let data: Vec<u8> = (0..3_000_000)
+        .map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 56) as u8
+        })
+        .collect();
+    fs::write(dir.join("a"), data).unwrap();
+    assert!(utxz(&dir, &["-0", "a"]).status.success());
+    let xz = fs::read(dir.join("a.xz")).unwrap();
+
+    // 从 FIFO 读取输入，中途停顿超过 JSON 事件的间隔
+    let names = ["f1", "f2"];
+    let writers: Vec<_> = names
+        .iter()
+        .map(|name| {
+            let path = dir.join(name);
+            let cpath = CString::new(path.to_str().unwrap()).unwrap();
+            assert_eq!(unsafe { libc::mkfifo(cpath.as_ptr(), 0o600) }, 0);
+            let xz = xz.clone();
+            thread::spawn(move || {
+                let mut fifo = fs::OpenOptions::new().write(true).open(path).unwrap();
+                fifo.write_all(&xz[..600_000]).unwrap();
+                thread::sleep(Duration::from_millis(1200));
+                fifo.write_all(&xz[600_000..]).unwrap();
+            })
+        })
+        .collect();
+
+    let out = utxz(
+        &dir,
+        &["--progress=json", "-t", "-f", "--jobs=2", "f1", "f2"],
+    );
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    assert!(out.status.success());
+
+    let stderr = stderr(&out);
+    for name in names {
+        let file = format!("\"file\":\"{}\"", name);
+        for event in ["start", "progress", "end"] {
+            assert!(
+                stderr
+                    .lines()
+                    .any(|l| l.contains(&format!("\"event\":\"{}\"", event)) && l.contains(&file)),
+                "{} {}: {}",
+                name,
+                event,
+                stderr
+            );
+        }
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
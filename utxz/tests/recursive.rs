@@ -0,0 +1,36 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! -r 处理目录中的文件
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+
+/// 命令行给出的目录展开为其中的文件，每个文件只处理一次
+#[test]
+fn recursive_args() {
+    let dir = test_dir("recursive");
+    for d in ["a/sub", "b/sub"] {
+        fs::create_dir_all(dir.join(d)).unwrap();
+        fs::write(dir.join(d).join("f"), "data").unwrap();
+        fs::write(dir.join(d).join("../g"), "data").unwrap();
+    }
+
+    let out = utxz(&dir, &["-r", "-v", "a", "b"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    for d in ["a", "b"] {
+        for f in ["sub/f.xz", "g.xz"] {
+            assert!(dir.join(d).join(f).exists(), "{}/{}", d, f);
+        }
+        assert!(!dir.join(d).join("g").exists());
+    }
+    // 每个文件只显示一次文件名
+    assert_eq!(stderr(&out).matches("sub/f").count(), 2, "{}", stderr(&out));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
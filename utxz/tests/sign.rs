@@ -0,0 +1,85 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --sign-key 和 .sig 文件
+
+mod common;
+
+use common::{test_dir, utxz};
+use std::fs;
+use std::path::Path;
+
+/// 目录中的文件名，排好序
+fn list(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn sign_files() {
+    let dir = test_dir("sign");
+    let mut seed = 1u64;
+    let data: Vec<u8> = (0..1_000_000)
+        .map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 56) as u8
+        })
+        .collect();
+    fs::write(dir.join("key"), b"secret").unwrap();
+    fs::write(dir.join("a"), &data).unwrap();
+
+    // 压缩写出 .sig，不留下临时文件
+    assert!(utxz(&dir, &["-0", "--sign-key=key", "a"]).status.success());
+    assert_eq!(list(&dir), ["a.xz", "a.xz.sig", "key"]);
+    let sig = fs::read(dir.join("a.xz.sig")).unwrap();
+
+    // 测试不删除任何文件
+    assert!(utxz(&dir, &["-t", "--sign-key=key", "a.xz"])
+        .status
+        .success());
+    assert_eq!(list(&dir), ["a.xz", "a.xz.sig", "key"]);
+
+    // 已有的 .sig 没有 -f 时不覆盖
+    fs::write(dir.join("b"), &data[..1000]).unwrap();
+    fs::write(dir.join("b.xz.sig"), b"old").unwrap();
+    let out = utxz(&dir, &["-0", "--sign-key=key", "b"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(fs::read(dir.join("b.xz.sig")).unwrap(), b"old");
+    assert!(!dir.join("b.xz").exists());
+
+    assert!(utxz(&dir, &["-0", "-f", "--sign-key=key", "b"])
+        .status
+        .success());
+    assert_ne!(fs::read(dir.join("b.xz.sig")).unwrap(), b"old");
+    fs::remove_file(dir.join("b.xz")).unwrap();
+    fs::remove_file(dir.join("b.xz.sig")).unwrap();
+
+    // 签名不匹配时不解压
+    let mut xz = fs::read(dir.join("a.xz")).unwrap();
+    fs::write(dir.join("c.xz"), &xz).unwrap();
+    fs::write(dir.join("c.xz.sig"), &sig).unwrap();
+    let len = xz.len();
+    xz[len / 2] ^= 1;
+    fs::write(dir.join("c.xz"), &xz).unwrap();
+    let out = utxz(&dir, &["-d", "--sign-key=key", "c.xz"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(!dir.join("c").exists());
+    fs::remove_file(dir.join("c.xz")).unwrap();
+    fs::remove_file(dir.join("c.xz.sig")).unwrap();
+
+    // 解压缩删除源文件时 .sig 一起删除
+    assert!(utxz(&dir, &["-d", "--sign-key=key", "a.xz"])
+        .status
+        .success());
+    assert_eq!(list(&dir), ["a", "key"]);
+    assert_eq!(fs::read(dir.join("a")).unwrap(), data);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
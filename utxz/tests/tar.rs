@@ -0,0 +1,147 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --tar 创建归档和解包时对恶意归档的处理
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// 生成一个 ustar 头部
+fn tar_header(path: &str, typeflag: u8, size: u64, link: &str) -> [u8; 512] {
+    let mut h = [0u8; 512];
+    h[..path.len()].copy_from_slice(path.as_bytes());
+    h[100..107].copy_from_slice(b"0000644");
+    h[108..115].copy_from_slice(b"0000000");
+    h[116..123].copy_from_slice(b"0000000");
+    h[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+    h[136..147].copy_from_slice(b"00000000000");
+    h[156] = typeflag;
+    h[157..157 + link.len()].copy_from_slice(link.as_bytes());
+    h[257..263].copy_from_slice(b"ustar\0");
+    h[263..265].copy_from_slice(b"00");
+
+    h[148..156].copy_from_slice(b"        ");
+    let sum: u64 = h.iter().map(|&b| b as u64).sum();
+    h[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+    h
+}
+
+/// 把成员写入 dir/name.tar 并用 utxz 压缩成 name.tar.xz
+fn tar_xz(dir: &Path, name: &str, members: &[([u8; 512], &[u8])]) -> String {
+    let mut tar = Vec::new();
+    for (header, data) in members {
+        tar.extend_from_slice(header);
+        tar.extend_from_slice(data);
+        tar.resize(tar.len().div_ceil(512) * 512, 0);
+    }
+    tar.extend_from_slice(&[0u8; 1024]);
+
+    let tar_name = format!("{}.tar", name);
+    fs::write(dir.join(&tar_name), tar).unwrap();
+    assert!(utxz(dir, &[&tar_name]).status.success());
+    format!("{}.tar.xz", name)
+}
+
+#[test]
+fn extract_refuses_paths_through_symlinks() {
+    let dir = test_dir("tar-symlink");
+    let outside = dir.join("outside");
+    fs::create_dir(&outside).unwrap();
+    let target = dir.join("target");
+    fs::create_dir(&target).unwrap();
+
+    let outside_abs = outside.to_str().unwrap();
+    let archive = tar_xz(
+        &dir,
+        "evil",
+        &[
+            (tar_header("rel", b'2', 0, "../outside"), b""),
+            (tar_header("rel/pwned", b'0', 5, ""), b"pwned"),
+            (tar_header("abs", b'2', 0, outside_abs), b""),
+            (tar_header("abs/sub/pwned", b'0', 5, ""), b"pwned"),
+            (tar_header("abs", b'5', 0, ""), b""),
+            (tar_header("ok", b'0', 2, ""), b"ok"),
+        ],
+    );
+
+    let out = utxz(&target, &["--tar", "-d", &format!("../{}", archive)]);
+    let stderr = stderr(&out);
+    assert!(stderr.contains("rel/pwned: Path goes through a symbolic link"));
+    assert!(stderr.contains("abs/sub/pwned: Path goes through a symbolic link"));
+
+    assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+    assert_eq!(fs::read(target.join("ok")).unwrap(), b"ok");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn oversized_extended_header_is_an_error() {
+    let dir = test_dir("tar-ext");
+    let archive = tar_xz(
+        &dir,
+        "huge",
+        &[
+            (tar_header("././@LongLink", b'L', 0o77777777777, ""), b""),
+            (tar_header("a", b'0', 0, ""), b""),
+        ],
+    );
+
+    for mode in ["-d", "-l"] {
+        let out = utxz(&dir, &["--tar", mode, &archive]);
+        assert_eq!(out.status.code(), Some(1));
+        assert!(stderr(&out).contains("Extended header is too large"));
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn create_keeps_existing_archive() {
+    let dir = test_dir("tar-create");
+    fs::write(dir.join("small"), b"hello\n").unwrap();
+    assert!(utxz(&dir, &["--tar", "a.tar.xz", "small"]).status.success());
+    let orig = fs::read(dir.join("a.tar.xz")).unwrap();
+
+    // 没有 -f 时不覆盖已有的归档
+    let out = utxz(&dir, &["--tar", "a.tar.xz", "small"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(fs::read(dir.join("a.tar.xz")).unwrap() == orig);
+
+    // 创建期间被终止时已有的归档不变，临时文件被删除
+    let big = fs::File::create(dir.join("big")).unwrap();
+    big.set_len(1 << 30).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_utxz"))
+        .args(["--tar", "-f", "a.tar.xz", "big"])
+        .current_dir(&dir)
+        .env("LC_ALL", "C")
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(unsafe { libc::kill(child.id() as i32, libc::SIGTERM) }, 0);
+    assert!(!child.wait().unwrap().success());
+    assert!(fs::read(dir.join("a.tar.xz")).unwrap() == orig);
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a.tar.xz", "big", "small"]);
+
+    fs::remove_file(dir.join("big")).unwrap();
+    fs::write(dir.join("small"), b"changed\n").unwrap();
+    assert!(utxz(&dir, &["--tar", "-f", "a.tar.xz", "small"])
+        .status
+        .success());
+    assert!(fs::read(dir.join("a.tar.xz")).unwrap() != orig);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
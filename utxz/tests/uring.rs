@@ -0,0 +1,115 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! io_uring 读写的结果与 read()/write() 相同，io_uring 不可用时回退到后者
+
+#![cfg(feature = "io_uring")]
+
+mod common;
+
+use common::{stderr, test_dir};
+use std::fs;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn bpf_stmt(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+/// 在子进程中安装 seccomp 过滤器，使 io_uring_setup() 返回 ENOSYS，
+/// 与内核不支持 io_uring 或容器禁止使用它时相同
+fn deny_io_uring() -> io::Result<()> {
+    let filter = [
+        // seccomp_data.nr
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 0),
+        libc::sock_filter {
+            code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+            jt: 0,
+            jf: 1,
+            k: libc::SYS_io_uring_setup as u32,
+        },
+        bpf_stmt(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32,
+        ),
+        bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW),
+    ];
+    let prog = libc::sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_ptr() as *mut libc::sock_filter,
+    };
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+            || libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &prog) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn run(dir: &Path, args: &[&str], uring: bool) -> Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_utxz"));
+    cmd.args(args).current_dir(dir).env("LC_ALL", "C");
+    if !uring {
+        unsafe {
+            cmd.pre_exec(deny_io_uring);
+        }
+    }
+    cmd.output().unwrap()
+}
+
+/// 超过几个 io_uring 缓冲区的数据，中间有一段可以写成空洞的零
+fn test_data() -> Vec<u8> {
+    let mut seed = 1u64;
+    let mut data: Vec<u8> = (0..3 << 20)
+        .map(|_| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            b"abcdefgh"[(seed >> 61) as usize]
+        })
+        .collect();
+    data.resize(data.len() + (2 << 20) + 12345, 0);
+    data.extend_from_slice(b"tail");
+    data
+}
+
+/// 压缩和解压缩 data，返回压缩结果
+fn round_trip(dir: &Path, args: &[&str], data: &[u8], uring: bool) -> Vec<u8> {
+    fs::write(dir.join("a"), data).unwrap();
+    let out = run(dir, &[args, &["a"]].concat(), uring);
+    assert!(out.status.success(), "{:?}: {}", args, stderr(&out));
+    let compressed = fs::read(dir.join("a.xz")).unwrap();
+
+    let out = run(dir, &[args, &["-d", "a.xz"]].concat(), uring);
+    assert!(out.status.success(), "{:?}: {}", args, stderr(&out));
+    assert!(fs::read(dir.join("a")).unwrap() == data, "{:?}", args);
+    fs::remove_file(dir.join("a")).unwrap();
+    compressed
+}
+
+#[test]
+fn same_output_with_and_without_io_uring() {
+    let dir = test_dir("uring");
+    let data = test_data();
+
+    // --no-mmap 时源文件也用 io_uring 读取，否则只有目标文件用 io_uring 写入
+    for args in [&["-0", "--no-mmap"][..], &["-0"]] {
+        let with = round_trip(&dir, args, &data, true);
+        let without = round_trip(&dir, args, &data, false);
+        assert!(with == without, "{:?}", args);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,57 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! --verify=structure 和 --verify=full
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::fs;
+use std::path::Path;
+
+/// 对 name 运行两种检查，返回 (structure, full) 的退出状态
+fn verify(dir: &Path, name: &str) -> (Option<i32>, Option<i32>) {
+    let structure = utxz(dir, &["--verify=structure", name]);
+    let full = utxz(dir, &["--verify=full", name]);
+    (structure.status.code(), full.status.code())
+}
+
+/// 复制 src 并把 pos 处的字节取反
+fn corrupt(dir: &Path, src: &str, dest: &str, pos: usize) {
+    let mut data = fs::read(dir.join(src)).unwrap();
+    data[pos] ^= 0xFF;
+    fs::write(dir.join(dest), data).unwrap();
+}
+
+#[test]
+fn verify_levels() {
+    let dir = test_dir("verify");
+    let mut seed = 1u64;
+    let data: Vec<u8> = (0..100_000)
+        .map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 56) as u8
+        })
+        .collect();
+    fs::write(dir.join("clean"), data).unwrap();
+    fs::write(dir.join("empty"), b"").unwrap();
+    assert!(utxz(&dir, &["-0", "clean", "empty"]).status.success());
+
+    assert_eq!(verify(&dir, "clean.xz"), (Some(0), Some(0)));
+    assert_eq!(verify(&dir, "empty.xz"), (Some(0), Some(0)));
+
+    // 压缩数据损坏只有解码之后才能发现
+    corrupt(&dir, "clean.xz", "payload.xz", 50_000);
+    assert_eq!(verify(&dir, "payload.xz"), (Some(0), Some(1)));
+    let out = utxz(&dir, &["--verify=full", "payload.xz"]);
+    assert!(stderr(&out).contains("payload.xz: Block 1: "));
+
+    // Block Header 紧跟在 12 字节的 Stream Header 之后
+    corrupt(&dir, "clean.xz", "header.xz", 14);
+    assert_eq!(verify(&dir, "header.xz"), (Some(1), Some(1)));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
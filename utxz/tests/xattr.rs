@@ -0,0 +1,144 @@
+/*
+ * SPDX-FileCopyrightText: 2025 UnionTech Software Technology Co., Ltd.
+ *
+ * SPDX-License-Identifier: GPL-2.0-or-later
+ */
+
+//! 压缩和解压时复制扩展属性
+
+mod common;
+
+use common::{stderr, test_dir, utxz};
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+fn c_path(path: &Path) -> CString {
+    CString::new(path.as_os_str().as_bytes()).unwrap()
+}
+
+fn set_xattr(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    let name = CString::new(name).unwrap();
+    let ret = unsafe {
+        libc::setxattr(
+            c_path(path).as_ptr(),
+            name.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn get_xattr(path: &Path, name: &str) -> Option<Vec<u8>> {
+    let name = CString::new(name).unwrap();
+    let mut buf = vec![0u8; 65536];
+    let ret = unsafe {
+        libc::getxattr(
+            c_path(path).as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if ret < 0 {
+        return None;
+    }
+    buf.truncate(ret as usize);
+    Some(buf)
+}
+
+/// user.* 属性在压缩和解压后保留，--no-xattrs 时不复制
+#[test]
+fn user_xattr_round_trip() {
+    let dir = test_dir("xattr");
+    let a = dir.join("a");
+    fs::write(&a, "data").unwrap();
+    if let Err(e) = set_xattr(&a, "user.utxz.test", b"value\0with nul") {
+        eprintln!(
+            "skipped: the file system does not support user xattrs: {}",
+            e
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        return;
+    }
+    set_xattr(&a, "user.utxz.empty", b"").unwrap();
+
+    let out = utxz(&dir, &["a"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let xz = dir.join("a.xz");
+    assert_eq!(
+        get_xattr(&xz, "user.utxz.test").unwrap(),
+        b"value\0with nul"
+    );
+    assert_eq!(get_xattr(&xz, "user.utxz.empty").unwrap(), b"");
+
+    let out = utxz(&dir, &["-d", "a.xz"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(get_xattr(&a, "user.utxz.test").unwrap(), b"value\0with nul");
+
+    let out = utxz(&dir, &["--no-xattrs", "a"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(get_xattr(&xz, "user.utxz.test"), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 目标文件系统拒绝某个属性时显示警告，其余属性照常复制
+///
+/// 源文件放在 tmpfs 上，通过符号链接压缩，目标文件写在测试目录中。ext4 在一个
+/// 块中保存全部属性，放不下 tmpfs 可以保存的大属性值。
+#[test]
+fn rejected_xattr_warns() {
+    let dir = test_dir("xattr-rejected");
+    let shm_dir = Path::new("/dev/shm").join(format!("utxz-xattr-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&shm_dir);
+    if fs::create_dir(&shm_dir).is_err() {
+        eprintln!("skipped: /dev/shm is not available");
+        fs::remove_dir_all(&dir).unwrap();
+        return;
+    }
+
+    let src = shm_dir.join("src");
+    fs::write(&src, "data").unwrap();
+    let big = vec![b'v'; 10000];
+    let probe = dir.join("probe");
+    fs::write(&probe, "").unwrap();
+    if set_xattr(&src, "user.utxz.big", &big).is_err()
+        || set_xattr(&probe, "user.utxz.big", &big).is_ok()
+    {
+        eprintln!("skipped: cannot set up xattrs that only the source accepts");
+        fs::remove_dir_all(&shm_dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        return;
+    }
+    fs::remove_file(&probe).unwrap();
+    set_xattr(&src, "user.utxz.small", b"small").unwrap();
+    symlink(&src, dir.join("a")).unwrap();
+
+    let out = utxz(&dir, &["-f", "a"]);
+    assert_eq!(out.status.code(), Some(2), "{}", stderr(&out));
+    assert!(
+        stderr(&out).contains("a.xz: Cannot set extended attribute user.utxz.big: "),
+        "{}",
+        stderr(&out)
+    );
+    let xz = dir.join("a.xz");
+    assert_eq!(get_xattr(&xz, "user.utxz.small").unwrap(), b"small");
+    assert_eq!(get_xattr(&xz, "user.utxz.big"), None);
+
+    let out = utxz(&dir, &["-d", "a.xz"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(fs::read(dir.join("a")).unwrap(), b"data");
+
+    fs::remove_dir_all(&shm_dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -44,6 +44,54 @@ pub fn unlink(path: &CStr) -> io::Result<()> {
     }
 }
 
+#[inline]
+pub fn rename(old: &CStr, new: &CStr) -> io::Result<()> {
+    let ret = unsafe { libc::rename(old.as_ptr(), new.as_ptr()) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[inline]
+pub fn link(old: &CStr, new: &CStr) -> io::Result<()> {
+    let ret = unsafe { libc::link(old.as_ptr(), new.as_ptr()) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// 原子地重命名，new 已存在时失败并返回 EEXIST。
+/// 内核或文件系统不支持 RENAME_NOREPLACE 时改用 link() 加 unlink()。
+pub fn rename_noreplace(old: &CStr, new: &CStr) -> io::Result<()> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        let ret = unsafe {
+            libc::renameat2(
+                libc::AT_FDCWD,
+                old.as_ptr(),
+                libc::AT_FDCWD,
+                new.as_ptr(),
+                libc::RENAME_NOREPLACE,
+            )
+        };
+        if ret == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINVAL) | Some(libc::ENOSYS) => {}
+            _ => return Err(err),
+        }
+    }
+
+    link(old, new)?;
+    unlink(old)
+}
+
 #[inline]
 pub fn lseek(fd: RawFd, offset: libc::off_t, whence: i32) -> io::Result<libc::off_t> {
     let ret = unsafe { libc::lseek(fd, offset, whence) };
@@ -99,8 +147,19 @@ pub fn posix_fadvise(
     }
 }
 
+/// 对应 `umask(2)`，设置新的掩码并返回原来的掩码
+#[inline]
+pub fn umask(mask: libc::mode_t) -> libc::mode_t {
+    unsafe { libc::umask(mask) }
+}
+
 #[inline]
 pub fn zeroed_stat() -> libc::stat {
     // 这里集中承载 “C struct 置零初始化” 的 unsafe。
     unsafe { std::mem::zeroed() }
 }
+
+#[inline]
+pub fn fnmatch(pattern: &CStr, name: &CStr, flags: i32) -> bool {
+    unsafe { libc::fnmatch(pattern.as_ptr(), name.as_ptr(), flags) == 0 }
+}
@@ -3,6 +3,10 @@
 pub mod errno;
 pub mod fcntl;
 pub mod fs;
+pub mod locale;
+pub mod mman;
 pub mod poll;
 pub mod signal;
+pub mod termios;
 pub mod unistd;
+pub mod xattr;
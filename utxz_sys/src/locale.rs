@@ -0,0 +1,21 @@
+use std::ffi::CStr;
+
+/// 按环境变量设置当前进程的所有区域类别，对应 `setlocale(LC_ALL, "")`
+///
+/// 之后 strerror() 等 libc 函数按 LC_MESSAGES 输出本地化的文字。
+#[inline]
+pub fn setlocale_from_env() -> bool {
+    !unsafe { libc::setlocale(libc::LC_ALL, c"".as_ptr()) }.is_null()
+}
+
+/// errnum 对应的错误描述，使用当前的 LC_MESSAGES
+pub fn strerror(errnum: i32) -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    // libc crate 在 glibc 上把 strerror_r 绑定到符合 XSI 的版本，结果总是写入 buf
+    if unsafe { libc::strerror_r(errnum, buf.as_mut_ptr(), buf.len()) } != 0 {
+        return format!("errno {}", errnum);
+    }
+    unsafe { CStr::from_ptr(buf.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
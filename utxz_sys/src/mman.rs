@@ -0,0 +1,86 @@
+use std::ffi::c_void;
+use std::io;
+use std::os::unix::io::RawFd;
+
+/// 以只读方式映射文件开头的 len 字节
+#[inline]
+pub fn mmap_readonly(fd: RawFd, len: usize) -> io::Result<*mut c_void> {
+    let ret = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            fd,
+            0,
+        )
+    };
+    if ret == libc::MAP_FAILED {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// 用全零的匿名页替换 [addr, addr + len) 处的映射
+///
+/// 只调用 mmap()，可以在信号处理函数中使用。
+///
+/// # Safety
+///
+/// 该范围必须属于调用者自己的映射，原来的内容会丢失。
+#[inline]
+pub unsafe fn mmap_zero_fixed(addr: *mut c_void, len: usize) -> io::Result<()> {
+    let ret = unsafe {
+        libc::mmap(
+            addr,
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED,
+            -1,
+            0,
+        )
+    };
+    if ret == libc::MAP_FAILED {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// # Safety
+///
+/// [addr, addr + len) 必须是 mmap 返回的映射，之后不能再访问。
+#[inline]
+pub unsafe fn munmap(addr: *mut c_void, len: usize) -> io::Result<()> {
+    if unsafe { libc::munmap(addr, len) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// 对应 `madvise(2)`，只是提示，调用者通常忽略错误
+///
+/// # Safety
+///
+/// [addr, addr + len) 必须属于调用者自己的映射。
+#[inline]
+pub unsafe fn madvise(addr: *mut c_void, len: usize, advice: i32) -> io::Result<()> {
+    if unsafe { libc::madvise(addr, len, advice) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// 内存页大小
+#[inline]
+pub fn page_size() -> usize {
+    let ret = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if ret > 0 {
+        ret as usize
+    } else {
+        4096
+    }
+}
@@ -0,0 +1,27 @@
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::io::RawFd;
+
+/// 对应 `tcgetattr(3)`，读取终端的属性。
+#[inline]
+pub fn tcgetattr(fd: RawFd) -> io::Result<libc::termios> {
+    let mut termios = MaybeUninit::<libc::termios>::uninit();
+    let ret = unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) };
+    if ret == 0 {
+        // tcgetattr 成功时已填满整个结构体
+        Ok(unsafe { termios.assume_init() })
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// 对应 `tcsetattr(3)`，optional_actions 为 `TCSANOW`、`TCSAFLUSH` 等。
+#[inline]
+pub fn tcsetattr(fd: RawFd, optional_actions: i32, termios: &libc::termios) -> io::Result<()> {
+    let ret = unsafe { libc::tcsetattr(fd, optional_actions, termios) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
@@ -45,6 +45,16 @@ pub fn read(fd: RawFd, buf: &mut [u8]) -> io::Result<usize> {
     }
 }
 
+#[inline]
+pub fn pread(fd: RawFd, buf: &mut [u8], offset: libc::off_t) -> io::Result<usize> {
+    let ret = unsafe { libc::pread(fd, buf.as_mut_ptr() as *mut c_void, buf.len(), offset) };
+    if ret >= 0 {
+        Ok(ret as usize)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
 #[inline]
 pub fn write(fd: RawFd, buf: &[u8]) -> io::Result<usize> {
     let ret = unsafe { libc::write(fd, buf.as_ptr() as *const c_void, buf.len()) };
@@ -54,3 +64,13 @@ pub fn write(fd: RawFd, buf: &[u8]) -> io::Result<usize> {
         Err(io::Error::last_os_error())
     }
 }
+
+#[inline]
+pub fn fsync(fd: RawFd) -> io::Result<()> {
+    let ret = unsafe { libc::fsync(fd) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
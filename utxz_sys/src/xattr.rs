@@ -0,0 +1,73 @@
+use std::ffi::CStr;
+use std::io;
+use std::os::unix::io::RawFd;
+
+#[cfg(target_os = "linux")]
+use std::ffi::c_void;
+
+/// 把属性名列表写入 buf，各名称以 '\0' 结尾；buf 为空时只返回需要的大小
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn flistxattr(fd: RawFd, buf: &mut [u8]) -> io::Result<usize> {
+    let ret = unsafe { libc::flistxattr(fd, buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if ret >= 0 {
+        Ok(ret as usize)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// 把属性值写入 buf；buf 为空时只返回需要的大小
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn fgetxattr(fd: RawFd, name: &CStr, buf: &mut [u8]) -> io::Result<usize> {
+    let ret = unsafe {
+        libc::fgetxattr(
+            fd,
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut c_void,
+            buf.len(),
+        )
+    };
+    if ret >= 0 {
+        Ok(ret as usize)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn fsetxattr(fd: RawFd, name: &CStr, value: &[u8], flags: i32) -> io::Result<()> {
+    let ret = unsafe {
+        libc::fsetxattr(
+            fd,
+            name.as_ptr(),
+            value.as_ptr() as *const c_void,
+            value.len(),
+            flags,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+// 其他系统上不支持扩展属性
+
+#[cfg(not(target_os = "linux"))]
+pub fn flistxattr(_fd: RawFd, _buf: &mut [u8]) -> io::Result<usize> {
+    Err(io::Error::from_raw_os_error(libc::ENOTSUP))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fgetxattr(_fd: RawFd, _name: &CStr, _buf: &mut [u8]) -> io::Result<usize> {
+    Err(io::Error::from_raw_os_error(libc::ENOTSUP))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fsetxattr(_fd: RawFd, _name: &CStr, _value: &[u8], _flags: i32) -> io::Result<()> {
+    Err(io::Error::from_raw_os_error(libc::ENOTSUP))
+}